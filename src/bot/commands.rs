@@ -1,4 +1,17 @@
+use crate::bot::config::{parse_config_command, BotConfig, ConfigCommand};
+use crate::bot::games::chat::{format_eval, variation_to_san};
+use crate::bot::games::games::OperatorAction;
+use crate::bot::net::BotNet;
+use crate::bot::replay::Replay;
+use crate::bot::seek::parse_seek_arguments;
 use crate::bot::state::BotStateRef;
+use chess::engine::eval::position::{determine_game_phase, evaluate_board_detailed};
+use chess::engine::Engine;
+use chess::model::board::Board;
+use chess::model::game_state::GameState;
+use chess::model::moves::is_move_notation;
+use chess::model::piece::Color;
+use std::sync::Mutex;
 
 // Constants
 const EXIT_COMMAND: &str = "exit";
@@ -7,8 +20,30 @@ const Q_COMMAND: &str = "q";
 const HELP_COMMAND: &str = "help";
 const PLAY_COMMAND: &str = "play";
 const P_COMMAND: &str = "p";
+const EVAL_COMMAND: &str = "eval";
+const ANALYZE_COMMAND: &str = "analyze";
+const MAX_GAMES_COMMAND: &str = "max_games";
+const UPGRADE_COMMAND: &str = "upgrade";
+const NET_COMMAND: &str = "net";
+const SEEK_COMMAND: &str = "seek";
+const AUTOSEEK_COMMAND: &str = "autoseek";
+const SEARCH_LOG_COMMAND: &str = "searchlog";
+const REPLAY_COMMAND: &str = "replay";
+const REPLAY_OFF_ARGUMENT: &str = "off";
+const NEXT_COMMAND: &str = "next";
+const N_COMMAND: &str = "n";
+const PREVIOUS_COMMAND: &str = "prev";
+const RESIGN_COMMAND: &str = "resign";
+const ABORT_COMMAND: &str = "abort";
+const DRAW_COMMAND: &str = "draw";
+const MOVE_COMMAND: &str = "move";
+const CONFIG_COMMAND: &str = "config";
 const EMPTY_COMMAND: &str = "";
 
+// Default values for the analyze command
+const ANALYZE_DEFAULT_DEPTH: usize = 8;
+const ANALYZE_DEFAULT_LINES: usize = 3;
+
 // -----------------------------------------------------------------------------
 // Trait definition
 pub trait BotCommands {
@@ -31,15 +66,339 @@ fn print_help() {
            EXIT_COMMAND);
   println!("{} or {} - Exits the program - Aborts/resigns ongoing games",
            QUIT_COMMAND, Q_COMMAND);
+  println!("{} <fen> - Evaluates a position and prints each evaluation term",
+           EVAL_COMMAND);
+  println!("{} <fen> [depth <d>] [lines <n>] - Searches a position and prints the best lines",
+           ANALYZE_COMMAND);
+  println!("{} <n> - Sets the maximum number of games played at the same time",
+           MAX_GAMES_COMMAND);
+  println!("{} <minutes> <increment> [rated] - Seeks a game, also used by {}",
+           SEEK_COMMAND, AUTOSEEK_COMMAND);
+  println!("{} on | off - Seeks a game periodically when we can play more games",
+           AUTOSEEK_COMMAND);
+  println!("{} on | off - Appends the depth, nodes and time of each of our searches to a CSV file",
+           SEARCH_LOG_COMMAND);
+  println!("{} <pgn-file> | {} - Loads a game to step through it, with the engine lines at each move",
+           REPLAY_COMMAND, REPLAY_OFF_ARGUMENT);
+  println!("{} or {} / {} or {} - Goes to the next / previous move of the replayed game",
+           NEXT_COMMAND, N_COMMAND, PREVIOUS_COMMAND, P_COMMAND);
+  println!("{} / {} / {} <game_id> - Resigns, aborts or offers a draw in one of our games",
+           RESIGN_COMMAND, ABORT_COMMAND, DRAW_COMMAND);
+  println!("{} <game_id> <uci> - Stops the engine and plays a move in one of our games",
+           MOVE_COMMAND);
+  println!("{} load <path> | on | off | info - Loads, switches on/off or describes the NNUE used by our engines",
+           NET_COMMAND);
+  println!("{} dump | load <path> - Prints our settings and engine options as JSON, or applies the ones saved in a file",
+           CONFIG_COMMAND);
+  println!("{} - Upgrades our account to a bot account. Irreversible, only for accounts without games",
+           UPGRADE_COMMAND);
   println!("{} - Displays the help", HELP_COMMAND);
 }
 
+/// Parses the arguments of the commands acting on one of our games
+///
+/// # Arguments
+///
+/// * `command` -   Command, e.g. `resign` or `move`
+/// * `arguments` - Game ID, followed by the move in UCI notation for `move`
+///
+/// # Return value
+///
+/// The game ID and the action to execute, None if the arguments are invalid.
+fn parse_operator_command(command: &str, arguments: &str) -> Option<(String, OperatorAction)> {
+  let mut words = arguments.split_whitespace();
+  let game_id = words.next()?.to_string();
+  let action = match command {
+    RESIGN_COMMAND => OperatorAction::Resign,
+    ABORT_COMMAND => OperatorAction::Abort,
+    DRAW_COMMAND => OperatorAction::Draw,
+    MOVE_COMMAND => {
+      let chess_move = words.next()?;
+      if !is_move_notation(chess_move) {
+        return None;
+      }
+      OperatorAction::Move(chess_move.to_string())
+    },
+    _ => return None,
+  };
+  if words.next().is_some() {
+    return None;
+  }
+  Some((game_id, action))
+}
+
+/// Reads a configuration file, warning about the keys we do not know
+///
+/// # Arguments
+///
+/// * `path` - Path of the JSON file saved with `config dump`
+///
+/// # Return value
+///
+/// The configuration, None if the file cannot be read or parsed.
+fn load_config(path: &str) -> Option<BotConfig> {
+  let json = match std::fs::read_to_string(path) {
+    Ok(json) => json,
+    Err(e) => {
+      println!("Could not read {}: {}", path, e);
+      return None;
+    },
+  };
+  match BotConfig::from_json(&json) {
+    Ok((config, unknown_keys)) => {
+      for key in unknown_keys {
+        println!("Warning: ignoring unknown setting '{}' in {}", key, path);
+      }
+      Some(config)
+    },
+    Err(e) => {
+      println!("Invalid configuration in {}: {}", path, e);
+      None
+    },
+  }
+}
+
+/// Checks that a FEN describes a legal position and loads it
+///
+/// # Arguments
+///
+/// * `fen` - FEN of the position to load
+///
+/// # Return value
+///
+/// The game state for the FEN, None if the FEN is invalid.
+fn parse_fen(fen: &str) -> Option<GameState> {
+  let fen = fen.split_whitespace().collect::<Vec<&str>>().join(" ");
+  if let Err(e) = Board::try_from_fen(&fen) {
+    println!("Invalid FEN: {}", e);
+    return None;
+  }
+
+  // The side that just played cannot be in check
+  let game_state = GameState::from_fen(&fen);
+  let board = &game_state.board;
+  let opponent = Color::opposite(board.side_to_play);
+  if board.get_attackers(board.get_king(opponent), board.side_to_play) != 0 {
+    return None;
+  }
+
+  Some(game_state)
+}
+
+/// Prints the detailed evaluation of a position
+///
+/// # Arguments
+///
+/// * `fen` - FEN of the position to evaluate
+/// * `net` - NNUE used by our engines, its evaluation is printed when on
+fn print_evaluation(fen: &str, net: &BotNet) {
+  let game_state = parse_fen(fen);
+  if game_state.is_none() {
+    println!("Invalid FEN: '{}'. Usage: {} <fen>", fen, EVAL_COMMAND);
+    return;
+  }
+
+  let game_state = game_state.unwrap();
+  println!("Position: {}", game_state.to_fen());
+  println!("Game phase: {:?}", determine_game_phase(&game_state));
+  println!("{}", evaluate_board_detailed(&game_state));
+  if net.is_enabled() {
+    let mut engine = Engine::new(false);
+    net.attach(&mut engine);
+    println!("Eval with NNUE: {}",
+             format_eval(engine.static_eval(&game_state)));
+  }
+}
+
+/// Parses the arguments of the analyze command: `<fen> [depth <d>] [lines <n>]`
+///
+/// # Arguments
+///
+/// * `arguments` - Text following the analyze command
+///
+/// # Return value
+///
+/// The FEN, depth and number of lines. None if a value cannot be parsed.
+fn parse_analyze_arguments(arguments: &str) -> Option<(String, usize, usize)> {
+  let mut fen_parts: Vec<&str> = Vec::new();
+  let mut depth = ANALYZE_DEFAULT_DEPTH;
+  let mut lines = ANALYZE_DEFAULT_LINES;
+
+  let mut words = arguments.split_whitespace();
+  while let Some(word) = words.next() {
+    match word {
+      "depth" => depth = words.next()?.parse::<usize>().ok()?,
+      "lines" => lines = words.next()?.parse::<usize>().ok()?,
+      _ => fen_parts.push(word),
+    }
+  }
+
+  Some((fen_parts.join(" "), depth.max(1), lines.max(1)))
+}
+
+/// Searches a position and prints the best lines in SAN, with their
+/// evaluation
+///
+/// # Arguments
+///
+/// * `arguments` - Arguments of the analyze command: `<fen> [depth <d>] [lines
+///   <n>]`
+/// * `net` -       NNUE used by our engines
+fn print_analysis(arguments: &str, net: &BotNet) {
+  let parsed_arguments = parse_analyze_arguments(arguments);
+  if parsed_arguments.is_none() {
+    println!("Invalid arguments: '{}'. Usage: {} <fen> [depth <d>] [lines <n>]",
+             arguments, ANALYZE_COMMAND);
+    return;
+  }
+  let (fen, depth, lines) = parsed_arguments.unwrap();
+
+  let game_state = parse_fen(&fen);
+  if game_state.is_none() {
+    println!("Invalid FEN: '{}'. Usage: {} <fen> [depth <d>] [lines <n>]",
+             fen, ANALYZE_COMMAND);
+    return;
+  }
+  print_best_lines(&game_state.unwrap(), depth, lines, net);
+}
+
+/// Searches a position and prints the best lines in SAN, with their
+/// evaluation
+///
+/// # Arguments
+///
+/// * `game_state` - Position to search
+/// * `depth` -      Depth of the search
+/// * `lines` -      Number of lines to print
+/// * `net` -        NNUE used by our engines
+fn print_best_lines(game_state: &GameState, depth: usize, lines: usize, net: &BotNet) {
+  if game_state.get_moves().is_empty() {
+    println!("No legal move in position {}", game_state.to_fen());
+    return;
+  }
+
+  let mut engine = Engine::new(false);
+  net.attach(&mut engine);
+  engine.set_position(&game_state.to_fen());
+  engine.set_maximum_depth(depth);
+  engine.set_multipv(lines);
+  engine.go();
+
+  let analysis = engine.get_analysis();
+  println!("Position: {} - depth {}", game_state.to_fen(), depth);
+  println!("{:>3} | {:>28} | Line", "#", "Eval");
+  for (i, line) in analysis.variations.iter().enumerate() {
+    println!("{:>3} | {:>28} | {}",
+             i + 1,
+             format_eval(line.eval),
+             variation_to_san(game_state, &line.variation));
+  }
+}
+
+/// Loads a PGN file to replay it, or stops the replay
+///
+/// # Arguments
+///
+/// * `arguments` - Path of the PGN file, or `off` to stop the replay
+/// * `replay` -    Game replayed in the console
+///
+/// # Return value
+///
+/// The replay loaded, None if the file cannot be loaded or the replay stopped.
+fn load_replay(arguments: &str, replay: &Mutex<Option<Replay>>) -> Option<Replay> {
+  let arguments = arguments.trim();
+  if arguments.is_empty() {
+    println!("Usage: {} <pgn-file> | {}",
+             REPLAY_COMMAND, REPLAY_OFF_ARGUMENT);
+    return None;
+  }
+  if arguments == REPLAY_OFF_ARGUMENT {
+    *replay.lock().unwrap() = None;
+    return None;
+  }
+
+  match Replay::from_file(arguments) {
+    Ok(loaded) => {
+      println!("Loaded {} moves from {}",
+               loaded.get_move_count(),
+               arguments);
+      *replay.lock().unwrap() = Some(loaded.clone());
+      Some(loaded)
+    },
+    Err(e) => {
+      println!("{}", e);
+      None
+    },
+  }
+}
+
+/// Goes one move forward or backward in the replayed game
+///
+/// # Arguments
+///
+/// * `replay` -  Game replayed in the console
+/// * `forward` - Set to true to go to the next move, false for the previous one
+///
+/// # Return value
+///
+/// The replay at its new position, None if there is no replay or no move to go
+/// to.
+fn step_replay(replay: &Mutex<Option<Replay>>, forward: bool) -> Option<Replay> {
+  let mut replay = replay.lock().unwrap();
+  if replay.is_none() {
+    println!("No game to replay. Usage: {} <pgn-file>", REPLAY_COMMAND);
+    return None;
+  }
+
+  let replay = replay.as_mut().unwrap();
+  if forward && !replay.step_forward() {
+    println!("End of the game");
+    return None;
+  }
+  if !forward && !replay.step_backward() {
+    println!("Start of the game");
+    return None;
+  }
+  Some(replay.clone())
+}
+
+/// Prints the current position of a replayed game and the best lines of the
+/// engine
+///
+/// # Arguments
+///
+/// * `replay` - Game replayed in the console
+/// * `net` -    NNUE used by our engines
+fn print_replay_position(replay: &Replay, net: &BotNet) {
+  let game_state = replay.get_game_state();
+  match replay.last_move_description() {
+    Some(last_move) => println!("After {} ({}/{})",
+                                last_move,
+                                replay.get_ply(),
+                                replay.get_move_count()),
+    None => println!("Start of the game (0/{})", replay.get_move_count()),
+  }
+  println!("{}", game_state.board);
+  print_best_lines(&game_state,
+                   ANALYZE_DEFAULT_DEPTH,
+                   ANALYZE_DEFAULT_LINES,
+                   net);
+}
+
 // -----------------------------------------------------------------------------
 // Implementation
 impl BotCommands for BotStateRef {
   fn execute_command(self, input: &str) {
     // Remember to trim, it will also remove the newline
-    match input.trim() as &str {
+    let input = input.trim();
+    let (command, arguments) = input.split_once(' ').unwrap_or((input, ""));
+    match command {
+      // While we replay a game, p goes to the previous move instead of playing
+      P_COMMAND if self.replay.lock().unwrap().is_some() => {
+        if let Some(replay) = step_replay(&self.replay, false) {
+          tokio::task::spawn_blocking(move || print_replay_position(&replay, &self.net));
+        }
+      },
       PLAY_COMMAND | P_COMMAND => {
         tokio::spawn(async { self.challenge_somebody().await });
       },
@@ -49,9 +408,153 @@ impl BotCommands for BotStateRef {
       QUIT_COMMAND | Q_COMMAND => {
         self.request_exit(true);
       },
+      EVAL_COMMAND => print_evaluation(arguments.trim(), &self.net),
+      ANALYZE_COMMAND => {
+        let arguments = arguments.to_string();
+        tokio::task::spawn_blocking(move || print_analysis(&arguments, &self.net));
+      },
+      NET_COMMAND => println!("{}", self.net.execute_command(arguments)),
+      SEEK_COMMAND => match parse_seek_arguments(arguments) {
+        Some((clock, rated)) => {
+          tokio::spawn(async move { self.seek_game(clock, rated).await });
+        },
+        None => print_help(),
+      },
+      AUTOSEEK_COMMAND => match arguments.trim() {
+        "on" => self.autoseek.set_enabled(true),
+        "off" => self.autoseek.set_enabled(false),
+        _ => print_help(),
+      },
+      SEARCH_LOG_COMMAND => match arguments.trim() {
+        "on" => self.search_log.set_enabled(true),
+        "off" => self.search_log.set_enabled(false),
+        _ => print_help(),
+      },
+      REPLAY_COMMAND => {
+        if let Some(replay) = load_replay(arguments, &self.replay) {
+          tokio::task::spawn_blocking(move || print_replay_position(&replay, &self.net));
+        }
+      },
+      NEXT_COMMAND | N_COMMAND | PREVIOUS_COMMAND => {
+        if let Some(replay) = step_replay(&self.replay, command != PREVIOUS_COMMAND) {
+          tokio::task::spawn_blocking(move || print_replay_position(&replay, &self.net));
+        }
+      },
+      RESIGN_COMMAND | ABORT_COMMAND | DRAW_COMMAND | MOVE_COMMAND => {
+        match parse_operator_command(command, arguments) {
+          Some((game_id, action)) => {
+            tokio::spawn(async move { self.operate_game(&game_id, action).await });
+          },
+          None => print_help(),
+        }
+      },
+      CONFIG_COMMAND => match parse_config_command(arguments) {
+        Some(ConfigCommand::Dump) => println!("{}", self.get_config().to_json()),
+        Some(ConfigCommand::Load(path)) => {
+          if let Some(config) = load_config(&path) {
+            self.apply_config(&config);
+            println!("Applied the configuration from {}, the engine options apply to the next games",
+                     path);
+          }
+        },
+        None => print_help(),
+      },
+      MAX_GAMES_COMMAND => match arguments.trim().parse::<usize>() {
+        Ok(max_games) => self.set_max_concurrent_games(max_games),
+        Err(_) => print_help(),
+      },
+      UPGRADE_COMMAND => {
+        tokio::spawn(async {
+          if self.api.upgrade_to_bot_account().await.is_err() {
+            println!("Could not upgrade our account to a bot account");
+          }
+        });
+      },
       HELP_COMMAND => print_help(),
       EMPTY_COMMAND => {},
       _ => print_help(),
     }
   }
 }
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_fen() {
+    assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_some());
+    // Missing fields, wrong number of squares or kings
+    assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").is_none());
+    assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKBNR w KQkq - 0 1").is_none());
+    assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w kq - 0 1").is_none());
+    assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").is_none());
+    // Black to play while the white king is in check
+    assert!(parse_fen("4k3/8/8/8/8/8/4r3/4K3 b - - 0 1").is_none());
+    assert!(parse_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").is_some());
+  }
+
+  #[test]
+  fn test_parse_analyze_arguments() {
+    let fen = "4k3/8/8/8/8/8/4r3/4K3 w - - 0 1";
+    assert_eq!(Some((fen.to_string(), 12, 5)),
+               parse_analyze_arguments(format!("{fen} depth 12 lines 5").as_str()));
+    assert_eq!(Some((fen.to_string(), ANALYZE_DEFAULT_DEPTH, 2)),
+               parse_analyze_arguments(format!("{fen} lines 2").as_str()));
+    assert_eq!(None,
+               parse_analyze_arguments(format!("{fen} depth").as_str()));
+    assert_eq!(None,
+               parse_analyze_arguments(format!("{fen} depth x").as_str()));
+  }
+
+  #[test]
+  fn test_parse_operator_command() {
+    assert_eq!(Some((String::from("abcd1234"), OperatorAction::Resign)),
+               parse_operator_command(RESIGN_COMMAND, "abcd1234"));
+    assert_eq!(Some((String::from("abcd1234"), OperatorAction::Abort)),
+               parse_operator_command(ABORT_COMMAND, " abcd1234 "));
+    assert_eq!(Some((String::from("abcd1234"), OperatorAction::Draw)),
+               parse_operator_command(DRAW_COMMAND, "abcd1234"));
+    assert_eq!(Some((String::from("abcd1234"), OperatorAction::Move(String::from("e7e8q")))),
+               parse_operator_command(MOVE_COMMAND, "abcd1234 e7e8q"));
+
+    // Missing game ID or move, extra arguments, moves that are not UCI
+    assert_eq!(None, parse_operator_command(RESIGN_COMMAND, ""));
+    assert_eq!(None, parse_operator_command(DRAW_COMMAND, "abcd1234 now"));
+    assert_eq!(None, parse_operator_command(MOVE_COMMAND, "abcd1234"));
+    assert_eq!(None, parse_operator_command(MOVE_COMMAND, "abcd1234 Nf3"));
+    assert_eq!(None, parse_operator_command(MOVE_COMMAND, "abcd1234 e2e9"));
+    assert_eq!(None,
+               parse_operator_command(MOVE_COMMAND, "abcd1234 e2e4 e7e5"));
+  }
+
+  #[test]
+  fn test_step_through_a_replay() {
+    let path = std::env::temp_dir().join(format!("schnecken_replay_{}.pgn", std::process::id()));
+    let path = path.to_str().unwrap();
+    std::fs::write(path, "[Event \"Test\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n").unwrap();
+
+    let replay: Mutex<Option<Replay>> = Mutex::new(None);
+    assert!(step_replay(&replay, true).is_none());
+    assert!(load_replay("/does/not/exist.pgn", &replay).is_none());
+    assert!(replay.lock().unwrap().is_none());
+
+    let loaded = load_replay(path, &replay);
+    let _ = std::fs::remove_file(path);
+    assert_eq!(0, loaded.unwrap().get_ply());
+    assert!(step_replay(&replay, false).is_none());
+    for _ in 0..3 {
+      assert!(step_replay(&replay, true).is_some());
+    }
+    assert_eq!("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+               step_replay(&replay, true).unwrap().get_game_state().to_fen());
+    assert!(step_replay(&replay, true).is_none());
+    assert_eq!(3, step_replay(&replay, false).unwrap().get_ply());
+
+    assert!(load_replay(REPLAY_OFF_ARGUMENT, &replay).is_none());
+    assert!(replay.lock().unwrap().is_none());
+  }
+}
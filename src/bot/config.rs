@@ -0,0 +1,195 @@
+use crate::bot::games::games::NUMBER_OF_SIMULTANEOUS_GAMES;
+use chess::engine::config::options::EngineOptions;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+// Constants
+const CONFIG_DUMP_COMMAND: &str = "dump";
+const CONFIG_LOAD_COMMAND: &str = "load";
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Settings of the bot and of the engines of its games, which can be dumped
+/// and loaded as JSON, e.g. to attach them to a bug report.
+/// Missing settings take their default value.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BotConfig {
+  /// Maximum number of games played at the same time
+  pub max_games:  usize,
+  /// Whether we seek games when idle
+  pub autoseek:   bool,
+  /// Whether the performance of our searches is logged
+  pub search_log: bool,
+  /// Whether the engines use the NNUE
+  pub nnue:       bool,
+  /// Whether our games look up the opening explorer once out of book
+  pub explorer:   bool,
+  /// Options the engines of new games start from, before being adjusted to
+  /// the opponent
+  pub engine:     EngineOptions,
+}
+
+/// Console commands acting on the configuration
+#[derive(Debug, PartialEq)]
+pub enum ConfigCommand {
+  /// Print the configuration as JSON
+  Dump,
+  /// Apply the configuration saved in a file
+  Load(String),
+}
+
+impl Default for BotConfig {
+  fn default() -> Self {
+    BotConfig { max_games:  NUMBER_OF_SIMULTANEOUS_GAMES,
+                autoseek:   false,
+                search_log: false,
+                nnue:       false,
+                explorer:   false,
+                engine:     default_engine_options(), }
+  }
+}
+
+impl BotConfig {
+  /// Serializes the configuration
+  ///
+  /// ### Return value
+  ///
+  /// Pretty-printed JSON describing the configuration
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).unwrap_or_default()
+  }
+
+  /// Parses a configuration. Unknown keys are ignored, so that configurations
+  /// written by other versions of the bot can still be loaded.
+  ///
+  /// ### Arguments
+  ///
+  /// * `json`: JSON describing the configuration
+  ///
+  /// ### Return value
+  ///
+  /// The configuration and the list of unknown keys it contained, or an error
+  /// message if the JSON is invalid.
+  pub fn from_json(json: &str) -> Result<(BotConfig, Vec<String>), String> {
+    let value: JsonValue = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let config: BotConfig = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+
+    let known = serde_json::to_value(BotConfig::default()).unwrap_or_default();
+    let mut unknown_keys = Vec::new();
+    find_unknown_keys(&value, &known, "", &mut unknown_keys);
+    Ok((config, unknown_keys))
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Options of the engines of our games when nothing is configured. We do not
+/// use the UCI interface internally, and we check our moves for blunders.
+pub fn default_engine_options() -> EngineOptions {
+  EngineOptions { uci: false,
+                  blunder_check: true,
+                  ..Default::default() }
+}
+
+/// Lists the keys of a JSON object that do not exist in a reference object,
+/// looking into nested objects.
+///
+/// ### Arguments
+///
+/// * `value`:        JSON object to check
+/// * `known`:        JSON object with all the known keys
+/// * `prefix`:       Path of the objects, prepended to the keys
+/// * `unknown_keys`: List to which the unknown keys are added
+fn find_unknown_keys(value: &JsonValue,
+                     known: &JsonValue,
+                     prefix: &str,
+                     unknown_keys: &mut Vec<String>) {
+  let (Some(object), Some(known_object)) = (value.as_object(), known.as_object()) else {
+    return;
+  };
+  for (key, nested_value) in object {
+    let path = format!("{}{}", prefix, key);
+    match known_object.get(key) {
+      Some(known_value) => find_unknown_keys(nested_value,
+                                             known_value,
+                                             &format!("{}.", path),
+                                             unknown_keys),
+      None => unknown_keys.push(path),
+    }
+  }
+}
+
+/// Parses the arguments of the config command: `dump` or `load <path>`
+///
+/// ### Arguments
+///
+/// * `arguments`: Text following the config command
+///
+/// ### Return value
+///
+/// The config command, None if the arguments are invalid
+pub fn parse_config_command(arguments: &str) -> Option<ConfigCommand> {
+  let (command, path) = arguments.trim().split_once(' ').unwrap_or((arguments.trim(), ""));
+  match (command, path.trim()) {
+    (CONFIG_DUMP_COMMAND, "") => Some(ConfigCommand::Dump),
+    (CONFIG_LOAD_COMMAND, path) if !path.is_empty() => Some(ConfigCommand::Load(path.to_string())),
+    _ => None,
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::engine::config::play_style::PlayStyle;
+
+  #[test]
+  fn test_config_round_trip() {
+    let mut config = BotConfig { max_games: 3,
+                                 autoseek: true,
+                                 nnue: true,
+                                 explorer: true,
+                                 ..Default::default() };
+    config.engine.max_depth = 12;
+    config.engine.multi_pv = 1;
+    config.engine.play_style = PlayStyle::Aggressive;
+    config.engine.seed = Some(42);
+    config.engine.elo_limit = Some(1800);
+
+    let json = config.to_json();
+    assert!(json.contains("\"play_style\": \"aggressive\""));
+    let (loaded, unknown_keys) = BotConfig::from_json(&json).unwrap();
+    assert_eq!(config, loaded);
+    assert!(unknown_keys.is_empty());
+  }
+
+  #[test]
+  fn test_config_with_missing_and_unknown_keys() {
+    let json = r#"{"max_games": 2, "colour": "white", "engine": {"max_depth": 5, "hash": 64}}"#;
+    let (config, unknown_keys) = BotConfig::from_json(json).unwrap();
+    assert_eq!(2, config.max_games);
+    assert_eq!(5, config.engine.max_depth);
+    assert_eq!(default_engine_options().multi_pv, config.engine.multi_pv);
+    assert_eq!(vec![String::from("colour"), String::from("engine.hash")],
+               unknown_keys);
+
+    // Invalid JSON or values are errors
+    assert!(BotConfig::from_json("{").is_err());
+    assert!(BotConfig::from_json(r#"{"max_games": "many"}"#).is_err());
+  }
+
+  #[test]
+  fn test_parse_config_command() {
+    assert_eq!(Some(ConfigCommand::Dump), parse_config_command(" dump "));
+    assert_eq!(Some(ConfigCommand::Load(String::from("my config.json"))),
+               parse_config_command("load my config.json"));
+    assert_eq!(None, parse_config_command("load"));
+    assert_eq!(None, parse_config_command("dump now"));
+    assert_eq!(None, parse_config_command(""));
+  }
+}
@@ -0,0 +1,124 @@
+use futures_util::future::{select, Either};
+use log::*;
+use std::future::Future;
+use std::io::BufRead;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Starts reading the console in a dedicated thread, so that waiting for input
+/// never blocks the bot. Each line typed is sent as a command.
+///
+/// ### Return value
+///
+/// Channel receiving the console commands
+pub fn read_console_commands() -> UnboundedReceiver<String> {
+  let (sender, receiver) = mpsc::unbounded_channel();
+  std::thread::spawn(move || forward_lines(std::io::stdin().lock(), sender));
+  receiver
+}
+
+/// Forwards each line of an input as a command, until the input ends or
+/// nobody listens to the commands anymore.
+///
+/// ### Arguments
+///
+/// * `input`:  Input to read the lines from
+/// * `sender`: Channel on which the lines are sent
+fn forward_lines<R: BufRead>(input: R, sender: UnboundedSender<String>) {
+  for line in input.lines() {
+    match line {
+      Ok(line) => {
+        if sender.send(line).is_err() {
+          break;
+        }
+      },
+      Err(e) => {
+        warn!("Cannot read the console input: {}", e);
+        break;
+      },
+    }
+  }
+  debug!("Console input closed");
+}
+
+/// Executes the commands as they arrive, until the bot is requested to exit.
+/// If the command channel closes, e.g. when stdin is not a terminal, we keep
+/// running until the exit request.
+///
+/// ### Arguments
+///
+/// * `commands`: Channel receiving the commands
+/// * `exit`:     Completes when the bot should exit
+/// * `execute`:  Executes one command
+pub async fn run_command_loop<E, F>(commands: &mut UnboundedReceiver<String>,
+                                    exit: E,
+                                    mut execute: F)
+  where E: Future<Output = ()>,
+        F: FnMut(&str)
+{
+  let mut exit = std::pin::pin!(exit);
+  loop {
+    // The exit request is polled first, so that it wins over queued commands
+    match select(exit.as_mut(), std::pin::pin!(commands.recv())).await {
+      Either::Left(_) => break,
+      Either::Right((Some(command), _)) => execute(command.trim()),
+      Either::Right((None, _)) => {
+        exit.as_mut().await;
+        break;
+      },
+    }
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Arc;
+  use std::time::Duration;
+  use tokio::sync::Notify;
+
+  #[test]
+  fn test_queued_commands_and_exit() {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let exit = Arc::new(Notify::new());
+    let mut executed: Vec<String> = Vec::new();
+
+    // Commands are queued before the loop runs, nothing comes from stdin
+    sender.send(String::from("help")).unwrap();
+    sender.send(String::from("  exit\n")).unwrap();
+    sender.send(String::from("never executed")).unwrap();
+
+    let exit_signal = exit.clone();
+    let command_loop = run_command_loop(&mut receiver, exit.notified(), |command| {
+      executed.push(command.to_string());
+      if command == "exit" {
+        exit_signal.notify_one();
+      }
+    });
+    let result =
+      rt.block_on(async { tokio::time::timeout(Duration::from_secs(1), command_loop).await });
+
+    // The sender is still open: only the exit request ended the loop
+    assert!(result.is_ok());
+    assert_eq!(vec![String::from("help"), String::from("exit")], executed);
+    drop(sender);
+  }
+
+  #[test]
+  fn test_forward_lines() {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    forward_lines("status\nseek 3 2\n".as_bytes(), sender);
+
+    assert_eq!(Some(String::from("status")), receiver.try_recv().ok());
+    assert_eq!(Some(String::from("seek 3 2")), receiver.try_recv().ok());
+    // End of the input closes the channel
+    assert!(receiver.try_recv().is_err());
+    assert!(receiver.is_closed());
+  }
+}
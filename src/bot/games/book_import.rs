@@ -0,0 +1,179 @@
+use super::game_log::GAME_LOG_DIRECTORY;
+use chess::engine::books::book::{learn_opening_from_pgn, load_learnt_openings,
+                                 save_learnt_opening};
+use chess::engine::books::{initialize_chess_books, load_book_weights, save_book_weights,
+                           BOOK_WEIGHTS};
+use lichess::traits::GameExporter;
+use lichess::types::Color;
+use log::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Number of times we try to export a game, the export may not be available
+/// right after the game ends
+const GAME_EXPORT_ATTEMPTS: usize = 3;
+/// Delay between two attempts to export a game
+pub const GAME_EXPORT_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// File in which we save the weights of the book moves, in the game log
+/// directory
+const BOOK_WEIGHTS_FILE: &str = "book_weights.txt";
+/// File in which we save the openings learnt from our won games, in the game
+/// log directory
+const LEARNT_OPENINGS_FILE: &str = "learnt_openings.txt";
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Fetches the PGN of a finished game and passes it to a book importer.
+///
+/// ### Arguments
+///
+/// * `api`:         API used to export the game
+/// * `game_id`:     Game ID to export
+/// * `retry_delay`: Time to wait before trying again when the export fails
+/// * `import`:      Adds the PGN to the book
+///
+/// ### Return value
+///
+/// True if the PGN was exported and imported
+pub async fn import_game_into_book<T, F>(api: &T,
+                                         game_id: &str,
+                                         retry_delay: Duration,
+                                         import: F)
+                                         -> bool
+  where T: GameExporter,
+        F: FnOnce(&str)
+{
+  for attempt in 1..=GAME_EXPORT_ATTEMPTS {
+    if let Ok(pgn) = api.get_game_pgn(game_id).await {
+      info!("Adding the opening of game {} to the book", game_id);
+      import(&pgn);
+      return true;
+    }
+
+    debug!("Export of game {} not available yet (attempt {}/{})",
+           game_id, attempt, GAME_EXPORT_ATTEMPTS);
+    if attempt < GAME_EXPORT_ATTEMPTS {
+      tokio::time::sleep(retry_delay).await;
+    }
+  }
+
+  warn!("Could not export game {}, its opening is not added to the book",
+        game_id);
+  false
+}
+
+/// Adds the moves we played in the opening of a won game to the book, and
+/// saves them so that they survive restarts
+///
+/// ### Arguments
+///
+/// * `pgn`:   PGN of the game
+/// * `color`: Color we played in the game
+pub fn learn_opening(pgn: &str, color: Color) {
+  let color = match color {
+    Color::White => chess::model::piece::Color::White,
+    Color::Black => chess::model::piece::Color::Black,
+  };
+  let line = learn_opening_from_pgn(pgn, color);
+  let path = Path::new(GAME_LOG_DIRECTORY).join(LEARNT_OPENINGS_FILE);
+  if let Err(error) = save_learnt_opening(&path, &line, color) {
+    warn!("Cannot save the learnt opening in {}: {}", path.display(), error);
+  }
+}
+
+/// File in which we save the weights of the book moves
+pub fn book_weights_path() -> PathBuf {
+  Path::new(GAME_LOG_DIRECTORY).join(BOOK_WEIGHTS_FILE)
+}
+
+/// Loads what we learnt about the books in the previous runs of the bot
+pub fn load_learnt_books() {
+  let path = book_weights_path();
+  match load_book_weights(&BOOK_WEIGHTS, &path) {
+    Ok(()) => info!("Loaded the book move weights from {}", path.display()),
+    Err(error) => debug!("No book move weights loaded from {}: {}", path.display(), error),
+  }
+
+  // The books are not initialized anymore once they contain moves, so they
+  // must be initialized before we add the learnt openings
+  initialize_chess_books();
+  let path = Path::new(GAME_LOG_DIRECTORY).join(LEARNT_OPENINGS_FILE);
+  match load_learnt_openings(&path) {
+    Ok(count) => info!("Loaded {} learnt openings from {}", count, path.display()),
+    Err(error) => debug!("No learnt openings loaded from {}: {}", path.display(), error),
+  }
+}
+
+/// Saves the weights of the book moves, so that they survive restarts
+///
+/// ### Arguments
+///
+/// * `path`: File in which the weights are saved, see `book_weights_path`
+pub fn save_learnt_book_weights(path: &Path) {
+  if let Err(error) = save_book_weights(&BOOK_WEIGHTS, path) {
+    warn!("Cannot save the book move weights in {}: {}", path.display(), error);
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+
+  const PGN: &str = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0\n\n\n";
+
+  /// Replies with a PGN once the export failed a number of times
+  struct MockApi {
+    failures: Mutex<usize>,
+    requests: Mutex<Vec<String>>,
+  }
+
+  impl MockApi {
+    fn new(failures: usize) -> Self {
+      MockApi { failures: Mutex::new(failures),
+                requests: Mutex::new(Vec::new()), }
+    }
+  }
+
+  impl GameExporter for MockApi {
+    async fn get_game_pgn(&self, game_id: &str) -> Result<String, ()> {
+      self.requests.lock().unwrap().push(game_id.to_string());
+      let mut failures = self.failures.lock().unwrap();
+      if *failures > 0 {
+        *failures -= 1;
+        return Err(());
+      }
+      Ok(String::from(PGN))
+    }
+  }
+
+  #[test]
+  fn test_import_game_into_book() {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+    let delay = Duration::from_millis(1);
+
+    // The export is not ready right away, we retry
+    let api = MockApi::new(1);
+    let mut imported: Option<String> = None;
+    assert!(rt.block_on(import_game_into_book(&api, "abcd1234", delay, |pgn| {
+                          imported = Some(pgn.to_string())
+                        })));
+    assert_eq!(Some(String::from(PGN)), imported);
+    assert_eq!(vec![String::from("abcd1234"); 2],
+               *api.requests.lock().unwrap());
+
+    // We give up after a few attempts
+    let api = MockApi::new(GAME_EXPORT_ATTEMPTS);
+    let mut imported = false;
+    assert!(!rt.block_on(import_game_into_book(&api, "abcd1234", delay, |_| imported = true)));
+    assert!(!imported);
+    assert_eq!(GAME_EXPORT_ATTEMPTS, api.requests.lock().unwrap().len());
+  }
+}
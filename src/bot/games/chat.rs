@@ -0,0 +1,213 @@
+use chess::engine::search_result::Variation;
+use chess::model::game_state::{render_line_san, GameState};
+use lichess::types::{ChatMessage, ChatRoom};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Maximum number of characters accepted by Lichess for a chat message
+pub const CHAT_MESSAGE_MAX_LENGTH: usize = 140;
+/// Minimum time between 2 replies in the chat of a game
+const CHAT_REPLY_INTERVAL: Duration = Duration::from_secs(5);
+/// Message sent when somebody asks for help in the chat
+pub const CHAT_HELP_MESSAGE: &str =
+  "Commands: !eval - current evaluation, !pv - best line I see, !help - this message";
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Commands that players or spectators can send in the chat of a game
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ChatCommand {
+  /// This represents !eval
+  Eval,
+  /// This represents !pv
+  Pv,
+  /// This represents !help
+  Help,
+}
+
+impl FromStr for ChatCommand {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "!eval" => Ok(ChatCommand::Eval),
+      "!pv" => Ok(ChatCommand::Pv),
+      "!help" => Ok(ChatCommand::Help),
+      _ => Err(()),
+    }
+  }
+}
+
+/// Keeps track of the chat of a game, to decide which messages we reply to.
+#[derive(Debug, Clone)]
+pub struct GameChat {
+  /// Our username, our own messages are ignored
+  username:           String,
+  /// Whether we reply to commands sent in the player room
+  pub player_room:    bool,
+  /// Whether we reply to commands sent in the spectator room
+  pub spectator_room: bool,
+  /// When we replied in the chat for the last time
+  last_reply:         Option<Instant>,
+}
+
+impl GameChat {
+  /// Creates a new game chat, replying in both rooms.
+  ///
+  /// ### Arguments
+  ///
+  /// * `username`: Our Lichess username
+  pub fn new(username: &str) -> Self {
+    GameChat { username:       username.to_string(),
+               player_room:    true,
+               spectator_room: true,
+               last_reply:     None, }
+  }
+
+  /// Checks if an incoming chat message is a command that we should reply to.
+  /// Calling this function counts as a reply for the rate limit if a command
+  /// is returned.
+  ///
+  /// ### Arguments
+  ///
+  /// * `message`: Incoming chat message
+  /// * `now`:     Time at which the message is handled
+  ///
+  /// ### Return value
+  ///
+  /// The command to reply to, None if the message should be ignored.
+  pub fn get_command(&mut self, message: &ChatMessage, now: Instant) -> Option<ChatCommand> {
+    if message.username.eq_ignore_ascii_case(&self.username) {
+      return None;
+    }
+
+    let room_enabled = match message.room {
+      ChatRoom::Player => self.player_room,
+      ChatRoom::Spectator => self.spectator_room,
+    };
+    if !room_enabled {
+      return None;
+    }
+
+    let command = message.text.parse::<ChatCommand>().ok()?;
+
+    if let Some(last_reply) = self.last_reply {
+      if now.duration_since(last_reply) < CHAT_REPLY_INTERVAL {
+        return None;
+      }
+    }
+    self.last_reply = Some(now);
+
+    Some(command)
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Helper functions
+
+/// Formats an evaluation for the chat, from White's point of view.
+///
+/// ### Arguments
+///
+/// * `eval`: Evaluation returned by the engine
+pub fn format_eval(eval: f32) -> String {
+  if eval.abs() > 100.0 {
+    let side = if eval > 0.0 { "White" } else { "Black" };
+    format!("{} mates in {} half-moves",
+            side,
+            (200.0 - eval.abs()) as isize)
+  } else {
+    format!("{:+.2}", eval)
+  }
+}
+
+/// Converts a variation to SAN notation, starting from a position.
+///
+/// ### Arguments
+///
+/// * `game_state`: Position from which the variation starts
+/// * `variation`:  List of moves to convert
+pub fn variation_to_san(game_state: &GameState, variation: &Variation) -> String {
+  render_line_san(game_state, &variation.to_vec()).join(" ")
+}
+
+/// Shortens a message so that it fits in the chat, cutting it after the last
+/// complete word.
+///
+/// ### Arguments
+///
+/// * `message`: Message to shorten
+pub fn truncate_message(message: &str) -> String {
+  if message.chars().count() <= CHAT_MESSAGE_MAX_LENGTH {
+    return message.to_string();
+  }
+
+  let mut truncated: String = message.chars().take(CHAT_MESSAGE_MAX_LENGTH - 3).collect();
+  if let Some(index) = truncated.rfind(' ') {
+    truncated.truncate(index);
+  }
+  truncated.push_str("...");
+  truncated
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn chat_message(username: &str, room: ChatRoom, text: &str) -> ChatMessage {
+    ChatMessage { room,
+                  text: text.to_string(),
+                  message_type: String::from("chatLine"),
+                  username: username.to_string() }
+  }
+
+  #[test]
+  fn test_eval_command_triggers_one_reply() {
+    let mut chat = GameChat::new("schnecken_bot");
+    let now = Instant::now();
+    let message = chat_message("opponent", ChatRoom::Player, "!eval");
+
+    assert_eq!(Some(ChatCommand::Eval), chat.get_command(&message, now));
+    // Same command right after is rate limited
+    assert_eq!(None,
+               chat.get_command(&message, now + Duration::from_secs(1)));
+    assert_eq!(Some(ChatCommand::Eval),
+               chat.get_command(&message, now + CHAT_REPLY_INTERVAL));
+  }
+
+  #[test]
+  fn test_ignored_chat_messages() {
+    let mut chat = GameChat::new("schnecken_bot");
+    chat.spectator_room = false;
+    let now = Instant::now();
+
+    let message = chat_message("Schnecken_Bot", ChatRoom::Player, "!help");
+    assert_eq!(None, chat.get_command(&message, now));
+    let message = chat_message("spectator", ChatRoom::Spectator, "!help");
+    assert_eq!(None, chat.get_command(&message, now));
+    let message = chat_message("opponent", ChatRoom::Player, "good luck!");
+    assert_eq!(None, chat.get_command(&message, now));
+    let message = chat_message("opponent", ChatRoom::Player, "!PV");
+    assert_eq!(Some(ChatCommand::Pv), chat.get_command(&message, now));
+  }
+
+  #[test]
+  fn test_truncate_message() {
+    let short = "Best line: e4 e5 Nf3";
+    assert_eq!(short, truncate_message(short));
+
+    let long = "Best line: ".to_string() + "Nf3 Nf6 Ng1 Ng8 ".repeat(20).as_str();
+    let truncated = truncate_message(&long);
+    assert!(truncated.chars().count() <= CHAT_MESSAGE_MAX_LENGTH);
+    assert!(truncated.ends_with("..."));
+    // Cut after a complete move
+    assert!(long.contains(format!("{} ", truncated.trim_end_matches("...")).as_str()));
+  }
+}
@@ -0,0 +1,357 @@
+use super::game_log::GAME_LOG_DIRECTORY;
+use chess::engine::Engine;
+use chess::model::board::Board;
+use lichess::traits::GamePlayer;
+use lichess::types::{Color, GameStart};
+use log::*;
+use serde_json::Value as JsonValue;
+use std::path::{Path, PathBuf};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Interval at which we look for correspondence games where it is our turn
+pub const CORRESPONDENCE_POLL_INTERVAL_S: u64 = 300;
+/// File in which we remember the positions on which we already played, in the
+/// game log directory
+const CORRESPONDENCE_STATE_FILE: &str = "correspondence_games.txt";
+/// Fraction of the time left for the move that we spend searching
+const CORRESPONDENCE_TIME_FRACTION: usize = 100;
+/// Maximum search time per move in correspondence games
+const CORRESPONDENCE_MAX_SEARCH_TIME_MS: usize = 60_000;
+/// Minimum search time per move in correspondence games
+const CORRESPONDENCE_MIN_SEARCH_TIME_MS: usize = 1_000;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Correspondence games survive restarts of the bot, so we keep on disk the
+/// last position on which we played in each game. If Lichess still reports
+/// that it is our turn in that position, the move is in flight and we must not
+/// play again.
+#[derive(Debug, Clone)]
+pub struct CorrespondenceState {
+  /// File in which the state is saved
+  path:       PathBuf,
+  /// Game ID and FEN on which we played our last move
+  last_moves: Vec<(String, String)>,
+}
+
+impl CorrespondenceState {
+  /// Loads the state from the default location.
+  pub fn new() -> Self {
+    Self::load(&Path::new(GAME_LOG_DIRECTORY).join(CORRESPONDENCE_STATE_FILE))
+  }
+
+  /// Loads the state from a file. A missing or unreadable file gives an empty
+  /// state.
+  ///
+  /// ### Arguments
+  ///
+  /// * `path`: File in which the state is saved
+  pub fn load(path: &Path) -> Self {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let last_moves = content.lines()
+                            .filter_map(|line| line.split_once(' '))
+                            .map(|(id, fen)| (id.to_string(), fen.to_string()))
+                            .collect();
+    CorrespondenceState { path: path.to_path_buf(),
+                          last_moves }
+  }
+
+  /// Writes the state in its file
+  fn save(&self) {
+    let content: String = self.last_moves.iter().map(|(id, fen)| format!("{id} {fen}\n")).collect();
+    if let Some(directory) = self.path.parent() {
+      let _ = std::fs::create_dir_all(directory);
+    }
+    if let Err(error) = std::fs::write(&self.path, content) {
+      warn!("Cannot save the correspondence games in {}: {}",
+            self.path.display(),
+            error);
+    }
+  }
+
+  /// Checks if we already played a move on a position
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id`: Lichess Game ID
+  /// * `fen`:     Position reported by Lichess
+  pub fn has_played_on(&self, game_id: &str, fen: &str) -> bool {
+    self.last_moves.iter().any(|(id, last_fen)| id == game_id && last_fen == fen)
+  }
+
+  /// Records that we played a move on a position, and saves the state.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id`: Lichess Game ID
+  /// * `fen`:     Position on which we played
+  pub fn record_move(&mut self, game_id: &str, fen: &str) {
+    self.last_moves.retain(|(id, _)| id != game_id);
+    self.last_moves.push((game_id.to_string(), fen.to_string()));
+    self.save();
+  }
+
+  /// Forgets the games that are not ongoing anymore.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_ids`: IDs of the ongoing games
+  fn retain_games(&mut self, game_ids: &[String]) {
+    let length = self.last_moves.len();
+    self.last_moves.retain(|(id, _)| game_ids.contains(id));
+    if self.last_moves.len() != length {
+      self.save();
+    }
+  }
+}
+
+impl Default for CorrespondenceState {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Decides how much time we spend on a correspondence move.
+///
+/// ### Arguments
+///
+/// * `seconds_left`: Time left for the move, in seconds
+///
+/// ### Return value
+///
+/// Search time, in milliseconds
+pub fn correspondence_search_time(seconds_left: usize) -> usize {
+  (seconds_left * 1000 / CORRESPONDENCE_TIME_FRACTION).clamp(CORRESPONDENCE_MIN_SEARCH_TIME_MS,
+                                                             CORRESPONDENCE_MAX_SEARCH_TIME_MS)
+}
+
+/// Lichess only gives the piece placement of the position of ongoing games.
+/// Completes it into a full FEN: the side to play is known, castling rights
+/// are assumed when the king and rooks are on their initial squares and en
+/// passant is not available.
+///
+/// ### Arguments
+///
+/// * `fen`:          FEN reported by Lichess, full or piece placement only
+/// * `side_to_play`: Color of the side to play
+///
+/// ### Return value
+///
+/// Full FEN of the position
+pub fn complete_fen(fen: &str, side_to_play: Color) -> String {
+  if fen.trim().contains(' ') {
+    return fen.trim().to_string();
+  }
+
+  let ranks: Vec<String> = fen.split('/').map(expand_rank).collect();
+  let piece_at =
+    |rank: usize, file: usize| ranks.get(8 - rank).and_then(|r| r.chars().nth(file)).unwrap_or('1');
+  let mut castling = String::new();
+  for (king, rook, rank, rook_file, right) in [('K', 'R', 1, 7, 'K'),
+                                               ('K', 'R', 1, 0, 'Q'),
+                                               ('k', 'r', 8, 7, 'k'),
+                                               ('k', 'r', 8, 0, 'q')]
+  {
+    if piece_at(rank, 4) == king && piece_at(rank, rook_file) == rook {
+      castling.push(right);
+    }
+  }
+  if castling.is_empty() {
+    castling.push('-');
+  }
+
+  let side = match side_to_play {
+    Color::White => 'w',
+    Color::Black => 'b',
+  };
+  format!("{fen} {side} {castling} - 0 1")
+}
+
+/// Replaces the digits of a FEN rank by as many empty squares
+fn expand_rank(rank: &str) -> String {
+  rank.chars()
+      .map(|c| match c.to_digit(10) {
+        Some(empty) => "1".repeat(empty as usize),
+        None => c.to_string(),
+      })
+      .collect()
+}
+
+/// Reads the games listed in the reply of the `account/playing` endpoint
+///
+/// ### Arguments
+///
+/// * `json`: Reply of the API
+pub fn parse_ongoing_games(json: &JsonValue) -> Vec<GameStart> {
+  let games = json["nowPlaying"].as_array().cloned().unwrap_or_default();
+  games.into_iter()
+       .filter_map(|game| match serde_json::from_value::<GameStart>(game) {
+         Ok(game) => Some(game),
+         Err(error) => {
+           warn!("Error deserializing an ongoing game: {:?}", error);
+           None
+         },
+       })
+       .collect()
+}
+
+/// Looks for the correspondence games where it is our turn and plays a move in
+/// each of them. Real-time games are streamed and played by their game loop,
+/// they are ignored here.
+///
+/// ### Arguments
+///
+/// * `api`:   API used to list the games and make the moves
+/// * `state`: Positions on which we already played
+///
+/// ### Return value
+///
+/// Number of moves played
+pub async fn play_correspondence_games<T: GamePlayer>(api: &T,
+                                                      state: &mut CorrespondenceState)
+                                                      -> usize {
+  let json = match api.get_ongoing_games().await {
+    Ok(json) => json,
+    Err(()) => {
+      warn!("Cannot look up our ongoing games");
+      return 0;
+    },
+  };
+
+  let games: Vec<GameStart> =
+    parse_ongoing_games(&json).into_iter().filter(|game| game.is_correspondence()).collect();
+  state.retain_games(&games.iter().map(|game| game.game_id.clone()).collect::<Vec<String>>());
+
+  let mut moves_played = 0;
+  for game in games.iter().filter(|game| game.is_my_turn) {
+    let fen = game.fen.clone().unwrap_or_default();
+    if state.has_played_on(&game.game_id, &fen) {
+      debug!("Already played on {} in correspondence game {}",
+             fen, game.game_id);
+      continue;
+    }
+
+    let full_fen = complete_fen(&fen, game.color);
+    if let Err(error) = Board::try_from_fen(&full_fen) {
+      error!("Correspondence game {} has an invalid position {}: {}",
+             game.game_id, full_fen, error);
+      continue;
+    }
+
+    let mut engine = Engine::new(false);
+    engine.set_position(&full_fen);
+    engine.set_search_time_limit(correspondence_search_time(game.seconds_left));
+    engine.go();
+
+    let mv = match engine.get_best_move() {
+      Some(mv) => mv,
+      None => {
+        error!("No move found for correspondence game {}", game.game_id);
+        continue;
+      },
+    };
+    info!("Playing {} in correspondence game {} - eval: {:?}",
+          mv,
+          game.game_id,
+          engine.get_eval());
+    if api.make_move(&game.game_id, &mv.to_string(), false).await {
+      state.record_move(&game.game_id, &fen);
+      moves_played += 1;
+    }
+  }
+
+  moves_played
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+
+  /// Replies with a fixed list of ongoing games and records our moves instead
+  /// of calling Lichess
+  struct MockApi {
+    ongoing_games: JsonValue,
+    moves:         Mutex<Vec<(String, String)>>,
+  }
+
+  impl GamePlayer for MockApi {
+    async fn get_ongoing_games(&self) -> Result<JsonValue, ()> {
+      Ok(self.ongoing_games.clone())
+    }
+
+    async fn make_move(&self, game_id: &str, chess_move: &str, _offer_draw: bool) -> bool {
+      self.moves.lock().unwrap().push((game_id.to_string(), chess_move.to_string()));
+      true
+    }
+  }
+
+  /// Game listed by the `account/playing` endpoint, after 1.e4
+  const ONGOING_GAME: &str = r#"{"gameId":"GAME_ID","fullId":"GAME_IDabcd","color":"black",
+    "fen":"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR","hasMoved":false,
+    "isMyTurn":MY_TURN,"lastMove":"e2e4","source":"friend","speed":"SPEED",
+    "perf":"SPEED","rated":false,"secondsLeft":100,
+    "status":{"id":20,"name":"started"},"variant":{"key":"standard","name":"Standard"},
+    "opponent":{"id":"nobriot","username":"nobriot","rating":1500}}"#;
+
+  fn ongoing_game(id: &str, speed: &str, is_my_turn: bool) -> String {
+    ONGOING_GAME.replace("GAME_ID", id)
+                .replace("SPEED", speed)
+                .replace("MY_TURN", &is_my_turn.to_string())
+  }
+
+  #[test]
+  fn test_correspondence_game_on_our_turn_gets_a_move() {
+    let payload = format!(r#"{{"nowPlaying":[{},{},{}]}}"#,
+                          ongoing_game("corres01", "correspondence", true),
+                          ongoing_game("corres02", "correspondence", false),
+                          ongoing_game("blitz001", "blitz", true));
+    let api = MockApi { ongoing_games: serde_json::from_str(&payload).unwrap(),
+                        moves:         Mutex::new(Vec::new()), };
+
+    let path = std::env::temp_dir().join(format!("schnecken_corres_{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut state = CorrespondenceState::load(&path);
+
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    assert_eq!(1, rt.block_on(play_correspondence_games(&api, &mut state)));
+    let moves = api.moves.lock().unwrap().clone();
+    assert_eq!(1, moves.len());
+    assert_eq!("corres01", moves[0].0);
+    let board = Board::from_fen(&complete_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR",
+                                              Color::Black));
+    assert!(board.get_moves().iter().any(|m| m.to_string() == moves[0].1));
+
+    // After a restart, Lichess still reports the same position: we do not play
+    // twice
+    let mut state = CorrespondenceState::load(&path);
+    assert_eq!(0, rt.block_on(play_correspondence_games(&api, &mut state)));
+    assert_eq!(1, api.moves.lock().unwrap().len());
+    let _ = std::fs::remove_file(&path);
+  }
+
+  #[test]
+  fn test_complete_fen() {
+    assert_eq!("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+               complete_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR",
+                            Color::Black));
+    assert_eq!("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1",
+               complete_fen("r3k3/8/8/8/8/8/8/4K2R", Color::White));
+    assert_eq!("8/8/8/8/8/8/8/K6k w - - 0 1",
+               complete_fen("8/8/8/8/8/8/8/K6k", Color::White));
+    assert_eq!("8/8/8/8/8/8/8/K6k b - - 3 40",
+               complete_fen("8/8/8/8/8/8/8/K6k b - - 3 40", Color::White));
+
+    assert_eq!(1_000, correspondence_search_time(0));
+    assert_eq!(60_000, correspondence_search_time(172_800));
+  }
+}
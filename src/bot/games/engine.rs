@@ -1,43 +1,155 @@
+use chess::engine::config::options::EngineOptions;
 use chess::engine::config::play_style::PlayStyle;
 use chess::engine::Engine;
-use chess::model::game_state::START_POSITION_FEN;
+use chess::model::board::Board;
+use chess::model::game_state::{GameStatus, START_POSITION_FEN};
+use chess::model::moves::Move;
 use lichess;
-use lichess::types::{GameStart, Title};
+use lichess::traits::{GamePlayer, GameTerminator};
+use lichess::types::{Color, GameStart, GameState, Title};
 use log::*;
+use std::cmp::min;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Below this remaining time on our clock, we switch to panic mode and just
+/// try not to lose on time
+pub const PANIC_MODE_TIME_THRESHOLD_MS: usize = 10_000;
+/// Maximum search time per move in panic mode
+pub const PANIC_MODE_SEARCH_TIME_MS: usize = 200;
+/// Maximum search depth in panic mode
+pub const PANIC_MODE_MAX_DEPTH: usize = 4;
+/// Increments above this value are not taken into account
+const MAX_INCREMENT_MS: usize = 60_000;
+/// Number of plies after leaving the book during which we vary our moves, so
+/// that opponents cannot prepare against us
+const BOOK_EXIT_RANDOMNESS_PLIES: usize = 6;
+/// Evaluation we are ready to give away to vary our moves after the book, in
+/// centipawns
+const BOOK_EXIT_MARGIN_CP: usize = 20;
+/// Number of times we rebuild the position and play a new move when Lichess
+/// refuses our moves
+const MAX_RESYNC_ATTEMPTS: usize = 3;
+/// Search time for a move computed after rebuilding the position
+const RESYNC_SEARCH_TIME_MS: usize = 500;
+/// Depth of the quick search looking for an obvious move before the real
+/// search
+const OBVIOUS_MOVE_SEARCH_DEPTH: usize = 5;
+/// Maximum time of the quick search looking for an obvious move
+const OBVIOUS_MOVE_SEARCH_TIME_MS: usize = 100;
+/// The best move is obvious when it is better than the second best move by at
+/// least this margin, in pawns, e.g. recapturing a piece
+const OBVIOUS_MOVE_MARGIN: f32 = 2.0;
+/// Share of the allocated time we spend on an obvious move
+const OBVIOUS_MOVE_TIME_DIVIDER: usize = 8;
+/// Minimum search time, even with only one legal move: we still search to see
+/// what comes next, and obvious moves can be traps
+const MIN_SEARCH_TIME_MS: usize = 50;
+/// Share of the allocated time we add at most from the time saved on obvious
+/// moves
+const BANKED_TIME_DIVIDER: usize = 2;
+/// We accept draw offers when our evaluation is at or below this value, in
+/// pawns
+const DRAW_ACCEPT_MAX_EVAL: f32 = -0.5;
+/// We offer a draw when fewer half-moves than this are left before the
+/// fifty-move rule applies
+const FIFTY_MOVE_DRAW_OFFER_PLIES: u8 = 20;
+/// Largest absolute evaluation, in pawns, for which we consider the position
+/// equal when offering a draw
+const DRAW_OFFER_MAX_EVAL: f32 = 0.3;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Berserk flags of an arena game, from our point of view.
+/// Going berserk halves the clock of a player, for a bonus point if they win.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Berserk {
+  /// We went berserk
+  pub us:       bool,
+  /// Our opponent went berserk
+  pub opponent: bool,
+}
+
+impl Berserk {
+  /// Reads the berserk flags from the game state
+  ///
+  /// ### Arguments
+  ///
+  /// * `game`:  Game state received from Lichess
+  /// * `color`: Color we are playing
+  pub fn from_game_state(game: &GameState, color: Color) -> Self {
+    let opponent_color = match color {
+      Color::White => Color::Black,
+      Color::Black => Color::White,
+    };
+    Berserk { us:       game.has_berserked(color),
+              opponent: game.has_berserked(opponent_color), }
+  }
+}
+
+/// How hard it is to choose our move, which decides how long we search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveChoice {
+  /// There is only one legal move
+  OnlyMove,
+  /// One move is much better than all the others, e.g. a recapture
+  Obvious,
+  /// We have to search to choose between several moves
+  Open,
+}
+
+// -----------------------------------------------------------------------------
+// Functions
 
 /// Looks at the game data (which kind of opponent, time control, start
 /// position) and configures the engine accordingly.
-pub fn configure_engine(game: &GameStart) -> Engine {
+///
+/// ### Arguments
+///
+/// * `game`:    Game start data received from Lichess
+/// * `options`: Engine options to start from, adjusted to the opponent
+pub fn configure_engine(game: &GameStart, options: &EngineOptions) -> Engine {
   // We are not using the uci interface internally
   let mut engine = Engine::new(false);
+  engine.options = EngineOptions { uci: false,
+                                   ..options.clone() };
+  if let Some(seed) = options.seed {
+    engine.set_seed(seed);
+  }
 
   // Cache table is kinda always the same value, regardless of the game, and
   // opponent
   engine.resize_cache_tables(1024);
 
   // Configure the start position
-  let start_fen = game.fen.as_deref().unwrap_or(START_POSITION_FEN);
-  engine.set_position(&start_fen);
+  let start_fen = initial_position_fen(&game.game_id,
+                                       game.fen.as_deref().unwrap_or(START_POSITION_FEN));
+  engine.set_position(start_fen);
+
+  engine.options.book_exit_randomness_plies = BOOK_EXIT_RANDOMNESS_PLIES;
+  engine.options.book_exit_margin_cp = BOOK_EXIT_MARGIN_CP;
 
   // Adjust the level of difficulty based on the rating of the opponent, if they
   // are human
   if !game.opponent_is_bot() && game.opponent.title.is_none() {
     if game.opponent.rating < 1300 {
-      engine.options.max_depth = 1;
-      engine.options.play_style = PlayStyle::Provocative;
+      engine.set_maximum_depth(1);
+      engine.set_play_style(PlayStyle::Provocative);
     } else if game.opponent.rating < 1500 {
-      engine.options.max_depth = 2;
-      engine.options.play_style = PlayStyle::Provocative;
+      engine.set_maximum_depth(2);
+      engine.set_play_style(PlayStyle::Provocative);
     } else if game.opponent.rating < 1700 {
-      engine.options.max_depth = 3;
-      engine.options.play_style = PlayStyle::Provocative;
+      engine.set_maximum_depth(3);
+      engine.set_play_style(PlayStyle::Provocative);
     } else if game.opponent.rating < 1800 {
-      engine.options.max_depth = 4;
-      engine.options.play_style = PlayStyle::Provocative;
+      engine.set_maximum_depth(4);
+      engine.set_play_style(PlayStyle::Provocative);
     } else if game.opponent.rating < 1900 {
-      engine.options.max_depth = 5;
+      engine.set_maximum_depth(5);
     } else if game.opponent.rating < 2000 {
-      engine.options.max_depth = 6;
+      engine.set_maximum_depth(6);
     }
   }
 
@@ -46,3 +158,970 @@ pub fn configure_engine(game: &GameStart) -> Engine {
 
   engine
 }
+
+/// Finds the position a game starts from. Lichess reports "startpos" for
+/// standard games, and the FEN of the position for "From Position" games.
+///
+/// ### Arguments
+///
+/// * `game_id`:     Game ID, used for logging
+/// * `initial_fen`: Initial FEN reported by Lichess
+///
+/// ### Return value
+///
+/// FEN of the start position. The standard start position if the FEN is
+/// invalid.
+pub fn initial_position_fen<'a>(game_id: &str, initial_fen: &'a str) -> &'a str {
+  if initial_fen == "startpos" {
+    return START_POSITION_FEN;
+  }
+  if let Err(e) = Board::try_from_fen(initial_fen) {
+    error!("Game {} has an invalid start position {}: {}",
+           game_id, initial_fen, e);
+    return START_POSITION_FEN;
+  }
+  initial_fen
+}
+
+/// Sets the position of the engine to the start position of a game, followed
+/// by all the moves played so far.
+///
+/// ### Arguments
+///
+/// * `engine`:    Engine of the game
+/// * `start_fen`: FEN of the start position
+/// * `moves`:     Moves played since the start position, separated by spaces
+pub fn set_up_game_position(engine: &mut Engine, start_fen: &str, moves: &str) {
+  engine.set_position(start_fen);
+  for m in moves.split_whitespace() {
+    engine.apply_move(m);
+  }
+}
+
+/// Finds the color we have to move with in a position. When we play against
+/// ourselves, we move for both sides.
+///
+/// ### Arguments
+///
+/// * `our_colors`:   Colors we play in the game
+/// * `side_to_play`: Side to play in the current position
+///
+/// ### Return value
+///
+/// The color we have to move with, None if it is not our turn
+pub fn color_to_move(our_colors: &[Color],
+                     side_to_play: chess::model::piece::Color)
+                     -> Option<Color> {
+  let color = match side_to_play {
+    chess::model::piece::Color::White => Color::White,
+    chess::model::piece::Color::Black => Color::Black,
+  };
+  our_colors.contains(&color).then_some(color)
+}
+
+/// Checks if we are running so low on time that we should switch to panic
+/// mode and play shallow searches.
+///
+/// ### Arguments
+///
+/// * `time_left_ms`: Time left on our clock, in milliseconds
+pub fn is_panic_mode(time_left_ms: usize) -> bool {
+  time_left_ms < PANIC_MODE_TIME_THRESHOLD_MS
+}
+
+/// Decides how much time we spend on the next move.
+///
+/// ### Arguments
+///
+/// * `time_left_ms`: Time left on our clock, in milliseconds
+/// * `increment_ms`: Increment per move, in milliseconds
+/// * `berserk`:      Berserk flags of the game
+///
+/// ### Return value
+///
+/// Search time for the next move, in milliseconds
+pub fn allocate_search_time(time_left_ms: usize, increment_ms: usize, berserk: Berserk) -> usize {
+  if is_panic_mode(time_left_ms) {
+    // Note: 0 would mean no time limit for the engine
+    return min(PANIC_MODE_SEARCH_TIME_MS, time_left_ms / 10).max(1);
+  }
+
+  let increment_ms = min(increment_ms, MAX_INCREMENT_MS);
+  let search_time_ms = (time_left_ms / 90) + increment_ms * 10 / 9;
+
+  // Berserk is only possible in arena games without increment
+  if increment_ms > 0 {
+    return search_time_ms;
+  }
+  if berserk.us {
+    // We gave up half of our clock, play much faster
+    search_time_ms / 2
+  } else if berserk.opponent {
+    // Keep the pressure on the reduced clock of the opponent
+    search_time_ms * 3 / 4
+  } else {
+    search_time_ms
+  }
+}
+
+/// Runs a quick search to find out if the best move is obvious, so that we do
+/// not spend our full time on it.
+///
+/// ### Arguments
+///
+/// * `engine`: Engine of the game, set to the position to search. Its search
+///   options are left unchanged.
+///
+/// ### Return value
+///
+/// How hard it is to choose the move in the position
+pub fn find_move_choice(engine: &mut Engine) -> MoveChoice {
+  let move_count = engine.position.get_moves().len();
+  if move_count == 1 {
+    return MoveChoice::OnlyMove;
+  }
+  if move_count == 0 {
+    return MoveChoice::Open;
+  }
+
+  let (max_depth, multi_pv, max_search_time) =
+    (engine.options.max_depth, engine.options.multi_pv, engine.options.max_search_time);
+  engine.set_maximum_depth(OBVIOUS_MOVE_SEARCH_DEPTH);
+  engine.set_multipv(2);
+  engine.set_search_time_limit(OBVIOUS_MOVE_SEARCH_TIME_MS);
+  engine.go();
+  engine.set_maximum_depth(max_depth);
+  engine.set_multipv(multi_pv);
+  engine.set_search_time_limit(max_search_time);
+
+  let analysis = engine.get_analysis();
+  if analysis.variations.len() < 2 {
+    return MoveChoice::Open;
+  }
+  if (analysis.variations[0].eval - analysis.variations[1].eval).abs() >= OBVIOUS_MOVE_MARGIN {
+    MoveChoice::Obvious
+  } else {
+    MoveChoice::Open
+  }
+}
+
+/// Adjusts the search time to how hard the move is to find: we play obvious
+/// moves quickly and bank the time saved for the next positions where we have
+/// to think. We always search a little, in case the obvious move is a trap.
+///
+/// ### Arguments
+///
+/// * `search_time_ms`: Search time allocated for the move, in milliseconds
+/// * `choice`:         How hard it is to choose the move
+/// * `banked_time_ms`: Time saved on the previous obvious moves, updated with
+///   the time saved or spent on this move
+///
+/// ### Return value
+///
+/// Search time for the move, in milliseconds
+pub fn smooth_search_time(search_time_ms: usize,
+                          choice: MoveChoice,
+                          banked_time_ms: &mut usize)
+                          -> usize {
+  let smoothed_time_ms = match choice {
+    MoveChoice::OnlyMove => min(MIN_SEARCH_TIME_MS, search_time_ms),
+    MoveChoice::Obvious => {
+      min((search_time_ms / OBVIOUS_MOVE_TIME_DIVIDER).max(MIN_SEARCH_TIME_MS),
+          search_time_ms)
+    },
+    MoveChoice::Open => {
+      let bonus_ms = min(*banked_time_ms, search_time_ms / BANKED_TIME_DIVIDER);
+      *banked_time_ms -= bonus_ms;
+      return search_time_ms + bonus_ms;
+    },
+  };
+
+  *banked_time_ms += search_time_ms - smoothed_time_ms;
+  smoothed_time_ms
+}
+
+/// Limits the search depth of the engine in panic mode.
+///
+/// ### Arguments
+///
+/// * `max_depth`: Maximum depth configured for the game, 0 for no limit
+///
+/// ### Return value
+///
+/// Maximum depth to use in panic mode
+pub fn panic_mode_max_depth(max_depth: usize) -> usize {
+  if max_depth == 0 {
+    PANIC_MODE_MAX_DEPTH
+  } else {
+    min(max_depth, PANIC_MODE_MAX_DEPTH)
+  }
+}
+
+/// Claims the draw when the position is drawn by insufficient material or the
+/// fifty-move rule, so that we do not keep spending clock time searching a
+/// dead position.
+///
+/// ### Arguments
+///
+/// * `api`:     API used to claim the draw
+/// * `engine`:  Engine of the game, stopped if the position is drawn
+/// * `game_id`: Lichess Game ID
+///
+/// ### Return value
+///
+/// True if the position is drawn and we claimed the draw
+pub async fn claim_draw_if_drawn<T: GameTerminator>(api: &T,
+                                                    engine: &Engine,
+                                                    game_id: &str)
+                                                    -> bool {
+  if engine.position.game_status() != GameStatus::Draw {
+    return false;
+  }
+
+  info!("Drawn position in game {}, claiming the draw", game_id);
+  engine.stop();
+  if api.claim_draw(game_id).await.is_err() {
+    warn!("Could not claim the draw in game {}", game_id);
+  }
+  true
+}
+
+/// Claims the draw when the current position occurred for the third time in
+/// the game and we are not better. On Lichess, threefold repetitions have to be
+/// claimed, otherwise the game goes on.
+/// When we are winning, or when we do not have any evaluation yet, we do not
+/// claim the draw and play on instead.
+///
+/// ### Arguments
+///
+/// * `api`:     API used to claim the draw
+/// * `engine`:  Engine of the game, stopped if we claim the draw
+/// * `game_id`: Lichess Game ID
+/// * `color`:   Color we are playing
+/// * `eval`:    Last evaluation of the engine, from White's point of view. None
+///   if we do not have any evaluation for the game
+///
+/// ### Return value
+///
+/// True if the position is repeated three times and we claimed the draw
+pub async fn claim_draw_if_repetition<T: GameTerminator>(api: &T,
+                                                         engine: &Engine,
+                                                         game_id: &str,
+                                                         color: Color,
+                                                         eval: Option<f32>)
+                                                         -> bool {
+  if engine.position.game_status() != GameStatus::ThreeFoldRepetition {
+    return false;
+  }
+
+  let Some(eval) = eval else {
+    info!("Threefold repetition in game {}, but we have no evaluation, playing on",
+          game_id);
+    return false;
+  };
+  let our_eval = match color {
+    Color::White => eval,
+    Color::Black => -eval,
+  };
+  if our_eval > 0.0 {
+    info!("Threefold repetition in game {}, but we are better ({}), playing on",
+          game_id, our_eval);
+    return false;
+  }
+
+  info!("Threefold repetition in game {}, claiming the draw",
+        game_id);
+  engine.stop();
+  if api.claim_draw(game_id).await.is_err() {
+    warn!("Could not claim the draw in game {}", game_id);
+  }
+  true
+}
+
+/// Restricts the search to the moves reaching a position that did not occur
+/// yet in the game when we are better, so that we do not walk into a
+/// repetition that the opponent can claim. The restriction is lifted
+/// otherwise, or if all the moves repeat a position.
+///
+/// ### Arguments
+///
+/// * `engine`: Engine of the game, about to search for our move
+/// * `color`:  Color we are playing
+/// * `eval`:   Last evaluation of the engine, from White's point of view. None
+///   if we do not have any evaluation for the game
+///
+/// ### Return value
+///
+/// True if the search is restricted to the moves avoiding repetitions
+pub fn avoid_repetitions(engine: &mut Engine, color: Color, eval: Option<f32>) -> bool {
+  engine.options.search_moves.clear();
+  let Some(eval) = eval else {
+    return false;
+  };
+  let our_eval = match color {
+    Color::White => eval,
+    Color::Black => -eval,
+  };
+  if our_eval <= 0.0 {
+    return false;
+  }
+
+  let moves = engine.position.get_moves();
+  let new_positions: Vec<Move> =
+    moves.iter()
+         .filter(|m| engine.position.with_move(m).get_board_repetitions() == 0)
+         .copied()
+         .collect();
+  if new_positions.is_empty() || new_positions.len() == moves.len() {
+    return false;
+  }
+
+  engine.options.search_moves = new_positions;
+  true
+}
+
+/// Checks if the fifty-move rule is about to draw the game, i.e. if we are in
+/// the range where we offer draws.
+///
+/// ### Arguments
+///
+/// * `engine`: Engine of the game
+///
+/// ### Return value
+///
+/// True if only a few reversible moves are left before the fifty-move rule
+pub fn is_near_fifty_move_draw(engine: &Engine) -> bool {
+  engine.position.moves_until_fifty_move_draw() < FIFTY_MOVE_DRAW_OFFER_PLIES
+}
+
+/// Offers a draw when the fifty-move rule is about to apply and the position is
+/// equal, rather than shuffling pieces until the end.
+/// We do not offer it when we are better, as we would withdraw it right away.
+///
+/// ### Arguments
+///
+/// * `api`:     API used to offer the draw
+/// * `engine`:  Engine of the game
+/// * `game_id`: Lichess Game ID
+/// * `color`:   Color we are playing
+/// * `eval`:    Last evaluation of the engine, from White's point of view. None
+///   if we do not have any evaluation for the game
+///
+/// ### Return value
+///
+/// True if we offered a draw
+pub async fn offer_draw_near_fifty_move_rule<T: GameTerminator>(api: &T,
+                                                                engine: &Engine,
+                                                                game_id: &str,
+                                                                color: Color,
+                                                                eval: Option<f32>)
+                                                                -> bool {
+  let Some(eval) = eval else {
+    return false;
+  };
+  let our_eval = match color {
+    Color::White => eval,
+    Color::Black => -eval,
+  };
+  if !is_near_fifty_move_draw(engine) || !(-DRAW_OFFER_MAX_EVAL..=0.0).contains(&our_eval) {
+    return false;
+  }
+
+  info!("{} moves left before the fifty-move rule in game {}, offering a draw",
+        engine.position.moves_until_fifty_move_draw(),
+        game_id);
+  if api.claim_draw(game_id).await.is_err() {
+    warn!("Could not offer a draw in game {}", game_id);
+    return false;
+  }
+  true
+}
+
+/// Decides if we accept a draw offer: only when we are clearly worse.
+///
+/// ### Arguments
+///
+/// * `color`: Color we are playing
+/// * `eval`:  Current evaluation of the game, from White's point of view. None
+///   if we do not have any evaluation for the game
+///
+/// ### Return value
+///
+/// True if we should accept the draw
+pub fn should_accept_draw(color: Color, eval: Option<f32>) -> bool {
+  match (color, eval) {
+    (_, None) => false,
+    (Color::White, Some(eval)) => eval <= DRAW_ACCEPT_MAX_EVAL,
+    (Color::Black, Some(eval)) => -eval <= DRAW_ACCEPT_MAX_EVAL,
+  }
+}
+
+/// Reads the draw offer flags of the game state. Accepts or declines the draw
+/// offer of our opponent, and withdraws our own draw offer if we are now
+/// better.
+///
+/// ### Arguments
+///
+/// * `api`:     API used to accept or decline the draws
+/// * `game_id`: Lichess Game ID
+/// * `game`:    Game state received from Lichess
+/// * `color`:   Color we are playing
+/// * `eval`:    Current evaluation of the game, from White's point of view.
+///   None if we do not have any evaluation for the game
+///
+/// ### Return value
+///
+/// True if we accepted a draw offer
+pub async fn handle_draw_offers<T: GameTerminator>(api: &T,
+                                                   game_id: &str,
+                                                   game: &GameState,
+                                                   color: Color,
+                                                   eval: Option<f32>)
+                                                   -> bool {
+  let opponent_color = match color {
+    Color::White => Color::Black,
+    Color::Black => Color::White,
+  };
+
+  if game.offers_draw(opponent_color) {
+    if should_accept_draw(color, eval) {
+      info!("Accepting the draw offer in game {} - eval: {:?}",
+            game_id, eval);
+      if api.claim_draw(game_id).await.is_err() {
+        warn!("Could not accept the draw in game {}", game_id);
+      }
+      return true;
+    }
+    info!("Declining the draw offer in game {} - eval: {:?}",
+          game_id, eval);
+    if api.decline_draw(game_id).await.is_err() {
+      warn!("Could not decline the draw in game {}", game_id);
+    }
+  } else if game.offers_draw(color) {
+    let our_eval = match color {
+      Color::White => eval.unwrap_or(0.0),
+      Color::Black => -eval.unwrap_or(0.0),
+    };
+    if our_eval > 0.0 {
+      info!("Withdrawing our draw offer in game {}, we are better ({})",
+            game_id, our_eval);
+      if api.decline_draw(game_id).await.is_err() {
+        warn!("Could not withdraw our draw offer in game {}", game_id);
+      }
+    }
+  }
+  false
+}
+
+/// Plays a move. Lichess already retries the moves it fails to receive, so if
+/// it refuses the move, we are probably out of sync with the game: `resync`
+/// rebuilds the position and finds the move to play instead. Gives up after
+/// `MAX_RESYNC_ATTEMPTS`, or when the move found is the one refused.
+///
+/// ### Arguments
+///
+/// * `api`:        API used to play the moves
+/// * `game_id`:    Lichess Game ID
+/// * `chess_move`: Move we want to play
+/// * `resync`:     Rebuilds the position and returns the move to play, None if
+///   there is no move to play
+///
+/// ### Return value
+///
+/// True if Lichess accepted one of our moves
+pub async fn make_move_or_resync<T, F>(api: &T,
+                                       game_id: &str,
+                                       chess_move: Move,
+                                       mut resync: F)
+                                       -> bool
+  where T: GamePlayer,
+        F: FnMut() -> Option<Move>
+{
+  let mut chess_move = chess_move;
+  for attempt in 0..=MAX_RESYNC_ATTEMPTS {
+    if api.make_move(game_id, &chess_move.to_string(), false).await {
+      return true;
+    }
+    if attempt == MAX_RESYNC_ATTEMPTS {
+      break;
+    }
+
+    warn!("Move {} refused in game {}, rebuilding the position (attempt {}/{})",
+          chess_move,
+          game_id,
+          attempt + 1,
+          MAX_RESYNC_ATTEMPTS);
+    match resync() {
+      Some(mv) if mv != chess_move => chess_move = mv,
+      Some(_) => {
+        error!("Move {} is still our move in game {} after rebuilding the position",
+               chess_move, game_id);
+        return false;
+      },
+      None => {
+        info!("No move to play in game {} after rebuilding the position",
+              game_id);
+        return false;
+      },
+    }
+  }
+
+  error!("Lichess keeps refusing our moves in game {}", game_id);
+  false
+}
+
+/// Rebuilds the position of a game from the moves received from Lichess and
+/// searches it quickly, e.g. after Lichess refused our move. Blocks while
+/// searching, so it must be called from the game thread.
+///
+/// ### Arguments
+///
+/// * `engine`:     Engine of the game
+/// * `start_fen`:  FEN of the start position of the game
+/// * `moves`:      Moves played since the start position, separated by spaces
+/// * `our_colors`: Colors we play in the game
+///
+/// ### Return value
+///
+/// The move to play, None if it is not our turn or no move was found
+pub fn resync_game_position(engine: &mut Engine,
+                            start_fen: &str,
+                            moves: &str,
+                            our_colors: &[Color])
+                            -> Option<Move> {
+  set_up_game_position(engine, start_fen, moves);
+  color_to_move(our_colors, engine.position.board.side_to_play)?;
+
+  engine.set_search_time_limit(RESYNC_SEARCH_TIME_MS);
+  engine.go();
+  let mv = engine.get_best_move();
+  if mv.is_none() {
+    error!("No move found in position {}", engine.position.to_fen());
+  }
+  mv
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+
+  /// Records the games on which we claim or decline a draw instead of calling
+  /// Lichess
+  #[derive(Default)]
+  struct MockApi {
+    draw_claims:   Mutex<Vec<String>>,
+    draw_declines: Mutex<Vec<String>>,
+  }
+
+  impl GameTerminator for MockApi {
+    async fn abort_game(&self, _game_id: &str) -> Result<(), ()> {
+      Err(())
+    }
+
+    async fn resign_game(&self, _game_id: &str) -> Result<(), ()> {
+      Err(())
+    }
+
+    async fn claim_draw(&self, game_id: &str) -> Result<(), ()> {
+      self.draw_claims.lock().unwrap().push(game_id.to_string());
+      Ok(())
+    }
+
+    async fn decline_draw(&self, game_id: &str) -> Result<(), ()> {
+      self.draw_declines.lock().unwrap().push(game_id.to_string());
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_set_up_game_from_position() {
+    let payload = r#"{"type":"gameFull","id":"abcd1234","rated":false,
+      "variant":{"key":"fromPosition","name":"From Position","short":"FEN"},
+      "clock":{"initial":180000,"increment":2000},"speed":"blitz",
+      "initialFen":"8/8/4k3/8/8/3K4/4P3/8 b - - 0 40",
+      "state":{"type":"gameState","moves":"e6e5 d3e3","wtime":180000,"btime":180000,
+               "winc":2000,"binc":2000,"status":"started"}}"#;
+    let game: lichess::types::GameFull = serde_json::from_str(payload).unwrap();
+
+    let mut engine = Engine::new(false);
+    let start_fen = initial_position_fen(&game.id, &game.initial_fen);
+    set_up_game_position(&mut engine, start_fen, &game.state.moves);
+    assert_eq!("8/8/8/4k3/8/4K3/4P3/8 b - - 2 41", engine.position.to_fen());
+
+    // Standard games and invalid positions start from the initial position
+    assert_eq!(START_POSITION_FEN,
+               initial_position_fen(&game.id, "startpos"));
+    assert_eq!(START_POSITION_FEN,
+               initial_position_fen(&game.id, "8/8/8 w - - 0 1"));
+    set_up_game_position(&mut engine, START_POSITION_FEN, "e2e4 e7e5");
+    assert_eq!("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+               engine.position.to_fen());
+  }
+
+  #[test]
+  fn test_moving_for_both_sides_against_ourselves() {
+    let payload = r#"{"type":"gameFull","id":"abcd1234","rated":false,
+      "variant":{"key":"standard","name":"Standard","short":"Std"},
+      "clock":{"initial":180000,"increment":2000},"speed":"blitz",
+      "white":{"id":"schnecken_bot","name":"schnecken_bot","title":"BOT","rating":2000},
+      "black":{"id":"schnecken_bot","name":"schnecken_bot","title":"BOT","rating":2000},
+      "initialFen":"startpos",
+      "state":{"type":"gameState","moves":"","wtime":180000,"btime":180000,
+               "winc":2000,"binc":2000,"status":"started"}}"#;
+    let game: lichess::types::GameFull = serde_json::from_str(payload).unwrap();
+    let our_colors = game.colors_of("Schnecken_Bot");
+    assert_eq!(vec![Color::White, Color::Black], our_colors);
+
+    // We move for the side to play, every ply
+    let mut engine = Engine::new(false);
+    set_up_game_position(&mut engine, START_POSITION_FEN, &game.state.moves);
+    for (m, color) in [("e2e4", Color::White),
+                       ("e7e5", Color::Black),
+                       ("g1f3", Color::White),
+                       ("b8c6", Color::Black)]
+    {
+      assert_eq!(Some(color),
+                 color_to_move(&our_colors, engine.position.board.side_to_play));
+      engine.apply_move(m);
+    }
+
+    // Against someone else, we only move for our side
+    let our_colors = vec![Color::Black];
+    assert_eq!(None,
+               color_to_move(&our_colors, engine.position.board.side_to_play));
+    engine.apply_move("f1b5");
+    assert_eq!(Some(Color::Black),
+               color_to_move(&our_colors, engine.position.board.side_to_play));
+
+    // Players are missing in older payloads, or are AI opponents
+    let game: lichess::types::GameFull =
+      serde_json::from_str(&payload.replace(r#""black":{"id":"schnecken_bot","name":"schnecken_bot","title":"BOT","rating":2000},"#,
+                                            r#""black":{"aiLevel":3},"#)).unwrap();
+    assert_eq!(vec![Color::White], game.colors_of("schnecken_bot"));
+    assert!(game.black.id.is_none());
+  }
+
+  #[test]
+  fn test_allocate_search_time() {
+    let no_berserk = Berserk::default();
+    // 3+2 game with plenty of time
+    assert_eq!(2000 + 2222,
+               allocate_search_time(180_000, 2_000, no_berserk));
+    // Huge increments are capped
+    assert_eq!(1000 + 66_666,
+               allocate_search_time(90_000, 120_000, no_berserk));
+
+    // 1+0 game with the clock artificially low: the search time is capped
+    assert!(is_panic_mode(9_999));
+    assert!(!is_panic_mode(PANIC_MODE_TIME_THRESHOLD_MS));
+    assert_eq!(PANIC_MODE_SEARCH_TIME_MS,
+               allocate_search_time(9_999, 0, no_berserk));
+    assert_eq!(PANIC_MODE_SEARCH_TIME_MS,
+               allocate_search_time(5_000, 60_000, no_berserk));
+    assert_eq!(50, allocate_search_time(500, 0, no_berserk));
+    assert_eq!(1, allocate_search_time(0, 0, no_berserk));
+    for time_left_ms in 0..PANIC_MODE_TIME_THRESHOLD_MS {
+      let search_time_ms = allocate_search_time(time_left_ms, 0, no_berserk);
+      assert!(search_time_ms > 0 && search_time_ms <= PANIC_MODE_SEARCH_TIME_MS);
+    }
+
+    assert_eq!(PANIC_MODE_MAX_DEPTH, panic_mode_max_depth(0));
+    assert_eq!(2, panic_mode_max_depth(2));
+    assert_eq!(PANIC_MODE_MAX_DEPTH, panic_mode_max_depth(20));
+  }
+
+  #[test]
+  fn test_allocate_search_time_with_berserk() {
+    let payload = r#"{"type":"gameState","moves":"e2e4","wtime":90000,"btime":180000,
+                      "winc":0,"binc":0,"status":"started","wberserk":true}"#;
+    let game: GameState = serde_json::from_str(payload).unwrap();
+    let berserk = Berserk::from_game_state(&game, Color::White);
+    assert_eq!(Berserk { us:       true,
+                         opponent: false, },
+               berserk);
+    assert_eq!(Berserk { us:       false,
+                         opponent: true, },
+               Berserk::from_game_state(&game, Color::Black));
+
+    // 3+0 arena game: we spend half the time when we went berserk
+    let time_left_ms = 90_000;
+    let search_time_ms = allocate_search_time(time_left_ms, 0, Berserk::default());
+    assert_eq!(search_time_ms / 2,
+               allocate_search_time(time_left_ms, 0, berserk));
+    let opponent_berserk = Berserk { us:       false,
+                                     opponent: true, };
+    assert!(allocate_search_time(time_left_ms, 0, opponent_berserk) < search_time_ms);
+
+    // Flags are not present when nobody went berserk
+    let payload = r#"{"type":"gameState","moves":"","wtime":180000,"btime":180000,
+                      "winc":2000,"binc":2000,"status":"started"}"#;
+    let game: GameState = serde_json::from_str(payload).unwrap();
+    assert_eq!(Berserk::default(),
+               Berserk::from_game_state(&game, Color::White));
+  }
+
+  #[test]
+  fn test_smooth_search_time() {
+    let mut engine = Engine::new(false);
+    engine.set_use_book(false);
+    engine.set_maximum_depth(12);
+    engine.set_multipv(1);
+    engine.set_search_time_limit(3_000);
+
+    // The king has a single square to escape the check
+    engine.set_position("k7/8/8/8/8/7P/5PP1/1r4K1 w - - 0 1");
+    assert_eq!(MoveChoice::OnlyMove, find_move_choice(&mut engine));
+    let mut banked_time_ms = 0;
+    assert_eq!(MIN_SEARCH_TIME_MS,
+               smooth_search_time(4_000, MoveChoice::OnlyMove, &mut banked_time_ms));
+    assert_eq!(4_000 - MIN_SEARCH_TIME_MS, banked_time_ms);
+
+    // Taking the hanging queen
+    engine.set_position("rnb1kbnr/pppp1ppp/8/4p3/3qP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3");
+    assert_eq!(MoveChoice::Obvious, find_move_choice(&mut engine));
+    // The quick search does not change the engine options
+    assert_eq!(12, engine.options.max_depth);
+    assert_eq!(1, engine.options.multi_pv);
+    assert_eq!(3_000, engine.options.max_search_time);
+
+    let mut banked_time_ms = 0;
+    assert_eq!(500,
+               smooth_search_time(4_000, MoveChoice::Obvious, &mut banked_time_ms));
+    assert_eq!(3_500, banked_time_ms);
+    // We never skip the search, even with little time
+    assert_eq!(MIN_SEARCH_TIME_MS,
+               smooth_search_time(100, MoveChoice::Obvious, &mut banked_time_ms));
+    assert_eq!(10, smooth_search_time(10, MoveChoice::Obvious, &mut banked_time_ms));
+    assert_eq!(3_550, banked_time_ms);
+
+    // The time saved is spent on the next positions where we have to think
+    engine.set_position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    assert_eq!(MoveChoice::Open, find_move_choice(&mut engine));
+    assert_eq!(6_000,
+               smooth_search_time(4_000, MoveChoice::Open, &mut banked_time_ms));
+    assert_eq!(5_550,
+               smooth_search_time(4_000, MoveChoice::Open, &mut banked_time_ms));
+    assert_eq!(0, banked_time_ms);
+    assert_eq!(4_000,
+               smooth_search_time(4_000, MoveChoice::Open, &mut banked_time_ms));
+  }
+
+  #[test]
+  fn test_claim_draw_on_insufficient_material() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let api = MockApi::default();
+    let mut engine = Engine::new(false);
+
+    engine.set_position("k7/8/8/4r3/4K3/8/8/8 w - - 0 50");
+    let claimed = rt.block_on(claim_draw_if_drawn(&api, &engine, "game1"));
+    assert!(!claimed);
+    assert!(api.draw_claims.lock().unwrap().is_empty());
+
+    // The king captures the last rook: only kings are left
+    engine.apply_move("e4e5");
+    let claimed = rt.block_on(claim_draw_if_drawn(&api, &engine, "game1"));
+    assert!(claimed);
+    assert!(engine.stop_requested());
+    assert_eq!(vec![String::from("game1")],
+               *api.draw_claims.lock().unwrap());
+
+    // Fifty moves without a capture or a pawn move
+    engine.set_position("8/8/4k3/8/8/3RK3/8/8 w - - 99 80");
+    assert!(!rt.block_on(claim_draw_if_drawn(&api, &engine, "game2")));
+    engine.apply_move("d3d1");
+    assert!(rt.block_on(claim_draw_if_drawn(&api, &engine, "game2")));
+  }
+
+  #[test]
+  fn test_claim_draw_on_threefold_repetition() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let api = MockApi::default();
+    let mut engine = Engine::new(false);
+
+    // Knights going back and forth: the start position occurs for the third
+    // time after the second round trip
+    engine.set_position(START_POSITION_FEN);
+    for mv in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"] {
+      engine.apply_move(mv);
+    }
+    let claimed =
+      rt.block_on(claim_draw_if_repetition(&api, &engine, "game1", Color::White, Some(0.0)));
+    assert!(!claimed);
+
+    engine.apply_move("f6g8");
+    // White is winning, we play on
+    let claimed =
+      rt.block_on(claim_draw_if_repetition(&api, &engine, "game1", Color::White, Some(1.5)));
+    assert!(!claimed);
+    assert!(api.draw_claims.lock().unwrap().is_empty());
+
+    // Black is worse, we claim the draw
+    let claimed =
+      rt.block_on(claim_draw_if_repetition(&api, &engine, "game1", Color::Black, Some(1.5)));
+    assert!(claimed);
+    assert!(engine.stop_requested());
+    assert_eq!(vec![String::from("game1")],
+               *api.draw_claims.lock().unwrap());
+
+    // No evaluation yet, we do not know if we are better
+    let claimed =
+      rt.block_on(claim_draw_if_repetition(&api, &engine, "game2", Color::White, None));
+    assert!(!claimed);
+  }
+
+  #[test]
+  fn test_avoid_repetitions_when_better() {
+    let mut engine = Engine::new(false);
+    engine.set_position(START_POSITION_FEN);
+    for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+      engine.apply_move(mv);
+    }
+
+    // Equal or worse: no restriction
+    assert!(!avoid_repetitions(&mut engine, Color::White, Some(0.0)));
+    assert!(!avoid_repetitions(&mut engine, Color::White, None));
+    assert!(!avoid_repetitions(&mut engine, Color::Black, Some(1.5)));
+    assert!(engine.options.search_moves.is_empty());
+
+    // Better: Nf3 would repeat the position after 1.Nf3
+    assert!(avoid_repetitions(&mut engine, Color::White, Some(1.5)));
+    assert_eq!(19, engine.options.search_moves.len());
+    assert!(!engine.options.search_moves.iter().any(|m| m.to_string() == "g1f3"));
+    engine.set_maximum_depth(2);
+    engine.go();
+    assert_ne!("g1f3", engine.get_best_move().unwrap().to_string());
+
+    // The restriction is lifted once we are no longer better
+    assert!(!avoid_repetitions(&mut engine, Color::White, Some(-0.2)));
+    assert!(engine.options.search_moves.is_empty());
+  }
+
+  #[test]
+  fn test_draw_offer_flags() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let payload = r#"{"type":"gameState","moves":"e2e4 e7e5","wtime":180000,"btime":180000,
+                      "winc":0,"binc":0,"status":"started","bdraw":true}"#;
+    let game: GameState = serde_json::from_str(payload).unwrap();
+    assert!(game.offers_draw(Color::Black));
+    assert!(!game.offers_draw(Color::White));
+
+    // Black offers a draw, we are White and clearly worse: accepted
+    let api = MockApi::default();
+    assert!(rt.block_on(handle_draw_offers(&api, "game1", &game, Color::White, Some(-1.2))));
+    assert_eq!(vec![String::from("game1")],
+               *api.draw_claims.lock().unwrap());
+    assert!(api.draw_declines.lock().unwrap().is_empty());
+
+    // Equal position or no evaluation: declined
+    let api = MockApi::default();
+    assert!(!rt.block_on(handle_draw_offers(&api, "game1", &game, Color::White, Some(0.0))));
+    assert!(!rt.block_on(handle_draw_offers(&api, "game2", &game, Color::White, None)));
+    assert!(api.draw_claims.lock().unwrap().is_empty());
+    assert_eq!(vec![String::from("game1"), String::from("game2")],
+               *api.draw_declines.lock().unwrap());
+
+    // Our own draw offer as Black is withdrawn once we are better
+    let api = MockApi::default();
+    assert!(!rt.block_on(handle_draw_offers(&api, "game1", &game, Color::Black, Some(0.3))));
+    assert!(api.draw_declines.lock().unwrap().is_empty());
+    assert!(!rt.block_on(handle_draw_offers(&api, "game1", &game, Color::Black, Some(-0.8))));
+    assert_eq!(vec![String::from("game1")],
+               *api.draw_declines.lock().unwrap());
+    assert!(api.draw_claims.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_draw_offer_near_fifty_move_rule() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let api = MockApi::default();
+
+    // Plenty of moves left: we play on
+    let mut engine = Engine::new(false);
+    engine.set_position("8/8/4k3/2r5/8/3K4/2R5/8 w - - 60 120");
+    assert!(!is_near_fifty_move_draw(&engine));
+    let offered =
+      rt.block_on(offer_draw_near_fifty_move_rule(&api, &engine, "game1", Color::White, Some(0.0)));
+    assert!(!offered);
+
+    // 8 reversible moves left in an equal position: we offer the draw
+    engine.set_position("8/8/4k3/2r5/8/3K4/2R5/8 w - - 92 120");
+    assert!(is_near_fifty_move_draw(&engine));
+    let offered = rt.block_on(offer_draw_near_fifty_move_rule(&api,
+                                                              &engine,
+                                                              "game1",
+                                                              Color::White,
+                                                              Some(-0.1)));
+    assert!(offered);
+    assert_eq!(vec![String::from("game1")],
+               *api.draw_claims.lock().unwrap());
+
+    // Not when we are better, clearly worse, or without evaluation
+    for (color, eval) in [(Color::White, Some(0.2)),
+                          (Color::Black, Some(-0.2)),
+                          (Color::Black, Some(1.5)),
+                          (Color::White, None)]
+    {
+      assert!(!rt.block_on(offer_draw_near_fifty_move_rule(&api, &engine, "game2", color, eval)));
+    }
+    assert_eq!(1, api.draw_claims.lock().unwrap().len());
+  }
+
+  /// Refuses the moves we configure instead of calling Lichess
+  struct ResyncMockApi {
+    refused: Vec<String>,
+    moves:   Mutex<Vec<String>>,
+  }
+
+  impl GamePlayer for ResyncMockApi {
+    async fn get_ongoing_games(&self) -> Result<serde_json::Value, ()> {
+      Err(())
+    }
+
+    async fn make_move(&self, _game_id: &str, chess_move: &str, _offer_draw: bool) -> bool {
+      self.moves.lock().unwrap().push(chess_move.to_string());
+      !self.refused.iter().any(|m| m == "*" || m == chess_move)
+    }
+  }
+
+  #[test]
+  fn test_refused_move_is_replaced_by_a_move_from_the_game_position() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+    // We play Black, but our engine missed 1.e4 and Lichess refuses our move
+    let mut engine = Engine::new(false);
+    engine.set_position(START_POSITION_FEN);
+    let api = ResyncMockApi { refused: vec![String::from("g1f3")],
+                              moves:   Mutex::new(Vec::new()), };
+    let resync = || resync_game_position(&mut engine, START_POSITION_FEN, "e2e4", &[Color::Black]);
+    assert!(rt.block_on(make_move_or_resync(&api, "game1", Move::from_string("g1f3"), resync)));
+    let moves = api.moves.lock().unwrap().clone();
+    assert_eq!(2, moves.len());
+    assert_eq!("g1f3", moves[0]);
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+    assert_eq!(fen, engine.position.to_fen());
+    assert!(engine.position.get_moves().iter().any(|m| m.to_string() == moves[1]));
+
+    // Lichess refuses everything: we give up after a few attempts
+    let api = ResyncMockApi { refused: vec![String::from("*")],
+                              moves:   Mutex::new(Vec::new()), };
+    let mut candidates = vec!["a7a6", "b7b6", "c7c6", "d7d6", "e7e6"].into_iter();
+    let resync = || candidates.next().map(Move::from_string);
+    assert!(!rt.block_on(make_move_or_resync(&api, "game1", Move::from_string("g1f3"), resync)));
+    assert_eq!(1 + MAX_RESYNC_ATTEMPTS, api.moves.lock().unwrap().len());
+
+    // Same move after rebuilding the position: not a sync issue, we give up
+    let api = ResyncMockApi { refused: vec![String::from("*")],
+                              moves:   Mutex::new(Vec::new()), };
+    let resync = || Some(Move::from_string("g1f3"));
+    assert!(!rt.block_on(make_move_or_resync(&api, "game1", Move::from_string("g1f3"), resync)));
+    assert_eq!(1, api.moves.lock().unwrap().len());
+
+    // Not our turn after rebuilding the position, nothing to play
+    let mut engine = Engine::new(false);
+    assert_eq!(None, resync_game_position(&mut engine, START_POSITION_FEN, "e2e4", &[Color::White]));
+  }
+}
@@ -0,0 +1,122 @@
+use chess::model::board::Board;
+use chess::model::moves::Move;
+use lichess::api::{ApiError, LichessApi};
+use lichess::types::{Color, ExplorerDb, ExplorerResult};
+use log::*;
+use std::time::Duration;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// We stop looking at the opening explorer after this number of half-moves
+pub const OPENING_EXPLORER_MAX_PLY: usize = 20;
+/// Minimum number of games played with a move to consider it
+const OPENING_EXPLORER_MIN_GAMES: u64 = 50;
+/// Maximum time we wait for the opening explorer to reply
+const OPENING_EXPLORER_TIMEOUT_MS: u64 = 2000;
+/// We do not query the opening explorer with less time than this to play a
+/// move
+pub const OPENING_EXPLORER_MIN_TIME_MS: u64 = 1000;
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Selects the move with the best score in the opening explorer, among the
+/// moves that were played often enough.
+///
+/// ### Arguments
+///
+/// * `result`: Reply of the opening explorer for the position
+/// * `color`:  Side to play
+///
+/// ### Return value
+///
+/// SAN notation of the selected move, None if no move was played often enough
+pub fn select_explorer_move(result: &ExplorerResult, color: Color) -> Option<String> {
+  let mut best_move: Option<(f64, u64, &str)> = None;
+  for mv in result.moves.iter().filter(|m| m.games() >= OPENING_EXPLORER_MIN_GAMES) {
+    let candidate = (mv.score(color), mv.games(), mv.san.as_str());
+    if best_move.is_none() || candidate > best_move.unwrap() {
+      best_move = Some(candidate);
+    }
+  }
+
+  best_move.map(|(_, _, san)| san.to_string())
+}
+
+/// Looks up a position in the masters database of the opening explorer and
+/// picks a statistically good move.
+///
+/// ### Arguments
+///
+/// * `api`:        Lichess API instance
+/// * `board`:      Position to look up
+/// * `fen`:        FEN of the position
+/// * `color`:      Side to play
+/// * `time_limit`: Maximum time we are ready to wait for the reply, in ms
+///
+/// ### Return value
+///
+/// Err if the opening explorer could not be reached. Ok(None) if it did not
+/// suggest any move, else the legal move to play.
+pub async fn get_explorer_move(api: &LichessApi,
+                               board: &Board,
+                               fen: &str,
+                               color: Color,
+                               time_limit: u64)
+                               -> Result<Option<Move>, ApiError> {
+  let timeout = Duration::from_millis(time_limit.min(OPENING_EXPLORER_TIMEOUT_MS));
+  let result =
+    match tokio::time::timeout(timeout, api.opening_explorer(fen, ExplorerDb::Masters)).await {
+      Ok(Ok(result)) => result,
+      Ok(Err(error)) => return Err(error),
+      Err(_) => {
+        warn!("Opening explorer did not reply within {:?}", timeout);
+        return Err(ApiError::Request);
+      },
+    };
+
+  let san = select_explorer_move(&result, color);
+  if san.is_none() {
+    return Ok(None);
+  }
+  let san = san.unwrap();
+
+  let mv = board.get_moves().into_iter().find(|m| board.move_to_san(m) == san);
+  if mv.is_none() {
+    warn!("Opening explorer suggested {} which is not legal in {}",
+          san, fen);
+  }
+  Ok(mv)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lichess::api::explorer::parse_explorer_result;
+
+  #[test]
+  fn test_select_explorer_move() {
+    let payload = r#"{"white":1000,"draws":400,"black":1000,
+      "moves":[
+        {"uci":"e7e5","san":"e5","white":500,"draws":200,"black":300},
+        {"uci":"c7c5","san":"c5","white":400,"draws":200,"black":400},
+        {"uci":"b7b5","san":"b5","white":0,"draws":0,"black":10}
+      ],
+      "opening":null}"#;
+    let result = parse_explorer_result(payload).unwrap();
+
+    // b5 has too few games to be trusted
+    assert_eq!(Some(String::from("c5")),
+               select_explorer_move(&result, Color::Black));
+    assert_eq!(Some(String::from("e5")),
+               select_explorer_move(&result, Color::White));
+
+    let payload = r#"{"white":0,"draws":0,"black":0,"moves":[],"opening":null}"#;
+    let result = parse_explorer_result(payload).unwrap();
+    assert_eq!(None, select_explorer_move(&result, Color::White));
+  }
+}
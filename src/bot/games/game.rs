@@ -1,56 +1,152 @@
-use super::engine::configure_engine;
+use super::book_import::{book_weights_path, save_learnt_book_weights};
+use super::chat::*;
+use super::engine::*;
+use super::explorer::*;
+use super::game_log::GameLog;
 use super::handle::GameHandle;
 use super::message::GameMessage;
-use chess::engine::Engine;
-use chess::model::game_state::START_POSITION_FEN;
+use super::search_log::SearchLog;
+use crate::bot::net::BotNet;
+use chess::engine::books::{get_book_moves, learn_from_game_result, BOOK_WEIGHTS};
+use chess::engine::config::options::EngineOptions;
+use chess::engine::config::play_style::PlayStyle;
+use chess::engine::{BookStatus, Engine};
+use chess::model::board::Board;
+use chess::model::game_state::{GameState, START_POSITION_FEN};
 use chess::model::moves::Move;
 use lichess::api::LichessApi;
 use lichess::types::Color;
 use log::*;
-use rand::Rng;
-use std::sync::{mpsc, Arc};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 use tokio::runtime::Handle;
 
-static MESSAGE_HAVE_TO_LEAVE: &str = "Sorry, I have to leave. I'll resign now!";
 static BOT_VERSION: &str = env!("CARGO_PKG_VERSION");
 static BOT_NAME: &str = env!("CARGO_PKG_NAME");
 
 pub struct Game {
   /// Channel to receive messages from the bot or whoever is controlling the
   /// game
-  rx:        mpsc::Receiver<GameMessage>,
+  rx:             mpsc::Receiver<GameMessage>,
   /// Lichess API instance to interact with the server
-  api:       LichessApi,
+  api:            LichessApi,
   /// Start FEN
-  start_fen: String,
+  start_fen:      String,
+  /// Ply of the start position. Lichess counts the moves from there
+  start_ply:      usize,
   /// Short Lichess Game ID, used in URLs
-  id:        String,
+  id:             String,
   /// Color played by the bot in the ongoing game
-  color:     lichess::types::Color,
+  color:          lichess::types::Color,
+  /// Colors played by the bot, both when playing against itself
+  our_colors:     Vec<lichess::types::Color>,
+  /// Our Lichess ID, to find which side we play
+  username:       String,
   // Chess engine instance used to analyze the game
-  engine:    Engine,
+  engine:         Engine,
+  /// NNUE shared with the other games, which can be switched on/off
+  net:            BotNet,
+  /// Keeps track of the chat commands we reply to
+  chat:           GameChat,
+  /// Whether the game is rated. We only learn from rated games
+  rated:          bool,
+  /// Book moves played during the game, with the board they were played on
+  book_moves:     Vec<(Board, Move)>,
+  /// File in which the book move weights are saved once we learnt from them
+  book_weights:   PathBuf,
+  /// Number of moves played in the game, shared with the game handle
+  moves:          Arc<Mutex<usize>>,
+  /// Moves of the game received from Lichess, to rebuild the position if
+  /// Lichess refuses our moves
+  move_list:      String,
+  /// Whether the opening explorer is enabled for this game
+  use_explorer:   bool,
+  /// Whether we still look up positions in the opening explorer
+  explorer:       bool,
+  /// Log file of the game, recording the moves we play
+  log:            GameLog,
+  /// CSV file recording the performance of our searches, shared by the games
+  search_log:     SearchLog,
+  /// Evaluation of the last line we played, from White's point of view
+  last_eval:      Option<f32>,
+  /// Whether we offered a draw as the fifty-move rule approaches. We offer it
+  /// only once, until a capture or pawn move resets the count
+  draw_offered:   bool,
+  /// Whether we claimed a draw. We claim it only once, if the game goes on
+  /// we keep playing
+  draw_claimed:   bool,
+  /// Ply at which we left the opening book, None while still in book
+  book_exit:      Option<usize>,
+  /// Search time saved by playing obvious moves quickly, spent on the
+  /// positions where we have to think
+  banked_time_ms: usize,
+  /// Move requested by the operator, shared with the game handle
+  forced_move:    Arc<Mutex<Option<String>>>,
 }
 
 impl Game {
   /// Allocates all the resources for playing a game on Lichess.
   /// returns a thread handle and a channel transmitter to send messages to the
   /// game.
-  pub fn new(game: lichess::types::GameStart, api: &LichessApi) -> GameHandle {
+  ///
+  /// ### Arguments
+  ///
+  /// * `game`:       Game start data received from Lichess
+  /// * `api`:        Lichess API instance to interact with the server
+  /// * `username`:   Our Lichess username, used to ignore our own chat messages
+  /// * `net`:        NNUE shared by our engines
+  /// * `search_log`: Search statistics shared by our games
+  /// * `options`:    Engine options to start from
+  /// * `explorer`:   Whether we look up the opening explorer once out of book
+  pub fn new(game: lichess::types::GameStart,
+             api: &LichessApi,
+             username: &str,
+             net: &BotNet,
+             search_log: &SearchLog,
+             options: &EngineOptions,
+             explorer: bool)
+             -> GameHandle {
     println!("Game::new with game data: {:?}", game);
 
     // Communication with the game instance
     let (tx, rx) = mpsc::channel();
 
     // Create a new engine for playing
-    let engine = configure_engine(&game);
+    let mut engine = configure_engine(&game, options);
+    net.attach(&mut engine);
+    let moves = Arc::new(Mutex::new(0));
+    let forced_move = Arc::new(Mutex::new(None));
+    let handle_engine = engine.clone();
 
+    let start_fen = game.fen.unwrap_or(String::from(START_POSITION_FEN));
+    let start_ply = engine.position.move_count.into();
     let mut bot_game: Game = Game { rx,
                                     api: api.clone(),
-                                    start_fen: game.fen
-                                                   .unwrap_or(String::from(START_POSITION_FEN)),
+                                    use_explorer: explorer,
+                                    explorer: explorer && start_fen == START_POSITION_FEN,
+                                    start_fen,
+                                    start_ply,
                                     id: game.game_id.clone(),
                                     color: game.color,
-                                    engine };
+                                    our_colors: vec![game.color],
+                                    username: username.to_string(),
+                                    engine,
+                                    net: net.clone(),
+                                    chat: GameChat::new(username),
+                                    rated: game.rated,
+                                    book_moves: Vec::new(),
+                                    book_weights: book_weights_path(),
+                                    moves: moves.clone(),
+                                    move_list: String::new(),
+                                    log: GameLog::new(&game.game_id),
+                                    search_log: search_log.clone(),
+                                    last_eval: None,
+                                    draw_offered: false,
+                                    draw_claimed: false,
+                                    book_exit: None,
+                                    banked_time_ms: 0,
+                                    forced_move: forced_move.clone() };
 
     // Start the game loop
     // Spawn blocking as calculating chess moves is CPU intense and would block the
@@ -63,7 +159,10 @@ impl Game {
     // Return a handle to the game
     GameHandle { tx,
                  handle: Arc::new(handle),
-                 id: game.game_id.clone() }
+                 id: game.game_id.clone(),
+                 moves,
+                 engine: handle_engine,
+                 forced_move }
   }
 
   /// Writes a couple of message
@@ -90,12 +189,20 @@ impl Game {
         Ok(GameMessage::Start(game)) => {
           println!("Received a Game Start : {:?}", game);
         },
+        Ok(GameMessage::Full(game)) => {
+          println!("Received a Game Full: {:?}", game);
+          self.set_up_colors(&game);
+          self.set_up_position(&game.initial_fen, &game.state.moves);
+          self.play(game.state).await;
+        },
         Ok(GameMessage::Update(game)) => {
           println!("Received a Game Update: {:?}", game);
           self.play(game).await;
         },
-        Ok(GameMessage::End(_game)) => {
+        Ok(GameMessage::End(winner)) => {
           println!("Game {} is over", self.id);
+          // The game stream may not have told us about the end of the game
+          self.learn_from_book_moves(winner);
           self.end_of_game_announcement().await;
           break;
         },
@@ -105,9 +212,8 @@ impl Game {
         },
         Ok(GameMessage::Terminate) => {
           println!("Leaving game {}", &self.id);
-          self.api.write_in_chat(&self.id, MESSAGE_HAVE_TO_LEAVE).await;
-          self.api.write_in_spectator_room(&self.id, MESSAGE_HAVE_TO_LEAVE).await;
-          let _ = self.api.resign_game(&self.id).await;
+          self.engine.stop();
+          break;
         },
         Ok(GameMessage::OpponentGone(opt_t)) => {
           if opt_t.is_some() {
@@ -116,6 +222,12 @@ impl Game {
             self.api.claim_victory_after_timeout(timeout, &self.id).await;
           }
         },
+        Ok(GameMessage::Chat(message)) => {
+          self.on_chat_message(message).await;
+        },
+        Ok(GameMessage::ForcedMove) => {
+          self.play_forced_move().await;
+        },
         Ok(o) => {
           println!("Received a Game Message : {:?}", o);
         },
@@ -128,6 +240,156 @@ impl Game {
     }
   }
 
+  /// Sets the engine position from the position the game started from and
+  /// the moves played since, e.g. for "From Position" games.
+  ///
+  /// ### Arguments
+  ///
+  /// * `initial_fen`: Initial FEN of the game, "startpos" for standard games
+  /// * `moves`:       Moves played since the start of the game
+  fn set_up_position(&mut self, initial_fen: &str, moves: &str) {
+    let start_fen = initial_position_fen(&self.id, initial_fen);
+    if start_fen != START_POSITION_FEN {
+      info!("Game {} starts from position {}", self.id, start_fen);
+    }
+    set_up_game_position(&mut self.engine, start_fen, moves);
+
+    self.start_ply = GameState::from_fen(start_fen).move_count.into();
+    self.explorer = self.use_explorer && start_fen == START_POSITION_FEN;
+    self.start_fen = start_fen.to_string();
+  }
+
+  /// Finds out which side we play from the players of the game. When we play
+  /// against ourselves, we move for both sides.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game`: Full game data received from Lichess
+  fn set_up_colors(&mut self, game: &lichess::types::GameFull) {
+    let colors = game.colors_of(&self.username);
+    match colors.len() {
+      0 => {
+        warn!("{} is not a player of GameID {}, assuming we play {:?}",
+              self.username, self.id, self.color);
+        return;
+      },
+      2 => info!("Playing against ourselves in GameID {}", self.id),
+      _ => {},
+    }
+    self.color = colors[0];
+    self.our_colors = colors;
+  }
+
+  /// Replies to chat commands such as !eval or !pv sent by players or
+  /// spectators
+  async fn on_chat_message(&mut self, message: lichess::types::ChatMessage) {
+    let command = self.chat.get_command(&message, Instant::now());
+    if command.is_none() {
+      return;
+    }
+
+    let reply = match command.unwrap() {
+      ChatCommand::Eval => match self.engine.get_eval() {
+        Some(eval) => format!("Evaluation: {}", format_eval(eval)),
+        None => String::from("No evaluation yet"),
+      },
+      ChatCommand::Pv => {
+        let analysis = self.engine.get_analysis();
+        match analysis.variations.first() {
+          Some(line) => {
+            format!("{} - {}",
+                    format_eval(line.eval),
+                    variation_to_san(&self.engine.position, &line.variation))
+          },
+          None => String::from("No line calculated yet"),
+        }
+      },
+      ChatCommand::Help => String::from(CHAT_HELP_MESSAGE),
+    };
+
+    self.api.write_in_chat_room(&self.id, message.room, &truncate_message(&reply)).await;
+  }
+
+  /// Updates the weights of the book moves we played, based on the result of
+  /// the game. Only decisive rated games are taken into account, and only
+  /// once per game.
+  ///
+  /// ### Arguments
+  ///
+  /// * `winner`: Winner of the game, None for draws and aborted games
+  fn learn_from_book_moves(&mut self, winner: Option<Color>) {
+    let book_moves = std::mem::take(&mut self.book_moves);
+    if !self.rated || book_moves.is_empty() {
+      return;
+    }
+
+    if let Some(winner) = winner {
+      let won = winner == self.color;
+      info!("Updating {} book moves after a {} in game {}",
+            book_moves.len(),
+            if won { "win" } else { "loss" },
+            self.id);
+      learn_from_game_result(&BOOK_WEIGHTS, &book_moves, won);
+      save_learnt_book_weights(&self.book_weights);
+    }
+  }
+
+  /// Checks if it is our turn to play in the position of the engine
+  fn is_our_turn(&self) -> bool {
+    color_to_move(&self.our_colors, self.engine.position.board.side_to_play).is_some()
+  }
+
+  /// Plays the move requested by the operator, if any. The move is dropped if
+  /// it is not our turn or if it is not legal in the current position.
+  ///
+  /// ### Return value
+  ///
+  /// True if the move of the operator was sent to Lichess
+  async fn play_forced_move(&mut self) -> bool {
+    let chess_move = match self.forced_move.lock().unwrap().take() {
+      Some(chess_move) => chess_move,
+      None => return false,
+    };
+    let fen = self.engine.position.to_fen();
+    let legal_move =
+      self.engine.position.get_moves().into_iter().find(|m| m.to_string() == chess_move);
+    let mv = match legal_move {
+      Some(mv) if self.is_our_turn() => mv,
+      _ => {
+        error!("Cannot play {} for GameID {} in position {}",
+               chess_move, self.id, fen);
+        return false;
+      },
+    };
+
+    info!("Playing {} for GameID {} as requested by the operator",
+          mv, self.id);
+    self.log.log_move(&fen, &mv, None, 0);
+    self.make_move(mv).await;
+    true
+  }
+
+  /// Sends a move to Lichess. If Lichess refuses it, rebuilds the position
+  /// of the engine from the moves received from Lichess and plays the move
+  /// found there instead.
+  ///
+  /// ### Arguments
+  ///
+  /// * `mv`: Move to play
+  ///
+  /// ### Return value
+  ///
+  /// True if Lichess accepted one of our moves
+  async fn make_move(&mut self, mv: Move) -> bool {
+    let resync = || {
+      resync_game_position(&mut self.engine,
+                           &self.start_fen,
+                           &self.move_list,
+                           &self.our_colors)
+    };
+    make_move_or_resync(&self.api, &self.id, mv, resync).await
+  }
+
   /// Plays a move in a game if it is ongoing and our turn
   pub async fn play(&mut self, game: lichess::types::GameState) {
     // Check if we just got a notification that the game is over, and make sure to
@@ -135,85 +397,280 @@ impl Game {
     if game.status != lichess::types::GameStatus::Started {
       // Write a well played / goodbye message
       // self.api.send_end_of_game_message(&game_id_clone, game_state.winner).await;
+      self.learn_from_book_moves(game.winner);
       return;
     }
 
     // debug!("Update engine and play if needed for GameState: {:?}", game);
     debug!("Play: game {} {:?} {}", self.id, self.color, self.start_fen);
 
-    // Update whether it is our turn
     let move_list = Move::string_to_vec(game.moves.as_str());
-    let is_our_turn = match self.color {
-      Color::White => move_list.len() % 2 == 0,
-      Color::Black => move_list.len() % 2 == 1,
-    };
+    *self.moves.lock().unwrap() = move_list.len();
+    self.move_list = game.moves.clone();
+
+    // Make sure the engine knows the latest move. The game does not
+    // necessarily start from the standard position.
+    let position_ply: usize = self.engine.position.move_count.into();
+    let move_count = position_ply.saturating_sub(self.start_ply);
+    if move_list.len() > move_count {
+      for m in move_list.iter().skip(move_count) {
+        self.engine.apply_move(m.to_string().as_str());
+      }
+    }
+
+    // Update whether it is our turn. Against ourselves, we play the side to
+    // move.
+    let to_move = color_to_move(&self.our_colors, self.engine.position.board.side_to_play);
+    if let Some(color) = to_move {
+      self.color = color;
+    }
+    let is_our_turn = to_move.is_some();
+
+    // Answer draw offers based on our current evaluation
+    if handle_draw_offers(&self.api, &self.id, &game, self.color, self.last_eval).await {
+      self.engine.stop();
+      return;
+    }
+
+    // Offer a draw instead of shuffling until the fifty-move rule
+    if !is_near_fifty_move_draw(&self.engine) {
+      self.draw_offered = false;
+    } else if is_our_turn && !self.draw_offered {
+      self.draw_offered = offer_draw_near_fifty_move_rule(&self.api,
+                                                          &self.engine,
+                                                          &self.id,
+                                                          self.color,
+                                                          self.last_eval).await;
+    }
 
     if !is_our_turn {
       return;
     }
 
-    debug!("It's our turn on game {}", self.id);
+    // No need to burn our clock on a dead drawn position
+    if !self.draw_claimed && claim_draw_if_drawn(&self.api, &self.engine, &self.id).await {
+      self.draw_claimed = true;
+      return;
+    }
 
-    // Make sure the engine knows the latest move:
-    let move_count: usize = self.engine.position.move_count.into();
-    if move_list.len() > move_count {
-      for m in move_list.iter().skip(move_count) {
-        self.engine.apply_move(m.to_string().as_str());
-      }
+    // Threefold repetitions are not automatic on Lichess
+    if !self.draw_claimed
+       && claim_draw_if_repetition(&self.api,
+                                   &self.engine,
+                                   &self.id,
+                                   self.color,
+                                   self.last_eval).await
+    {
+      self.draw_claimed = true;
+      return;
     }
 
+    // When we are better, do not give the opponent a repetition to claim
+    if avoid_repetitions(&mut self.engine, self.color, self.last_eval) {
+      info!("Avoiding repetitions in game {}", self.id);
+    }
+
+    debug!("It's our turn on game {}", self.id);
+
     info!("Trying to find a move for game {}", self.id);
-    let (time_left, mut increment_ms) = match self.color {
+    let (time_left, increment_ms) = match self.color {
       Color::White => (game.wtime, game.winc),
       Color::Black => (game.btime, game.binc),
     };
 
-    if increment_ms > 60_000 {
-      increment_ms = 60_000;
+    // Play as quick as possible if we are running out of time
+    let panic_mode = is_panic_mode(time_left);
+    let berserk = Berserk::from_game_state(&game, self.color);
+    let suggested_time_ms = allocate_search_time(time_left, increment_ms, berserk);
+    let max_depth = self.engine.options.max_depth;
+    if panic_mode {
+      info!("Low on time for game {}, switching to panic mode", self.id);
+      self.engine.set_maximum_depth(panic_mode_max_depth(max_depth));
     }
 
-    // Play as quick as possible if we have less than 10 seconds left
-    let suggested_time_ms =
-      if time_left < 10_000 { 100 } else { (time_left / 90) + increment_ms * 10 / 9 };
-
     info!("Using {} ms to find a move for position {}",
           suggested_time_ms,
           self.engine.position.to_fen());
 
-    self.engine.options.max_search_time = suggested_time_ms;
+    self.engine.set_search_time_limit(suggested_time_ms);
+    self.engine.set_use_nnue(self.net.is_enabled());
+    let start_time = Instant::now();
+    let fen = self.engine.position.to_fen();
+    let board = self.engine.position.board;
+    let book_moves = get_book_moves(&board,
+                                    self.engine.options.play_style == PlayStyle::Provocative);
+    match self.engine.book_status() {
+      BookStatus::InBook(count) => {
+        info!("Still in theory for GameID {}, {} book moves",
+              self.id, count)
+      },
+      BookStatus::OutOfBook => debug!("Out of book for GameID {}", self.id),
+    }
+
+    // Out of our book, see what the masters played in this position, unless we
+    // cannot afford to wait for the reply
+    if book_moves.is_none()
+       && !panic_mode
+       && self.explorer
+       && move_list.len() < OPENING_EXPLORER_MAX_PLY
+       && suggested_time_ms as u64 >= OPENING_EXPLORER_MIN_TIME_MS
+    {
+      let time_limit = suggested_time_ms as u64;
+      match get_explorer_move(&self.api, &board, &fen, self.color, time_limit).await {
+        Ok(Some(mv)) => {
+          info!("Playing {} from the opening explorer for GameID {}",
+                mv, self.id);
+          self.log.log_move(&fen, &mv, None, start_time.elapsed().as_millis());
+          self.make_move(mv).await;
+          return;
+        },
+        _ => {
+          info!("No move from the opening explorer for GameID {}, searching from now on",
+                self.id);
+          self.explorer = false;
+        },
+      }
+    }
+
+    // Play obvious moves quickly and keep the time for harder positions
+    if book_moves.is_none() && !panic_mode {
+      let choice = find_move_choice(&mut self.engine);
+      let search_time_ms = smooth_search_time(suggested_time_ms, choice, &mut self.banked_time_ms);
+      if choice != MoveChoice::Open {
+        info!("{:?} for game {}, searching for {} ms only",
+              choice, self.id, search_time_ms);
+      }
+      self.engine.set_search_time_limit(search_time_ms);
+    }
+
     self.engine.go();
+    self.engine.set_maximum_depth(max_depth);
 
-    // Select randomly one of the good moves.
-    let analysis = self.engine.get_analysis();
-    let best_eval = analysis.get_eval().unwrap_or(f32::NAN);
-    let mut cutoff = 1;
-    // We are in trouble if the engine could not find a move
-    if analysis.is_empty() {
-      error!("Empty result from the engine.");
-      self.api.write_in_spectator_room(&self.id, "Error: Could not find a move to play.").await;
-      self.api.write_in_chat(&self.id, "Error: Could not find a move to play.").await;
-      let _ = self.api.resign_game(&self.id).await;
+    // The operator took over while we were searching
+    if self.play_forced_move().await {
       return;
     }
 
-    while analysis.len() > cutoff {
-      if analysis.get(cutoff).eval.is_nan() {
-        break;
+    // Make sure we do not hang a piece right after the search horizon. Half
+    // of the search time at most, so that we never flag because of it.
+    if book_moves.is_none() && !panic_mode {
+      let check_time_ms = self.engine.options.max_search_time / 2;
+      if self.engine.check_for_blunder(check_time_ms) {
+        info!("Searched again for game {} after a blunder check", self.id);
       }
-      if (best_eval - analysis.get(cutoff).eval).abs() > 0.015 {
-        break;
+    }
+
+    // Book moves are already sorted by the engine based on their weights,
+    // otherwise select randomly one of the good moves. No time for that in
+    // panic mode.
+    let line = if book_moves.is_some() || panic_mode {
+      let analysis = self.engine.get_analysis();
+      if analysis.is_empty() {
+        None
       } else {
-        cutoff += 1;
+        Some((0, analysis.get(0)))
       }
+    } else {
+      let book_exit = *self.book_exit.get_or_insert(move_list.len());
+      self.engine
+          .select_book_exit_line(move_list.len() - book_exit)
+          .or_else(|| self.engine.select_line(0.015))
+    };
+    // We are in trouble if the engine could not find a move
+    if line.is_none() {
+      error!("Empty result from the engine.");
+      self.api.write_in_spectator_room(&self.id, "Error: Could not find a move to play.").await;
+      self.api.write_in_chat(&self.id, "Error: Could not find a move to play.").await;
+      let _ = self.api.resign_game(&self.id).await;
+      return;
     }
 
-    let move_index = rand::thread_rng().gen_range(0..cutoff);
-    let mv = analysis.get(move_index).variation.get_first_move().unwrap();
-    let eval = analysis.get(move_index).eval;
+    let (move_index, line) = line.unwrap();
+    let mv = line.variation.get_first_move().unwrap();
+    let eval = line.eval;
+    self.last_eval = Some(eval);
     info!("Playing Line {} ({})  as {:?} for GameID {} - eval: {}",
           move_index, mv, self.color, self.id, eval);
+    let time_ms = start_time.elapsed().as_millis();
+    self.log.log_move(&fen, &mv, Some(eval), time_ms);
+    self.search_log.log_search(&self.id, &fen, &self.engine, &mv, eval, time_ms);
+
+    // Remember the book moves we played, to learn from the result of the game
+    if book_moves.unwrap_or_default().iter().any(|m| m.to_string() == mv.to_string()) {
+      self.book_moves.push((board, mv));
+    }
 
     // Make the move
-    self.api.make_move(&self.id, &mv.to_string(), false).await;
+    self.make_move(mv).await;
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::engine::books::get_book_move_weight;
+  use std::path::Path;
+
+  /// Creates a rated game, played as White, which cannot reach Lichess and
+  /// writes its files in a directory
+  fn new_game(directory: &Path) -> (Game, mpsc::Sender<GameMessage>) {
+    let (tx, rx) = mpsc::channel();
+    let game = Game { rx,
+                      api: LichessApi::with_base_url("token", "http://127.0.0.1:9/"),
+                      start_fen: String::from(START_POSITION_FEN),
+                      start_ply: 0,
+                      id: String::from("abcd1234"),
+                      color: Color::White,
+                      our_colors: vec![Color::White],
+                      username: String::from("schnecken_bot"),
+                      engine: Engine::new(false),
+                      net: BotNet::new(),
+                      chat: GameChat::new("schnecken_bot"),
+                      rated: true,
+                      book_moves: Vec::new(),
+                      book_weights: directory.join("book_weights.txt"),
+                      moves: Arc::new(Mutex::new(0)),
+                      move_list: String::new(),
+                      use_explorer: false,
+                      explorer: false,
+                      log: GameLog::open(directory, "abcd1234"),
+                      search_log: SearchLog::with_path(&directory.join("searches.csv")),
+                      last_eval: None,
+                      draw_offered: false,
+                      draw_claimed: false,
+                      book_exit: None,
+                      banked_time_ms: 0,
+                      forced_move: Arc::new(Mutex::new(None)) };
+    (game, tx)
+  }
+
+  #[test]
+  fn test_book_moves_are_learnt_when_the_game_end_comes_first() {
+    let directory = std::env::temp_dir().join(format!("schnecken_game_{}", std::process::id()));
+    let (mut game, tx) = new_game(&directory);
+
+    // Book move that no other test learns from
+    let board = Board::from_fen("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5");
+    let mv = Move::from_string("d7d6");
+    game.book_moves.push((board, mv));
+    let weight = get_book_move_weight(&BOOK_WEIGHTS, &board, &mv);
+
+    // The event stream tells that we won before the game stream does
+    tx.send(GameMessage::End(Some(Color::White))).unwrap();
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    rt.block_on(game.game_loop());
+    assert_eq!(weight + 1, get_book_move_weight(&BOOK_WEIGHTS, &board, &mv));
+    assert!(directory.join("book_weights.txt").exists());
+
+    // The final state of the game does not count the result twice
+    let state = r#"{"moves":"e2e4","wtime":0,"btime":0,"winc":0,"binc":0,
+                    "status":"resign","winner":"white"}"#;
+    rt.block_on(game.play(serde_json::from_str(state).unwrap()));
+    assert_eq!(weight + 1, get_book_move_weight(&BOOK_WEIGHTS, &board, &mv));
+
+    let _ = std::fs::remove_dir_all(&directory);
   }
 }
@@ -0,0 +1,147 @@
+use chess::model::moves::Move;
+use log::*;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Directory in which the log files of the games are written
+pub const GAME_LOG_DIRECTORY: &str = "logs";
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Log file of a single game, named after the game ID, e.g. `logs/rCRw1AuO.log`
+/// It records one line per move that we play. Clones write to the same file,
+/// so it can be shared between the threads handling the game.
+#[derive(Debug, Clone)]
+pub struct GameLog {
+  file: Arc<Mutex<Option<File>>>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementations
+
+impl GameLog {
+  /// Opens the log file of a game in the default log directory.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id`: Lichess Game ID
+  pub fn new(game_id: &str) -> Self {
+    Self::open(Path::new(GAME_LOG_DIRECTORY), game_id)
+  }
+
+  /// Opens the log file of a game, appending to it if it already exists.
+  /// If the file cannot be opened, the log is disabled and nothing gets
+  /// written.
+  ///
+  /// ### Arguments
+  ///
+  /// * `directory`: Directory in which the log file is created
+  /// * `game_id`:   Lichess Game ID
+  pub fn open(directory: &Path, game_id: &str) -> Self {
+    let path = directory.join(format!("{game_id}.log"));
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    let file = match std::fs::create_dir_all(directory) {
+      Ok(_) => options.open(&path),
+      Err(error) => Err(error),
+    };
+    if let Err(error) = &file {
+      warn!("Cannot open the game log {}: {}", path.display(), error);
+    }
+
+    GameLog { file: Arc::new(Mutex::new(file.ok())), }
+  }
+
+  /// Records a move that we played in the game.
+  ///
+  /// ### Arguments
+  ///
+  /// * `fen`:     Position on which the move was played
+  /// * `mv`:      Move played
+  /// * `eval`:    Evaluation of the engine, None if the move did not come from
+  ///   a search (e.g. book or opening explorer moves)
+  /// * `time_ms`: Time used to find the move, in ms
+  pub fn log_move(&self, fen: &str, mv: &Move, eval: Option<f32>, time_ms: u128) {
+    self.write_line(&format_move_line(fen, mv, eval, time_ms));
+  }
+
+  /// Writes a line in the log file
+  ///
+  /// ### Arguments
+  ///
+  /// * `line`: Text to write, without new line
+  fn write_line(&self, line: &str) {
+    let mut file = self.file.lock().unwrap();
+    if let Some(file) = file.as_mut() {
+      if let Err(error) = writeln!(file, "{line}") {
+        warn!("Cannot write in the game log: {}", error);
+      }
+    }
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Formats the log line for a move
+///
+/// ### Arguments
+///
+/// * `fen`:     Position on which the move was played
+/// * `mv`:      Move played
+/// * `eval`:    Evaluation of the engine, if any
+/// * `time_ms`: Time used to find the move, in ms
+///
+/// ### Return value
+///
+/// Line to write in the log, e.g.
+/// `fen: <FEN> | move: e2e4 | eval: 0.35 | time: 1200 ms`
+fn format_move_line(fen: &str, mv: &Move, eval: Option<f32>, time_ms: u128) -> String {
+  let eval = match eval {
+    Some(eval) => format!("{eval:.2}"),
+    None => String::from("-"),
+  };
+  format!("fen: {fen} | move: {mv} | eval: {eval} | time: {time_ms} ms")
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::model::game_state::START_POSITION_FEN;
+
+  #[test]
+  fn test_moves_are_logged_with_fen_and_eval() {
+    let directory = std::env::temp_dir().join(format!("schnecken_logs_{}", std::process::id()));
+    let log = GameLog::open(&directory, "abcd1234");
+
+    // Clones write to the same file, e.g. from the game thread
+    let thread_log = log.clone();
+    let e4 = Move::from_string("e2e4");
+    let thread = std::thread::spawn(move || {
+      thread_log.log_move(START_POSITION_FEN, &e4, Some(0.35), 1200);
+    });
+    thread.join().unwrap();
+    log.log_move(START_POSITION_FEN, &Move::from_string("d2d4"), None, 15);
+
+    let content = std::fs::read_to_string(directory.join("abcd1234.log")).unwrap();
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(2, lines.len());
+    assert!(lines[0].contains(START_POSITION_FEN));
+    assert!(lines[0].contains("move: e2e4"));
+    assert!(lines[0].contains("eval: 0.35"));
+    assert!(lines[0].contains("time: 1200 ms"));
+    assert!(lines[1].contains("move: d2d4"));
+    assert!(lines[1].contains("eval: -"));
+  }
+}
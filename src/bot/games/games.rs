@@ -1,20 +1,45 @@
 // Internal
-use super::handle::GameHandle;
+use super::correspondence::parse_ongoing_games;
+use super::handle::{GameHandle, ShutdownAction};
 use super::message::GameMessage;
 use lichess::api::LichessApi;
-use lichess::traits::GameStreamHandler;
+use lichess::traits::{GamePlayer, GameStreamHandler, GameTerminator};
+use lichess::types::{Color, GameStart, GameStatus};
 // External
 use log::*;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 // -----------------------------------------------------------------------------
 // Constants
-const NUMBER_OF_SIMULTANEOUS_GAMES: usize = 4;
+pub const NUMBER_OF_SIMULTANEOUS_GAMES: usize = 4;
+/// Number of times we reopen the stream of a game before giving up on it
+const GAME_STREAM_MAX_RECONNECTS: usize = 10;
+/// Time we wait before reopening the stream of a game
+const GAME_STREAM_RECONNECT_DELAY: Duration = Duration::from_secs(2);
 
-#[derive(Debug)]
+/// Action of the operator on one of our games, e.g. when the bot misbehaves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperatorAction {
+  /// Resign the game
+  Resign,
+  /// Abort the game, possible as long as both players have not moved
+  Abort,
+  /// Offer a draw, or accept the draw offered by the opponent
+  Draw,
+  /// Play a move, in UCI notation, instead of the move of the engine
+  Move(String),
+}
+
+#[derive(Debug, Clone)]
 pub struct BotGames {
-  games: Arc<Mutex<Vec<Arc<GameHandle>>>>,
-  api:   &'static LichessApi,
+  games:     Arc<Mutex<Vec<Arc<GameHandle>>>>,
+  /// Challenges we accepted, for which the game has not started yet.
+  /// Always lock `games` first when using it.
+  accepted:  Arc<Mutex<Vec<String>>>,
+  /// Maximum number of games we play at the same time
+  max_games: Arc<Mutex<usize>>,
+  api:       &'static LichessApi,
 }
 
 impl BotGames {
@@ -27,13 +52,56 @@ impl BotGames {
   pub fn new(api: &'static LichessApi) -> Self {
     let games = Vec::with_capacity(NUMBER_OF_SIMULTANEOUS_GAMES);
     let games = Arc::new(Mutex::new(games));
-    Self { games, api }
+    Self { games,
+           accepted: Arc::new(Mutex::new(Vec::new())),
+           max_games: Arc::new(Mutex::new(NUMBER_OF_SIMULTANEOUS_GAMES)),
+           api }
+  }
+
+  /// Sets the maximum number of games we play at the same time.
+  ///
+  /// # Arguments
+  ///
+  /// * `max_games` - Maximum number of simultaneous games, at least 1.
+  pub fn set_max_concurrent_games(&self, max_games: usize) {
+    *self.max_games.lock().unwrap() = max_games.max(1);
   }
 
-  /// Checks if the current set of games has reached its capacity.
-  pub fn is_full(&self) -> bool {
+  /// Gets the maximum number of games we play at the same time.
+  pub fn get_max_concurrent_games(&self) -> usize {
+    *self.max_games.lock().unwrap()
+  }
+
+  /// Reserves a slot for the game of a challenge that we are about to accept.
+  /// Ongoing games and accepted challenges count towards the capacity, so
+  /// that simultaneous challenges cannot be over-accepted.
+  ///
+  /// # Arguments
+  ///
+  /// * `challenge_id` - ID of the challenge, which is also the ID of the game.
+  ///
+  /// # Returns
+  ///
+  /// True if the challenge can be accepted, false if we are at capacity.
+  pub fn try_accept_challenge(&self, challenge_id: &str) -> bool {
     let games = self.games.lock().unwrap();
-    games.len() >= NUMBER_OF_SIMULTANEOUS_GAMES
+    let mut accepted = self.accepted.lock().unwrap();
+    if games.len() + accepted.len() >= self.get_max_concurrent_games() {
+      return false;
+    }
+    accepted.push(challenge_id.to_string());
+    true
+  }
+
+  /// Frees the slot reserved for a challenge, e.g. if accepting it failed.
+  ///
+  /// # Arguments
+  ///
+  /// * `challenge_id` - ID of the challenge.
+  pub fn release_challenge(&self, challenge_id: &str) {
+    let _games = self.games.lock().unwrap();
+    let mut accepted = self.accepted.lock().unwrap();
+    accepted.retain(|id| id != challenge_id);
   }
 
   /// Checks if we are playing
@@ -48,29 +116,133 @@ impl BotGames {
     games.len()
   }
 
-  /// Adds a new game to the set of games if there is capacity.
+  /// Registers a game that just started and starts streaming its state.
+  /// The games are locked during the whole registration, so that concurrent or
+  /// duplicate gameStart events cannot register a game twice or go over
+  /// capacity.
   ///
   /// # Arguments
   ///
-  /// * `game` - A `lichess::types::GameStart` instance representing the game to
-  ///   be added.
-  pub fn add(&self, game_handle: GameHandle) {
+  /// * `game_id` -       ID of the game that started.
+  /// * `create_handle` - Creates the game and returns its handle, called only
+  ///   if the game gets registered.
+  ///
+  /// # Returns
+  ///
+  /// True if the game was registered, false if it was already registered or if
+  /// the set of games is full.
+  pub fn on_game_start(&self, game_id: &str, create_handle: impl FnOnce() -> GameHandle) -> bool {
     let mut games = self.games.lock().unwrap();
-    if games.len() >= NUMBER_OF_SIMULTANEOUS_GAMES {
+    self.accepted.lock().unwrap().retain(|id| id != game_id);
+    if games.iter().any(|handle| handle.id == game_id) {
+      info!("Game {} is already registered, ignoring the gameStart event",
+            game_id);
+      return false;
+    }
+    if games.len() >= self.get_max_concurrent_games() {
       error!("Error: Cannot add more games. The set of games is full.");
-      return;
+      return false;
     }
 
-    // Start the game stream
-    let api = self.api.clone();
-    let handle = game_handle.clone();
-    let _ = tokio::spawn(async move {
-      let _ =
-        api.stream_game_state_with_callback(&handle.id, &handle, GameHandle::game_stream_handler)
-           .await;
-    });
+    let game_handle = create_handle();
+    let stream = Self::stream_game(self.clone(), game_handle.clone());
+    let _ = tokio::spawn(stream);
 
     games.push(Arc::new(game_handle));
+    true
+  }
+
+  /// Registers and starts streaming the real-time games that are already
+  /// ongoing on Lichess, e.g. after the bot restarted in the middle of a game.
+  /// Games that are already registered are skipped.
+  ///
+  /// # Arguments
+  ///
+  /// * `api` -           API used to list our ongoing games.
+  /// * `create_handle` - Creates a game and returns its handle, called for each
+  ///   game that gets registered.
+  ///
+  /// # Returns
+  ///
+  /// The number of games that were registered.
+  pub async fn resume_ongoing_games<T: GamePlayer>(&self,
+                                                   api: &T,
+                                                   create_handle: impl Fn(GameStart) -> GameHandle)
+                                                   -> usize {
+    let json = match api.get_ongoing_games().await {
+      Ok(json) => json,
+      Err(()) => {
+        warn!("Cannot look up our ongoing games");
+        return 0;
+      },
+    };
+
+    // Correspondence games are played when polling our games
+    let mut resumed = 0;
+    for game in parse_ongoing_games(&json).into_iter().filter(|game| !game.is_correspondence()) {
+      let game_id = game.game_id.clone();
+      if self.on_game_start(&game_id, || create_handle(game)) {
+        info!("Resuming ongoing game {}", game_id);
+        resumed += 1;
+      }
+    }
+    resumed
+  }
+
+  /// Tears down a game that finished: it is removed from the set of games and
+  /// its game loop is stopped, which frees its engine.
+  ///
+  /// # Arguments
+  ///
+  /// * `game_id` - ID of the game that finished.
+  /// * `winner` -  Winner of the game, None if it was not decisive.
+  ///
+  /// # Returns
+  ///
+  /// True if the game was registered, false otherwise.
+  pub fn on_game_finish(&self, game_id: &str, winner: Option<Color>) -> bool {
+    let handle = self.get_handle(game_id);
+    self.remove(game_id);
+    if handle.is_none() {
+      // Both the event stream and the game stream report the end of games
+      debug!("Game {} finished, but it is not registered anymore",
+             game_id);
+      return false;
+    }
+
+    let handle = handle.unwrap();
+    handle.stop_search();
+    let _ = handle.tx.send(GameMessage::End(winner));
+    true
+  }
+
+  /// Streams the state of a game to its game loop. The stream gets reopened
+  /// if it closes while the game is still registered, e.g. after a network
+  /// error.
+  ///
+  /// # Arguments
+  ///
+  /// * `games` -  Set of registered games.
+  /// * `handle` - Handle of the game to stream.
+  async fn stream_game(games: BotGames, handle: GameHandle) {
+    let api = games.api;
+    let stream = GameStream { games, handle };
+    for attempt in 0..=GAME_STREAM_MAX_RECONNECTS {
+      if attempt > 0 {
+        tokio::time::sleep(GAME_STREAM_RECONNECT_DELAY).await;
+      }
+      if stream.games.get_handle(&stream.handle.id).is_none() || stream.handle.is_over() {
+        return;
+      }
+      if attempt > 0 {
+        warn!("Game stream for {} closed, reconnecting (attempt {})",
+              stream.handle.id, attempt);
+      }
+
+      let _ = api.stream_game_state_with_callback(&stream.handle.id, &stream, GameStream::on_event)
+                 .await;
+    }
+    error!("Giving up streaming game {}", stream.handle.id);
   }
 
   /// Removes a game from the set of games based on the game ID.
@@ -82,6 +254,7 @@ impl BotGames {
   pub fn remove(&self, game_id: &str) {
     let mut games = self.games.lock().unwrap();
     games.retain(|handle| handle.id != game_id);
+    self.accepted.lock().unwrap().retain(|id| id != game_id);
   }
 
   /// Remove finished games from our list that we do not need anymore.
@@ -90,13 +263,54 @@ impl BotGames {
     games.retain(|handle| !handle.is_over());
   }
 
-  pub fn terminate_all(&self) {
-    let games = self.games.lock().unwrap();
+  /// Leaves all ongoing games, typically because the bot shuts down.
+  /// Running searches are stopped, games in which both players have not moved
+  /// yet are aborted and the other ones are resigned.
+  ///
+  /// # Arguments
+  ///
+  /// * `api` -     API used to abort or resign the games.
+  /// * `timeout` - Maximum time to wait for the API, so that we exit even if
+  ///   the server does not answer.
+  pub async fn shutdown<T: GameTerminator>(&self, api: &T, timeout: Duration) {
+    let games: Vec<Arc<GameHandle>> = self.games.lock().unwrap().drain(..).collect();
+    for handle in games.iter() {
+      handle.stop_search();
+    }
+
+    let requests = games.iter().map(|handle| Self::leave_game(api, handle));
+    if tokio::time::timeout(timeout, futures_util::future::join_all(requests)).await.is_err() {
+      warn!("Timeout while leaving the ongoing games");
+    }
+
     for handle in games.iter() {
       let _ = handle.tx.send(GameMessage::Terminate);
     }
   }
 
+  /// Aborts a game if it just started, resigns it otherwise.
+  ///
+  /// # Arguments
+  ///
+  /// * `api` -    API used to abort or resign the game.
+  /// * `handle` - Handle of the game to leave.
+  async fn leave_game<T: GameTerminator>(api: &T, handle: &GameHandle) {
+    let mut action = handle.shutdown_action();
+    if action == ShutdownAction::Abort {
+      info!("Aborting game {}", handle.id);
+      if api.abort_game(&handle.id).await.is_err() {
+        // The opponent may have moved in the meantime
+        action = ShutdownAction::Resign;
+      }
+    }
+    if action == ShutdownAction::Resign {
+      info!("Resigning game {}", handle.id);
+      if api.resign_game(&handle.id).await.is_err() {
+        warn!("Could not resign game {}", handle.id);
+      }
+    }
+  }
+
   /// Checks if any of the players we like is online and sends a challenge.
   pub fn on_game_update(&self, game_state: lichess::types::GameState, game_id: &str) {
     let handle = self.get_handle(game_id);
@@ -113,4 +327,510 @@ impl BotGames {
     let games = self.games.lock().unwrap();
     games.iter().find(|handle| handle.id == game_id).cloned()
   }
+
+  /// Executes an action of the operator on one of our games. Moves are
+  /// handed over to the game, which stops searching and plays them.
+  ///
+  /// # Arguments
+  ///
+  /// * `api` -     API used to resign, abort or offer a draw.
+  /// * `game_id` - Game ID on which the action applies.
+  /// * `action` -  Action to execute.
+  ///
+  /// # Return value
+  ///
+  /// Error message if the game is not one of ours or Lichess refused the
+  /// action.
+  pub async fn operate<T: GameTerminator>(&self,
+                                          api: &T,
+                                          game_id: &str,
+                                          action: OperatorAction)
+                                          -> Result<(), String> {
+    let handle = match self.get_handle(game_id) {
+      Some(handle) => handle,
+      None => return Err(format!("Unknown game {}", game_id)),
+    };
+
+    info!("Operator action on game {}: {:?}", game_id, action);
+    let result = match &action {
+      OperatorAction::Resign => api.resign_game(game_id).await,
+      OperatorAction::Abort => api.abort_game(game_id).await,
+      OperatorAction::Draw => api.claim_draw(game_id).await,
+      OperatorAction::Move(chess_move) => {
+        handle.force_move(chess_move);
+        Ok(())
+      },
+    };
+    result.map_err(|()| format!("Lichess refused {:?} on game {}", action, game_id))
+  }
+}
+
+/// Stream of the state of one of our games
+struct GameStream {
+  /// Set of registered games, from which the game is removed when it is over
+  games:  BotGames,
+  /// Handle of the streamed game
+  handle: GameHandle,
+}
+
+impl GameStream {
+  /// Forwards an event of the game stream to the game loop, and tears the game
+  /// down if the event says that the game is over, e.g. aborted or mated.
+  ///
+  /// # Arguments
+  ///
+  /// * `json_value` - Event received in the game stream.
+  /// * `game_id` -    ID of the streamed game.
+  fn on_event(&self, json_value: serde_json::Value, game_id: String) {
+    let status = game_stream_status(&json_value);
+    let winner = game_stream_winner(&json_value);
+    self.handle.game_stream_handler(json_value, game_id.clone());
+
+    if status.is_some_and(|status| status.is_terminal()) {
+      info!("Game {} is over: {:?}", game_id, status.unwrap());
+      self.games.on_game_finish(&game_id, winner);
+    }
+  }
+}
+
+/// Reads the status of the game in an event of the game stream
+///
+/// # Arguments
+///
+/// * `json_value` - Event received in the game stream.
+///
+/// # Returns
+///
+/// The status of the game, None if the event does not carry it.
+fn game_stream_status(json_value: &serde_json::Value) -> Option<GameStatus> {
+  serde_json::from_value(game_stream_state(json_value)?["status"].clone()).ok()
+}
+
+/// Reads the winner of the game in an event of the game stream
+///
+/// # Arguments
+///
+/// * `json_value` - Event received in the game stream.
+///
+/// # Returns
+///
+/// The winner of the game, None if there is none (yet) or if the event does
+/// not carry the game state.
+fn game_stream_winner(json_value: &serde_json::Value) -> Option<Color> {
+  serde_json::from_value(game_stream_state(json_value)?["winner"].clone()).ok()
+}
+
+/// Finds the state of the game in an event of the game stream
+///
+/// # Arguments
+///
+/// * `json_value` - Event received in the game stream.
+///
+/// # Returns
+///
+/// The game state object, None if the event does not carry it.
+fn game_stream_state(json_value: &serde_json::Value) -> Option<&serde_json::Value> {
+  match json_value["type"].as_str()? {
+    "gameFull" => Some(&json_value["state"]),
+    "gameState" => Some(json_value),
+    _ => None,
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::engine::Engine;
+  use std::sync::mpsc;
+
+  /// Records the games we abort and resign instead of calling Lichess
+  struct MockApi {
+    calls: Mutex<Vec<(String, ShutdownAction)>>,
+    delay: Duration,
+  }
+
+  impl MockApi {
+    fn new(delay: Duration) -> Self {
+      MockApi { calls: Mutex::new(Vec::new()),
+                delay }
+    }
+  }
+
+  impl GameTerminator for MockApi {
+    async fn abort_game(&self, game_id: &str) -> Result<(), ()> {
+      tokio::time::sleep(self.delay).await;
+      self.calls.lock().unwrap().push((game_id.to_string(), ShutdownAction::Abort));
+      Ok(())
+    }
+
+    async fn resign_game(&self, game_id: &str) -> Result<(), ()> {
+      tokio::time::sleep(self.delay).await;
+      self.calls.lock().unwrap().push((game_id.to_string(), ShutdownAction::Resign));
+      Ok(())
+    }
+
+    async fn claim_draw(&self, _game_id: &str) -> Result<(), ()> {
+      // Games are never drawn when shutting down
+      Err(())
+    }
+
+    async fn decline_draw(&self, _game_id: &str) -> Result<(), ()> {
+      Err(())
+    }
+  }
+
+  /// Records the API methods called by the operator actions
+  #[derive(Default)]
+  struct OperatorApi {
+    calls: Mutex<Vec<(&'static str, String)>>,
+  }
+
+  impl OperatorApi {
+    fn record(&self, method: &'static str, game_id: &str) -> Result<(), ()> {
+      self.calls.lock().unwrap().push((method, game_id.to_string()));
+      Ok(())
+    }
+  }
+
+  impl GameTerminator for OperatorApi {
+    async fn abort_game(&self, game_id: &str) -> Result<(), ()> {
+      self.record("abort_game", game_id)
+    }
+
+    async fn resign_game(&self, game_id: &str) -> Result<(), ()> {
+      self.record("resign_game", game_id)
+    }
+
+    async fn claim_draw(&self, game_id: &str) -> Result<(), ()> {
+      self.record("claim_draw", game_id)
+    }
+
+    async fn decline_draw(&self, game_id: &str) -> Result<(), ()> {
+      self.record("decline_draw", game_id)
+    }
+  }
+
+  /// Replies with a fixed list of ongoing games
+  struct PlayingApi {
+    ongoing_games: serde_json::Value,
+  }
+
+  impl GamePlayer for PlayingApi {
+    async fn get_ongoing_games(&self) -> Result<serde_json::Value, ()> {
+      Ok(self.ongoing_games.clone())
+    }
+
+    async fn make_move(&self, _game_id: &str, _chess_move: &str, _offer_draw: bool) -> bool {
+      false
+    }
+  }
+
+  fn new_handle(id: &str, moves: usize) -> (GameHandle, mpsc::Receiver<GameMessage>) {
+    let (tx, rx) = mpsc::channel();
+    let handle = GameHandle { tx,
+                              handle: Arc::new(tokio::spawn(async {})),
+                              id: id.to_string(),
+                              moves: Arc::new(Mutex::new(moves)),
+                              engine: Engine::new(false),
+                              forced_move: Arc::new(Mutex::new(None)) };
+    (handle, rx)
+  }
+
+  fn add_game(games: &BotGames, id: &str, moves: usize) -> mpsc::Receiver<GameMessage> {
+    let (handle, rx) = new_handle(id, moves);
+    games.accepted.lock().unwrap().retain(|game_id| game_id != id);
+    games.games.lock().unwrap().push(Arc::new(handle));
+    rx
+  }
+
+  fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    rt.block_on(future)
+  }
+
+  fn new_bot_games() -> BotGames {
+    let api: &'static LichessApi = Box::leak(Box::new(LichessApi::new("")));
+    BotGames::new(api)
+  }
+
+  async fn shutdown_aborts_or_resigns_all_games() {
+    let games = new_bot_games();
+    let rx_1 = add_game(&games, "game1", 0);
+    let rx_2 = add_game(&games, "game2", 1);
+    let rx_3 = add_game(&games, "game3", 12);
+
+    let api = MockApi::new(Duration::from_millis(0));
+    games.shutdown(&api, Duration::from_secs(5)).await;
+
+    let mut calls = api.calls.lock().unwrap().clone();
+    calls.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(vec![(String::from("game1"), ShutdownAction::Abort),
+                    (String::from("game2"), ShutdownAction::Abort),
+                    (String::from("game3"), ShutdownAction::Resign)],
+               calls);
+    assert!(games.is_empty());
+    for rx in [rx_1, rx_2, rx_3] {
+      assert!(matches!(rx.try_recv(), Ok(GameMessage::Terminate)));
+    }
+  }
+
+  async fn shutdown_with_unresponsive_api() {
+    let games = new_bot_games();
+    let rx = add_game(&games, "game1", 20);
+
+    let api = MockApi::new(Duration::from_secs(60));
+    let start = std::time::Instant::now();
+    games.shutdown(&api, Duration::from_millis(100)).await;
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(api.calls.lock().unwrap().is_empty());
+    assert!(matches!(rx.try_recv(), Ok(GameMessage::Terminate)));
+  }
+
+  async fn challenges_are_declined_at_capacity() {
+    let games = new_bot_games();
+    games.set_max_concurrent_games(2);
+    let _rx_1 = add_game(&games, "game1", 10);
+    assert!(games.try_accept_challenge("game2"));
+
+    // The N+1th challenge is declined, even if the game of the Nth challenge
+    // has not started yet
+    assert!(!games.try_accept_challenge("game3"));
+    let _rx_2 = add_game(&games, "game2", 0);
+    assert!(!games.try_accept_challenge("game3"));
+
+    // A game finishes, we accept again
+    games.remove("game1");
+    assert!(games.try_accept_challenge("game3"));
+    assert!(!games.try_accept_challenge("game4"));
+
+    // Accepting the challenge failed
+    games.release_challenge("game3");
+    assert!(games.try_accept_challenge("game4"));
+
+    games.set_max_concurrent_games(0);
+    assert_eq!(1, games.get_max_concurrent_games());
+  }
+
+  /// Parses the game of a gameStart or gameFinish event of the event stream
+  fn parse_game_event(event: &str) -> lichess::types::GameStart {
+    let json_value: serde_json::Value = serde_json::from_str(event).unwrap();
+    serde_json::from_value(json_value["game"].clone()).unwrap()
+  }
+
+  async fn games_are_registered_on_start_and_removed_on_finish() {
+    let game_start = r#"{"type":"gameStart","game":{"gameId":"rCRw1AuO","fullId":"rCRw1AuOvonq",
+      "color":"black","fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "hasMoved":false,"isMyTurn":false,"lastMove":"","source":"friend","speed":"blitz",
+      "perf":"blitz","rated":false,"secondsLeft":300,"status":{"id":20,"name":"started"},
+      "variant":{"key":"standard","name":"Standard"},
+      "opponent":{"id":"nobriot","username":"nobriot","rating":1500}}}"#;
+    let game_finish = game_start.replace("gameStart", "gameFinish")
+                                .replace(r#""name":"started"}"#, r#""name":"resign"},"winner":"white""#);
+    let game_start = parse_game_event(game_start);
+    let game_finish = parse_game_event(&game_finish);
+
+    let games = new_bot_games();
+    assert!(games.try_accept_challenge(&game_start.game_id));
+
+    let (handle, rx) = new_handle(&game_start.game_id, 0);
+    assert!(games.on_game_start(&game_start.game_id, || handle));
+    assert_eq!(1, games.len());
+    assert!(games.get_handle("rCRw1AuO").is_some());
+
+    // A duplicate gameStart event does not register the game twice
+    let mut created = false;
+    let duplicate = || {
+      created = true;
+      new_handle("rCRw1AuO", 0).0
+    };
+    assert!(!games.on_game_start(&game_start.game_id, duplicate));
+    assert!(!created);
+    assert_eq!(1, games.len());
+
+    // The game finishes: it is removed and its game loop is stopped
+    assert!(games.on_game_finish(&game_finish.game_id, game_finish.winner));
+    assert!(games.is_empty());
+    assert!(games.get_handle("rCRw1AuO").is_none());
+    assert!(matches!(rx.try_recv(), Ok(GameMessage::End(Some(Color::White)))));
+    assert!(!games.on_game_finish(&game_finish.game_id, game_finish.winner));
+
+    // All the slots are free again
+    games.set_max_concurrent_games(1);
+    assert!(games.try_accept_challenge("game2"));
+  }
+
+  async fn ongoing_games_are_resumed() {
+    let ongoing_game = r#"{"gameId":"GAME_ID","fullId":"GAME_IDabcd","color":"white",
+      "fen":"rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR","hasMoved":true,
+      "isMyTurn":true,"lastMove":"e7e5","source":"friend","speed":"SPEED",
+      "perf":"SPEED","rated":false,"secondsLeft":100,
+      "status":{"id":20,"name":"started"},"variant":{"key":"standard","name":"Standard"},
+      "opponent":{"id":"nobriot","username":"nobriot","rating":1500}}"#;
+    let game = |id: &str, speed: &str| ongoing_game.replace("GAME_ID", id).replace("SPEED", speed);
+    let payload = format!(r#"{{"nowPlaying":[{},{},{}]}}"#,
+                          game("blitz001", "blitz"),
+                          game("rapid001", "rapid"),
+                          game("corres01", "correspondence"));
+    let api = PlayingApi { ongoing_games: serde_json::from_str(&payload).unwrap(), };
+
+    let games = new_bot_games();
+    let created = Mutex::new(Vec::new());
+    let create_handle = |game: GameStart| {
+      created.lock().unwrap().push(game.game_id.clone());
+      new_handle(&game.game_id, 2).0
+    };
+    assert_eq!(2, games.resume_ongoing_games(&api, create_handle).await);
+    assert_eq!(vec![String::from("blitz001"), String::from("rapid001")],
+               *created.lock().unwrap());
+    assert_eq!(2, games.len());
+    assert!(games.get_handle("blitz001").is_some());
+    assert!(games.get_handle("rapid001").is_some());
+
+    // The games are already streamed, they are not registered twice
+    assert_eq!(0, games.resume_ongoing_games(&api, create_handle).await);
+    assert_eq!(2, created.lock().unwrap().len());
+    assert_eq!(2, games.len());
+  }
+
+  async fn games_are_torn_down_when_the_game_stream_reports_the_end() {
+    let game_state = r#"{"type":"gameState","moves":"e2e4","wtime":180000,"btime":180000,
+                         "winc":0,"binc":0,"status":"STATUS"}"#;
+    let event = |status: &str| -> serde_json::Value {
+      serde_json::from_str(&game_state.replace("STATUS", status)).unwrap()
+    };
+    // Streams a registered game, with its engine done with a search
+    let new_stream = |games: &BotGames| {
+      let mut handle = (*games.get_handle("game1").unwrap()).clone();
+      handle.engine.set_maximum_depth(1);
+      handle.engine.go();
+      assert!(!handle.engine.stop_requested());
+      GameStream { games: games.clone(),
+                   handle }
+    };
+
+    // The game goes on
+    let games = new_bot_games();
+    let rx = add_game(&games, "game1", 1);
+    let stream = new_stream(&games);
+    for status in ["created", "started"] {
+      stream.on_event(event(status), String::from("game1"));
+      assert!(!game_stream_status(&event(status)).unwrap().is_terminal());
+    }
+    assert_eq!(1, games.len());
+    assert!(!stream.handle.engine.stop_requested());
+    assert_eq!(2,
+               rx.try_iter().filter(|m| matches!(m, GameMessage::Update(_))).count());
+
+    for status in ["aborted",
+                   "noStart",
+                   "mate",
+                   "resign",
+                   "draw",
+                   "timeout",
+                   "outoftime",
+                   "stalemate",
+                   "cheat",
+                   "unknownFinish",
+                   "variantEnd"]
+    {
+      let games = new_bot_games();
+      let rx = add_game(&games, "game1", 1);
+      let stream = new_stream(&games);
+
+      // The gameFinish event of the event stream may come on top of the game
+      // stream, the game is torn down once
+      stream.on_event(event(status), String::from("game1"));
+      assert!(games.is_empty(), "Game not removed on status {status}");
+      assert!(stream.handle.engine.stop_requested());
+      assert!(!games.on_game_finish("game1", None));
+      stream.on_event(event(status), String::from("game1"));
+
+      let messages: Vec<GameMessage> = rx.try_iter().collect();
+      assert!(matches!(messages[0], GameMessage::Update(_)));
+      assert!(matches!(messages[1], GameMessage::End(_)));
+      assert_eq!(1,
+                 messages.iter().filter(|m| matches!(m, GameMessage::End(_))).count(),
+                 "Game not torn down once on status {status}");
+    }
+
+    // The winner is passed on to the game loop
+    let games = new_bot_games();
+    let rx = add_game(&games, "game1", 1);
+    let stream = new_stream(&games);
+    let mate = game_state.replace(r#""status":"STATUS""#, r#""status":"mate","winner":"black""#);
+    stream.on_event(serde_json::from_str(&mate).unwrap(), String::from("game1"));
+    assert!(rx.try_iter().any(|m| matches!(m, GameMessage::End(Some(Color::Black)))));
+
+    // Events without status
+    assert_eq!(None,
+               game_stream_status(&serde_json::json!({"type": "chatLine", "text": "hi"})));
+  }
+
+  async fn operator_actions_are_routed_to_the_game() {
+    let games = new_bot_games();
+    let _rx1 = add_game(&games, "game1", 10);
+    let rx2 = add_game(&games, "game2", 10);
+
+    let api = OperatorApi::default();
+    for (action, method) in [(OperatorAction::Resign, "resign_game"),
+                             (OperatorAction::Abort, "abort_game"),
+                             (OperatorAction::Draw, "claim_draw")]
+    {
+      assert_eq!(Ok(()), games.operate(&api, "game2", action).await);
+      assert_eq!(Some((method, String::from("game2"))),
+                 api.calls.lock().unwrap().pop());
+    }
+
+    // Moves are played by the game, which stops searching first
+    let action = OperatorAction::Move(String::from("e2e4"));
+    assert_eq!(Ok(()), games.operate(&api, "game2", action).await);
+    let handle = games.get_handle("game2").unwrap();
+    assert_eq!(Some(String::from("e2e4")),
+               *handle.forced_move.lock().unwrap());
+    assert!(handle.engine.stop_requested());
+    assert!(matches!(rx2.try_recv(), Ok(GameMessage::ForcedMove)));
+    assert!(api.calls.lock().unwrap().is_empty());
+
+    // Games that are not ours
+    assert!(games.operate(&api, "game3", OperatorAction::Resign).await.is_err());
+    assert!(api.calls.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_operator_actions_are_routed_to_the_game() {
+    block_on(operator_actions_are_routed_to_the_game());
+  }
+
+  #[test]
+  fn test_games_are_torn_down_when_the_game_stream_reports_the_end() {
+    block_on(games_are_torn_down_when_the_game_stream_reports_the_end());
+  }
+
+  #[test]
+  fn test_ongoing_games_are_resumed() {
+    block_on(ongoing_games_are_resumed());
+  }
+
+  #[test]
+  fn test_games_are_registered_on_start_and_removed_on_finish() {
+    block_on(games_are_registered_on_start_and_removed_on_finish());
+  }
+
+  #[test]
+  fn test_challenges_are_declined_at_capacity() {
+    block_on(challenges_are_declined_at_capacity());
+  }
+
+  #[test]
+  fn test_shutdown_aborts_or_resigns_all_games() {
+    block_on(shutdown_aborts_or_resigns_all_games());
+  }
+
+  #[test]
+  fn test_shutdown_with_unresponsive_api() {
+    block_on(shutdown_with_unresponsive_api());
+  }
 }
@@ -1,18 +1,40 @@
 use super::message::GameMessage;
+use chess::engine::Engine;
 use lichess::traits::GameStreamHandler;
 use log::*;
-use std::sync::{mpsc, Arc};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
 type Handle = tokio::task::JoinHandle<()>;
 
-#[derive(Debug, Clone)]
+/// Time given to a search to stop before the stop request is sent again
+const SEARCH_STOP_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// What we do with an ongoing game when the bot shuts down
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ShutdownAction {
+  /// Abort the game, possible as long as both players have not moved
+  Abort,
+  /// Resign the game
+  Resign,
+}
+
+#[derive(Clone)]
 pub struct GameHandle {
   /// Channel to send messages to the game
-  pub tx:     mpsc::Sender<GameMessage>,
+  pub tx:          mpsc::Sender<GameMessage>,
   /// Handle to the game thread
-  pub handle: Arc<Handle>,
+  pub handle:      Arc<Handle>,
   /// Lichess Game ID
-  pub id:     String,
+  pub id:          String,
+  /// Number of moves played in the game, updated by the game thread
+  pub moves:       Arc<Mutex<usize>>,
+  /// Engine of the game thread. It shares its search state with the game
+  /// thread, so that we can stop the search from here
+  pub engine:      Engine,
+  /// Move requested by the operator, played by the game thread instead of
+  /// the move of the engine
+  pub forced_move: Arc<Mutex<Option<String>>>,
 }
 
 impl GameHandle {
@@ -25,6 +47,45 @@ impl GameHandle {
   pub fn is_over(&self) -> bool {
     self.handle.is_finished()
   }
+
+  /// Stops the engine if it is searching a move for the game. A watchdog
+  /// makes sure that the search does not keep running if the stop request
+  /// gets missed.
+  pub fn stop_search(&self) {
+    let _ = self.engine.stop_with_watchdog(SEARCH_STOP_GRACE_PERIOD);
+  }
+
+  /// Makes the game play a move chosen by the operator. The search is stopped
+  /// and the move it found is discarded.
+  ///
+  /// ### Arguments
+  ///
+  /// * `chess_move`: Move to play, in UCI notation
+  pub fn force_move(&self, chess_move: &str) {
+    *self.forced_move.lock().unwrap() = Some(chess_move.to_string());
+    self.stop_search();
+    let _ = self.tx.send(GameMessage::ForcedMove);
+  }
+
+  /// Decides how to leave the game if the bot shuts down
+  pub fn shutdown_action(&self) -> ShutdownAction {
+    if *self.moves.lock().unwrap() < 2 {
+      ShutdownAction::Abort
+    } else {
+      ShutdownAction::Resign
+    }
+  }
+}
+
+impl std::fmt::Debug for GameHandle {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("GameHandle")
+     .field("tx", &self.tx)
+     .field("handle", &self.handle)
+     .field("id", &self.id)
+     .field("moves", &self.moves)
+     .finish_non_exhaustive()
+  }
 }
 
 impl GameStreamHandler for GameHandle {
@@ -60,8 +121,7 @@ impl GameStreamHandler for GameHandle {
           let game_full = game_full.unwrap();
           debug!("Parsed data: {:?}", game_full);
 
-          let _ = self.tx.send(GameMessage::Update(game_full.state));
-          // self.games.update_game_and_play(game_full.state, game_id.as_str());
+          let _ = self.tx.send(GameMessage::Full(game_full));
         }
       },
 
@@ -83,15 +143,10 @@ impl GameStreamHandler for GameHandle {
           warn!("Error deserializing ChatLine data !! {:?}", error);
         } else {
           let message = result.unwrap();
-          // Ignore our own messages
-          // FIXME: remove harcoded username
-          if message.username == "schnecken_bot" {
-            return;
-          }
           info!("Received a message on game ID {} - {:?}",
                 game_id.as_str(),
                 message);
-          // self.on_incoming_message(game_id.as_str(), result.unwrap());
+          let _ = self.tx.send(GameMessage::Chat(message));
         }
       },
 
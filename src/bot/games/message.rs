@@ -5,13 +5,19 @@ pub enum GameMessage {
   /// Starts a game and allocates all the resources for playing a game on
   /// Lichess.
   Start(lichess::types::GameStart),
+  /// Full game data, received when the game stream starts. It tells where the
+  /// game started from and plays moves if it is our turn
+  Full(lichess::types::GameFull),
   /// Updates the game state, plays moves if it is our turn
   Update(lichess::types::GameState),
-  /// Notifies that the game is over (based on what the server says)
-  End(lichess::types::GameState),
+  /// Notifies that the game is over (based on what the server says), with the
+  /// winner if the game was decisive
+  End(Option<lichess::types::Color>),
   /// Notifiies of an opponent gone event. Bool indicates if the opponent is
   /// gone, or back
   OpponentGone(Option<u64>),
+  /// Incoming message in the player or spectator chat of the game
+  Chat(lichess::types::ChatMessage),
   /// Terminates the game loop, typically because the program wants to shut down
   /// But leaves the game open (no resignation)
   Terminate,
@@ -19,4 +25,6 @@ pub enum GameMessage {
   Nop,
   /// Resigns the game and stops it
   Resign,
+  /// Plays the move requested by the operator, see `GameHandle::force_move`
+  ForcedMove,
 }
@@ -1,5 +1,11 @@
+pub mod book_import;
+pub mod chat;
+pub mod correspondence;
 pub mod engine;
+pub mod explorer;
 pub mod game;
+pub mod game_log;
 pub mod games;
 pub mod handle;
 pub mod message;
+pub mod search_log;
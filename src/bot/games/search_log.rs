@@ -0,0 +1,251 @@
+use super::game_log::GAME_LOG_DIRECTORY;
+use chess::engine::Engine;
+use chess::model::moves::Move;
+use log::*;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// File in which the search statistics are appended, in the game log directory
+const SEARCH_LOG_FILE: &str = "search_stats.csv";
+/// First line of the CSV file
+const SEARCH_LOG_HEADER: &str =
+  "timestamp,game_id,fen,depth,nodes,time_ms,nps,move,eval,ponderhit_rate";
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// CSV file recording the performance of each search that gave us a move in
+/// our games, to track regressions of the engine over time. Clones write to
+/// the same file, so it is shared by all the games. It is off until switched
+/// on.
+#[derive(Debug, Clone)]
+pub struct SearchLog {
+  /// Path of the CSV file
+  path:    PathBuf,
+  /// CSV file, opened when the first row is written. Rows are written while
+  /// holding the lock, so that rows from different games do not interleave.
+  file:    Arc<Mutex<Option<File>>>,
+  /// Whether we record the searches
+  enabled: Arc<Mutex<bool>>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementations
+
+impl SearchLog {
+  /// Creates the search log in the default log directory.
+  pub fn new() -> Self {
+    Self::with_path(&Path::new(GAME_LOG_DIRECTORY).join(SEARCH_LOG_FILE))
+  }
+
+  /// Creates a search log writing to a CSV file. Rows are appended if the file
+  /// already exists.
+  ///
+  /// ### Arguments
+  ///
+  /// * `path`: Path of the CSV file
+  pub fn with_path(path: &Path) -> Self {
+    SearchLog { path:    path.to_path_buf(),
+                file:    Arc::new(Mutex::new(None)),
+                enabled: Arc::new(Mutex::new(false)), }
+  }
+
+  /// Checks if we record the searches
+  pub fn is_enabled(&self) -> bool {
+    *self.enabled.lock().unwrap()
+  }
+
+  /// Switches the search log on or off
+  ///
+  /// ### Arguments
+  ///
+  /// * `enabled`: Set to true to record the searches
+  pub fn set_enabled(&self, enabled: bool) {
+    *self.enabled.lock().unwrap() = enabled;
+  }
+
+  /// Records the search that gave us a move, if the log is on.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id`: Lichess Game ID
+  /// * `fen`:     Position that was searched
+  /// * `engine`:  Engine that completed the search
+  /// * `mv`:      Move chosen
+  /// * `eval`:    Evaluation of the move chosen
+  /// * `time_ms`: Time used to find the move, in ms
+  pub fn log_search(&self,
+                    game_id: &str,
+                    fen: &str,
+                    engine: &Engine,
+                    mv: &Move,
+                    eval: f32,
+                    time_ms: u128) {
+    if !self.is_enabled() {
+      return;
+    }
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let row = format_search_row(timestamp.as_millis(),
+                                game_id,
+                                fen,
+                                engine,
+                                mv,
+                                eval,
+                                time_ms);
+    self.write_row(&row);
+  }
+
+  /// Writes a row in the CSV file, opening it first if needed. The header is
+  /// written in new files.
+  ///
+  /// ### Arguments
+  ///
+  /// * `row`: Row to write, without new line
+  fn write_row(&self, row: &str) {
+    let mut file = self.file.lock().unwrap();
+    if file.is_none() {
+      *file = self.open_file();
+    }
+    if let Some(file) = file.as_mut() {
+      if let Err(error) = writeln!(file, "{row}") {
+        warn!("Cannot write in the search log: {}", error);
+      }
+    }
+  }
+
+  /// Opens the CSV file in append mode and writes the header if it is empty.
+  ///
+  /// ### Return value
+  ///
+  /// The file, None if it cannot be opened
+  fn open_file(&self) -> Option<File> {
+    if let Some(directory) = self.path.parent() {
+      let _ = std::fs::create_dir_all(directory);
+    }
+    let mut file = match OpenOptions::new().create(true).append(true).open(&self.path) {
+      Ok(file) => file,
+      Err(error) => {
+        warn!("Cannot open the search log {}: {}",
+              self.path.display(),
+              error);
+        return None;
+      },
+    };
+
+    let is_empty = file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(false);
+    if is_empty {
+      let _ = writeln!(file, "{SEARCH_LOG_HEADER}");
+    }
+    Some(file)
+  }
+}
+
+impl Default for SearchLog {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Formats the CSV row of a search
+///
+/// ### Arguments
+///
+/// * `timestamp_ms`: Time of the search, in ms since the UNIX epoch
+/// * `game_id`:      Lichess Game ID
+/// * `fen`:          Position that was searched
+/// * `engine`:       Engine that completed the search
+/// * `mv`:           Move chosen
+/// * `eval`:         Evaluation of the move chosen
+/// * `time_ms`:      Time used to find the move, in ms
+///
+/// ### Return value
+///
+/// Row following `SEARCH_LOG_HEADER`
+fn format_search_row(timestamp_ms: u128,
+                     game_id: &str,
+                     fen: &str,
+                     engine: &Engine,
+                     mv: &Move,
+                     eval: f32,
+                     time_ms: u128)
+                     -> String {
+  let depth = engine.get_depth();
+  let nodes = engine.get_nodes_visited();
+  let nps = nodes as u128 * 1000 / time_ms.max(1);
+  // Empty until the engine pondered
+  let ponderhit_rate = match engine.get_ponder_stats().hit_rate() {
+    Some(rate) => format!("{rate:.2}"),
+    None => String::new(),
+  };
+  format!("{timestamp_ms},{game_id},{fen},{depth},{nodes},{time_ms},{nps},{mv},{eval:.2},\
+           {ponderhit_rate}")
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Instant;
+
+  #[test]
+  fn test_search_is_appended_as_csv_row() {
+    let path = std::env::temp_dir().join(format!("schnecken_search_{}.csv", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let log = SearchLog::with_path(&path);
+
+    let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4";
+    let mut engine = Engine::new(false);
+    engine.set_position(fen);
+    engine.set_use_book(false);
+    engine.set_maximum_depth(3);
+    let start_time = Instant::now();
+    engine.go();
+    let time_ms = start_time.elapsed().as_millis();
+    let mv = engine.get_best_move().unwrap();
+    let eval = engine.get_eval().unwrap();
+
+    // Nothing is written while the log is off
+    log.log_search("abcd1234", fen, &engine, &mv, eval, time_ms);
+    assert!(!path.exists());
+
+    // Clones write to the same file, e.g. from the game threads
+    log.set_enabled(true);
+    let thread_log = log.clone();
+    let thread_engine = engine.clone();
+    let thread = std::thread::spawn(move || {
+      thread_log.log_search("abcd1234", fen, &thread_engine, &mv, eval, time_ms);
+    });
+    thread.join().unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(vec![SEARCH_LOG_HEADER], lines[..1]);
+    assert_eq!(2, lines.len());
+    let fields: Vec<&str> = lines[1].split(',').collect();
+    assert_eq!(SEARCH_LOG_HEADER.split(',').count(), fields.len());
+    assert!(fields[0].parse::<u128>().is_ok());
+    assert_eq!("abcd1234", fields[1]);
+    assert_eq!(fen, fields[2]);
+    assert_eq!("3", fields[3]);
+    assert_eq!(engine.get_nodes_visited().to_string(), fields[4]);
+    assert_eq!(time_ms.to_string(), fields[5]);
+    assert!(fields[6].parse::<u128>().is_ok());
+    assert_eq!(mv.to_string(), fields[7]);
+    assert_eq!(format!("{eval:.2}"), fields[8]);
+    assert_eq!("", fields[9]);
+  }
+}
@@ -1,4 +1,9 @@
 pub mod bot_control;
 pub mod commands;
+pub mod config;
+pub mod console;
 pub mod games;
+pub mod net;
+pub mod replay;
+pub mod seek;
 pub mod state;
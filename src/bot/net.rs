@@ -0,0 +1,168 @@
+use chess::engine::nnue::NNUE;
+use chess::engine::{default_nnue_path, Engine};
+use log::*;
+use std::sync::{Arc, Mutex};
+
+// Constants
+const NET_LOAD_COMMAND: &str = "load";
+const NET_ON_COMMAND: &str = "on";
+const NET_OFF_COMMAND: &str = "off";
+const NET_INFO_COMMAND: &str = "info";
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// NNUE shared by all the engines of the bot, so that nets can be swapped and
+/// switched on/off during a session.
+#[derive(Debug, Clone)]
+pub struct BotNet {
+  /// NNUE used by the engines. Loading a net replaces its content, so the
+  /// engines holding it pick up the new net right away.
+  nnue:    Arc<Mutex<NNUE>>,
+  /// Path of the file the NNUE was loaded from, None for an untrained net
+  path:    Arc<Mutex<Option<String>>>,
+  /// Whether the engines use the NNUE to evaluate positions
+  enabled: Arc<Mutex<bool>>,
+}
+
+impl BotNet {
+  /// Creates the bot NNUE, loading the default net file. The NNUE is off
+  /// until switched on.
+  pub fn new() -> Self {
+    let default_path = default_nnue_path();
+    let (nnue, path) = match NNUE::load_validated(&default_path) {
+      Ok(nnue) => (nnue, Some(default_path)),
+      Err(_) => (NNUE::default(), None),
+    };
+
+    BotNet { nnue:    Arc::new(Mutex::new(nnue)),
+             path:    Arc::new(Mutex::new(path)),
+             enabled: Arc::new(Mutex::new(false)), }
+  }
+
+  /// Makes an engine use the bot NNUE, if it is switched on.
+  ///
+  /// ### Arguments
+  ///
+  /// * `engine`: Engine to configure
+  pub fn attach(&self, engine: &mut Engine) {
+    engine.set_nnue(self.nnue.clone());
+    engine.set_use_nnue(self.is_enabled());
+  }
+
+  /// Checks if the engines should use the NNUE
+  pub fn is_enabled(&self) -> bool {
+    *self.enabled.lock().unwrap()
+  }
+
+  /// Switches the NNUE on or off. Engines pick it up on their next search.
+  ///
+  /// ### Arguments
+  ///
+  /// * `enabled`: Set to true to evaluate positions with the NNUE
+  pub fn set_enabled(&self, enabled: bool) {
+    *self.enabled.lock().unwrap() = enabled;
+  }
+
+  /// Loads a net file into the NNUE shared by the engines.
+  /// The current net is kept if the file cannot be loaded.
+  ///
+  /// ### Arguments
+  ///
+  /// * `path`: Path of the NNUE file to load
+  pub fn load(&self, path: &str) -> std::io::Result<()> {
+    let nnue = NNUE::load_validated(path)?;
+    *self.nnue.lock().unwrap() = nnue;
+    *self.path.lock().unwrap() = Some(path.to_string());
+    Ok(())
+  }
+
+  /// Describes the NNUE state: on/off, architecture and file it was loaded
+  /// from.
+  pub fn info(&self) -> String {
+    let origin = match self.path.lock().unwrap().as_ref() {
+      Some(path) => format!("loaded from {path}"),
+      None => String::from("untrained net"),
+    };
+    format!("NNUE {} - architecture {} - {}",
+            if self.is_enabled() { "on" } else { "off" },
+            self.nnue.lock().unwrap().architecture(),
+            origin)
+  }
+
+  /// Executes a net console command: `load <path>`, `on`, `off` or `info`
+  ///
+  /// ### Arguments
+  ///
+  /// * `arguments`: Text following the net command
+  ///
+  /// ### Return value
+  ///
+  /// Message describing the outcome of the command
+  pub fn execute_command(&self, arguments: &str) -> String {
+    let (command, path) = arguments.trim().split_once(' ').unwrap_or((arguments.trim(), ""));
+    match command {
+      NET_LOAD_COMMAND => match self.load(path.trim()) {
+        Ok(()) => {
+          info!("Loaded NNUE {}", path.trim());
+          self.info()
+        },
+        Err(e) => format!("Could not load NNUE '{}': {}", path.trim(), e),
+      },
+      NET_ON_COMMAND => {
+        self.set_enabled(true);
+        self.info()
+      },
+      NET_OFF_COMMAND => {
+        self.set_enabled(false);
+        self.info()
+      },
+      NET_INFO_COMMAND => self.info(),
+      _ => format!("Unknown net command '{}'. Usage: net load <path> | on | off | info",
+                   arguments.trim()),
+    }
+  }
+}
+
+impl Default for BotNet {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::engine::eval::position::evaluate_board;
+  use chess::model::game_state::GameState;
+
+  #[test]
+  fn test_net_commands_swap_the_engine_evaluation() {
+    let path = std::env::temp_dir().join("schnecken_bot_test_net.nnue");
+    let path = path.to_str().unwrap();
+    NNUE::default().save(path).unwrap();
+
+    let net = BotNet::new();
+    let mut engine = Engine::new(false);
+    let game_state =
+      GameState::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+    let classical_eval = evaluate_board(&game_state);
+
+    // Invalid files are rejected
+    assert!(net.execute_command("load /does/not/exist.nnue").starts_with("Could not load"));
+
+    assert!(net.execute_command(&format!("load {path}")).contains(path));
+    assert!(net.execute_command("on").starts_with("NNUE on - architecture 768-64-8-1"));
+    net.attach(&mut engine);
+    assert_ne!(classical_eval, engine.static_eval(&game_state));
+
+    net.execute_command("off");
+    net.attach(&mut engine);
+    assert_eq!(classical_eval, engine.static_eval(&game_state));
+
+    let _ = std::fs::remove_file(path);
+  }
+}
@@ -0,0 +1,241 @@
+use chess::engine::books::PGN_REGEX;
+use chess::model::board::Board;
+use chess::model::game_state::{GameState, START_POSITION_FEN};
+use chess::model::moves::Move;
+use chess::model::piece::Color;
+use regex::Regex;
+
+// Constants
+/// PGN tag giving the starting position of games that do not start from the
+/// initial position
+const FEN_TAG: &str = "[FEN \"";
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Game loaded from a PGN, that we step through move by move in the console to
+/// look at past games.
+#[derive(Debug, Clone)]
+pub struct Replay {
+  /// Position from which the game started
+  start_fen: String,
+  /// Moves of the game
+  moves:     Vec<Move>,
+  /// Number of moves played to reach the current position
+  ply:       usize,
+}
+
+impl Replay {
+  /// Loads a game from a PGN, positioned at the start of the game.
+  ///
+  /// ### Arguments
+  ///
+  /// * `pgn`: PGN of the game, with or without tags. The `FEN` tag is used as
+  ///   starting position when present.
+  ///
+  /// ### Return value
+  ///
+  /// The replay, or a message explaining why the PGN cannot be loaded
+  pub fn from_pgn(pgn: &str) -> Result<Self, String> {
+    let start_fen = match pgn.lines().find_map(|l| l.trim().strip_prefix(FEN_TAG)) {
+      Some(tag) => tag.trim_end_matches([']', '"']).to_string(),
+      None => START_POSITION_FEN.to_string(),
+    };
+    if let Err(e) = Board::try_from_fen(&start_fen) {
+      return Err(format!("Invalid FEN tag '{}': {}", start_fen, e));
+    }
+
+    // Keep the move text only, without tags and comments
+    let mut move_text: String =
+      pgn.lines().filter(|l| !l.trim_start().starts_with('[')).collect::<Vec<&str>>().join(" ");
+    while let Some(start) = move_text.find('{') {
+      let end = move_text[start..].find('}').map(|i| start + i + 1).unwrap_or(move_text.len());
+      move_text.replace_range(start..end, " ");
+    }
+    move_text.push(' ');
+
+    let mut game_state = GameState::from_fen(&start_fen);
+    let mut moves: Vec<Move> = Vec::new();
+    let pgn_re = Regex::new(PGN_REGEX).unwrap();
+    for capture in pgn_re.captures_iter(&move_text) {
+      let notation = capture.name("mv").unwrap().as_str();
+      match game_state.board.find_move_from_pgn_notation(notation) {
+        Ok(m) => {
+          game_state.apply_move(&m);
+          moves.push(m);
+        },
+        Err(()) => {
+          return Err(format!("Illegal move {} in position {}",
+                             notation,
+                             game_state.to_fen()))
+        },
+      }
+    }
+
+    if moves.is_empty() {
+      return Err(String::from("No move found in the PGN"));
+    }
+
+    Ok(Replay { start_fen,
+                moves,
+                ply: 0 })
+  }
+
+  /// Loads a game from a PGN file, positioned at the start of the game.
+  ///
+  /// ### Arguments
+  ///
+  /// * `path`: Path of the PGN file. Only the first game of the file is used.
+  ///
+  /// ### Return value
+  ///
+  /// The replay, or a message explaining why the file cannot be loaded
+  pub fn from_file(path: &str) -> Result<Self, String> {
+    let pgn = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+
+    // Tags after the move text belong to the next game
+    let mut first_game: Vec<&str> = Vec::new();
+    let mut in_move_text = false;
+    for line in pgn.lines() {
+      let is_tag = line.trim_start().starts_with('[');
+      if is_tag && in_move_text {
+        break;
+      }
+      in_move_text |= !is_tag && !line.trim().is_empty();
+      first_game.push(line);
+    }
+    Self::from_pgn(&first_game.join("\n"))
+  }
+
+  /// Number of moves played to reach the current position
+  pub fn get_ply(&self) -> usize {
+    self.ply
+  }
+
+  /// Number of moves in the game
+  pub fn get_move_count(&self) -> usize {
+    self.moves.len()
+  }
+
+  /// Goes one move forward
+  ///
+  /// ### Return value
+  ///
+  /// False if we are already at the end of the game
+  pub fn step_forward(&mut self) -> bool {
+    if self.ply >= self.moves.len() {
+      return false;
+    }
+    self.ply += 1;
+    true
+  }
+
+  /// Goes one move backward
+  ///
+  /// ### Return value
+  ///
+  /// False if we are already at the start of the game
+  pub fn step_backward(&mut self) -> bool {
+    if self.ply == 0 {
+      return false;
+    }
+    self.ply -= 1;
+    true
+  }
+
+  /// Computes the current position of the replay
+  pub fn get_game_state(&self) -> GameState {
+    let mut game_state = GameState::from_fen(&self.start_fen);
+    for m in &self.moves[..self.ply] {
+      game_state.apply_move(m);
+    }
+    game_state
+  }
+
+  /// Describes the last move played, e.g. `12. Nf3` or `12... Nc6`, None at the
+  /// start of the game
+  pub fn last_move_description(&self) -> Option<String> {
+    if self.ply == 0 {
+      return None;
+    }
+    let mut game_state = GameState::from_fen(&self.start_fen);
+    for m in &self.moves[..self.ply - 1] {
+      game_state.apply_move(m);
+    }
+    let dots = match game_state.board.side_to_play {
+      Color::White => ".",
+      Color::Black => "...",
+    };
+    Some(format!("{}{} {}",
+                 game_state.move_count / 2 + 1,
+                 dots,
+                 game_state.board.move_to_san(&self.moves[self.ply - 1])))
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PGN: &str = "[Event \"Rated blitz game\"]\n\
+                     [Site \"https://lichess.org/abcd1234\"]\n\
+                     [White \"schnecken_bot\"]\n\
+                     [Black \"somebody\"]\n\n\
+                     1. e4 { [%eval 0.36] [%clk 0:03:00] } 1... e5 2. Nf3 Nc6 3. Bb5 a6 \
+                     4. Bxc6 dxc6 5. O-O f6 1-0\n\n\n";
+
+  #[test]
+  fn test_replay_steps_through_a_pgn() {
+    let mut replay = Replay::from_pgn(PGN).unwrap();
+    assert_eq!(10, replay.get_move_count());
+    assert_eq!(START_POSITION_FEN, replay.get_game_state().to_fen());
+    assert_eq!(None, replay.last_move_description());
+    assert!(!replay.step_backward());
+
+    for _ in 0..6 {
+      assert!(replay.step_forward());
+    }
+    assert_eq!(6, replay.get_ply());
+    assert_eq!("r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4",
+               replay.get_game_state().to_fen());
+    assert_eq!(Some(String::from("3... a6")),
+               replay.last_move_description());
+
+    assert!(replay.step_backward());
+    assert_eq!("r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+               replay.get_game_state().to_fen());
+    assert_eq!(Some(String::from("3. Bb5")), replay.last_move_description());
+
+    while replay.step_forward() {}
+    assert_eq!(10, replay.get_ply());
+    assert_eq!("r1bqkbnr/1pp3pp/p1p2p2/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 0 6",
+               replay.get_game_state().to_fen());
+  }
+
+  #[test]
+  fn test_replay_from_a_custom_position() {
+    let pgn = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n\n1... Kd7 2. Ra7+ Kc6 *\n";
+    let mut replay = Replay::from_pgn(pgn).unwrap();
+    assert_eq!(3, replay.get_move_count());
+    assert_eq!("4k3/8/8/8/8/8/8/R3K3 b - - 0 1",
+               replay.get_game_state().to_fen());
+
+    replay.step_forward();
+    replay.step_forward();
+    assert_eq!("8/R2k4/8/8/8/8/8/4K3 b - - 2 2",
+               replay.get_game_state().to_fen());
+    assert_eq!(Some(String::from("2. Ra7+")),
+               replay.last_move_description());
+  }
+
+  #[test]
+  fn test_replay_rejects_invalid_pgns() {
+    assert!(Replay::from_pgn("1. e4 e5 2. Ke3 *").is_err());
+    assert!(Replay::from_pgn("[FEN \"8/8/8 w - - 0 1\"]\n\n1. e4 *").is_err());
+    assert!(Replay::from_pgn("[Event \"Nothing\"]\n\n*").is_err());
+    assert!(Replay::from_file("/does/not/exist.pgn").is_err());
+  }
+}
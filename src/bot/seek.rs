@@ -0,0 +1,271 @@
+use lichess::traits::GameSeeker;
+use lichess::types::Clock;
+use log::*;
+use rand::seq::SliceRandom;
+use std::sync::{Arc, Mutex};
+
+// Constants
+/// Interval at which we check if we are idle and should seek a game
+pub const AUTOSEEK_INTERVAL_S: u64 = 60;
+/// Default seek: 3+2, rated
+const DEFAULT_SEEK_MINUTES: i32 = 3;
+const DEFAULT_SEEK_INCREMENT_S: i32 = 2;
+/// Number of online bots among which we pick an opponent when we cannot seek
+const ONLINE_BOTS_TO_LIST: usize = 50;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Settings for seeking games on our own, to keep the bot active when nobody
+/// challenges us.
+#[derive(Debug, Clone)]
+pub struct AutoSeek {
+  /// Whether we periodically seek a game when idle
+  enabled:  Arc<Mutex<bool>>,
+  /// Clock and rated flag of the games we seek
+  settings: Arc<Mutex<(Clock, bool)>>,
+}
+
+impl AutoSeek {
+  /// Creates the auto-seek settings, off by default, seeking rated 3+2 games.
+  pub fn new() -> Self {
+    let clock = Clock { initial:   DEFAULT_SEEK_MINUTES * 60,
+                        increment: DEFAULT_SEEK_INCREMENT_S,
+                        totaltime: None, };
+    AutoSeek { enabled:  Arc::new(Mutex::new(false)),
+               settings: Arc::new(Mutex::new((clock, true))), }
+  }
+
+  /// Checks if we periodically seek games
+  pub fn is_enabled(&self) -> bool {
+    *self.enabled.lock().unwrap()
+  }
+
+  /// Switches the periodic seeks on or off
+  ///
+  /// ### Arguments
+  ///
+  /// * `enabled`: Set to true to seek games when idle
+  pub fn set_enabled(&self, enabled: bool) {
+    *self.enabled.lock().unwrap() = enabled;
+  }
+
+  /// Sets the games we seek
+  ///
+  /// ### Arguments
+  ///
+  /// * `clock`: Clock settings of the games
+  /// * `rated`: Set to true to seek rated games
+  pub fn set_seek(&self, clock: Clock, rated: bool) {
+    *self.settings.lock().unwrap() = (clock, rated);
+  }
+
+  /// Seeks a game if auto-seek is on and we can play one more game.
+  ///
+  /// ### Arguments
+  ///
+  /// * `api`:       API used to create the seek
+  /// * `username`:  Our username, so that we do not challenge ourselves
+  /// * `games`:     Number of games we are playing
+  /// * `max_games`: Maximum number of games we play at the same time
+  ///
+  /// ### Return value
+  ///
+  /// True if we created a seek or sent a challenge
+  pub async fn seek_if_idle<T: GameSeeker>(&self,
+                                           api: &T,
+                                           username: &str,
+                                           games: usize,
+                                           max_games: usize)
+                                           -> bool {
+    if !self.is_enabled() || games >= max_games {
+      return false;
+    }
+
+    let (clock, rated) = self.settings.lock().unwrap().clone();
+    seek_or_challenge_bot(api, username, &clock, rated).await
+  }
+}
+
+impl Default for AutoSeek {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Seeks a game. BOT accounts are not allowed to seek games, so if Lichess
+/// refuses the seek, we challenge a random online bot instead.
+///
+/// ### Arguments
+///
+/// * `api`:      API used to seek the game
+/// * `username`: Our username, so that we do not challenge ourselves
+/// * `clock`:    Clock settings of the game
+/// * `rated`:    Set to true to seek a rated game
+///
+/// ### Return value
+///
+/// True if we created a seek or sent a challenge
+pub async fn seek_or_challenge_bot<T: GameSeeker>(api: &T,
+                                                  username: &str,
+                                                  clock: &Clock,
+                                                  rated: bool)
+                                                  -> bool {
+  info!("Seeking a {} {}+{} game",
+        if rated { "rated" } else { "casual" },
+        clock.initial / 60,
+        clock.increment);
+  if api.create_seek(clock, rated).await.is_ok() {
+    return true;
+  }
+
+  let Ok(bots) = api.get_online_bots(ONLINE_BOTS_TO_LIST).await else {
+    warn!("Could not list the online bots to challenge one");
+    return false;
+  };
+  let opponents: Vec<&String> =
+    bots.iter().filter(|bot| !bot.eq_ignore_ascii_case(username)).collect();
+  let Some(opponent) = opponents.choose(&mut rand::thread_rng()) else {
+    info!("No online bot to challenge");
+    return false;
+  };
+
+  info!("Challenging {} instead of seeking a game", opponent);
+  api.send_challenge(opponent, clock, rated).await.is_ok()
+}
+
+/// Parses the arguments of the seek command: `<time> <increment> [rated]`
+///
+/// ### Arguments
+///
+/// * `arguments` - Text following the seek command. Time in minutes, increment
+///   in seconds
+///
+/// ### Return value
+///
+/// The clock and rated flag of the seek. None if the arguments are invalid.
+pub fn parse_seek_arguments(arguments: &str) -> Option<(Clock, bool)> {
+  let words: Vec<&str> = arguments.split_whitespace().collect();
+  if words.len() < 2 || words.len() > 3 {
+    return None;
+  }
+  let minutes = words[0].parse::<i32>().ok()?;
+  let increment = words[1].parse::<i32>().ok()?;
+  if minutes < 0 || increment < 0 || minutes + increment == 0 {
+    return None;
+  }
+  let rated = match words.get(2) {
+    None => false,
+    Some(&"rated") => true,
+    Some(_) => return None,
+  };
+
+  Some((Clock { initial: minutes * 60,
+                increment,
+                totaltime: None },
+        rated))
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Counts the seeks and challenges instead of calling Lichess
+  #[derive(Default)]
+  struct MockApi {
+    /// Refuse the seeks, as Lichess does for BOT accounts
+    refuse_seeks: bool,
+    online_bots:  Vec<String>,
+    seeks:        Mutex<Vec<(Clock, bool)>>,
+    challenges:   Mutex<Vec<(String, Clock, bool)>>,
+  }
+
+  impl GameSeeker for MockApi {
+    async fn create_seek(&self, clock: &Clock, rated: bool) -> Result<(), ()> {
+      if self.refuse_seeks {
+        return Err(());
+      }
+      self.seeks.lock().unwrap().push((clock.clone(), rated));
+      Ok(())
+    }
+
+    async fn get_online_bots(&self, count: usize) -> Result<Vec<String>, ()> {
+      Ok(self.online_bots.iter().take(count).cloned().collect())
+    }
+
+    async fn send_challenge(&self, player: &str, clock: &Clock, rated: bool) -> Result<(), ()> {
+      self.challenges.lock().unwrap().push((player.to_string(), clock.clone(), rated));
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_autoseek_only_when_idle() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let api = MockApi::default();
+    let autoseek = AutoSeek::new();
+
+    // Off by default
+    assert!(!rt.block_on(autoseek.seek_if_idle(&api, "schnecken_bot", 0, 2)));
+    assert!(api.seeks.lock().unwrap().is_empty());
+
+    autoseek.set_enabled(true);
+    let (clock, rated) = parse_seek_arguments("5 3").unwrap();
+    autoseek.set_seek(clock, rated);
+    assert!(rt.block_on(autoseek.seek_if_idle(&api, "schnecken_bot", 1, 2)));
+    // At the game limit, we do not seek
+    assert!(!rt.block_on(autoseek.seek_if_idle(&api, "schnecken_bot", 2, 2)));
+
+    let seeks = api.seeks.lock().unwrap();
+    assert_eq!(1, seeks.len());
+    assert_eq!(300, seeks[0].0.initial);
+    assert_eq!(3, seeks[0].0.increment);
+    assert!(!seeks[0].1);
+    assert!(api.challenges.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_challenge_a_bot_when_the_seek_is_refused() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let (clock, rated) = parse_seek_arguments("3 2 rated").unwrap();
+
+    // We never challenge ourselves
+    let api = MockApi { refuse_seeks: true,
+                        online_bots: vec![String::from("schnecken_bot"), String::from("maia1")],
+                        ..Default::default() };
+    for _ in 0..5 {
+      assert!(rt.block_on(seek_or_challenge_bot(&api, "Schnecken_Bot", &clock, rated)));
+    }
+    let challenges = api.challenges.lock().unwrap();
+    assert_eq!(5, challenges.len());
+    assert!(challenges.iter().all(|c| *c == (String::from("maia1"), clock.clone(), true)));
+    assert!(api.seeks.lock().unwrap().is_empty());
+
+    // Nobody to challenge
+    let api = MockApi { refuse_seeks: true,
+                        online_bots: vec![String::from("schnecken_bot")],
+                        ..Default::default() };
+    assert!(!rt.block_on(seek_or_challenge_bot(&api, "schnecken_bot", &clock, rated)));
+    assert!(api.challenges.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_parse_seek_arguments() {
+    let (clock, rated) = parse_seek_arguments("3 2 rated").unwrap();
+    assert_eq!(180, clock.initial);
+    assert_eq!(2, clock.increment);
+    assert!(rated);
+    assert!(!parse_seek_arguments("1 0").unwrap().1);
+
+    assert!(parse_seek_arguments("3").is_none());
+    assert!(parse_seek_arguments("3 x").is_none());
+    assert!(parse_seek_arguments("0 0").is_none());
+    assert!(parse_seek_arguments("3 2 casual").is_none());
+  }
+}
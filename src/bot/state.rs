@@ -1,18 +1,32 @@
+use super::games::book_import::*;
+use super::games::correspondence::*;
 use super::games::game::Game;
-use crate::bot::games::games::BotGames;
-use crate::bot::games::handle::GameHandle;
-use lichess::api::LichessApi;
-use lichess::types::Clock;
+use super::games::handle::GameHandle;
+use super::games::search_log::SearchLog;
+use crate::bot::config::{default_engine_options, BotConfig};
+use crate::bot::games::games::{BotGames, OperatorAction};
+use crate::bot::net::BotNet;
+use crate::bot::replay::Replay;
+use crate::bot::seek::*;
+use chess::engine::config::options::EngineOptions;
+use lichess::api::account::bot_account_status;
+use lichess::api::{ApiError, LichessApi};
+use lichess::types::{BotAccountStatus, Clock};
 // Other libraries from our repo
 use log::*;
 use rand::Rng;
 use serde_json::Value as JsonValue;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 
 // -----------------------------------------------------------------------------
 // Constants
 const DEFAULT_USERNAME: &str = "schnecken_bot";
+/// Maximum time we wait for Lichess when leaving our games at shutdown
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 const LICHESS_PLAYERS: &str =
   include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/players_we_like.txt"));
 
@@ -21,20 +35,61 @@ const LICHESS_PLAYERS: &str =
 
 pub type BotStateRef = &'static BotState;
 
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Checks that our account can use the bot API endpoints, and explains what to
+/// do otherwise. They reply with errors for accounts without the BOT title.
+///
+/// ### Arguments
+///
+/// * `profile` - Our profile, received from the `account` API endpoint
+fn check_bot_account(profile: &JsonValue) {
+  match bot_account_status(profile) {
+    BotAccountStatus::Bot => {},
+    BotAccountStatus::Upgradable => {
+      warn!("Our account is not a bot account yet, the bot API endpoints will fail.");
+      warn!("Type 'upgrade' to upgrade it to a bot account. This cannot be undone, the account \
+             will not be able to play as a human anymore.");
+    },
+    BotAccountStatus::NotUpgradable => {
+      error!("Our account is not a bot account and it already played games, so it cannot be \
+              upgraded. Create a new Lichess account without any game and use an API token \
+              from it.");
+    },
+  }
+}
+
 // -----------------------------------------------------------------------------
 // Main BotState
 
 pub struct BotState {
   /// Lichess API
-  pub api:   &'static LichessApi,
+  pub api:        &'static LichessApi,
   /// Cache of our lichess username
-  username:  String,
+  username:       String,
   /// List of ongoing games
-  games:     BotGames,
+  games:          BotGames,
+  /// NNUE shared by the engines of all our games
+  pub net:        BotNet,
+  /// Whether and which games we seek when idle
+  pub autoseek:   AutoSeek,
+  /// Records the performance of the searches of our games
+  pub search_log: SearchLog,
+  /// Whether our games look up the opening explorer once out of book
+  explorer:       Arc<Mutex<bool>>,
+  /// Options the engines of new games start from
+  engine_options: Arc<Mutex<EngineOptions>>,
+  /// Game loaded in the console to step through it
+  pub replay:     Arc<Mutex<Option<Replay>>>,
   /// Timestamp of the last game we played
-  last_game: Arc<Mutex<std::time::Instant>>,
+  last_game:      Arc<Mutex<std::time::Instant>>,
   /// Bool value indicating if the bot should exit
-  exit:      Arc<Mutex<bool>>,
+  exit:           Arc<Mutex<bool>>,
+  /// Wakes up whoever waits for the exit request
+  exit_signal:    Arc<Notify>,
+  /// Bool value indicating if ongoing games should be left when exiting
+  resign:         Arc<Mutex<bool>>,
 }
 
 // We pass bot state references accross threads
@@ -43,28 +98,49 @@ unsafe impl Sync for BotState {}
 impl BotState {
   /// Instantiates a new bot state, using a given api token for Lichess
   /// (to identify itself in the games, challenges, etc.)
-  pub async fn new(api_token: &str) -> BotStateRef {
+  ///
+  /// ### Arguments
+  ///
+  /// * `api_token` - Lichess API token of our account
+  ///
+  /// ### Return value
+  ///
+  /// The bot state, `ApiError::Unauthorized` if Lichess refuses the token. We
+  /// still start if Lichess cannot be reached, with a default username.
+  pub async fn new(api_token: &str) -> Result<BotStateRef, ApiError> {
     let api: &'static _ = Box::leak(Box::new(LichessApi::new(api_token)));
     let bot_games = BotGames::new(api);
 
     // Find out our username with the API token:
     let mut username = String::from(DEFAULT_USERNAME);
 
-    let account_info = api.get_profile().await;
-    if account_info.is_ok() {
-      let json = account_info.unwrap();
-      if json["id"].as_str().is_some() {
-        username = String::from(json["id"].as_str().unwrap());
-      }
+    match api.get_profile().await {
+      Ok(json) => {
+        if let Some(id) = json["id"].as_str() {
+          username = String::from(id);
+        }
+        check_bot_account(&json);
+      },
+      Err(ApiError::Unauthorized) => return Err(ApiError::Unauthorized),
+      Err(error) => warn!("Could not read our Lichess profile: {}", error),
     }
 
     let bot_state_ref: &'static _ =
       Box::leak(Box::new(BotState { api,
                                     username,
                                     games: bot_games,
+                                    net: BotNet::new(),
+                                    autoseek: AutoSeek::new(),
+                                    search_log: SearchLog::new(),
+                                    explorer: Arc::new(Mutex::new(false)),
+                                    engine_options:
+                                      Arc::new(Mutex::new(default_engine_options())),
+                                    replay: Arc::new(Mutex::new(None)),
                                     last_game: Arc::new(Mutex::new(std::time::Instant::now())),
-                                    exit: Arc::new(Mutex::new(false)) }));
-    bot_state_ref
+                                    exit: Arc::new(Mutex::new(false)),
+                                    exit_signal: Arc::new(Notify::new()),
+                                    resign: Arc::new(Mutex::new(false)) }));
+    Ok(bot_state_ref)
   }
 
   /// Checks if the bot was configured to exit.
@@ -74,12 +150,101 @@ impl BotState {
   }
 
   /// Indicates the bot that it should stop and exit everything.
+  ///
+  /// ### Arguments
+  ///
+  /// * `resign` - Set to true to abort/resign the ongoing games when exiting
   pub fn request_exit(&self, resign: bool) {
     if resign {
-      self.games.terminate_all();
+      *self.resign.lock().unwrap() = true;
     }
     let mut exit = self.exit.lock().unwrap();
     *exit = true;
+    self.exit_signal.notify_one();
+  }
+
+  /// Waits until the bot is requested to exit
+  pub async fn exit_requested(&self) {
+    while !self.should_exit() {
+      self.exit_signal.notified().await;
+    }
+  }
+
+  /// Leaves the ongoing games before exiting, if requested with
+  /// `request_exit`. Games that just started are aborted, the other ones are
+  /// resigned. Returns after `SHUTDOWN_TIMEOUT` at the latest.
+  pub async fn shutdown(&self) {
+    if !*self.resign.lock().unwrap() {
+      return;
+    }
+    info!("Leaving {} ongoing games", self.games.len());
+    self.games.shutdown(self.api, SHUTDOWN_TIMEOUT).await;
+  }
+
+  /// Sets the maximum number of games we play at the same time. Challenges
+  /// received above this number are declined.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_games` - Maximum number of simultaneous games
+  pub fn set_max_concurrent_games(&self, max_games: usize) {
+    self.games.set_max_concurrent_games(max_games);
+    info!("Playing at most {} games at the same time",
+          self.games.get_max_concurrent_games());
+  }
+
+  /// Gathers the current settings of the bot and of the engines of new games
+  pub fn get_config(&self) -> BotConfig {
+    BotConfig { max_games:  self.games.get_max_concurrent_games(),
+                autoseek:   self.autoseek.is_enabled(),
+                search_log: self.search_log.is_enabled(),
+                nnue:       self.net.is_enabled(),
+                explorer:   *self.explorer.lock().unwrap(),
+                engine:     self.engine_options.lock().unwrap().clone(), }
+  }
+
+  /// Applies settings to the bot. The engine options apply to the games
+  /// started from now on, ongoing games keep their engine.
+  ///
+  /// ### Arguments
+  ///
+  /// * `config` - Settings to apply
+  pub fn apply_config(&self, config: &BotConfig) {
+    self.set_max_concurrent_games(config.max_games);
+    self.autoseek.set_enabled(config.autoseek);
+    self.search_log.set_enabled(config.search_log);
+    self.net.set_enabled(config.nnue);
+    *self.explorer.lock().unwrap() = config.explorer;
+    *self.engine_options.lock().unwrap() = config.engine.clone();
+  }
+
+  /// Creates the handle of a game and starts playing it
+  ///
+  /// ### Arguments
+  ///
+  /// * `game` - Game start data received from Lichess
+  fn create_game(&self, game: lichess::types::GameStart) -> GameHandle {
+    let options = self.engine_options.lock().unwrap().clone();
+    Game::new(game,
+              self.api,
+              &self.username,
+              &self.net,
+              &self.search_log,
+              &options,
+              *self.explorer.lock().unwrap())
+  }
+
+  /// Executes an action of the operator on one of our games, e.g. resigning
+  /// or forcing a move. Errors are printed on the console.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` - Game ID on which the action applies
+  /// * `action` -  Action to execute
+  pub async fn operate_game(&self, game_id: &str, action: OperatorAction) {
+    if let Err(error) = self.games.operate(self.api, game_id, action).await {
+      println!("{}", error);
+    }
   }
 
   /// Update last_game time-stamp
@@ -93,6 +258,7 @@ impl BotState {
   pub fn start(self: BotStateRef) {
     info!("Starting the Lichess bot... ");
     info!("Watch it at: https://lichess.org/@/{}", self.username);
+    load_learnt_books();
     // Start streaming incoming events
     // Okay this is quite ugly, not sure how to do better :-D
 
@@ -105,8 +271,17 @@ impl BotState {
 
     tokio::spawn(async { self.restart_incoming_streams(handle).await });
 
+    // We may have restarted in the middle of games, pick them up again
+    tokio::spawn(async { self.resume_ongoing_games().await });
+
     // Start a thread that sends challenges with a given interval:
     tokio::spawn(async { self.send_challenges_with_interval(7200).await });
+
+    // Correspondence games are not streamed, we look them up periodically
+    tokio::spawn(async { self.poll_correspondence_games().await });
+
+    // Seek games when idle, if enabled with the autoseek command
+    tokio::spawn(async { self.seek_games_when_idle().await });
   }
 
   /// Checks if the stream_incoming_events has died and restarts it if that's
@@ -162,6 +337,81 @@ impl BotState {
     }
   }
 
+  /// Seeks a game, unless we are already playing as many games as we can.
+  /// The game settings are kept for the next automatic seeks.
+  ///
+  /// ### Arguments
+  ///
+  /// * `clock` - Clock settings of the game
+  /// * `rated` - Set to true to seek a rated game
+  pub async fn seek_game(&self, clock: Clock, rated: bool) {
+    self.autoseek.set_seek(clock.clone(), rated);
+    if self.games.len() >= self.games.get_max_concurrent_games() {
+      info!("Not seeking a game, we are already playing too many games");
+      return;
+    }
+    if !seek_or_challenge_bot(self.api, &self.username, &clock, rated).await {
+      warn!("Could not seek a game");
+    }
+  }
+
+  /// Periodically seeks a game when auto-seek is on and we can play more
+  /// games.
+  async fn seek_games_when_idle(self: BotStateRef) {
+    loop {
+      tokio::time::sleep(tokio::time::Duration::from_secs(AUTOSEEK_INTERVAL_S)).await;
+      self.autoseek
+          .seek_if_idle(self.api,
+                        &self.username,
+                        self.games.len(),
+                        self.games.get_max_concurrent_games())
+          .await;
+    }
+  }
+
+  /// Periodically plays a move in the correspondence games where it is our
+  /// turn. They last for days, so we do not keep them in our set of games and
+  /// the bot may restart between two moves.
+  async fn poll_correspondence_games(self: BotStateRef) {
+    let mut state = CorrespondenceState::new();
+    loop {
+      // Spawn blocking as calculating chess moves is CPU intense and would block
+      // the async runtime
+      let result = tokio::task::spawn_blocking(move || {
+                     let moves =
+                       Handle::current().block_on(play_correspondence_games(self.api, &mut state));
+                     (moves, state)
+                   }).await;
+
+      state = match result {
+        Ok((moves, state)) => {
+          if moves > 0 {
+            info!("Played {} moves in correspondence games", moves);
+            self.update_last_game_timestamp();
+          }
+          state
+        },
+        Err(error) => {
+          error!("Error while playing correspondence games: {:?}", error);
+          CorrespondenceState::new()
+        },
+      };
+
+      tokio::time::sleep(Duration::from_secs(CORRESPONDENCE_POLL_INTERVAL_S)).await;
+    }
+  }
+
+  /// Registers and streams the real-time games that are already ongoing on
+  /// Lichess, so that we keep playing them after a restart.
+  async fn resume_ongoing_games(self: BotStateRef) {
+    let create_handle = |game| self.create_game(game);
+    let resumed = self.games.resume_ongoing_games(self.api, create_handle).await;
+    if resumed > 0 {
+      info!("Resumed {} ongoing games", resumed);
+      self.update_last_game_timestamp();
+    }
+  }
+
   //----------------------------------------------------------------------------
   // Stream handlers
 
@@ -174,9 +424,16 @@ impl BotState {
     // Update the last game time-stamp
     self.update_last_game_timestamp();
 
-    // Create a game handle and start the game
-    let game_handle: GameHandle = Game::new(game, self.api);
-    self.games.add(game_handle);
+    if game.is_correspondence() {
+      info!("Correspondence game {} started, we will play it when polling our games",
+            game.game_id);
+      return;
+    }
+
+    // Create a game handle and start the game, unless it is already running
+    let game_id = game.game_id.clone();
+    let create_handle = || self.create_game(game);
+    self.games.on_game_start(&game_id, create_handle);
   }
 
   /// Handles incoming gameFinish events
@@ -188,8 +445,24 @@ impl BotState {
     // Update the last game time-stamp
     self.update_last_game_timestamp();
 
-    // Remove the game from the list of games
-    self.games.remove(&game.game_id);
+    // Learn the openings of the games we won
+    if game.winner == Some(game.color) {
+      let api = self.api;
+      let game_id = game.game_id.clone();
+      let color = game.color;
+      tokio::spawn(async move {
+        import_game_into_book(api, &game_id, GAME_EXPORT_RETRY_DELAY, |pgn| {
+          learn_opening(pgn, color)
+        }).await
+      });
+    }
+
+    if game.is_correspondence() {
+      return;
+    }
+
+    // Remove the game from the list of games and stop its game loop
+    self.games.on_game_finish(&game.game_id, game.winner);
   }
 
   /// Handles incoming gameStart events
@@ -222,8 +495,11 @@ impl BotState {
             challenge.challenger.id);
     }
 
-    // We do not play non-standard for now
-    if challenge.variant.key != lichess::types::VariantKey::Standard {
+    // We do not play non-standard for now, custom start positions are fine
+    let variant = &challenge.variant.key;
+    if *variant != lichess::types::VariantKey::Standard
+       && *variant != lichess::types::VariantKey::FromPosition
+    {
       info!("Ignoring challenge for variant {:?}. We play only standard for now.",
             challenge.variant);
 
@@ -247,16 +523,25 @@ impl BotState {
     }
 
     // We do not play infinitely long games either
-    if challenge.time_control.control_type != lichess::types::TimeControlType::Clock {
-      info!("Ignoring non-real-time challenge.");
+    if challenge.time_control.control_type == lichess::types::TimeControlType::Unlimited {
+      info!("Ignoring challenge without time control.");
       tokio::spawn(async move {
         self.api.decline_challenge(&challenge.id, lichess::types::DECLINE_TIME_CONTROL).await
       });
       return;
     }
 
-    // Do not accept we are playing at capacity
-    if self.games.is_full() {
+    // Correspondence games are played when polling our games, they do not take
+    // a slot of the real-time games.
+    if challenge.time_control.control_type == lichess::types::TimeControlType::Correspondence {
+      info!("Accepting correspondence challenge {}", challenge.id);
+      tokio::spawn(async move { self.api.accept_challenge(&challenge.id).await });
+      return;
+    }
+
+    // Do not accept if we are playing at capacity. A slot is reserved right
+    // away for the game, so that simultaneous challenges are not all accepted.
+    if !self.games.try_accept_challenge(&challenge.id) {
       info!("Ignoring challenge as we are already playing too many games");
       tokio::spawn(async move {
         self.api.decline_challenge(&challenge.id, lichess::types::DECLINE_LATER).await
@@ -265,7 +550,11 @@ impl BotState {
     }
 
     // Else we just accept.
-    tokio::spawn(async move { self.api.accept_challenge(&challenge.id).await });
+    tokio::spawn(async move {
+      if self.api.accept_challenge(&challenge.id).await.is_err() {
+        self.games.release_challenge(&challenge.id);
+      }
+    });
   }
 
   // ------------------------
@@ -295,7 +584,7 @@ impl BotState {
       }
       if self.api.is_online(username).await {
         info!("{username} is online. Sending a challenge!");
-        if let Err(()) = self.api.send_challenge(username, &clock).await {
+        if let Err(()) = self.api.send_challenge(username, &clock, true).await {
           info!("Error sending a challenge to {username}");
           continue;
         }
@@ -6,6 +6,7 @@ use chess::engine::eval::helpers::generic::*;
 use chess::engine::eval::helpers::pawn::is_passed;
 use chess::engine::eval::middlegame::*;
 use chess::engine::eval::opening::*;
+use chess::engine::eval::params::DEFAULT_EVAL_PARAMS;
 use chess::engine::eval::position::*;
 use chess::engine::nnue::*;
 use chess::model::board::Board;
@@ -98,7 +99,7 @@ fn detect_board_game_over(bencher: Bencher) {
   let mut game_state: GameState = GameState::from_board(&Board::new_random());
 
   bencher.bench_local(|| {
-    let _ = is_game_over(&cache, &game_state.board);
+    let _ = is_game_over(&cache, &game_state);
   });
 }
 
@@ -136,7 +137,7 @@ fn cache_for_moves(bencher: Bencher) {
 fn cache_for_game_status(bencher: Bencher) {
   let cache: EngineCache = EngineCache::new();
   let game_state: GameState = GameState::from_board(&Board::new_random());
-  let game_status = is_game_over(&cache, &game_state.board);
+  let game_status = is_game_over(&cache, &game_state);
 
   bencher.bench_local(|| {
     let eval_cache = cache.get_eval(&game_state.board).unwrap_or_default();
@@ -202,7 +203,7 @@ fn opening_piece_square_table_lookup(bencher: Bencher) {
   let move_list = game_state.get_moves();
 
   bencher.bench_local(|| {
-    let _ = get_square_table_opening_score(&game_state);
+    let _ = get_square_table_opening_score(&game_state, &DEFAULT_EVAL_PARAMS);
   });
 }
 
@@ -214,7 +215,7 @@ fn middlegame_piece_square_table_lookup(bencher: Bencher) {
   let move_list = game_state.get_moves();
 
   bencher.bench_local(|| {
-    let _ = get_square_table_middlegame_score(&game_state);
+    let _ = get_square_table_middlegame_score(&game_state, &DEFAULT_EVAL_PARAMS);
   });
 }
 
@@ -226,7 +227,7 @@ fn endgame_piece_square_table_lookup(bencher: Bencher) {
   let move_list = game_state.get_moves();
 
   bencher.bench_local(|| {
-    let _ = get_square_table_endgame_score(&game_state);
+    let _ = get_square_table_endgame_score(&game_state, &DEFAULT_EVAL_PARAMS);
   });
 }
 
@@ -1,11 +1,14 @@
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
 use std::sync::Mutex;
 use std::vec::Vec;
 
 use super::*;
 use crate::model::board::Board;
 use crate::model::moves::Move;
+use crate::model::piece::Color;
 
 lazy_static! {
   static ref CHESS_BOOK: ChessBook = Mutex::new(HashMap::new());
@@ -257,6 +260,72 @@ pub fn initialize_chess_book() {
 
 }
 
+/// Number of half-moves of our won games that we add to the book
+pub const LEARNT_OPENING_MAX_PLIES: usize = 16;
+
+/// Adds the moves we played in the opening of a game to the book, typically
+/// one that we won. The moves of the opponent are not added.
+///
+/// ### Arguments
+///
+/// * `pgn`:   PGN of the game
+/// * `color`: Color we played in the game
+///
+/// ### Return value
+///
+/// The opening of the game in UCI notation, to save with `save_learnt_opening`
+///
+pub fn learn_opening_from_pgn(pgn: &str, color: Color) -> String {
+  add_pgn_opening_to_book(&CHESS_BOOK, pgn, LEARNT_OPENING_MAX_PLIES, Some(color))
+}
+
+/// Appends a learnt opening to a file, as a `<w|b> <moves>` line.
+///
+/// ### Arguments
+///
+/// * `path`:  File in which the learnt openings are saved
+/// * `line`:  Opening in UCI notation, as returned by `learn_opening_from_pgn`
+/// * `color`: Color we played in the game
+///
+pub fn save_learnt_opening(path: &Path, line: &str, color: Color) -> std::io::Result<()> {
+  if line.is_empty() {
+    return Ok(());
+  }
+  if let Some(directory) = path.parent() {
+    std::fs::create_dir_all(directory)?;
+  }
+  let side = if color == Color::White { 'w' } else { 'b' };
+  let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+  writeln!(file, "{side} {line}")
+}
+
+/// Adds the openings saved with `save_learnt_opening` to the book. Must be
+/// called after `initialize_chess_book`, which does nothing once the book
+/// contains moves. Invalid lines are ignored.
+///
+/// ### Arguments
+///
+/// * `path`: File in which the learnt openings are saved
+///
+/// ### Return value
+///
+/// The number of openings added to the book
+///
+pub fn load_learnt_openings(path: &Path) -> std::io::Result<usize> {
+  let content = std::fs::read_to_string(path)?;
+  let mut count = 0;
+  for entry in content.lines() {
+    let (color, line) = match entry.split_once(' ') {
+      Some(("w", line)) => (Color::White, line),
+      Some(("b", line)) => (Color::Black, line),
+      _ => continue,
+    };
+    add_color_line_to_book(&CHESS_BOOK, line, Some(color));
+    count += 1;
+  }
+  Ok(count)
+}
+
 /// Check our known book moves, known positions that have been computed with an
 /// evaluation before, so that we do not need to find moves ourselves.
 pub fn get_book_moves(board: &Board) -> Option<Vec<Move>> {
@@ -285,4 +354,27 @@ mod tests {
     let game_state = GameState::from_fen(fen);
     assert_eq!(get_book_moves(&game_state.board), None);
   }
+
+  #[test]
+  fn test_learnt_openings_are_saved_and_loaded() {
+    initialize_chess_book();
+    let pgn = "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n\n\n";
+    let path = std::env::temp_dir().join("schnecken_bot_test_learnt_openings.txt");
+    let _ = std::fs::remove_file(&path);
+
+    // Playing black, the moves of the white opponent are not learnt
+    let line = learn_opening_from_pgn(pgn, Color::Black);
+    assert_eq!("e2e4 e7e5 d1h5 b8c6 f1c4 g8f6 h5f7", line);
+    save_learnt_opening(&path, &line, Color::Black).unwrap();
+    let fen = "rnbqkbnr/pppp1ppp/8/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 2";
+    let board = GameState::from_fen(fen).board;
+    assert!(get_book_moves(&board).unwrap().contains(&Move::from_string("b8c6")));
+    let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+    let board = GameState::from_fen(fen).board;
+    assert!(!get_book_moves(&board).unwrap_or_default().contains(&Move::from_string("d1h5")));
+
+    assert_eq!(1, load_learnt_openings(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert!(!get_book_moves(&board).unwrap_or_default().contains(&Move::from_string("d1h5")));
+  }
 }
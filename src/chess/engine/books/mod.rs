@@ -2,13 +2,18 @@ pub mod book;
 pub mod provocative_book;
 
 // Dependencies
+use lazy_static::lazy_static;
+use log::*;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Mutex;
 
 use crate::model::board::Board;
 use crate::model::game_state::GameState;
 use crate::model::moves::Move;
+use crate::model::piece::Color;
+use crate::model::tables::zobrist::BoardHash;
 
 // -----------------------------------------------------------------------------
 // Type definitions
@@ -16,12 +21,26 @@ use crate::model::moves::Move;
 // List of board configurations with an associated set of moves
 pub type ChessBook = Mutex<HashMap<Board, Vec<Move>>>;
 
+// Weights of book moves (board hash and move notation), learnt from the
+// results of our games
+pub type BookWeights = Mutex<BTreeMap<(BoardHash, String), u32>>;
+
 // -----------------------------------------------------------------------------
 // Constants
 
 /// Regex to parse PGN strings. We do not parse anotations here
 pub const PGN_REGEX: &str = r#"(\d*\.{1,3}\s+)?(?P<mv>([BKQNR]?[abcdefgh]?[12345678]?x?[abcdefgh][12345678]=?[BQNRbqnr]?|O-O|O-O-O)[#\+]?)[\?!]*\s+"#;
 
+/// Weight of a book move that has not been learnt from games yet
+pub const DEFAULT_BOOK_MOVE_WEIGHT: u32 = 10;
+/// Lowest weight of a book move, so that a line is never fully removed
+pub const MIN_BOOK_MOVE_WEIGHT: u32 = 1;
+
+lazy_static! {
+  /// Book move weights used by the engine
+  pub static ref BOOK_WEIGHTS: BookWeights = Mutex::new(BTreeMap::new());
+}
+
 // -----------------------------------------------------------------------------
 // Functions
 
@@ -48,6 +67,87 @@ pub fn get_book_moves(board: &Board, provocative: bool) -> Option<Vec<Move>> {
   }
 }
 
+/// Retrieves the weight of a book move. Moves with a higher weight are picked
+/// more often.
+///
+/// ### Arguments
+///
+/// * `book_weights`: Weights of the book moves
+/// * `board`:        Board configuration in which the book move is played
+/// * `mv`:           Book move
+///
+pub fn get_book_move_weight(book_weights: &BookWeights, board: &Board, mv: &Move) -> u32 {
+  let weights = book_weights.lock().unwrap();
+  *weights.get(&(board.hash, mv.to_string())).unwrap_or(&DEFAULT_BOOK_MOVE_WEIGHT)
+}
+
+/// Updates the weights of the book moves played in a game, based on the
+/// result of the game. Wins increment the weights, losses decrement them,
+/// without going below `MIN_BOOK_MOVE_WEIGHT`.
+///
+/// ### Arguments
+///
+/// * `book_weights`: Weights of the book moves
+/// * `book_moves`:   Board configurations and book moves that we played
+/// * `won`:          True if we won the game, false if we lost it
+///
+pub fn learn_from_game_result(book_weights: &BookWeights,
+                              book_moves: &[(Board, Move)],
+                              won: bool) {
+  let mut weights = book_weights.lock().unwrap();
+  for (board, mv) in book_moves {
+    let weight = weights.entry((board.hash, mv.to_string())).or_insert(DEFAULT_BOOK_MOVE_WEIGHT);
+    *weight = if won { *weight + 1 } else { (*weight - 1).max(MIN_BOOK_MOVE_WEIGHT) };
+  }
+}
+
+/// Writes the book move weights to a file, one `<hash> <move> <weight>` entry
+/// per line.
+///
+/// ### Arguments
+///
+/// * `book_weights`: Weights of the book moves
+/// * `path`:         File in which the weights are saved
+///
+pub fn save_book_weights(book_weights: &BookWeights, path: &Path) -> std::io::Result<()> {
+  let weights = book_weights.lock().unwrap();
+  let content: String = weights.iter()
+                               .map(|((hash, mv), weight)| format!("{hash:016x} {mv} {weight}\n"))
+                               .collect();
+  drop(weights);
+  if let Some(directory) = path.parent() {
+    std::fs::create_dir_all(directory)?;
+  }
+  std::fs::write(path, content)
+}
+
+/// Loads book move weights saved with `save_book_weights`. Loaded entries
+/// replace the weights already known for the same moves, invalid lines are
+/// ignored.
+///
+/// ### Arguments
+///
+/// * `book_weights`: Weights of the book moves
+/// * `path`:         File in which the weights are saved
+///
+pub fn load_book_weights(book_weights: &BookWeights, path: &Path) -> std::io::Result<()> {
+  let content = std::fs::read_to_string(path)?;
+  let mut weights = book_weights.lock().unwrap();
+  for line in content.lines() {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 3 {
+      continue;
+    }
+    let (Ok(hash), Ok(weight)) =
+      (BoardHash::from_str_radix(fields[0], 16), fields[2].parse::<u32>())
+    else {
+      continue;
+    };
+    weights.insert((hash, fields[1].to_string()), weight.max(MIN_BOOK_MOVE_WEIGHT));
+  }
+  Ok(())
+}
+
 /// Adds a line in the opening to the book
 ///
 /// ### Arguments
@@ -57,11 +157,31 @@ pub fn get_book_moves(board: &Board, provocative: bool) -> Option<Vec<Move>> {
 /// e.g. `e2e4 c7c5 g1f3 d7d6 c5d4 f3d4 g8f6 b1c3 a7a6`
 ///
 pub fn add_line_to_book(chess_book: &ChessBook, line: &str) {
+  add_color_line_to_book(chess_book, line, None);
+}
+
+/// Adds the moves played by one side in a line of the opening to the book
+///
+/// ### Arguments
+///
+/// * `chess_book`: Book in which the moves are added
+/// * `line`:       List of moves separated with spaces.
+/// * `color`:      Only the moves of this side are added, None adds both sides
+///
+pub fn add_color_line_to_book(chess_book: &ChessBook, line: &str, color: Option<Color>) {
   let mut game_state = GameState::default();
   let moves: Vec<&str> = line.split(' ').collect();
   let mut book = chess_book.lock().unwrap();
 
   for chess_move in moves {
+    if color.is_some_and(|c| c != game_state.board.side_to_play) {
+      if let Err(error) = game_state.apply_move_from_notation(chess_move) {
+        warn!("Cannot add the line {} to the book: {:?}", line, error);
+        return;
+      }
+      continue;
+    }
+
     if !book.contains_key(&game_state.board) {
       let _ = book.insert(game_state.board, Vec::new());
     }
@@ -72,7 +192,10 @@ pub fn add_line_to_book(chess_book: &ChessBook, line: &str) {
       move_list.push(m);
     }
 
-    game_state.apply_move_from_notation(chess_move);
+    if let Err(error) = game_state.apply_move_from_notation(chess_move) {
+      warn!("Cannot add the line {} to the book: {:?}", line, error);
+      return;
+    }
   }
 }
 
@@ -85,18 +208,42 @@ pub fn add_line_to_book(chess_book: &ChessBook, line: &str) {
 /// e.g. `1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O`
 ///
 pub fn add_pgn_to_book(chess_book: &ChessBook, pgn: &str) {
+  add_pgn_opening_to_book(chess_book, pgn, usize::MAX, None);
+}
+
+/// Adds the first moves of a game to the book, e.g. to learn the opening of a
+/// game we won without adding the whole game.
+///
+/// ### Arguments
+///
+/// * `chess_book`: Book in which the moves are added
+/// * `pgn`:        PGN format str.
+/// * `max_plies`:  Maximum number of half-moves read from the game
+/// * `color`:      Only the moves of this side are added, None adds both sides
+///
+/// ### Return value
+///
+/// The half-moves read from the game, in UCI notation separated with spaces,
+/// so that they can be added again with `add_color_line_to_book`
+///
+pub fn add_pgn_opening_to_book(chess_book: &ChessBook,
+                               pgn: &str,
+                               max_plies: usize,
+                               color: Option<Color>)
+                               -> String {
   let mut game_state = GameState::default();
   let mut book = chess_book.lock().unwrap();
+  let mut line: Vec<String> = Vec::new();
 
   let pgn_re = Regex::new(PGN_REGEX).unwrap();
 
   // Use regex to extract move notations
-  let captures = pgn_re.captures_iter(&pgn);
+  let captures = pgn_re.captures_iter(&pgn).take(max_plies);
   for value in captures {
     // Find the mv (e.g. 'Kf7') and the annotation (e.g. '{ [%eval 0.36] [%clk 0:10:00] }')
     let mv = value.name("mv");
     if mv.is_none() {
-      return;
+      break;
     }
     let mv = mv.unwrap().as_str();
     //println!("Move: {mv}");
@@ -104,21 +251,26 @@ pub fn add_pgn_to_book(chess_book: &ChessBook, pgn: &str) {
 
     if m_result.is_err() {
       println!("Could not parse move: {}", mv);
-      return;
+      break;
     }
     let m = m_result.unwrap();
 
-    if !book.contains_key(&game_state.board) {
-      let _ = book.insert(game_state.board, Vec::new());
-    }
+    if color.is_none_or(|c| c == game_state.board.side_to_play) {
+      if !book.contains_key(&game_state.board) {
+        let _ = book.insert(game_state.board, Vec::new());
+      }
 
-    let move_list = book.get_mut(&game_state.board).unwrap();
-    if !move_list.contains(&m) {
-      move_list.push(m);
+      let move_list = book.get_mut(&game_state.board).unwrap();
+      if !move_list.contains(&m) {
+        move_list.push(m);
+      }
     }
 
+    line.push(m.to_string());
     game_state.apply_move(&m);
   } // for value in captures
+
+  line.join(" ")
 }
 
 /// Adds a line in the from a position
@@ -187,3 +339,98 @@ pub fn add_single_move_to_book(chess_book: &ChessBook, fen: &str, mv: &str) {
     move_list.push(m);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_lost_game_downweights_book_move() {
+    let chess_book = ChessBook::default();
+    add_line_to_book(&chess_book, "e2e4 c7c5 g1f3 d7d6");
+    let book_weights = BookWeights::default();
+
+    let fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+    let board = GameState::from_fen(fen).board;
+    let book_move = chess_book.lock().unwrap().get(&board).unwrap()[0];
+    assert_eq!(DEFAULT_BOOK_MOVE_WEIGHT, get_book_move_weight(&book_weights, &board, &book_move));
+
+    learn_from_game_result(&book_weights, &[(board, book_move)], false);
+    assert_eq!(DEFAULT_BOOK_MOVE_WEIGHT - 1,
+               get_book_move_weight(&book_weights, &board, &book_move));
+
+    // Weights never go below the minimum
+    for _ in 0..2 * DEFAULT_BOOK_MOVE_WEIGHT {
+      learn_from_game_result(&book_weights, &[(board, book_move)], false);
+    }
+    assert_eq!(MIN_BOOK_MOVE_WEIGHT, get_book_move_weight(&book_weights, &board, &book_move));
+
+    learn_from_game_result(&book_weights, &[(board, book_move)], true);
+    assert_eq!(MIN_BOOK_MOVE_WEIGHT + 1,
+               get_book_move_weight(&book_weights, &board, &book_move));
+  }
+
+  #[test]
+  fn test_book_weights_are_saved_and_loaded() {
+    let board = GameState::default().board;
+    let book_move = Move::from_string("e2e4");
+    let book_weights = BookWeights::default();
+    learn_from_game_result(&book_weights, &[(board, book_move)], true);
+
+    let path = std::env::temp_dir().join("schnecken_bot_test_book_weights.txt");
+    save_book_weights(&book_weights, &path).unwrap();
+
+    let loaded_weights = BookWeights::default();
+    load_book_weights(&loaded_weights, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(DEFAULT_BOOK_MOVE_WEIGHT + 1,
+               get_book_move_weight(&loaded_weights, &board, &book_move));
+    assert_eq!(DEFAULT_BOOK_MOVE_WEIGHT,
+               get_book_move_weight(&loaded_weights, &board, &Move::from_string("d2d4")));
+  }
+
+  #[test]
+  fn test_add_pgn_opening_to_book() {
+    // PGN as exported by Lichess, without tags
+    let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 1-0\n\n\n";
+    let chess_book = ChessBook::default();
+    assert_eq!("e2e4 e7e5 g1f3 b8c6", add_pgn_opening_to_book(&chess_book, pgn, 4, None));
+
+    // Only the first 4 half-moves are added
+    let book = chess_book.lock().unwrap();
+    assert_eq!(4, book.len());
+    let board =
+      GameState::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").board;
+    assert_eq!("b8c6", book.get(&board).unwrap()[0].to_string());
+    let board =
+      GameState::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").board;
+    assert!(!book.contains_key(&board));
+
+    // Without limit, the whole game is added
+    drop(book);
+    add_pgn_to_book(&chess_book, pgn);
+    assert_eq!(10, chess_book.lock().unwrap().len());
+  }
+
+  #[test]
+  fn test_add_pgn_opening_of_one_color_to_book() {
+    let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0\n\n\n";
+    let chess_book = ChessBook::default();
+    let line = add_pgn_opening_to_book(&chess_book, pgn, 4, Some(Color::Black));
+    assert_eq!("e2e4 e7e5 g1f3 b8c6", line);
+
+    // Only the black moves are in the book
+    let book = chess_book.lock().unwrap();
+    assert_eq!(2, book.len());
+    assert!(!book.contains_key(&GameState::default().board));
+    let board =
+      GameState::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").board;
+    assert_eq!("b8c6", book.get(&board).unwrap()[0].to_string());
+    drop(book);
+
+    // The returned line adds the same moves back
+    let loaded_book = ChessBook::default();
+    add_color_line_to_book(&loaded_book, &line, Some(Color::Black));
+    assert_eq!(*chess_book.lock().unwrap(), *loaded_book.lock().unwrap());
+  }
+}
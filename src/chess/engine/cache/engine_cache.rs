@@ -10,6 +10,27 @@ use crate::model::game_state::GameState;
 use crate::model::moves::*;
 use crate::model::piece::Color;
 
+/// Usage statistics of the evaluation table (transposition table)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvaluationTableStats {
+  /// Number of entries that the table can hold
+  pub capacity:   usize,
+  /// Number of entries currently used
+  pub entries:    usize,
+  /// Number of times an entry was overwritten by another board position
+  pub collisions: usize,
+}
+
+impl EvaluationTableStats {
+  /// Returns the fraction of the table in use, between 0.0 and 1.0
+  pub fn occupancy(&self) -> f32 {
+    if self.capacity == 0 {
+      return 0.0;
+    }
+    self.entries as f32 / self.capacity as f32
+  }
+}
+
 #[derive(Clone)]
 pub struct EngineCache {
   // List of moves available from a board position
@@ -32,6 +53,21 @@ impl EngineCache {
     }
   }
 
+  /// Instantiate an EngineCache sharing the move lists and killer moves of
+  /// another one, but with its own evaluation table.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` :            EngineCache to share the move ordering with
+  ///
+  pub fn with_separate_evals(&self) -> Self {
+    EngineCache {
+      move_lists: self.move_lists.clone(),
+      evals: Arc::new(Mutex::new(EvaluationCacheTable::new(10))),
+      killer_moves: self.killer_moves.clone(),
+    }
+  }
+
   // ---------------------------------------------------------------------------
   // Generic cache functions
 
@@ -168,6 +204,65 @@ impl EngineCache {
     self.evals.lock().unwrap().clear();
   }
 
+  /// Returns usage statistics of the evaluation table
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` :            EngineCache
+  ///
+  /// ### Return value
+  ///
+  /// Capacity, number of entries used and number of collisions of the table
+  ///
+  pub fn get_eval_table_stats(&self) -> EvaluationTableStats {
+    let table = self.evals.lock().unwrap();
+    EvaluationTableStats { capacity:   table.capacity(),
+                           entries:    table.entries(),
+                           collisions: table.collisions(), }
+  }
+
+  /// Walks the cache from a position, following the first move of the cached
+  /// move lists, i.e. the best move found during the last search.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` :            EngineCache
+  /// * `game_state` :      Position to start from
+  /// * `max_length` :      Maximum number of moves to walk through
+  ///
+  /// ### Return value
+  ///
+  /// List of moves with the cached evaluation of the position they lead to.
+  /// The walk stops at the first position without cached data, or when a
+  /// position repeats.
+  ///
+  pub fn walk_best_line(&self,
+                        game_state: &GameState,
+                        max_length: usize)
+                        -> Vec<(Move, EvaluationCache)> {
+    let mut line = Vec::new();
+    let mut game_state = game_state.clone();
+    let mut visited = Vec::with_capacity(max_length);
+
+    while line.len() < max_length && !visited.contains(&game_state.board.hash) {
+      visited.push(game_state.board.hash);
+      let move_list = self.get_move_list(&game_state.board);
+      if move_list.is_none() || move_list.as_ref().unwrap().is_empty() {
+        break;
+      }
+      let best_move = move_list.unwrap()[0];
+      game_state.apply_move(&best_move);
+
+      let entry = self.get_eval(&game_state.board);
+      if entry.is_none() {
+        break;
+      }
+      line.push((best_move, entry.unwrap()));
+    }
+
+    line
+  }
+
   /// Clears and resizes the cache tables. (both for evals and move lists)
   ///
   /// ### Arguments
@@ -242,10 +337,8 @@ impl EngineCache {
     a: &Move,
     b: &Move,
   ) -> Ordering {
-    let mut game_state_a = game_state.clone();
-    game_state_a.apply_move(a);
-    let mut game_state_b = game_state.clone();
-    game_state_b.apply_move(b);
+    let game_state_a = game_state.with_move(a);
+    let game_state_b = game_state.with_move(b);
 
     let board_a_eval = self.get_eval(&game_state_a.board).unwrap_or_default();
     let board_b_eval = self.get_eval(&game_state_b.board).unwrap_or_default();
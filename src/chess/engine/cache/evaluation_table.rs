@@ -41,6 +41,8 @@ pub struct EvaluationCacheTable {
   max_index_mask: usize,
   /// Keeps track of how many time we access the cache. (both read and write)
   counter:        usize,
+  /// Number of times an entry was overwritten by another board position
+  collisions:     usize,
 }
 
 impl EvaluationCacheTable {
@@ -74,7 +76,8 @@ impl EvaluationCacheTable {
     let entries = vec![EvaluationCacheEntry::default(); size];
     EvaluationCacheTable { table:          entries.into_boxed_slice(),
                            max_index_mask: size - 1,
-                           counter:        0, }
+                           counter:        0,
+                           collisions:     0, }
   }
 
   /// Get a particular entry with the hash specified
@@ -108,6 +111,9 @@ impl EvaluationCacheTable {
   #[inline]
   pub fn add(&mut self, hash: BoardHash, evaluation: EvaluationCache) {
     let e = unsafe { self.table.get_unchecked_mut((hash as usize) & self.max_index_mask) };
+    if e.hash != 0 && e.hash != hash {
+      self.collisions = self.collisions.wrapping_add(1);
+    }
     *e = EvaluationCacheEntry { hash,
                                 evaluation_cache: evaluation };
     self.counter = self.counter.wrapping_add(1);
@@ -119,6 +125,24 @@ impl EvaluationCacheTable {
     self.counter
   }
 
+  /// Returns the number of entries that the table can hold.
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    self.table.len()
+  }
+
+  /// Counts the entries currently used in the table.
+  /// Note that this goes through the whole table, it is meant for debugging.
+  pub fn entries(&self) -> usize {
+    self.table.iter().filter(|e| e.hash != 0).count()
+  }
+
+  /// Checks how many times an entry was overwritten by another board position.
+  #[inline]
+  pub fn collisions(&self) -> usize {
+    self.collisions
+  }
+
   /// Zeroes out all the board hashes in the table and fill with default values.
   #[inline]
   pub fn clear(&mut self) {
@@ -127,6 +151,7 @@ impl EvaluationCacheTable {
       *e = EvaluationCacheEntry::default();
     }
     self.counter = 0;
+    self.collisions = 0;
   }
 }
 
@@ -1,2 +1,3 @@
 pub mod options;
 pub mod play_style;
+pub mod strength;
@@ -1,6 +1,12 @@
 use crate::engine::config::play_style::PlayStyle;
+use crate::engine::search_result::VARIATION_LENGTH;
+use crate::model::moves::Move;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+/// Options of the engine. They can be saved and loaded as JSON, missing
+/// options take their default value.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct EngineOptions {
   /// Whether this engine is used with the UCI interface and it
   /// should print information when searching
@@ -12,8 +18,14 @@ pub struct EngineOptions {
   /// time in milliseconds to spend on a calculation
   /// Set to 0 for no limit / automatic
   pub max_search_time: usize,
-  /// Number of threads to use for the search.
-  pub max_threads: usize,
+  /// Number of threads to use for the search. With more than 1 thread, helper
+  /// threads search the position in parallel and share the transposition
+  /// table and the move ordering with the main thread (Lazy SMP). The result
+  /// of a search then depends on the timing of the threads, use 1 thread to
+  /// get reproducible searches.
+  /// Defaults to the number of logical CPUs.
+  /// Use Engine::set_maximum_threads to change it.
+  pub threads: usize,
   /// Number of threads to use for the search.
   pub use_nnue: bool,
   /// Debug mode : The engine will print additional info (info string <debug string>)
@@ -21,8 +33,53 @@ pub struct EngineOptions {
   pub debug: bool,
   /// Set the play style of the engine.
   pub play_style: PlayStyle,
+  /// Play the moves from the opening book in known positions, instead of
+  /// searching them.
+  pub use_book: bool,
   /// Number of best lines that the engine will return.
   pub multi_pv: usize,
+  /// Seed used for the random decisions of the engine (book moves, choice
+  /// between equivalent lines). None uses entropy.
+  /// Use Engine::set_seed to change it.
+  pub seed: Option<u64>,
+  /// Skip moves that are very unlikely to change the evaluation at the end of
+  /// the search tree (futility pruning).
+  pub futility_pruning: bool,
+  /// Search the moves after the first one with a null window, and only
+  /// search them fully if they beat the first one (principal variation search).
+  pub principal_variation_search: bool,
+  /// Maximum number of moves displayed for each line, in the console and in
+  /// the UCI info.
+  pub max_pv_plies: usize,
+  /// Elo to which the strength of the engine is limited. None plays at full
+  /// strength.
+  /// Use Engine::set_elo_limit to change it.
+  pub elo_limit: Option<u32>,
+  /// Number of plies after leaving the opening book during which the engine
+  /// picks randomly among the moves close to the best one. 0 to disable.
+  pub book_exit_randomness_plies: usize,
+  /// Maximum evaluation loss, in centipawns, accepted when picking a random
+  /// move after leaving the opening book.
+  pub book_exit_margin_cp: usize,
+  /// Root moves to which the search is restricted, like UCI `go searchmoves`.
+  /// Empty searches all the moves. Only applies to one search, so it is not
+  /// saved with the other options.
+  #[serde(skip)]
+  pub search_moves: Vec<Move>,
+  /// Number of consecutive completed depths after which the search stops
+  /// before its time limit if the best move did not change. 0 to disable.
+  pub stable_move_depths: usize,
+  /// Maximum evaluation change, in centipawns, between two depths for the best
+  /// move to be considered stable.
+  pub stable_move_margin_cp: usize,
+  /// Search time, in milliseconds, under which the NNUE is not used even if
+  /// `use_nnue` is set, so that we search more nodes in time pressure.
+  /// 0 to always use the NNUE.
+  pub nnue_min_search_time: usize,
+  /// Before playing the best move, search the position after it shortly and
+  /// search again deeper if the opponent has a much better reply than
+  /// expected. See Engine::check_for_blunder.
+  pub blunder_check: bool,
 }
 
 impl Default for EngineOptions {
@@ -32,11 +89,29 @@ impl Default for EngineOptions {
       ponder: false,
       max_depth: 20,
       max_search_time: 0,
-      max_threads: 16,
+      threads: available_threads(),
       use_nnue: false,
       debug: false,
       play_style: PlayStyle::Normal,
+      use_book: true,
       multi_pv: 3,
+      seed: None,
+      futility_pruning: true,
+      principal_variation_search: true,
+      max_pv_plies: VARIATION_LENGTH,
+      elo_limit: None,
+      book_exit_randomness_plies: 0,
+      book_exit_margin_cp: 20,
+      search_moves: Vec::new(),
+      stable_move_depths: 0,
+      stable_move_margin_cp: 20,
+      nnue_min_search_time: 100,
+      blunder_check: false,
     }
   }
 }
+
+/// Number of logical CPUs available to the engine, 1 if it cannot be detected.
+pub fn available_threads() -> usize {
+  std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
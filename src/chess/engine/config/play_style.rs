@@ -1,6 +1,34 @@
+use crate::model::board::Board;
+use crate::model::moves::Move;
+use crate::model::piece::*;
+use crate::model::piece_moves::KING_MOVES;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-#[derive(Copy, Debug, Clone, Eq, PartialEq, Default)]
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Maximum evaluation that a play style can trade for its preferred moves.
+/// Moves evaluated worse than this compared to the best move never get a
+/// play style bonus.
+pub const PLAY_STYLE_MAX_EVAL_LOSS: f32 = 0.3;
+/// Bonus given to checks with the aggressive play style
+const AGGRESSIVE_CHECK_BONUS: f32 = 0.15;
+/// Bonus given to captures with the aggressive play style
+const AGGRESSIVE_CAPTURE_BONUS: f32 = 0.05;
+/// Bonus given to sacrifices with the aggressive play style
+const AGGRESSIVE_SACRIFICE_BONUS: f32 = 0.1;
+/// Penalty given to king walks with the conservative play style
+const CONSERVATIVE_KING_MOVE_PENALTY: f32 = 0.15;
+/// Penalty given to moving pawns next to the king with the conservative play
+/// style
+const CONSERVATIVE_PAWN_SHIELD_PENALTY: f32 = 0.1;
+
+// -----------------------------------------------------------------------------
+// Types
+
+#[derive(Copy, Debug, Clone, Eq, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayStyle {
   /// Normal play style for the engine
   #[default]
@@ -28,3 +56,60 @@ impl FromStr for PlayStyle {
     }
   }
 }
+
+impl PlayStyle {
+  /// Computes the score adjustment of a move at the root of the search,
+  /// according to the play style.
+  ///
+  /// ### Arguments
+  ///
+  /// * `board`: Board on which the move is played
+  /// * `mv`:    Move generated from the board
+  ///
+  /// ### Return value
+  ///
+  /// Bonus for the side playing the move, negative for a penalty.
+  pub fn get_move_bonus(&self, board: &Board, mv: &Move) -> f32 {
+    match self {
+      PlayStyle::Aggressive => {
+        let mut bonus = 0.0;
+        if board.gives_check(mv) {
+          bonus += AGGRESSIVE_CHECK_BONUS;
+        }
+        if mv.is_capture() {
+          bonus += AGGRESSIVE_CAPTURE_BONUS;
+        }
+
+        // Moving a piece where it can be taken, for less than it is worth
+        let piece_value = Piece::material_value_from_u8(board.pieces.get(mv.u8_src()));
+        let captured_value =
+          mv.captured_piece().map(Piece::material_value_from_type).unwrap_or(0.0);
+        if piece_value > captured_value
+           && piece_value < KING_VALUE
+           && board.get_attackers(mv.u8_dest(), Color::opposite(board.side_to_play)) != 0
+        {
+          bonus += AGGRESSIVE_SACRIFICE_BONUS;
+        }
+        bonus
+      },
+      PlayStyle::Conservative => {
+        let piece = board.pieces.get(mv.u8_src());
+        if piece == WHITE_KING || piece == BLACK_KING {
+          if mv.is_castle() {
+            0.0
+          } else {
+            -CONSERVATIVE_KING_MOVE_PENALTY
+          }
+        } else if (piece == WHITE_PAWN || piece == BLACK_PAWN)
+                  && (KING_MOVES[board.get_king(board.side_to_play) as usize] >> mv.u8_src()) & 1
+                     == 1
+        {
+          -CONSERVATIVE_PAWN_SHIELD_PENALTY
+        } else {
+          0.0
+        }
+      },
+      PlayStyle::Normal | PlayStyle::Provocative => 0.0,
+    }
+  }
+}
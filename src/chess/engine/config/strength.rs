@@ -0,0 +1,94 @@
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Lowest Elo that the engine can be limited to
+pub const ELO_LIMIT_MIN: u32 = 800;
+/// Highest Elo that the engine can be limited to
+pub const ELO_LIMIT_MAX: u32 = 2800;
+/// Number of Elo points per additional ply of search depth
+const ELO_PER_DEPTH: u32 = 400;
+/// Evaluation that the engine is ready to give away per Elo point below the
+/// maximum, when playing another move than the best one.
+const EVAL_MARGIN_PER_ELO: f32 = 0.001;
+/// Probability of not playing the best move at the lowest Elo
+const MAX_SUBOPTIMAL_MOVE_PROBABILITY: f32 = 0.5;
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Clamps an Elo value to the range supported by the engine.
+///
+/// ### Arguments
+///
+/// * `elo`: Requested Elo
+pub fn clamp_elo(elo: u32) -> u32 {
+  elo.clamp(ELO_LIMIT_MIN, ELO_LIMIT_MAX)
+}
+
+/// Maximum depth searched by an engine limited to an Elo.
+///
+/// ### Arguments
+///
+/// * `elo`: Elo limit of the engine
+///
+/// ### Return value
+///
+/// Search depth, from 1 ply at the lowest Elo to 6 at the highest
+pub fn elo_limit_max_depth(elo: u32) -> usize {
+  (1 + (clamp_elo(elo) - ELO_LIMIT_MIN) / ELO_PER_DEPTH) as usize
+}
+
+/// Maximum evaluation loss accepted when playing another move than the best
+/// one, for an engine limited to an Elo.
+///
+/// ### Arguments
+///
+/// * `elo`: Elo limit of the engine
+///
+/// ### Return value
+///
+/// Evaluation margin, 0 at the highest Elo
+pub fn elo_limit_eval_margin(elo: u32) -> f32 {
+  (ELO_LIMIT_MAX - clamp_elo(elo)) as f32 * EVAL_MARGIN_PER_ELO
+}
+
+/// Probability of playing another move than the best one, for an engine
+/// limited to an Elo.
+///
+/// ### Arguments
+///
+/// * `elo`: Elo limit of the engine
+///
+/// ### Return value
+///
+/// Probability between 0 (highest Elo) and 0.5 (lowest Elo)
+pub fn elo_limit_suboptimal_move_probability(elo: u32) -> f32 {
+  let range = (ELO_LIMIT_MAX - ELO_LIMIT_MIN) as f32;
+  (ELO_LIMIT_MAX - clamp_elo(elo)) as f32 / range * MAX_SUBOPTIMAL_MOVE_PROBABILITY
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_elo_limit_parameters_are_monotone() {
+    assert_eq!(1, elo_limit_max_depth(ELO_LIMIT_MIN));
+    assert_eq!(1, elo_limit_max_depth(0));
+    assert_eq!(6, elo_limit_max_depth(ELO_LIMIT_MAX));
+    assert_eq!(0.0, elo_limit_eval_margin(ELO_LIMIT_MAX));
+    assert_eq!(0.0, elo_limit_suboptimal_move_probability(4000));
+    assert_eq!(MAX_SUBOPTIMAL_MOVE_PROBABILITY,
+               elo_limit_suboptimal_move_probability(ELO_LIMIT_MIN));
+
+    for elo in ELO_LIMIT_MIN..ELO_LIMIT_MAX {
+      assert!(elo_limit_max_depth(elo) <= elo_limit_max_depth(elo + 1));
+      assert!(elo_limit_eval_margin(elo) > elo_limit_eval_margin(elo + 1));
+      assert!(elo_limit_suboptimal_move_probability(elo)
+              > elo_limit_suboptimal_move_probability(elo + 1));
+    }
+  }
+}
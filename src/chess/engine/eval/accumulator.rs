@@ -0,0 +1,212 @@
+use super::endgame::endgame_square_table_value;
+use super::middlegame::middlegame_square_table_value;
+use super::opening::opening_square_table_value;
+use super::params::EvalParams;
+use crate::model::game_state::{GamePhase, GameState, MoveDelta};
+use crate::model::piece::*;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Material, game phase and piece-square table scores of a position, updated with the move
+/// deltas instead of scanning the whole board at each node of the search.
+///
+/// All values are given from White's perspective.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvalAccumulator {
+  /// Number of White pieces minus number of Black pieces, indexed by
+  /// `PieceType`
+  pub piece_balance:           [isize; 6],
+  /// Sum of the opening piece-square table values
+  pub opening_square_table:    isize,
+  /// Sum of the middlegame piece-square table values
+  pub middlegame_square_table: isize,
+  /// Sum of the endgame piece-square table values
+  pub endgame_square_table:    isize,
+  /// Material count of both sides used to determine the game phase, see
+  /// `Board::game_phase_value`
+  pub phase_value:             isize,
+}
+
+// -----------------------------------------------------------------------------
+// Implementations
+
+impl EvalAccumulator {
+  /// Computes the accumulator of a position from scratch.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_state`: Position to look at
+  pub fn from_game_state(game_state: &GameState) -> Self {
+    let mut accumulator = EvalAccumulator::default();
+    for (square, color, piece) in game_state.board.pieces.iter() {
+      accumulator.add_piece(square, color, piece);
+    }
+    accumulator
+  }
+
+  /// Updates the accumulator after a move was made.
+  ///
+  /// ### Arguments
+  ///
+  /// * `delta`: Pieces moved, captured or promoted by the move
+  /// * `color`: Side that made the move
+  pub fn make_move(&mut self, delta: &MoveDelta, color: Color) {
+    let (piece, src, dest) = delta.moved;
+    self.remove_piece(src, color, piece);
+    if let Some((captured, square)) = delta.captured {
+      self.remove_piece(square, Color::opposite(color), captured);
+    }
+    self.add_piece(dest, color, delta.promoted.unwrap_or(piece));
+    if let Some((rook_src, rook_dest)) = delta.castling_rook {
+      self.remove_piece(rook_src, color, PieceType::Rook);
+      self.add_piece(rook_dest, color, PieceType::Rook);
+    }
+  }
+
+  /// Reverts the update of a move, i.e. `make_move` followed by `unmake_move`
+  /// leaves the accumulator unchanged.
+  ///
+  /// ### Arguments
+  ///
+  /// * `delta`: Pieces moved, captured or promoted by the move
+  /// * `color`: Side that made the move
+  pub fn unmake_move(&mut self, delta: &MoveDelta, color: Color) {
+    let (piece, src, dest) = delta.moved;
+    if let Some((rook_src, rook_dest)) = delta.castling_rook {
+      self.remove_piece(rook_dest, color, PieceType::Rook);
+      self.add_piece(rook_src, color, PieceType::Rook);
+    }
+    self.remove_piece(dest, color, delta.promoted.unwrap_or(piece));
+    if let Some((captured, square)) = delta.captured {
+      self.add_piece(square, Color::opposite(color), captured);
+    }
+    self.add_piece(src, color, piece);
+  }
+
+  /// Material balance, computed like `get_combined_material_score`
+  pub fn material(&self) -> f32 {
+    let balance = |piece: PieceType| self.piece_balance[piece as usize] as f32;
+    balance(PieceType::Queen) * QUEEN_VALUE
+    + balance(PieceType::Rook) * ROOK_VALUE
+    + balance(PieceType::Bishop) * BISHOP_VALUE
+    + balance(PieceType::Knight) * KNIGHT_VALUE
+    + balance(PieceType::Pawn) * PAWN_VALUE
+  }
+
+  /// Game phase value of the position, equal to `Board::game_phase_value`
+  pub fn game_phase_value(&self) -> u32 {
+    self.phase_value as u32
+  }
+
+  /// Piece-square table score for a game phase, computed like
+  /// `get_square_table_<phase>_score`
+  ///
+  /// ### Arguments
+  ///
+  /// * `phase`:  Game phase of the position
+  /// * `params`: Weights of the evaluation terms
+  pub fn square_table_score(&self, phase: GamePhase, params: &EvalParams) -> f32 {
+    match phase {
+      GamePhase::Opening => self.opening_square_table as f32 * params.opening_square_table_factor,
+      GamePhase::Middlegame => {
+        self.middlegame_square_table as f32 * params.middlegame_square_table_factor
+      },
+      GamePhase::Endgame => self.endgame_square_table as f32 * params.endgame_square_table_factor,
+    }
+  }
+
+  /// Adds a piece on a square
+  fn add_piece(&mut self, square: u8, color: Color, piece: PieceType) {
+    self.update_piece(square, color, piece, 1);
+  }
+
+  /// Removes a piece from a square
+  fn remove_piece(&mut self, square: u8, color: Color, piece: PieceType) {
+    self.update_piece(square, color, piece, -1);
+  }
+
+  /// Adds (sign = 1) or removes (sign = -1) a piece from the scores
+  fn update_piece(&mut self, square: u8, color: Color, piece: PieceType, sign: isize) {
+    self.piece_balance[piece as usize] += sign * Color::score_factor(color) as isize;
+    self.opening_square_table += sign * opening_square_table_value(square, color, piece);
+    self.middlegame_square_table += sign * middlegame_square_table_value(square, color, piece);
+    self.endgame_square_table += sign * endgame_square_table_value(square, color, piece);
+    self.phase_value += sign * Piece::phase_value_from_type(piece) as isize;
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::engine::eval::endgame::get_square_table_endgame_score;
+  use crate::engine::eval::helpers::generic::get_combined_material_score;
+  use crate::engine::eval::middlegame::get_square_table_middlegame_score;
+  use crate::engine::eval::opening::get_square_table_opening_score;
+  use crate::engine::eval::params::DEFAULT_EVAL_PARAMS;
+  use crate::engine::eval::position::{evaluate_board, evaluate_board_with_accumulator};
+  use rand::rngs::StdRng;
+  use rand::{Rng, SeedableRng};
+
+  #[test]
+  fn test_accumulator_matches_the_full_computation() {
+    let mut rng = StdRng::seed_from_u64(42);
+    // Positions with castling, en-passant and promotions available
+    let fens = ["r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                "8/2P3k1/8/8/8/8/1p4K1/8 b - - 0 1"];
+
+    for fen in fens {
+      for _ in 0..20 {
+        let mut game_state = GameState::from_fen(fen);
+        let mut accumulator = EvalAccumulator::from_game_state(&game_state);
+        let mut history: Vec<(GameState, MoveDelta, Color)> = Vec::new();
+
+        // Random walk, checking the accumulator against the full computation
+        for _ in 0..30 {
+          let moves = game_state.get_moves();
+          if moves.is_empty() {
+            break;
+          }
+          let mv = moves[rng.gen_range(0..moves.len())];
+          let color = game_state.board.side_to_play;
+          let previous_state = game_state.clone();
+          let delta = game_state.make_move(&mv);
+          accumulator.make_move(&delta, color);
+          history.push((previous_state, delta, color));
+
+          assert_eq!(EvalAccumulator::from_game_state(&game_state),
+                     accumulator,
+                     "Accumulator mismatch after {} on {}",
+                     mv,
+                     game_state.to_fen());
+          assert_eq!(game_state.board.game_phase_value(),
+                     accumulator.game_phase_value());
+          assert_eq!(get_combined_material_score(&game_state),
+                     accumulator.material());
+          let params = &DEFAULT_EVAL_PARAMS;
+          assert_eq!(get_square_table_opening_score(&game_state, params),
+                     accumulator.square_table_score(GamePhase::Opening, params));
+          assert_eq!(get_square_table_middlegame_score(&game_state, params),
+                     accumulator.square_table_score(GamePhase::Middlegame, params));
+          assert_eq!(get_square_table_endgame_score(&game_state, params),
+                     accumulator.square_table_score(GamePhase::Endgame, params));
+          assert_eq!(evaluate_board(&game_state),
+                     evaluate_board_with_accumulator(&game_state, &accumulator));
+        }
+
+        // Unmaking all the moves brings us back to the previous positions
+        while let Some((previous_state, delta, color)) = history.pop() {
+          accumulator.unmake_move(&delta, color);
+          assert_eq!(EvalAccumulator::from_game_state(&previous_state),
+                     accumulator);
+          assert_eq!(previous_state.board.game_phase_value(),
+                     accumulator.game_phase_value());
+        }
+      }
+    }
+  }
+}
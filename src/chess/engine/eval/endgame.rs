@@ -1,4 +1,6 @@
+use super::accumulator::EvalAccumulator;
 use super::helpers::generic::get_material_score;
+use super::params::*;
 use super::position::*;
 use crate::engine::eval::helpers::pawn::is_passed;
 use crate::engine::tables::squares::*;
@@ -10,46 +12,58 @@ use crate::model::piece_moves::KING_MOVES;
 
 //const PIECE_MOBILITY_FACTOR: f32 = 0.01;
 //const KING_DANGER_FACTOR: f32 = 2.0;
-const SQUARE_TABLE_FACTOR: f32 = 0.03;
-const PASSED_PAWN_FACTOR: f32 = 0.02;
 
 // TODO: Consider this https://lichess.org/blog/W3WeMyQAACQAdfAL/7-piece-syzygy-tablebases-are-complete
 // Or maybe just try as much as I can without any external resources.
 
+/// Value of a piece on a square in the endgame piece-square tables. Values
+/// are positive for White pieces and negative for Black pieces.
+///
+/// ### Arguments
+///
+/// * `square`: Square on which the piece is located
+/// * `color`:  Color of the piece
+/// * `piece`:  Type of the piece
+///
+/// ### Return value
+///
+/// Bonus of the piece on the square, before applying the weight of the table
+pub fn endgame_square_table_value(square: u8, color: Color, piece: PieceType) -> isize {
+  let i = square as usize;
+  match (color, piece) {
+    (Color::White, PieceType::King) => EndgameSquareTable::KING[i],
+    (Color::White, PieceType::Queen) => EndgameSquareTable::QUEEN[i],
+    (Color::White, PieceType::Rook) => EndgameSquareTable::WHITE_ROOK[i],
+    (Color::White, PieceType::Bishop) => SquareTable::WHITE_BISHOP[i],
+    (Color::White, PieceType::Knight) => SquareTable::WHITE_KNIGHT[i],
+    (Color::White, PieceType::Pawn) => SquareTable::WHITE_PAWN[i],
+    (Color::Black, PieceType::King) => -EndgameSquareTable::KING[i],
+    (Color::Black, PieceType::Queen) => -EndgameSquareTable::QUEEN[i],
+    (Color::Black, PieceType::Rook) => -EndgameSquareTable::BLACK_ROOK[i],
+    (Color::Black, PieceType::Bishop) => -SquareTable::BLACK_BISHOP[i],
+    (Color::Black, PieceType::Knight) => -SquareTable::BLACK_KNIGHT[i],
+    (Color::Black, PieceType::Pawn) => -SquareTable::BLACK_PAWN[i],
+  }
+}
+
 /// Computes a total score based on the square where pieces are located in the
 /// endgame.
 ///
 /// ### Arguments
 ///
 /// * `game_state`: GameState reference
+/// * `params`:     Weights of the evaluation terms
 ///
 /// ### Return value
 ///
 /// f32 score that can be applied to the evaluation
 ///
-pub fn get_square_table_endgame_score(game_state: &GameState) -> f32 {
+pub fn get_square_table_endgame_score(game_state: &GameState, params: &EvalParams) -> f32 {
   let mut score: isize = 0;
-  for (i, piece) in game_state.board.pieces.white {
-    match piece {
-      PieceType::King => score += EndgameSquareTable::KING[i as usize],
-      PieceType::Queen => score += EndgameSquareTable::QUEEN[i as usize],
-      PieceType::Rook => score += EndgameSquareTable::WHITE_ROOK[i as usize],
-      PieceType::Bishop => score += SquareTable::WHITE_BISHOP[i as usize],
-      PieceType::Knight => score += SquareTable::KNIGHT[i as usize],
-      PieceType::Pawn => score += SquareTable::WHITE_PAWN[i as usize],
-    }
+  for (i, color, piece) in game_state.board.pieces.iter() {
+    score += endgame_square_table_value(i, color, piece);
   }
-  for (i, piece) in game_state.board.pieces.black {
-    match piece {
-      PieceType::King => score -= EndgameSquareTable::KING[i as usize],
-      PieceType::Queen => score -= EndgameSquareTable::QUEEN[i as usize],
-      PieceType::Rook => score -= EndgameSquareTable::BLACK_ROOK[i as usize],
-      PieceType::Bishop => score -= SquareTable::BLACK_BISHOP[i as usize],
-      PieceType::Knight => score -= SquareTable::KNIGHT[i as usize],
-      PieceType::Pawn => score -= SquareTable::BLACK_PAWN[i as usize],
-    }
-  }
-  score as f32 * SQUARE_TABLE_FACTOR
+  score as f32 * params.endgame_square_table_factor
 }
 
 /// Gives a score based on the endgame situation.
@@ -59,8 +73,26 @@ pub fn get_square_table_endgame_score(game_state: &GameState) -> f32 {
 /// * `game_state` - A GameState object representing a position, side to play, etc.
 /// * `color` -      The color for which we want to determine if development is completed.
 pub fn get_endgame_position_evaluation(game_state: &GameState) -> f32 {
+  let accumulator = EvalAccumulator::from_game_state(game_state);
+  get_endgame_position_breakdown(game_state, &DEFAULT_EVAL_PARAMS, &accumulator).total()
+}
+
+/// Computes all the terms of the evaluation of a position in the endgame
+///
+/// # Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+/// * `accumulator` - Material and piece-square table scores of the position
+pub fn get_endgame_position_breakdown(game_state: &GameState,
+                                      params: &EvalParams,
+                                      accumulator: &EvalAccumulator)
+                                      -> EvalBreakdown {
+  let mut breakdown = EvalBreakdown::default();
+
   if just_the_opponent_king_left(game_state) {
-    return get_king_vs_queen_or_rook_score(game_state);
+    breakdown.endgame_adjustment = get_king_vs_queen_or_rook_score(game_state);
+    return breakdown;
   }
 
   // Check if we cannot win, cap the score to 0:
@@ -68,8 +100,6 @@ pub fn get_endgame_position_evaluation(game_state: &GameState) -> f32 {
   let min_score = if one_minor_left(game_state, Color::Black) { 0.0 } else { -200.0 };
 
   // TODO: Implement a proper evaluation here
-  let mut score: f32 = 0.0;
-
   // Check if we have good passed pawns for white.
   let mut pawns = game_state.board.pieces.white.pawn;
   let mut passed_pawns_score = 0;
@@ -82,7 +112,7 @@ pub fn get_endgame_position_evaluation(game_state: &GameState) -> f32 {
 
     pawns &= pawns - 1
   }
-  score += PASSED_PAWN_FACTOR * passed_pawns_score as f32;
+  breakdown.passed_pawns += params.passed_pawn_factor * passed_pawns_score as f32;
 
   // Check if we have good passed pawns for black.
   let mut pawns = game_state.board.pieces.black.pawn;
@@ -96,21 +126,22 @@ pub fn get_endgame_position_evaluation(game_state: &GameState) -> f32 {
 
     pawns &= pawns - 1
   }
-  score -= PASSED_PAWN_FACTOR * passed_pawns_score as f32;
+  breakdown.passed_pawns -= params.passed_pawn_factor * passed_pawns_score as f32;
 
   // Stop using the table square bonuses when we have no more pawns.
   if game_state.board.pieces.pawns() != 0 {
-    score += get_square_table_endgame_score(game_state);
+    breakdown.piece_square_tables = accumulator.square_table_score(GamePhase::Endgame, params);
   }
 
-  score += default_position_evaluation(game_state);
+  add_default_position_terms(game_state, params, accumulator, &mut breakdown);
+  let score = breakdown.total();
   if score < min_score {
-    score = min_score;
+    breakdown.endgame_adjustment = min_score - score;
   } else if score > max_score {
-    score = max_score;
+    breakdown.endgame_adjustment = max_score - score;
   }
 
-  score
+  breakdown
 }
 
 /// Checks if we just have the opponent king left against us
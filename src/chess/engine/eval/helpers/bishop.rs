@@ -67,6 +67,25 @@ pub fn get_bishop_victims(game_state: &GameState, color: Color) -> u32 {
   victims
 }
 
+/// Checks if a side has the bishop pair, i.e. bishops on both square colors
+///
+/// ### Arguments
+///
+/// * `game_state` :  Game to look at
+/// * `color` :       Color of the bishops to look at
+///
+/// ### Return value
+///
+/// True if the side has a light-squared and a dark-squared bishop
+#[inline]
+pub fn has_bishop_pair(game_state: &GameState, color: Color) -> bool {
+  let bishops = match color {
+    Color::White => game_state.board.pieces.white.bishop,
+    Color::Black => game_state.board.pieces.black.bishop,
+  };
+  (bishops & LIGHT_SQUARES) != 0 && (bishops & DARK_SQUARES) != 0
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -107,4 +126,20 @@ mod tests {
     assert_eq!(0, get_bishop_victims(&game_state, Color::White));
     assert_eq!(2, get_bishop_victims(&game_state, Color::Black));
   }
+
+  #[test]
+  fn test_has_bishop_pair() {
+    let game_state = GameState::default();
+    assert!(has_bishop_pair(&game_state, Color::White));
+    assert!(has_bishop_pair(&game_state, Color::Black));
+
+    // Two bishops on the same square color are not a pair
+    let fen = "4k3/8/8/8/8/4B3/8/2B1K3 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(!has_bishop_pair(&game_state, Color::White));
+    let fen = "2b1k3/8/8/8/8/8/8/2B1K1B1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(!has_bishop_pair(&game_state, Color::White));
+    assert!(!has_bishop_pair(&game_state, Color::Black));
+  }
 }
@@ -28,27 +28,12 @@ pub enum FileState {
 ///
 /// Score for material
 pub fn get_material_score(game_state: &GameState, color: Color) -> f32 {
-  // Basic material count
-  let mut score: f32 = 0.0;
-
-  match color {
-    Color::White => {
-      score += game_state.board.pieces.white.queen.count_few_ones() as f32 * QUEEN_VALUE;
-      score += game_state.board.pieces.white.rook.count_few_ones() as f32 * ROOK_VALUE;
-      score += game_state.board.pieces.white.bishop.count_few_ones() as f32 * BISHOP_VALUE;
-      score += game_state.board.pieces.white.knight.count_few_ones() as f32 * KNIGHT_VALUE;
-      score += game_state.board.pieces.white.pawn.count_ones() as f32 * PAWN_VALUE;
-    },
-    Color::Black => {
-      score += game_state.board.pieces.black.queen.count_few_ones() as f32 * QUEEN_VALUE;
-      score += game_state.board.pieces.black.rook.count_few_ones() as f32 * ROOK_VALUE;
-      score += game_state.board.pieces.black.bishop.count_few_ones() as f32 * BISHOP_VALUE;
-      score += game_state.board.pieces.black.knight.count_few_ones() as f32 * KNIGHT_VALUE;
-      score += game_state.board.pieces.black.pawn.count_ones() as f32 * PAWN_VALUE;
-    },
-  }
-
-  score
+  let board = &game_state.board;
+  board.piece_count(color, PieceType::Queen) as f32 * QUEEN_VALUE
+  + board.piece_count(color, PieceType::Rook) as f32 * ROOK_VALUE
+  + board.piece_count(color, PieceType::Bishop) as f32 * BISHOP_VALUE
+  + board.piece_count(color, PieceType::Knight) as f32 * KNIGHT_VALUE
+  + board.piece_count(color, PieceType::Pawn) as f32 * PAWN_VALUE
 }
 
 /// Computes the material score of a side
@@ -142,6 +142,14 @@ pub const BLACK_KING_SHELTER_PAWNS: [u64; 64] = [0x0000000000000000,
                                                  0x00E0E0E0E0E0E0E0,
                                                  0x00C0C0C0C0C0C0C0];
 
+/// Penalty of a king, in centipawns, indexed by the attack weight of the enemy
+/// pieces on its zone (see `Board::count_attackers_near_king`). It grows
+/// faster than the attack weight, as attacks with several pieces are the ones
+/// that break through.
+pub const KING_ATTACK_PENALTY: [u32; 32] = [0, 0, 1, 2, 3, 5, 7, 9, 12, 15, 18, 22, 26, 30, 35, 39,
+                                            44, 50, 56, 62, 68, 75, 82, 85, 89, 97, 105, 113, 122,
+                                            131, 140, 150];
+
 // -----------------------------------------------------------------------------
 // Functions
 
@@ -180,6 +188,28 @@ pub fn get_king_danger_score(game_state: &GameState, color: Color) -> f32 {
   attacked_squares as f32 / total_squares as f32
 }
 
+/// Evaluates how much a king is under attack, based on the number and weight
+/// of the enemy pieces attacking the zone around it. A single attacker is not
+/// considered dangerous.
+///
+/// # Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play,
+///   etc.
+/// * `color` -      The color of the king
+///
+/// # Returns
+///
+/// Penalty of the king, in pawns, from `KING_ATTACK_PENALTY`
+pub fn get_king_attack_penalty(game_state: &GameState, color: Color) -> f32 {
+  let (attackers, weight) = game_state.board.count_attackers_near_king(color);
+  if attackers < 2 {
+    return 0.0;
+  }
+  let index = (weight as usize).min(KING_ATTACK_PENALTY.len() - 1);
+  KING_ATTACK_PENALTY[index] as f32 / 100.0
+}
+
 /// Checks if the king is way too adventurous
 ///
 /// Noticed that the engine likes to walk
@@ -357,6 +387,25 @@ mod tests {
     assert_eq!(3.0 / 8.0, get_king_danger_score(&game_state, Color::White));
   }
 
+  #[test]
+  fn test_get_king_attack_penalty() {
+    // Nothing attacks the kings
+    let game_state = GameState::default();
+    assert_eq!(0.0, get_king_attack_penalty(&game_state, Color::White));
+    assert_eq!(0.0, get_king_attack_penalty(&game_state, Color::Black));
+
+    // Queen, rook, bishop and knight aiming at the white king
+    let fen = "6k1/5ppp/3b1r2/8/6nq/8/5PPP/6K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert_eq!(0.26, get_king_attack_penalty(&game_state, Color::White));
+    assert_eq!(0.0, get_king_attack_penalty(&game_state, Color::Black));
+
+    // A lone queen is not an attack yet
+    let fen = "6k1/5ppp/8/8/7q/8/5PPP/6K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert_eq!(0.0, get_king_attack_penalty(&game_state, Color::White));
+  }
+
   #[test]
   fn test_get_king_shelter_value() {
     let fen = "rnb1kbnr/pppp1ppp/5q2/4p3/4P3/5Q2/PPPP1PPP/RNB1KBNR w KQkq - 2 3";
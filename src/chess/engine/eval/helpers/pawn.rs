@@ -458,6 +458,21 @@ pub fn get_holes(game_state: &GameState, color: Color) -> BoardMask {
   holes
 }
 
+/// Computes the number of locked pawns, i.e. white pawns blocked by a black
+/// pawn right in front of them. Each pair of locked pawns counts once.
+///
+/// ### Arguments
+///
+/// * `game_state`: Game to look at
+///
+/// ### Return value
+///
+/// Number of pairs of pawns blocking each other
+#[inline]
+pub fn get_number_of_locked_pawns(game_state: &GameState) -> u32 {
+  ((game_state.board.pieces.white.pawn << 8) & game_state.board.pieces.black.pawn).count_ones()
+}
+
 /// Computes the number of pieces attacked by defended pawns
 #[inline]
 pub fn get_pawn_victims(game_state: &GameState, color: Color) -> u32 {
@@ -1,28 +1,72 @@
 use super::generic::*;
 use crate::model::board::*;
+use crate::model::board_geometry::*;
 use crate::model::board_mask::*;
 use crate::model::game_state::*;
 use crate::model::piece::*;
 use crate::model::tables::rook_destinations::get_rook_destinations;
-use crate::square_in_mask;
 
-/// Determine if rooks are connected for a color
+/// Determine if rooks are connected for a color, i.e. if two of its rooks
+/// share a rank or a file with no piece between them
 pub fn are_rooks_connected(game_state: &GameState, color: Color) -> bool {
   let mut rooks = match color {
     Color::White => game_state.board.pieces.white.rook,
     Color::Black => game_state.board.pieces.black.rook,
   };
+  let pieces = game_state.board.pieces.all();
 
-  if rooks.count_few_ones() != 2 {
-    return false;
+  while rooks != 0 {
+    let rook_1 = rooks.trailing_zeros() as u8;
+    rooks &= rooks - 1;
+
+    let mut others = rooks;
+    while others != 0 {
+      let rook_2 = others.trailing_zeros() as u8;
+      others &= others - 1;
+
+      let same_line = rook_1 / 8 == rook_2 / 8 || rook_1 % 8 == rook_2 % 8;
+      if same_line && squares_between(rook_1, rook_2) & pieces == 0 {
+        return true;
+      }
+    }
   }
-  let rook_1 = rooks.trailing_zeros() as u8;
-  rooks &= rooks - 1;
-  let rook_2 = rooks.trailing_zeros() as u8;
 
-  let destinations = game_state.board.get_piece_control_mask(rook_1);
+  false
+}
+
+/// Counts the rooks of a color on the 7th rank (2nd rank for Black), where they
+/// attack the pawns that did not move yet and cut off the enemy king.
+/// Rooks only count when the enemy king is on its back rank or enemy pawns are
+/// still on the 7th rank.
+///
+/// ### Arguments
+///
+/// * `game_state`: A GameState object representing a position, side to play,
+///   etc.
+/// * `color`:      Color of the rooks to count
+///
+/// ### Returns
+///
+/// Number of rooks on the 7th rank, doubled rooks count twice.
+pub fn get_rooks_on_seventh_rank(game_state: &GameState, color: Color) -> u32 {
+  let (rooks, enemy_king, enemy_pawns, seventh_rank, back_rank) = match color {
+    Color::White => (game_state.board.pieces.white.rook,
+                     game_state.board.pieces.black.king,
+                     game_state.board.pieces.black.pawn,
+                     RANKS[6],
+                     RANKS[7]),
+    Color::Black => (game_state.board.pieces.black.rook,
+                     game_state.board.pieces.white.king,
+                     game_state.board.pieces.white.pawn,
+                     RANKS[1],
+                     RANKS[0]),
+  };
+
+  if enemy_king & back_rank == 0 && enemy_pawns & seventh_rank == 0 {
+    return 0;
+  }
 
-  square_in_mask!(rook_2, destinations)
+  (rooks & seventh_rank).count_few_ones()
 }
 
 /// Assigns a score to a rooks based on if it is located on:
@@ -134,6 +178,52 @@ mod tests {
     assert_eq!(true, are_rooks_connected(&game_state, Color::White));
   }
 
+  #[test]
+  fn test_rooks_connected_on_a_line() {
+    // Rooks on the same file, with a knight between them
+    let fen = "6k1/8/8/8/2R5/2N5/8/2R3K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(!are_rooks_connected(&game_state, Color::White));
+
+    // Rooks next to each other, or doubled on a file
+    let fen = "6k1/8/8/8/2R5/8/8/2R3K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(are_rooks_connected(&game_state, Color::White));
+    let fen = "3rr1k1/8/8/8/8/8/8/6K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(are_rooks_connected(&game_state, Color::Black));
+
+    // Rooks on a diagonal are not connected
+    let fen = "6k1/8/8/8/8/8/1R6/R5K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(!are_rooks_connected(&game_state, Color::White));
+
+    // With 3 rooks, one connected pair is enough
+    let fen = "6k1/8/8/8/8/R7/8/RN2R1K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert!(are_rooks_connected(&game_state, Color::White));
+  }
+
+  #[test]
+  fn test_rooks_on_seventh_rank() {
+    // Rook on the 7th, with the enemy king on the back rank and pawns on the
+    // 7th
+    let fen = "6k1/R4ppp/8/8/8/8/5PPP/6K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert_eq!(1, get_rooks_on_seventh_rank(&game_state, Color::White));
+    assert_eq!(0, get_rooks_on_seventh_rank(&game_state, Color::Black));
+
+    // Doubled rooks on the 2nd rank for Black
+    let fen = "6k1/5ppp/8/8/8/8/rr3PPP/6K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert_eq!(2, get_rooks_on_seventh_rank(&game_state, Color::Black));
+
+    // No pawn on the 7th and the king left the back rank: no bonus
+    let fen = "8/R7/5kpp/5p2/8/8/5PPP/6K1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    assert_eq!(0, get_rooks_on_seventh_rank(&game_state, Color::White));
+  }
+
   #[test]
   fn evaluate_well_placed_rooks() {
     // Compare 3 position, one with rook on closed file, half open and then open
@@ -1,11 +1,42 @@
-use super::position::default_position_evaluation;
+use super::accumulator::EvalAccumulator;
+use super::params::*;
+use super::position::{add_default_position_terms, EvalBreakdown};
+use crate::engine::eval::helpers::king::get_king_attack_penalty;
 use crate::engine::tables::squares::*;
-use crate::model::game_state::GameState;
+use crate::model::game_state::{GamePhase, GameState};
 use crate::model::piece::*;
 
-//const KING_DANGER_FACTOR: f32 = 0.3;
 //const KING_TOO_ADVENTUROUS_PENALTY: f32 = 0.9;
-const SQUARE_TABLE_FACTOR: f32 = 0.02;
+
+/// Value of a piece on a square in the middlegame piece-square tables. Values
+/// are positive for White pieces and negative for Black pieces.
+///
+/// ### Arguments
+///
+/// * `square`: Square on which the piece is located
+/// * `color`:  Color of the piece
+/// * `piece`:  Type of the piece
+///
+/// ### Return value
+///
+/// Bonus of the piece on the square, before applying the weight of the table
+pub fn middlegame_square_table_value(square: u8, color: Color, piece: PieceType) -> isize {
+  let i = square as usize;
+  match (color, piece) {
+    (Color::White, PieceType::King) => MiddleGameSquareTable::WHITE_KING[i],
+    (Color::White, PieceType::Queen) => SquareTable::QUEEN[i],
+    (Color::White, PieceType::Rook) => MiddleGameSquareTable::WHITE_ROOK[i],
+    (Color::White, PieceType::Bishop) => SquareTable::WHITE_BISHOP[i],
+    (Color::White, PieceType::Knight) => SquareTable::WHITE_KNIGHT[i],
+    (Color::White, PieceType::Pawn) => SquareTable::WHITE_PAWN[i],
+    (Color::Black, PieceType::King) => -MiddleGameSquareTable::BLACK_KING[i],
+    (Color::Black, PieceType::Queen) => -SquareTable::QUEEN[i],
+    (Color::Black, PieceType::Rook) => -MiddleGameSquareTable::BLACK_ROOK[i],
+    (Color::Black, PieceType::Bishop) => -SquareTable::BLACK_BISHOP[i],
+    (Color::Black, PieceType::Knight) => -SquareTable::BLACK_KNIGHT[i],
+    (Color::Black, PieceType::Pawn) => -SquareTable::BLACK_PAWN[i],
+  }
+}
 
 /// Computes a total score based on the square where pieces are located in the
 /// middlegame.
@@ -13,34 +44,18 @@ const SQUARE_TABLE_FACTOR: f32 = 0.02;
 /// ### Arguments
 ///
 /// * `game_state`: GameState reference
+/// * `params`:     Weights of the evaluation terms
 ///
 /// ### Return value
 ///
 /// f32 score that can be applied to the evaluation
 ///
-pub fn get_square_table_middlegame_score(game_state: &GameState) -> f32 {
+pub fn get_square_table_middlegame_score(game_state: &GameState, params: &EvalParams) -> f32 {
   let mut score: isize = 0;
-  for (i, piece) in game_state.board.pieces.white {
-    match piece {
-      PieceType::King => score += MiddleGameSquareTable::WHITE_KING[i as usize],
-      PieceType::Queen => score += SquareTable::QUEEN[i as usize],
-      PieceType::Rook => score += MiddleGameSquareTable::WHITE_ROOK[i as usize],
-      PieceType::Bishop => score += SquareTable::WHITE_BISHOP[i as usize],
-      PieceType::Knight => score += SquareTable::KNIGHT[i as usize],
-      PieceType::Pawn => score += SquareTable::WHITE_PAWN[i as usize],
-    }
-  }
-  for (i, piece) in game_state.board.pieces.black {
-    match piece {
-      PieceType::King => score -= MiddleGameSquareTable::BLACK_KING[i as usize],
-      PieceType::Queen => score -= SquareTable::QUEEN[i as usize],
-      PieceType::Rook => score -= MiddleGameSquareTable::BLACK_ROOK[i as usize],
-      PieceType::Bishop => score -= SquareTable::BLACK_BISHOP[i as usize],
-      PieceType::Knight => score -= SquareTable::KNIGHT[i as usize],
-      PieceType::Pawn => score -= SquareTable::BLACK_PAWN[i as usize],
-    }
+  for (i, color, piece) in game_state.board.pieces.iter() {
+    score += middlegame_square_table_value(i, color, piece);
   }
-  score as f32 * SQUARE_TABLE_FACTOR
+  score as f32 * params.middlegame_square_table_factor
 }
 
 /// Gives a score based on the position in the middlegame
@@ -49,17 +64,26 @@ pub fn get_square_table_middlegame_score(game_state: &GameState) -> f32 {
 ///
 /// * `game_state` - A GameState object representing a position, side to play, etc.
 pub fn get_middlegame_position_evaluation(game_state: &GameState) -> f32 {
-  let mut score: f32 = 0.0;
+  let accumulator = EvalAccumulator::from_game_state(game_state);
+  get_middlegame_position_breakdown(game_state, &DEFAULT_EVAL_PARAMS, &accumulator).total()
+}
 
+/// Computes all the terms of the evaluation of a position in the middlegame
+///
+/// # Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+/// * `accumulator` - Material and piece-square table scores of the position
+pub fn get_middlegame_position_breakdown(game_state: &GameState,
+                                         params: &EvalParams,
+                                         accumulator: &EvalAccumulator)
+                                         -> EvalBreakdown {
   /*
   score += PIECE_MOBILITY_FACTOR
     * (get_piece_mobility(game_state, Color::White) as f32
       - get_piece_mobility(game_state, Color::Black) as f32);
 
-      score += KING_DANGER_FACTOR
-      * (get_king_danger_score(game_state, Color::Black)
-      - get_king_danger_score(game_state, Color::White));
-
       if is_king_too_adventurous(game_state, Color::White) {
         score -= KING_TOO_ADVENTUROUS_PENALTY;
       }
@@ -68,9 +92,17 @@ pub fn get_middlegame_position_evaluation(game_state: &GameState) -> f32 {
       }
       */
 
-  score += get_square_table_middlegame_score(game_state);
+  let king_safety = params.king_safety_factor
+    * (get_king_attack_penalty(game_state, Color::Black)
+      - get_king_attack_penalty(game_state, Color::White));
 
-  score + default_position_evaluation(game_state)
+  let piece_square_tables = accumulator.square_table_score(GamePhase::Middlegame, params);
+  let mut breakdown = EvalBreakdown { piece_square_tables,
+                                      king_safety,
+                                      ..Default::default() };
+  add_default_position_terms(game_state, params, accumulator, &mut breakdown);
+
+  breakdown
 }
 
 //------------------------------------------------------------------------------
@@ -110,4 +142,31 @@ mod tests {
     assert!(eval_reachable_outpost > eval_nothing);
     assert!(eval_outpost > eval_reachable_outpost);
   }
+
+  #[test]
+  fn evaluate_king_zone_attacks() {
+    // Black queen, rook, knight and bishop all aim at the zone around the white king
+    let fen = "r1b3k1/ppp2ppp/2n5/3b1r2/6nq/2N5/PPPB1PPP/R2Q1RK1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    let accumulator = EvalAccumulator::from_game_state(&game_state);
+    let breakdown =
+      get_middlegame_position_breakdown(&game_state, &DEFAULT_EVAL_PARAMS, &accumulator);
+
+    let mut params = DEFAULT_EVAL_PARAMS;
+    params.king_safety_factor = 0.0;
+    let breakdown_ignoring_attacks =
+      get_middlegame_position_breakdown(&game_state, &params, &accumulator);
+
+    println!("Breakdown:\n{breakdown}");
+    assert!(breakdown.king_safety < 0.0);
+    assert!(breakdown.total() < breakdown_ignoring_attacks.total());
+
+    // Same pieces, sitting away from the white king
+    let fen = "r1b1r1k1/pppb1ppp/2n5/q7/1n6/2N5/PPPB1PPP/R2Q1RK1 w - - 0 1";
+    let game_state = GameState::from_fen(fen);
+    let accumulator = EvalAccumulator::from_game_state(&game_state);
+    let breakdown =
+      get_middlegame_position_breakdown(&game_state, &DEFAULT_EVAL_PARAMS, &accumulator);
+    assert_eq!(breakdown.king_safety, 0.0);
+  }
 }
@@ -1,6 +1,9 @@
+pub mod accumulator;
 pub mod development;
 pub mod endgame;
 pub mod helpers;
 pub mod middlegame;
 pub mod opening;
+pub mod params;
 pub mod position;
+pub mod tuning;
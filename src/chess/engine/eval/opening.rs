@@ -1,50 +1,64 @@
-use super::position::default_position_evaluation;
+use super::accumulator::EvalAccumulator;
+use super::params::*;
+use super::position::{add_default_position_terms, EvalBreakdown};
 use crate::engine::eval::development::get_development_score;
+use crate::engine::eval::helpers::king::get_king_attack_penalty;
 use crate::engine::tables::squares::*;
-use crate::model::game_state::GameState;
+use crate::model::game_state::{GamePhase, GameState};
 use crate::model::piece::*;
 
 // Constants
-const DEVELOPMENT_FACTOR: f32 = 0.03;
-//const KING_DANGER_FACTOR: f32 = 0.3;
 //const KING_TOO_ADVENTUROUS_PENALTY: f32 = 0.9;
-const SQUARE_TABLE_FACTOR: f32 = 0.02;
 const _CASTLING_PENATLY: f32 = 1.0;
 
+/// Value of a piece on a square in the opening piece-square tables. Values
+/// are positive for White pieces and negative for Black pieces.
+///
+/// ### Arguments
+///
+/// * `square`: Square on which the piece is located
+/// * `color`:  Color of the piece
+/// * `piece`:  Type of the piece
+///
+/// ### Return value
+///
+/// Bonus of the piece on the square, before applying the weight of the table
+pub fn opening_square_table_value(square: u8, color: Color, piece: PieceType) -> isize {
+  let i = square as usize;
+  match (color, piece) {
+    (Color::White, PieceType::King) => OpeningSquareTable::WHITE_KING[i],
+    (Color::White, PieceType::Queen) => OpeningSquareTable::QUEEN[i],
+    (Color::White, PieceType::Rook) => OpeningSquareTable::WHITE_ROOK[i],
+    (Color::White, PieceType::Bishop) => SquareTable::WHITE_BISHOP[i],
+    (Color::White, PieceType::Knight) => SquareTable::WHITE_KNIGHT[i],
+    (Color::White, PieceType::Pawn) => SquareTable::WHITE_PAWN[i],
+    (Color::Black, PieceType::King) => -OpeningSquareTable::BLACK_KING[i],
+    (Color::Black, PieceType::Queen) => -OpeningSquareTable::QUEEN[i],
+    (Color::Black, PieceType::Rook) => -OpeningSquareTable::BLACK_ROOK[i],
+    (Color::Black, PieceType::Bishop) => -SquareTable::BLACK_BISHOP[i],
+    (Color::Black, PieceType::Knight) => -SquareTable::BLACK_KNIGHT[i],
+    (Color::Black, PieceType::Pawn) => -SquareTable::BLACK_PAWN[i],
+  }
+}
+
 /// Computes a total score based on the square where pieces are located in the
 /// opening.
 ///
 /// ### Arguments
 ///
 /// * `game_state`: GameState reference
+/// * `params`:     Weights of the evaluation terms
 ///
 /// ### Return value
 ///
 /// f32 score that can be applied to the evaluation
 ///
-pub fn get_square_table_opening_score(game_state: &GameState) -> f32 {
+pub fn get_square_table_opening_score(game_state: &GameState, params: &EvalParams) -> f32 {
   let mut score: isize = 0;
-  for (i, piece) in game_state.board.pieces.white {
-    match piece {
-      PieceType::King => score += OpeningSquareTable::WHITE_KING[i as usize],
-      PieceType::Queen => score += OpeningSquareTable::QUEEN[i as usize],
-      PieceType::Rook => score += OpeningSquareTable::WHITE_ROOK[i as usize],
-      PieceType::Bishop => score += SquareTable::WHITE_BISHOP[i as usize],
-      PieceType::Knight => score += SquareTable::KNIGHT[i as usize],
-      PieceType::Pawn => score += SquareTable::WHITE_PAWN[i as usize],
-    }
+  for (i, color, piece) in game_state.board.pieces.iter() {
+    score += opening_square_table_value(i, color, piece);
   }
-  for (i, piece) in game_state.board.pieces.black {
-    match piece {
-      PieceType::King => score -= OpeningSquareTable::BLACK_KING[i as usize],
-      PieceType::Queen => score -= OpeningSquareTable::QUEEN[i as usize],
-      PieceType::Rook => score -= OpeningSquareTable::BLACK_ROOK[i as usize],
-      PieceType::Bishop => score -= SquareTable::WHITE_BISHOP[i as usize],
-      PieceType::Knight => score -= SquareTable::KNIGHT[i as usize],
-      PieceType::Pawn => score -= SquareTable::BLACK_PAWN[i as usize],
-    }
-  }
-  score as f32 * SQUARE_TABLE_FACTOR
+  score as f32 * params.opening_square_table_factor
 }
 
 /// Gives a score based on the position in the opening
@@ -53,17 +67,30 @@ pub fn get_square_table_opening_score(game_state: &GameState) -> f32 {
 ///
 /// * `game_state` - A GameState object representing a position, side to play, etc.
 pub fn get_opening_position_evaluation(game_state: &GameState) -> f32 {
-  let mut score: f32 = 0.0;
+  let accumulator = EvalAccumulator::from_game_state(game_state);
+  get_opening_position_breakdown(game_state, &DEFAULT_EVAL_PARAMS, &accumulator).total()
+}
 
-  score += DEVELOPMENT_FACTOR
+/// Computes all the terms of the evaluation of a position in the opening
+///
+/// # Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+/// * `accumulator` - Material and piece-square table scores of the position
+pub fn get_opening_position_breakdown(game_state: &GameState,
+                                      params: &EvalParams,
+                                      accumulator: &EvalAccumulator)
+                                      -> EvalBreakdown {
+  let development = params.development_factor
     * (get_development_score(game_state, Color::White) as f32
       - get_development_score(game_state, Color::Black) as f32);
 
-  /*
-  score += KING_DANGER_FACTOR
-    * (get_king_danger_score(game_state, Color::Black)
-      - get_king_danger_score(game_state, Color::White));
+  let king_safety = params.king_safety_factor
+    * (get_king_attack_penalty(game_state, Color::Black)
+      - get_king_attack_penalty(game_state, Color::White));
 
+  /*
   if is_king_too_adventurous(game_state, Color::White) {
     score -= KING_TOO_ADVENTUROUS_PENALTY;
   }
@@ -79,9 +106,14 @@ pub fn get_opening_position_evaluation(game_state: &GameState) -> f32 {
   }
    */
 
-  score += get_square_table_opening_score(game_state);
+  let piece_square_tables = accumulator.square_table_score(GamePhase::Opening, params);
+  let mut breakdown = EvalBreakdown { development,
+                                      piece_square_tables,
+                                      king_safety,
+                                      ..Default::default() };
+  add_default_position_terms(game_state, params, accumulator, &mut breakdown);
 
-  score + default_position_evaluation(game_state)
+  breakdown
 }
 
 //------------------------------------------------------------------------------
@@ -256,23 +288,23 @@ mod tests {
   fn evaluate_weird_opening_moves() {
     let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
     let game_state = GameState::from_fen(fen);
-    let score_e4 = get_square_table_opening_score(&game_state);
+    let score_e4 = get_square_table_opening_score(&game_state, &DEFAULT_EVAL_PARAMS);
 
     let fen = "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1";
     let game_state = GameState::from_fen(fen);
-    let score_d4 = get_square_table_opening_score(&game_state);
+    let score_d4 = get_square_table_opening_score(&game_state, &DEFAULT_EVAL_PARAMS);
 
     let fen = "rnbqkbnr/pppppppp/8/8/P7/8/1PPPPPPP/RNBQKBNR b KQkq - 0 1";
     let game_state = GameState::from_fen(fen);
-    let score_a4 = get_square_table_opening_score(&game_state);
+    let score_a4 = get_square_table_opening_score(&game_state, &DEFAULT_EVAL_PARAMS);
 
     let fen = "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1";
     let game_state = GameState::from_fen(fen);
-    let score_nf3 = get_square_table_opening_score(&game_state);
+    let score_nf3 = get_square_table_opening_score(&game_state, &DEFAULT_EVAL_PARAMS);
 
     let fen = "rnbqkbnr/pppppppp/8/8/8/7N/PPPPPPPP/RNBQKB1R b KQkq - 1 1";
     let game_state = GameState::from_fen(fen);
-    let score_nh3 = get_square_table_opening_score(&game_state);
+    let score_nh3 = get_square_table_opening_score(&game_state, &DEFAULT_EVAL_PARAMS);
 
     println!("E4: {score_e4}");
     println!("D4: {score_d4}");
@@ -0,0 +1,118 @@
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Number of tunable parameters in `EvalParams`
+pub const EVAL_PARAMS_COUNT: usize = 19;
+
+/// Weights used by the evaluation function.
+pub const DEFAULT_EVAL_PARAMS: EvalParams = EvalParams { pawn_island_factor:             0.05,
+                                                         connected_rooks_factor:         0.03,
+                                                         rook_file_factor:               0.06,
+                                                         rook_seventh_rank_factor:       0.05,
+                                                         hanging_factor:                 0.4,
+                                                         hanging_penalty:                0.15,
+                                                         pin_penalty:                    0.25,
+                                                         pawn_attack_factor:             1.0,
+                                                         knight_attack_factor:           0.5,
+                                                         bishop_attack_factor:           0.5,
+                                                         rook_attack_factor:             0.3,
+                                                         development_factor:             0.03,
+                                                         opening_square_table_factor:    0.02,
+                                                         middlegame_square_table_factor: 0.02,
+                                                         endgame_square_table_factor:    0.03,
+                                                         passed_pawn_factor:             0.02,
+                                                         bishop_pair_bonus:              0.3,
+                                                         minor_piece_closedness_factor:  0.015,
+                                                         king_safety_factor:             1.0, };
+
+/// Share of the bishop pair bonus kept when all the pawns are gone. The bonus
+/// scales down linearly with the number of pawns on the board.
+pub const BISHOP_PAIR_MIN_SCALE: f32 = 0.5;
+/// Closedness of a position in which knights and bishops are worth the same.
+/// The closedness is the number of pawns plus the number of locked pawns.
+pub const NEUTRAL_CLOSEDNESS: f32 = 12.0;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Weights of the evaluation terms. The default values are the ones used by the
+/// engine, other values can be used to tune the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalParams {
+  /// Penalty per pawn island
+  pub pawn_island_factor:             f32,
+  /// Bonus for connected rooks
+  pub connected_rooks_factor:         f32,
+  /// Multiplier of the rook file score
+  pub rook_file_factor:               f32,
+  /// Bonus per rook on the 7th rank (2nd for Black), when it attacks the enemy
+  /// king or pawns there
+  pub rook_seventh_rank_factor:       f32,
+  /// Multiplier applied to pieces attacked more than they are defended
+  pub hanging_factor:                 f32,
+  /// Penalty for undefended pieces
+  pub hanging_penalty:                f32,
+  /// Penalty when having pinned pieces
+  pub pin_penalty:                    f32,
+  /// Multiplier of the pieces attacked by pawns
+  pub pawn_attack_factor:             f32,
+  /// Multiplier of the pieces attacked by knights
+  pub knight_attack_factor:           f32,
+  /// Multiplier of the pieces attacked by bishops
+  pub bishop_attack_factor:           f32,
+  /// Multiplier of the pieces attacked by rooks
+  pub rook_attack_factor:             f32,
+  /// Multiplier of the development score in the opening
+  pub development_factor:             f32,
+  /// Multiplier of the piece-square tables in the opening
+  pub opening_square_table_factor:    f32,
+  /// Multiplier of the piece-square tables in the middlegame
+  pub middlegame_square_table_factor: f32,
+  /// Multiplier of the piece-square tables in the endgame
+  pub endgame_square_table_factor:    f32,
+  /// Multiplier of the passed pawns table in the endgame
+  pub passed_pawn_factor:             f32,
+  /// Bonus for having the bishop pair when the opponent does not, with all
+  /// the pawns on the board
+  pub bishop_pair_bonus:              f32,
+  /// Bonus per knight (penalty per bishop) and per closedness point above
+  /// `NEUTRAL_CLOSEDNESS`, reversed in open positions
+  pub minor_piece_closedness_factor:  f32,
+  /// Multiplier of the king attack penalty, outside of the endgame
+  pub king_safety_factor:             f32,
+}
+
+// -----------------------------------------------------------------------------
+// Implementations
+
+impl EvalParams {
+  /// Returns mutable references to all the parameters, so that they can be
+  /// adjusted one after the other.
+  pub fn values_mut(&mut self) -> [&mut f32; EVAL_PARAMS_COUNT] {
+    [&mut self.pawn_island_factor,
+     &mut self.connected_rooks_factor,
+     &mut self.rook_file_factor,
+     &mut self.rook_seventh_rank_factor,
+     &mut self.hanging_factor,
+     &mut self.hanging_penalty,
+     &mut self.pin_penalty,
+     &mut self.pawn_attack_factor,
+     &mut self.knight_attack_factor,
+     &mut self.bishop_attack_factor,
+     &mut self.rook_attack_factor,
+     &mut self.development_factor,
+     &mut self.opening_square_table_factor,
+     &mut self.middlegame_square_table_factor,
+     &mut self.endgame_square_table_factor,
+     &mut self.passed_pawn_factor,
+     &mut self.bishop_pair_bonus,
+     &mut self.minor_piece_closedness_factor,
+     &mut self.king_safety_factor]
+  }
+}
+
+impl Default for EvalParams {
+  fn default() -> Self {
+    DEFAULT_EVAL_PARAMS
+  }
+}
@@ -1,28 +1,97 @@
 // From our module
-use super::endgame::get_endgame_position_evaluation;
-use super::helpers::bishop::get_bishop_victims;
+use super::accumulator::EvalAccumulator;
+use super::endgame::get_endgame_position_breakdown;
+use super::helpers::bishop::{get_bishop_victims, has_bishop_pair};
 use super::helpers::generic::*;
 use super::helpers::knight::get_knight_victims;
 use super::helpers::pawn::*;
 use super::helpers::rook::*;
-use super::middlegame::get_middlegame_position_evaluation;
-use super::opening::get_opening_position_evaluation;
+use super::middlegame::get_middlegame_position_breakdown;
+use super::opening::get_opening_position_breakdown;
+use super::params::*;
 use crate::engine::cache::engine_cache::EngineCache;
 use crate::engine::Engine;
 use crate::model::board::Board;
 use crate::model::board_geometry::*;
-use crate::model::board_mask::CountFewOnes;
 use crate::model::game_state::*;
 use crate::model::piece::*;
 use crate::model::piece_moves::KING_MOVES;
 
-// Constants
-const PAWN_ISLAND_FACTOR: f32 = 0.05;
-const CONNECTED_ROOKS_FACTOR: f32 = 0.03;
-const ROOK_FILE_FACTOR: f32 = 0.06;
-const HANGING_FACTOR: f32 = 0.4;
-const HANGING_PENALTY: f32 = 0.15;
-const PIN_PENALTY: f32 = 0.25;
+/// Evaluation of a position, split into the terms contributing to it.
+///
+/// All terms are given from White's perspective, i.e. positive values are
+/// good for White. The evaluation is the sum of all terms, see
+/// `EvalBreakdown::total()`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalBreakdown {
+  /// Material balance
+  pub material:            f32,
+  /// Bonuses from the piece-square tables of the current game phase
+  pub piece_square_tables: f32,
+  /// Piece development, only used in the opening
+  pub development:         f32,
+  /// Pawn structure (pawn islands)
+  pub pawn_structure:      f32,
+  /// Passed pawns, only used in the endgame
+  pub passed_pawns:        f32,
+  /// Connected rooks, rooks on open files and rooks on the 7th rank
+  pub rooks:               f32,
+  /// Undefended or outnumbered pieces
+  pub hanging_pieces:      f32,
+  /// Pieces attacked or forked by pawns, knights, bishops and rooks
+  pub piece_attacks:       f32,
+  /// Pinned pieces
+  pub pins:                f32,
+  /// Bishop pair, and knights against bishops depending on how closed the
+  /// position is
+  pub minor_pieces:        f32,
+  /// Enemy pieces attacking the zone around the kings, not used in the
+  /// endgame
+  pub king_safety:         f32,
+  /// Endgame corrections, i.e. mating patterns or capping the score when a
+  /// side cannot win anymore
+  pub endgame_adjustment:  f32,
+}
+
+impl EvalBreakdown {
+  /// Sums all the evaluation terms
+  ///
+  /// ### Return value
+  ///
+  /// Score assigned to the position.
+  pub fn total(&self) -> f32 {
+    self.material
+    + self.piece_square_tables
+    + self.development
+    + self.pawn_structure
+    + self.passed_pawns
+    + self.rooks
+    + self.hanging_pieces
+    + self.piece_attacks
+    + self.pins
+    + self.minor_pieces
+    + self.king_safety
+    + self.endgame_adjustment
+  }
+}
+
+impl std::fmt::Display for EvalBreakdown {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "Material:            {:>8.3}", self.material)?;
+    writeln!(f, "Piece-square tables: {:>8.3}", self.piece_square_tables)?;
+    writeln!(f, "Development:         {:>8.3}", self.development)?;
+    writeln!(f, "Pawn structure:      {:>8.3}", self.pawn_structure)?;
+    writeln!(f, "Passed pawns:        {:>8.3}", self.passed_pawns)?;
+    writeln!(f, "Rooks:               {:>8.3}", self.rooks)?;
+    writeln!(f, "Hanging pieces:      {:>8.3}", self.hanging_pieces)?;
+    writeln!(f, "Piece attacks:       {:>8.3}", self.piece_attacks)?;
+    writeln!(f, "Pins:                {:>8.3}", self.pins)?;
+    writeln!(f, "Minor pieces:        {:>8.3}", self.minor_pieces)?;
+    writeln!(f, "King safety:         {:>8.3}", self.king_safety)?;
+    writeln!(f, "Endgame adjustment:  {:>8.3}", self.endgame_adjustment)?;
+    write!(f, "Total:               {:>8.3}", self.total())
+  }
+}
 
 /// Default way to look at a position regardless of the game phase
 ///
@@ -35,10 +104,30 @@ const PIN_PENALTY: f32 = 0.25;
 /// Score assigned to the position, applicable in all game phases
 ///
 pub fn default_position_evaluation(game_state: &GameState) -> f32 {
-  let mut score: f32 = 0.0;
+  let mut breakdown = EvalBreakdown::default();
+  let accumulator = EvalAccumulator::from_game_state(game_state);
+  add_default_position_terms(game_state,
+                             &DEFAULT_EVAL_PARAMS,
+                             &accumulator,
+                             &mut breakdown);
+  breakdown.total()
+}
 
+/// Fills the evaluation terms that are applicable in all game phases
+///
+/// ### Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+/// * `accumulator` - Material and piece-square table scores of the position
+/// * `breakdown` -  Evaluation breakdown in which the terms are written
+///
+pub fn add_default_position_terms(game_state: &GameState,
+                                  params: &EvalParams,
+                                  accumulator: &EvalAccumulator,
+                                  breakdown: &mut EvalBreakdown) {
   // Pawn structure comparisons
-  score += PAWN_ISLAND_FACTOR
+  breakdown.pawn_structure = params.pawn_island_factor
     * (get_number_of_pawn_islands(game_state, Color::Black) as f32
       - get_number_of_pawn_islands(game_state, Color::White) as f32);
 
@@ -62,17 +151,24 @@ pub fn default_position_evaluation(game_state: &GameState) -> f32 {
   */
 
   // Evaluate the quality of our rooks:
+  let mut rooks: f32 = 0.0;
   if are_rooks_connected(game_state, Color::White) {
-    score += CONNECTED_ROOKS_FACTOR;
+    rooks += params.connected_rooks_factor;
   }
   if are_rooks_connected(game_state, Color::Black) {
-    score -= CONNECTED_ROOKS_FACTOR;
+    rooks -= params.connected_rooks_factor;
   }
 
-  score += ROOK_FILE_FACTOR
+  rooks += params.rook_file_factor
     * (get_rooks_file_score(game_state, Color::Black)
       - get_rooks_file_score(game_state, Color::White));
 
+  rooks += params.rook_seventh_rank_factor
+    * (get_rooks_on_seventh_rank(game_state, Color::White) as f32
+      - get_rooks_on_seventh_rank(game_state, Color::Black) as f32);
+  breakdown.rooks = rooks;
+
+  let mut hanging: f32 = 0.0;
   let mut white_pieces =
     game_state.board.pieces.white.minors() & game_state.board.pieces.white.majors();
   while white_pieces != 0 {
@@ -81,13 +177,13 @@ pub fn default_position_evaluation(game_state: &GameState) -> f32 {
     let attackers = game_state.board.get_attackers(i, Color::Black);
 
     if defenders == 0 {
-      score -= HANGING_PENALTY;
+      hanging -= params.hanging_penalty;
     }
     if attackers.count_ones() > defenders.count_ones()
       && game_state.board.side_to_play == Color::Black
     {
       // Lowest value of any piece is 3.0.
-      score -= HANGING_FACTOR * 3.0;
+      hanging -= params.hanging_factor * 3.0;
     }
     white_pieces &= white_pieces - 1;
   }
@@ -109,32 +205,36 @@ pub fn default_position_evaluation(game_state: &GameState) -> f32 {
     let attackers = game_state.board.get_attackers(i, Color::White);
 
     if defenders == 0 {
-      score -= HANGING_PENALTY;
+      hanging -= params.hanging_penalty;
     }
     if attackers.count_ones() > defenders.count_ones()
       && game_state.board.side_to_play == Color::White
     {
       // Lowest value of any piece is 3.0.
-      score += HANGING_FACTOR * 3.0;
+      hanging += params.hanging_factor * 3.0;
     }
     black_pieces &= black_pieces - 1;
   }
 
+  breakdown.hanging_pieces = hanging;
+
   // Look for pawns attacking pieces, or forking
-  score += get_pawn_victims(game_state, Color::White) as f32;
-  score -= get_pawn_victims(game_state, Color::Black) as f32;
+  let mut attacks: f32 = 0.0;
+  attacks += params.pawn_attack_factor * get_pawn_victims(game_state, Color::White) as f32;
+  attacks -= params.pawn_attack_factor * get_pawn_victims(game_state, Color::Black) as f32;
 
   // Look for knight spans
-  score += 0.5 * get_knight_victims(game_state, Color::White) as f32;
-  score -= 0.5 * get_knight_victims(game_state, Color::Black) as f32;
+  attacks += params.knight_attack_factor * get_knight_victims(game_state, Color::White) as f32;
+  attacks -= params.knight_attack_factor * get_knight_victims(game_state, Color::Black) as f32;
 
   // Look for bishop tricks
-  score += 0.5 * get_bishop_victims(game_state, Color::White) as f32;
-  score -= 0.5 * get_bishop_victims(game_state, Color::Black) as f32;
+  attacks += params.bishop_attack_factor * get_bishop_victims(game_state, Color::White) as f32;
+  attacks -= params.bishop_attack_factor * get_bishop_victims(game_state, Color::Black) as f32;
 
   // Look for rook attacks
-  score += 0.3 * get_rook_victims(game_state, Color::White) as f32;
-  score -= 0.3 * get_rook_victims(game_state, Color::Black) as f32;
+  attacks += params.rook_attack_factor * get_rook_victims(game_state, Color::White) as f32;
+  attacks -= params.rook_attack_factor * get_rook_victims(game_state, Color::Black) as f32;
+  breakdown.piece_attacks = attacks;
 
   /*
   // Check if we have some good positional stuff
@@ -147,24 +247,74 @@ pub fn default_position_evaluation(game_state: &GameState) -> f32 {
   */
 
   // Pinned pieces is never confortable
+  let mut pins: f32 = 0.0;
   if game_state.board.get_pins_rays(Color::White) != 0 {
-    score -= PIN_PENALTY;
+    pins -= params.pin_penalty;
   }
   if game_state.board.get_pins_rays(Color::Black) != 0 {
-    score += PIN_PENALTY;
+    pins += params.pin_penalty;
   }
+  breakdown.pins = pins;
+
+  breakdown.minor_pieces = get_minor_pieces_score(game_state, params);
 
   // Check on the material imbalance
-  score += get_combined_material_score(game_state);
+  breakdown.material = accumulator.material();
+}
+
+/// Evaluates the minor pieces beyond their material value: the bishop pair,
+/// worth less as pawns disappear, and knights doing better than bishops in
+/// closed positions (many pawns, locked pawns) and worse in open ones.
+///
+/// ### Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+///
+/// ### Returns
+///
+/// Score of the minor pieces, positive in favor of white
+///
+fn get_minor_pieces_score(game_state: &GameState, params: &EvalParams) -> f32 {
+  let pieces = &game_state.board.pieces;
+  let pawn_count = (pieces.white.pawn | pieces.black.pawn).count_ones() as f32;
+  let mut score: f32 = 0.0;
+
+  let pair_scale = BISHOP_PAIR_MIN_SCALE + (1.0 - BISHOP_PAIR_MIN_SCALE) * pawn_count / 16.0;
+  match (has_bishop_pair(game_state, Color::White), has_bishop_pair(game_state, Color::Black)) {
+    (true, false) => score += params.bishop_pair_bonus * pair_scale,
+    (false, true) => score -= params.bishop_pair_bonus * pair_scale,
+    _ => {},
+  }
+
+  let closedness = pawn_count + get_number_of_locked_pawns(game_state) as f32 - NEUTRAL_CLOSEDNESS;
+  let white_knights =
+    pieces.white.knight.count_ones() as f32 - pieces.white.bishop.count_ones() as f32;
+  let black_knights =
+    pieces.black.knight.count_ones() as f32 - pieces.black.bishop.count_ones() as f32;
+  score += params.minor_piece_closedness_factor * closedness * (white_knights - black_knights);
 
-  // Return our score
   score
 }
 
 // Determine the game phrase and update it.
 pub fn determine_game_phase(game_state: &GameState) -> GamePhase {
-  // Basic material count, disregarding pawns.
-  let mut material_count = 0;
+  determine_game_phase_from_value(game_state, game_state.board.game_phase_value())
+}
+
+/// Determines the game phase, using a game phase value already computed,
+/// e.g. maintained incrementally by the `EvalAccumulator`.
+///
+/// ### Arguments
+///
+/// * `game_state` -  A GameState object representing a position, side to play, etc.
+/// * `phase_value` - Material count of the position, see `Board::game_phase_value`
+///
+/// ### Returns
+///
+/// Game phase of the position
+///
+pub fn determine_game_phase_from_value(game_state: &GameState, phase_value: u32) -> GamePhase {
   let mut development_index = 0;
 
   // If one side lost their queen, consider this endgame:
@@ -172,10 +322,6 @@ pub fn determine_game_phase(game_state: &GameState) -> GamePhase {
     return GamePhase::Endgame;
   }
 
-  material_count += game_state.board.pieces.queens().count_few_ones() * 9;
-  material_count += game_state.board.pieces.rooks().count_few_ones() * 5;
-  material_count += game_state.board.pieces.minors().count_few_ones() * 3;
-
   development_index += ((game_state.board.pieces.white.minors()
     | game_state.board.pieces.white.queen)
     & BOARD_DOWN_EDGE)
@@ -186,7 +332,7 @@ pub fn determine_game_phase(game_state: &GameState) -> GamePhase {
     & BOARD_UP_EDGE)
     .count_ones();
 
-  if material_count < 20 {
+  if phase_value < 20 {
     return GamePhase::Endgame;
   } else if development_index > 6 {
     return GamePhase::Opening;
@@ -195,41 +341,21 @@ pub fn determine_game_phase(game_state: &GameState) -> GamePhase {
   }
 }
 
-/// Looks at a board and verifies if the game is over.
-/// Does not count game specific sequences like 3-fold repetitions and 100 ply.
+/// Looks at a game state and verifies if the game is over, using the move
+/// list from the cache. See `GameState::game_status` for the checks.
 ///
 /// ### Arguments
 ///
 /// * `cache` -      EngineCache to use to save the results
-/// * `board` -      Board reference to look at.
+/// * `game_state` - A GameState object representing a position, side to play, etc.
 ///
 /// ### Returns
 ///
 /// * GameStatus indicating if the game is ongoing or not.
 ///
-pub fn is_game_over(cache: &EngineCache, board: &Board) -> GameStatus {
-  Engine::find_move_list(cache, board);
-  if cache.get_move_list(board).unwrap().is_empty() {
-    match (board.side_to_play, board.checkers.count_few_ones()) {
-      (_, 0) => {
-        return GameStatus::Stalemate;
-      },
-      (Color::Black, _) => {
-        return GameStatus::WhiteWon;
-      },
-      (Color::White, _) => {
-        return GameStatus::BlackWon;
-      },
-    }
-  }
-
-  // 2 kings, or 1 king + knight or/bishop vs king is game over:
-  if board.is_game_over_by_insufficient_material() {
-    //debug!("game over by insufficient material detected");
-    return GameStatus::Draw;
-  }
-
-  return GameStatus::Ongoing;
+pub fn is_game_over(cache: &EngineCache, game_state: &GameState) -> GameStatus {
+  let has_legal_moves = !Engine::find_move_list(cache, &game_state.board).is_empty();
+  game_state.game_status_with_legal_moves(has_legal_moves)
 }
 
 /// Returns evaluation scores based on the game status.
@@ -292,7 +418,7 @@ pub fn can_declare_draw(game_state: &GameState) -> GameStatus {
   }
 
   // Check the 3-fold repetitions
-  if game_state.get_board_repetitions() >= 2 {
+  if game_state.is_draw_by_repetition() {
     return GameStatus::ThreeFoldRepetition;
   }
 
@@ -352,13 +478,100 @@ pub fn is_smothered_mate(board: &Board, game_status: GameStatus) -> bool {
 /// Score assigned to the position.
 ///
 pub fn evaluate_board(game_state: &GameState) -> f32 {
-  let score = match determine_game_phase(game_state) {
-    GamePhase::Opening => get_opening_position_evaluation(game_state),
-    GamePhase::Middlegame => get_middlegame_position_evaluation(game_state),
-    GamePhase::Endgame => get_endgame_position_evaluation(game_state),
-  };
+  evaluate_board_detailed(game_state).total()
+}
 
-  score
+/// Evaluates a position with custom weights for the evaluation terms, assuming
+/// that the game is Ongoing
+///
+/// ### Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+///
+/// ### Returns
+///
+/// Score assigned to the position.
+///
+pub fn evaluate_board_with_params(game_state: &GameState, params: &EvalParams) -> f32 {
+  evaluate_board_detailed_with_params(game_state, params).total()
+}
+
+/// Evaluates a position and returns each term contributing to the evaluation
+///
+/// ### Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+///
+/// ### Returns
+///
+/// Breakdown of the evaluation. The sum of all terms is the score returned by
+/// `evaluate_board`
+///
+pub fn evaluate_board_detailed(game_state: &GameState) -> EvalBreakdown {
+  evaluate_board_detailed_with_params(game_state, &DEFAULT_EVAL_PARAMS)
+}
+
+/// Evaluates a position with custom weights and returns each term
+/// contributing to the evaluation
+///
+/// ### Arguments
+///
+/// * `game_state` - A GameState object representing a position, side to play, etc.
+/// * `params` -     Weights of the evaluation terms
+///
+/// ### Returns
+///
+/// Breakdown of the evaluation.
+///
+pub fn evaluate_board_detailed_with_params(game_state: &GameState,
+                                           params: &EvalParams)
+                                           -> EvalBreakdown {
+  let accumulator = EvalAccumulator::from_game_state(game_state);
+  evaluate_board_detailed_with_accumulator(game_state, params, &accumulator)
+}
+
+/// Evaluates a position using the material and piece-square table scores
+/// maintained during the search, instead of recomputing them.
+///
+/// ### Arguments
+///
+/// * `game_state` -  A GameState object representing a position, side to play, etc.
+/// * `accumulator` - Material and piece-square table scores of the position
+///
+/// ### Returns
+///
+/// Score assigned to the position, same as `evaluate_board`
+///
+pub fn evaluate_board_with_accumulator(game_state: &GameState,
+                                       accumulator: &EvalAccumulator)
+                                       -> f32 {
+  evaluate_board_detailed_with_accumulator(game_state, &DEFAULT_EVAL_PARAMS, accumulator).total()
+}
+
+/// Evaluates a position with custom weights and returns each term
+/// contributing to the evaluation, using the material and piece-square table
+/// scores of the accumulator.
+///
+/// ### Arguments
+///
+/// * `game_state` -  A GameState object representing a position, side to play, etc.
+/// * `params` -      Weights of the evaluation terms
+/// * `accumulator` - Material and piece-square table scores of the position
+///
+/// ### Returns
+///
+/// Breakdown of the evaluation.
+///
+fn evaluate_board_detailed_with_accumulator(game_state: &GameState,
+                                            params: &EvalParams,
+                                            accumulator: &EvalAccumulator)
+                                            -> EvalBreakdown {
+  match determine_game_phase_from_value(game_state, accumulator.game_phase_value()) {
+    GamePhase::Opening => get_opening_position_breakdown(game_state, params, accumulator),
+    GamePhase::Middlegame => get_middlegame_position_breakdown(game_state, params, accumulator),
+    GamePhase::Endgame => get_endgame_position_breakdown(game_state, params, accumulator),
+  }
 }
 
 /// Very minimalistic version of evaluate board
@@ -378,9 +591,9 @@ pub fn evaluate_board_simple(game_state: &GameState) -> f32 {
   use crate::engine::eval::opening::get_square_table_opening_score;
 
   let score = match determine_game_phase(game_state) {
-    GamePhase::Opening => get_square_table_opening_score(game_state),
-    GamePhase::Middlegame => get_square_table_middlegame_score(game_state),
-    GamePhase::Endgame => get_square_table_endgame_score(game_state),
+    GamePhase::Opening => get_square_table_opening_score(game_state, &DEFAULT_EVAL_PARAMS),
+    GamePhase::Middlegame => get_square_table_middlegame_score(game_state, &DEFAULT_EVAL_PARAMS),
+    GamePhase::Endgame => get_square_table_endgame_score(game_state, &DEFAULT_EVAL_PARAMS),
   };
 
   score + get_combined_material_score(game_state)
@@ -420,7 +633,7 @@ mod tests {
     let fen = "1n4nr/5ppp/8/1P1Np3/1P6/4kP2/1B1NP1PP/R3KB1R b KQ - 2 37";
     let game_state = GameState::from_fen(fen);
     game_state.get_moves();
-    let game_status = is_game_over(&cache, &game_state.board);
+    let game_status = is_game_over(&cache, &game_state);
     assert_eq!(game_status, GameStatus::WhiteWon);
   }
 
@@ -470,43 +683,43 @@ mod tests {
     let mut game_state = GameState::from_fen(fen);
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("c4c3");
+    game_state.apply_move_from_notation("c4c3").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("a4a2");
+    game_state.apply_move_from_notation("a4a2").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("c3d4");
+    game_state.apply_move_from_notation("c3d4").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("a2c2");
+    game_state.apply_move_from_notation("a2c2").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("d4d5");
+    game_state.apply_move_from_notation("d4d5").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("c2g2");
+    game_state.apply_move_from_notation("c2g2").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("d5d6");
+    game_state.apply_move_from_notation("d5d6").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("g2c2");
+    game_state.apply_move_from_notation("g2c2").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("d6d5");
+    game_state.apply_move_from_notation("d6d5").unwrap();
     assert_eq!(1, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("c2c1");
+    game_state.apply_move_from_notation("c2c1").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("d5d4");
+    game_state.apply_move_from_notation("d5d4").unwrap();
     assert_eq!(0, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("c1c2");
+    game_state.apply_move_from_notation("c1c2").unwrap();
     assert_eq!(1, game_state.get_board_repetitions());
 
-    game_state.apply_move_from_notation("d4d5");
+    game_state.apply_move_from_notation("d4d5").unwrap();
     println!("{:?}", game_state);
     assert_eq!(2, game_state.get_board_repetitions());
   }
@@ -516,27 +729,27 @@ mod tests {
     // This three-fold repetition was not understood during the game: https://lichess.org/oBjYp62P/white
     let fen = "r2q1b1r/1pp1pkpp/2n1p3/p2p4/3PnB2/2NQ1NP1/PPP1PP1P/R3K2R w KQ - 2 9";
     let mut game_state = GameState::from_fen(fen);
-    game_state.apply_move_from_notation("c3e4");
-    game_state.apply_move_from_notation("d5e4");
-    game_state.apply_move_from_notation("f3g5");
-    game_state.apply_move_from_notation("f7f6");
-    game_state.apply_move_from_notation("g5e4");
-    game_state.apply_move_from_notation("f6f7");
-    game_state.apply_move_from_notation("e4g5");
-    game_state.apply_move_from_notation("f7f6");
-    game_state.apply_move_from_notation("g5h7");
-    game_state.apply_move_from_notation("f6f7");
-    game_state.apply_move_from_notation("h7g5");
-    game_state.apply_move_from_notation("f7f6");
-    game_state.apply_move_from_notation("g5e4");
-    game_state.apply_move_from_notation("f6f7");
-    game_state.apply_move_from_notation("e4g5");
-    game_state.apply_move_from_notation("f7f6");
-    game_state.apply_move_from_notation("g5h7");
+    game_state.apply_move_from_notation("c3e4").unwrap();
+    game_state.apply_move_from_notation("d5e4").unwrap();
+    game_state.apply_move_from_notation("f3g5").unwrap();
+    game_state.apply_move_from_notation("f7f6").unwrap();
+    game_state.apply_move_from_notation("g5e4").unwrap();
+    game_state.apply_move_from_notation("f6f7").unwrap();
+    game_state.apply_move_from_notation("e4g5").unwrap();
+    game_state.apply_move_from_notation("f7f6").unwrap();
+    game_state.apply_move_from_notation("g5h7").unwrap();
+    game_state.apply_move_from_notation("f6f7").unwrap();
+    game_state.apply_move_from_notation("h7g5").unwrap();
+    game_state.apply_move_from_notation("f7f6").unwrap();
+    game_state.apply_move_from_notation("g5e4").unwrap();
+    game_state.apply_move_from_notation("f6f7").unwrap();
+    game_state.apply_move_from_notation("e4g5").unwrap();
+    game_state.apply_move_from_notation("f7f6").unwrap();
+    game_state.apply_move_from_notation("g5h7").unwrap();
     assert_eq!(1, game_state.get_board_repetitions());
-    game_state.apply_move_from_notation("f6f7");
+    game_state.apply_move_from_notation("f6f7").unwrap();
     assert_eq!(1, game_state.get_board_repetitions());
-    game_state.apply_move_from_notation("h7g5");
+    game_state.apply_move_from_notation("h7g5").unwrap();
     assert_eq!(2, game_state.get_board_repetitions());
   }
 
@@ -561,7 +774,7 @@ mod tests {
     let game_state = GameState::from_fen(fen);
 
     let cache = EngineCache::new();
-    assert_eq!(GameStatus::Ongoing, is_game_over(&cache, &game_state.board));
+    assert_eq!(GameStatus::Ongoing, is_game_over(&cache, &game_state));
 
     assert!(evaluate_board(&game_state) < 0.0);
   }
@@ -573,7 +786,7 @@ mod tests {
     let cache = EngineCache::new();
     assert_eq!(
       GameStatus::BlackWon,
-      is_game_over(&cache, &game_state.board)
+      is_game_over(&cache, &game_state)
     );
   }
 
@@ -590,14 +803,14 @@ mod tests {
     let fen = "r1bqkb1r/pp1npppp/2pN1n2/8/3P4/8/PPP1QPPP/R1B1KBNR b KQkq - 4 6";
     let game_state = GameState::from_fen(fen);
     let cache = EngineCache::new();
-    let game_status = is_game_over(&cache, &game_state.board);
+    let game_status = is_game_over(&cache, &game_state);
     assert!(is_smothered_mate(&game_state.board, game_status));
 
     // Position 2:
     let fen = "r1b1k2r/ppppqppp/2n5/8/1PP2B2/3n1N2/1P1NPPPP/R2QKB1R w KQkq - 1 9";
     let game_state = GameState::from_fen(fen);
     let cache = EngineCache::new();
-    let game_status = is_game_over(&cache, &game_state.board);
+    let game_status = is_game_over(&cache, &game_state);
     assert!(is_smothered_mate(&game_state.board, game_status));
 
     // Test if the game status is not WhiteWon / BlackWon
@@ -627,7 +840,7 @@ mod tests {
     let fen = "rnbq3r/pppppppp/8/8/QPPk4/2N5/P2PPnPP/2B2BRK w - - 0 1";
     let game_state = GameState::from_fen(fen);
     let cache = EngineCache::new();
-    let game_status = is_game_over(&cache, &game_state.board);
+    let game_status = is_game_over(&cache, &game_state);
     assert!(is_smothered_mate(&game_state.board, game_status));
   }
 
@@ -642,4 +855,131 @@ mod tests {
     assert!(eval < 0.6);
     assert!(eval > -0.6);
   }
+
+  #[test]
+  fn test_evaluate_board_detailed_sums_to_evaluate_board() {
+    let fens = [START_POSITION_FEN,
+                "r1bqkbnr/pppppppp/2n5/8/2B1P3/1P3N2/PBPP1PPP/R2QK2R w KQkq - 3 8",
+                "r1bqk2r/ppp2ppp/2n2n2/3pN3/1bPPp3/2N1P2P/PP3PP1/R1BQKB1R w KQkq - 0 7",
+                "5R2/k1p3p1/1p4b1/8/8/4bP2/PPP3PK/8 w - - 0 34",
+                "8/8/8/8/8/4k3/8/3QK3 w - - 0 1",
+                "8/5k2/8/8/8/2KB4/8/8 w - - 0 1"];
+
+    for fen in fens {
+      let game_state = GameState::from_fen(fen);
+      let breakdown = evaluate_board_detailed(&game_state);
+      let sum = breakdown.material
+                + breakdown.piece_square_tables
+                + breakdown.development
+                + breakdown.pawn_structure
+                + breakdown.passed_pawns
+                + breakdown.rooks
+                + breakdown.hanging_pieces
+                + breakdown.piece_attacks
+                + breakdown.pins
+                + breakdown.minor_pieces
+                + breakdown.king_safety
+                + breakdown.endgame_adjustment;
+      let eval = evaluate_board(&game_state);
+      println!("Position {fen}:\n{breakdown}");
+      assert_eq!(sum, eval);
+    }
+  }
+
+  #[test]
+  fn test_evaluation_is_color_symmetric() {
+    for _ in 0..1000 {
+      let board = Board::new_random();
+      let game_state = GameState::from_board(&board);
+      let mirrored_state = GameState::from_board(&board.mirror());
+
+      let eval = evaluate_board(&game_state);
+      let mirrored_eval = evaluate_board(&mirrored_state);
+      assert!((eval + mirrored_eval).abs() < 0.001,
+              "Asymmetric evaluation: {} for {} and {} for {}",
+              eval,
+              game_state.to_fen(),
+              mirrored_eval,
+              mirrored_state.to_fen());
+    }
+  }
+
+  #[test]
+  fn test_opening_evaluation_is_color_symmetric() {
+    // Random boards are almost never in the opening phase, so check a few
+    // opening positions with developed bishops explicitly.
+    let fens = [
+      "rnbqkb1r/pppp1ppp/5n2/4p3/2B1P3/8/PPPP1PPP/RNBQK1NR w KQkq - 2 3",
+      "rn1qkbnr/ppp1pppp/8/3p1b2/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 1 2",
+      "r1bqk1nr/pppp1ppp/2n5/2b1p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 4 4",
+      "rnbqk1nr/pppp1ppp/8/4p3/1b1PP3/8/PPP2PPP/RNBQKBNR w KQkq - 1 3",
+    ];
+    for fen in fens {
+      let game_state = GameState::from_fen(fen);
+      let mirrored_state = GameState::from_board(&game_state.board.mirror());
+      assert_eq!(determine_game_phase(&game_state), GamePhase::Opening, "{fen}");
+      assert_eq!(determine_game_phase(&mirrored_state), GamePhase::Opening, "{fen}");
+
+      let eval = evaluate_board(&game_state);
+      let mirrored_eval = evaluate_board(&mirrored_state);
+      assert!((eval + mirrored_eval).abs() < 0.001,
+              "Asymmetric evaluation: {} for {} and {} for {}",
+              eval,
+              fen,
+              mirrored_eval,
+              mirrored_state.to_fen());
+    }
+  }
+
+  #[test]
+  fn test_bishop_pair_is_worth_more_than_bishop_and_knight() {
+    let params = DEFAULT_EVAL_PARAMS;
+
+    // Same material, with the bishop pair or with a bishop and a knight
+    let bishop_pair = "r3k2r/pp3ppp/2n5/8/8/8/PP3PPP/R1B1KB1R w KQkq - 0 1";
+    let bishop_and_knight = "r3k2r/pp3ppp/2n5/8/8/8/PP3PPP/R1B1KN1R w KQkq - 0 1";
+    let pair_score = get_minor_pieces_score(&GameState::from_fen(bishop_pair), &params);
+    let knight_score = get_minor_pieces_score(&GameState::from_fen(bishop_and_knight), &params);
+    assert!(pair_score > 0.0, "Bishop pair scored {pair_score}");
+    assert!(pair_score > knight_score);
+
+    // No bonus when both sides have the pair
+    let game_state = GameState::default();
+    assert_eq!(0.0, get_minor_pieces_score(&game_state, &params));
+  }
+
+  #[test]
+  fn test_knights_prefer_closed_positions() {
+    let params = DEFAULT_EVAL_PARAMS;
+
+    // Knight against bishop with locked pawn chains
+    let closed = "4k3/1p1n1p1p/pPp1pPpP/P1PpP1P1/3P4/8/4B3/4K3 w - - 0 1";
+    let score = get_minor_pieces_score(&GameState::from_fen(closed), &params);
+    assert!(score < 0.0,
+            "Bishop against knight in a closed position scored {score}");
+
+    // Knight against bishop with few pawns
+    let open = "4k3/5p2/3n4/8/8/8/P3B3/4K3 w - - 0 1";
+    let score = get_minor_pieces_score(&GameState::from_fen(open), &params);
+    assert!(score > 0.0,
+            "Bishop against knight in an open position scored {score}");
+  }
+
+  #[test]
+  fn test_rook_on_seventh_rank_bonus() {
+    // Same rook on the 7th or on the 5th rank
+    let seventh = GameState::from_fen("6k1/R4ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+    let fifth = GameState::from_fen("6k1/5ppp/8/R7/8/8/5PPP/6K1 w - - 0 1");
+    let seventh_breakdown = evaluate_board_detailed(&seventh);
+    let fifth_breakdown = evaluate_board_detailed(&fifth);
+    println!("Rook on the 7th:\n{seventh_breakdown}\nRook on the 5th:\n{fifth_breakdown}");
+    assert!(seventh_breakdown.rooks > fifth_breakdown.rooks);
+    assert!(evaluate_board(&seventh) > evaluate_board(&fifth));
+
+    // Same for Black on the 2nd rank
+    let second = GameState::from_fen("6k1/5ppp/8/8/8/8/r4PPP/6K1 b - - 0 1");
+    let fourth = GameState::from_fen("6k1/5ppp/8/8/r7/8/5PPP/6K1 b - - 0 1");
+    assert!(evaluate_board_detailed(&second).rooks < evaluate_board_detailed(&fourth).rooks);
+    assert!(evaluate_board(&second) < evaluate_board(&fourth));
+  }
 }
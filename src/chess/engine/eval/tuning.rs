@@ -0,0 +1,207 @@
+use super::params::*;
+use super::position::evaluate_board_with_params;
+use crate::model::game_state::GameState;
+use log::*;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Scaling of the evaluation in the sigmoid converting it to an expected score
+pub const TUNING_SIGMOID_SCALE: f32 = 1.0;
+/// Variation of each parameter used to compute the finite differences,
+/// relative to its value
+const TUNING_RELATIVE_EPSILON: f32 = 0.01;
+/// Smallest variation of a parameter used to compute the finite differences
+const TUNING_MIN_EPSILON: f32 = 0.0001;
+/// Smallest scale of a parameter. Parameters are updated proportionally to
+/// their scale, so that small weights do not move as much as large ones.
+const TUNING_MIN_SCALE: f32 = 0.01;
+/// Learning rate used for the first gradient descent step, i.e. the largest
+/// change of a parameter relative to its scale
+const TUNING_INITIAL_LEARNING_RATE: f32 = 1.0;
+/// We stop when the learning rate gets below this value
+const TUNING_MIN_LEARNING_RATE: f32 = 0.0001;
+/// Maximum number of gradient descent steps
+const TUNING_MAX_ITERATIONS: usize = 100;
+/// Bounds of the expected score in the loss, to keep the logarithms finite
+const TUNING_SCORE_EPSILON: f32 = 1e-6;
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Converts an evaluation into an expected score for White, between 0.0 (Black
+/// wins) and 1.0 (White wins).
+///
+/// ### Arguments
+///
+/// * `eval`: Evaluation of the position, from White's point of view
+fn eval_to_expected_score(eval: f32) -> f32 {
+  1.0 / (1.0 + 10.0_f32.powf(-TUNING_SIGMOID_SCALE * eval / 4.0))
+}
+
+/// Computes the average logistic loss (cross-entropy) between the game results
+/// and the expected scores given by the evaluation function.
+///
+/// ### Arguments
+///
+/// * `positions`: Positions with the result of the game they were taken from:
+///   1.0 if White won, 0.5 for a draw, 0.0 if Black won
+/// * `params`:    Weights of the evaluation terms
+///
+/// ### Return value
+///
+/// Loss of the evaluation function over the positions
+pub fn evaluation_loss(positions: &[(GameState, f32)], params: &EvalParams) -> f32 {
+  if positions.is_empty() {
+    return 0.0;
+  }
+
+  let mut loss = 0.0;
+  for (game_state, result) in positions {
+    let expected_score = eval_to_expected_score(evaluate_board_with_params(game_state, params))
+      .clamp(TUNING_SCORE_EPSILON, 1.0 - TUNING_SCORE_EPSILON);
+    loss -= result * expected_score.ln() + (1.0 - result) * (1.0 - expected_score).ln();
+  }
+  loss / positions.len() as f32
+}
+
+/// Estimates the gradient of the loss with central finite differences: each
+/// parameter is moved up and down by a small amount and we look at how the
+/// loss changes.
+///
+/// ### Arguments
+///
+/// * `positions`: Positions with the result of the game they were taken from
+/// * `params`:    Weights of the evaluation terms
+///
+/// ### Return value
+///
+/// Partial derivative of the loss for each parameter, in the order of
+/// `EvalParams::values_mut`
+fn loss_gradient(positions: &[(GameState, f32)], params: &EvalParams) -> [f32; EVAL_PARAMS_COUNT] {
+  let mut gradient = [0.0; EVAL_PARAMS_COUNT];
+  let mut params = *params;
+  for (i, derivative) in gradient.iter_mut().enumerate() {
+    let value = *params.values_mut()[i];
+    let epsilon = (value.abs() * TUNING_RELATIVE_EPSILON).max(TUNING_MIN_EPSILON);
+
+    *params.values_mut()[i] = value + epsilon;
+    let loss_up = evaluation_loss(positions, &params);
+    *params.values_mut()[i] = value - epsilon;
+    let loss_down = evaluation_loss(positions, &params);
+    *params.values_mut()[i] = value;
+
+    *derivative = (loss_up - loss_down) / (2.0 * epsilon);
+  }
+  gradient
+}
+
+/// Adjusts the weights of the evaluation to match game results (Texel tuning).
+/// We minimize the logistic loss with a gradient descent, the gradient being
+/// estimated with finite differences. Parameters are scaled by their initial
+/// magnitude and the gradient is normalized, so the learning rate is the
+/// relative size of the step. A step is only kept if it reduces the loss,
+/// otherwise the learning rate is halved. We stop when the learning rate gets
+/// too small.
+///
+/// ### Arguments
+///
+/// * `positions`: Positions with the result of the game they were taken from:
+///   1.0 if White won, 0.5 for a draw, 0.0 if Black won
+/// * `params`:    Weights of the evaluation terms, updated with the tuned
+///   values
+///
+/// ### Return value
+///
+/// Loss of the evaluation function with the tuned parameters
+pub fn tune(positions: &[(GameState, f32)], params: &mut EvalParams) -> f32 {
+  let mut best_loss = evaluation_loss(positions, params);
+  info!("Tuning on {} positions, initial loss: {}",
+        positions.len(),
+        best_loss);
+
+  // Each parameter moves proportionally to its initial magnitude
+  let mut scales = [0.0; EVAL_PARAMS_COUNT];
+  for (scale, value) in scales.iter_mut().zip(params.values_mut()) {
+    *scale = value.abs().max(TUNING_MIN_SCALE);
+  }
+
+  let mut learning_rate = TUNING_INITIAL_LEARNING_RATE;
+  for iteration in 0..TUNING_MAX_ITERATIONS {
+    // Gradient with respect to the scaled parameters
+    let mut gradient = loss_gradient(positions, params);
+    for (derivative, scale) in gradient.iter_mut().zip(scales) {
+      *derivative *= scale;
+    }
+    let norm = gradient.iter().map(|d| d * d).sum::<f32>().sqrt();
+    if norm == 0.0 {
+      break;
+    }
+
+    // Backtracking: shrink the step until it reduces the loss
+    while learning_rate >= TUNING_MIN_LEARNING_RATE {
+      let mut candidate = *params;
+      for (i, value) in candidate.values_mut().into_iter().enumerate() {
+        *value -= learning_rate * scales[i] * gradient[i] / norm;
+      }
+      let loss = evaluation_loss(positions, &candidate);
+      if loss < best_loss {
+        best_loss = loss;
+        *params = candidate;
+        break;
+      }
+      learning_rate /= 2.0;
+    }
+
+    debug!("Tuning iteration {}, loss: {}, learning rate: {}",
+           iteration, best_loss, learning_rate);
+    if learning_rate < TUNING_MIN_LEARNING_RATE {
+      break;
+    }
+  }
+
+  info!("Tuning done, final loss: {}", best_loss);
+  best_loss
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_tuning_reduces_the_loss() {
+    // Positions in the opening where one side is better developed, labelled
+    // with the results we would expect with a higher development factor.
+    let fens = ["rnbqkb1r/pppppppp/5n2/8/2B1P3/2N2N2/PPPP1PPP/R1BQK2R b KQkq - 6 4",
+                "r1bqkb1r/pppppppp/2n2n2/8/8/2N2N2/PPPPPPPP/R1BQKB1R w KQkq - 4 3",
+                "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1",
+                "r1bqkbnr/pppppppp/2n5/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"];
+    let mut target_params = DEFAULT_EVAL_PARAMS;
+    target_params.development_factor = 0.3;
+
+    let positions: Vec<(GameState, f32)> =
+      fens.iter()
+          .map(|fen| GameState::from_fen(fen))
+          .map(|game_state| {
+            let eval = evaluate_board_with_params(&game_state, &target_params);
+            (game_state, eval_to_expected_score(eval))
+          })
+          .collect();
+
+    let mut params = DEFAULT_EVAL_PARAMS;
+    let initial_loss = evaluation_loss(&positions, &params);
+    let final_loss = tune(&positions, &mut params);
+
+    println!("Initial loss: {initial_loss} - Final loss: {final_loss} - Params: {:?}",
+             params);
+    assert!(final_loss < initial_loss);
+    assert_eq!(final_loss, evaluation_loss(&positions, &params));
+    // The development factor got closer to its optimum
+    assert!((params.development_factor - 0.3).abs()
+            < (DEFAULT_EVAL_PARAMS.development_factor - 0.3).abs());
+  }
+}
@@ -61,6 +61,18 @@ impl GameHistory {
     self.entries.pop()
   }
 
+  /// Gets the last entry in the game history
+  ///
+  pub fn last(&self) -> Option<&GameHistoryEntry> {
+    self.entries.last()
+  }
+
+  /// Iterates over the entries of the game history, from the first position
+  ///
+  pub fn iter(&self) -> std::slice::Iter<'_, GameHistoryEntry> {
+    self.entries.iter()
+  }
+
   /// Gets the length of the current game history
   ///
   pub fn len(&self) -> usize {
@@ -137,7 +149,7 @@ mod tests {
     history.add(game_state.to_fen(), Move::null(), 40, Variation::new());
 
     let mv = "f2f3";
-    game_state.apply_move_from_notation(mv);
+    game_state.apply_move_from_notation(mv).unwrap();
     history.add(
       game_state.to_fen(),
       Move::from_string(mv),
@@ -148,7 +160,7 @@ mod tests {
     assert_eq!(history.len(), 2);
 
     let mv = "e7e5";
-    game_state.apply_move_from_notation(mv);
+    game_state.apply_move_from_notation(mv).unwrap();
     history.add(
       game_state.to_fen(),
       Move::from_string(mv),
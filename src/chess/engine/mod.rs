@@ -6,6 +6,7 @@ pub mod game_history;
 pub mod nnue;
 pub mod search;
 pub mod search_result;
+pub mod selfplay;
 pub mod tables;
 
 mod comments;
@@ -15,30 +16,83 @@ pub mod tests;
 // Same module (engine)
 use self::cache::engine_cache::EngineCache;
 use self::cache::evaluation_table::EvaluationCache;
+use self::eval::accumulator::EvalAccumulator;
 use self::eval::position::*;
 use self::game_history::GameHistory;
-use self::search_result::SearchResult;
+use self::search::mate::MateSearchResult;
+use self::search_result::{
+  format_eval, format_uci_score, mate_distance, SearchResult, Variation, MATE_EVAL,
+  MATE_EVAL_THRESHOLD, VARIATION_LENGTH,
+};
 // Chess model
 use super::model::game_state::GameState;
 use super::model::game_state::{GameStatus, START_POSITION_FEN};
 use super::model::moves::Move;
-use super::model::piece::Color;
+use super::model::piece::{Color, PieceType};
 use crate::engine::search_result::VariationWithEval;
-use crate::model::board::Board;
+use crate::model::board::{Board, FenError};
+use crate::model::board_mask::BoardMaskDisplay;
+use crate::model::containers::move_list::MoveList;
 use books::*;
 use config::options::*;
 use config::play_style::*;
+use config::strength::*;
 use log::*;
 use nnue::NNUE;
-use rand::seq::SliceRandom;
-use std::cmp::min;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::{max, min};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
 // -----------------------------------------------------------------------------
 // Constants
 pub const NNUE_FILE: &str = "engine/nnue/net.nnue";
 pub const NUMBER_OF_MOVES_IN_SEARCH_RESULTS: usize = 30;
+/// Maximum number of moves printed when walking the transposition table
+const TRANSPOSITION_TABLE_LINE_MAX_LENGTH: usize = 30;
+/// Number of root moves whose evaluation is printed in debug mode
+const DEBUG_ROOT_MOVES: usize = 3;
+/// Margin added to the static eval at frontier nodes, below which quiet moves
+/// are considered unable to improve the score and are skipped.
+const FUTILITY_MARGIN: f32 = 2.0;
+/// Margin per remaining ply by which the static eval has to beat the opponent's
+/// best score before we stop searching a node (reverse futility pruning)
+const REVERSE_FUTILITY_MARGIN: f32 = 1.5;
+/// Maximum number of remaining plies at which reverse futility pruning applies
+const REVERSE_FUTILITY_MAX_DEPTH: usize = 1;
+/// We do not prune positions with this number of pieces or less on the board,
+/// as the static eval is not reliable enough in endgames.
+const FUTILITY_MIN_PIECES: u32 = 10;
+/// Alpha has to exceed beta by this margin before we prune the remaining
+/// moves, as we have low trust in the accuracy of the eval
+const ALPHA_BETA_PRUNING_MARGIN: f32 = 0.5;
+/// Width of the null window used to check if a move beats the best one found
+/// so far (principal variation search)
+const PVS_WINDOW: f32 = 0.01;
+/// Mating sequences shorter than this number of plies end the search
+const MATING_SEQUENCE_MAX_PLIES: i8 = 51;
+/// Bonus given to smothered mates. Must stay below half a ply, so that the
+/// mate distance is still decoded correctly from the evaluation.
+const SMOTHERED_MATE_BONUS: f32 = 0.25;
+/// Perft depth and expected node count of the start position in the self-test
+const SELF_TEST_PERFT_DEPTH: usize = 3;
+const SELF_TEST_PERFT_NODES: usize = 8902;
+/// Mate in 1 that the engine has to find in the self-test, and its solution
+const SELF_TEST_MATE_FEN: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+const SELF_TEST_MATE_MOVE: &str = "a1a8";
+/// Below this time budget, in milliseconds, we do not check our move for
+/// blunders
+const BLUNDER_CHECK_MIN_TIME_MS: usize = 500;
+/// Depth of the search of the position after our move, looking for a reply
+/// that the search missed
+const BLUNDER_CHECK_DEPTH: usize = 2;
+/// Evaluation drop, in pawns, from which we consider that our move is a
+/// blunder and search the position again
+const BLUNDER_CHECK_MARGIN: f32 = 1.5;
+/// Additional depth of the search of the position when our move is a blunder
+const BLUNDER_CHECK_EXTRA_DEPTH: usize = 2;
 
 // -----------------------------------------------------------------------------
 // Type definitions
@@ -46,17 +100,137 @@ pub const NUMBER_OF_MOVES_IN_SEARCH_RESULTS: usize = 30;
 // TODO: I need to break this file down into simpler/more independent modules
 
 /// Evaluation for a position
-#[derive(Clone, Debug)]
+///
+/// Evaluations are ordered from White's point of view: a faster mate for White
+/// is better than a slower one, which is better than any score.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Eval {
   /// Mating sequence.
   /// Mate(-1) for black to mate in 1 half move
   /// Mate(4) for white to mate in 4 plys
-  /// Mate(0) for Game Over, either black or white won
+  /// Mate(0) for Game Over, either black or white won. It compares and
+  /// converts like a White win.
   Mate(i8),
   /// Score in centipawns for the position
   Score(i16),
 }
 
+impl Eval {
+  /// Drawn or balanced position
+  pub const DRAW: Eval = Eval::Score(0);
+  /// White checkmates with the next half move. Negate it for Black.
+  pub const MATE: Eval = Eval::Mate(1);
+
+  /// Converts an evaluation used by the search, in pawns from White's point
+  /// of view, where mates are scored `MATE_EVAL` minus one per ply
+  ///
+  /// ### Arguments
+  ///
+  /// * `eval`: Search evaluation. NaN converts to a draw.
+  pub fn from_search_score(eval: f32) -> Self {
+    if eval.is_nan() {
+      return Eval::DRAW;
+    }
+    if eval.abs() > MATE_EVAL_THRESHOLD {
+      let plies = (MATE_EVAL + 1.0 - eval.abs()).round().clamp(1.0, i8::MAX as f32) as i8;
+      return Eval::Mate(plies * eval.signum() as i8);
+    }
+
+    Eval::Score((eval * 100.0).round() as i16)
+  }
+
+  /// Converts to an evaluation used by the search, in pawns from White's
+  /// point of view
+  pub fn to_search_score(self) -> f32 {
+    match self {
+      Eval::Mate(0) => MATE_EVAL,
+      Eval::Mate(plies) => (MATE_EVAL + 1.0 - plies.unsigned_abs() as f32) * plies.signum() as f32,
+      Eval::Score(centipawns) => centipawns as f32 / 100.0,
+    }
+  }
+
+  /// Value on which evaluations are ordered
+  fn order_key(self) -> i32 {
+    const MATE_KEY: i32 = 1_000_000;
+    match self {
+      Eval::Mate(0) => MATE_KEY,
+      Eval::Mate(plies) if plies > 0 => MATE_KEY - plies as i32,
+      Eval::Mate(plies) => -MATE_KEY - plies as i32,
+      Eval::Score(centipawns) => centipawns as i32,
+    }
+  }
+}
+
+impl Ord for Eval {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    self.order_key().cmp(&other.order_key())
+  }
+}
+
+impl PartialOrd for Eval {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl std::ops::Neg for Eval {
+  type Output = Eval;
+
+  /// Flips the evaluation to the opponent's point of view
+  fn neg(self) -> Self::Output {
+    match self {
+      Eval::Mate(plies) => Eval::Mate(-plies),
+      Eval::Score(centipawns) => Eval::Score(-centipawns),
+    }
+  }
+}
+
+/// Statistics of the opponent moves we pondered on, telling if the expected
+/// reply of our principal variation is a good guess
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PonderStats {
+  /// Number of opponent moves played while we pondered on an expected reply
+  pub predictions: usize,
+  /// Number of times the opponent played the expected reply (ponderhits)
+  pub hits:        usize,
+}
+
+impl PonderStats {
+  /// Records the move of the opponent after we pondered on an expected reply
+  ///
+  /// ### Arguments
+  ///
+  /// * `hit`: Set to true if the opponent played the expected reply
+  fn record(&mut self, hit: bool) {
+    self.predictions += 1;
+    if hit {
+      self.hits += 1;
+    }
+  }
+
+  /// Computes the ratio of ponderhits, between 0 and 1
+  ///
+  /// ### Return value
+  ///
+  /// The ponderhit rate, None if we never pondered
+  pub fn hit_rate(&self) -> Option<f32> {
+    if self.predictions == 0 {
+      return None;
+    }
+    Some(self.hits as f32 / self.predictions as f32)
+  }
+}
+
+/// Whether a position is covered by the opening book
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BookStatus {
+  /// The book has moves for the position. Contains the number of candidate
+  /// book moves
+  InBook(usize),
+  /// The position is not in the book, the engine has to search it
+  OutOfBook,
+}
+
 #[derive(Clone, Debug)]
 struct Analysis {
   /// After the search, the nth best lines will be saved in this vector.
@@ -67,6 +241,11 @@ struct Analysis {
   pub selective_depth: Arc<Mutex<usize>>,
   /// Represents how many nodes we visited in the search
   pub nodes_visited:   Arc<Mutex<usize>>,
+  /// Debug information printed during the last search
+  pub debug_info:      Arc<Mutex<Vec<String>>>,
+  /// Move to play when the play style or the Elo limit picked another line
+  /// than the best one. The result keeps the best line on top.
+  pub selected_move:   Arc<Mutex<Option<Move>>>,
 }
 
 #[derive(Clone, Debug)]
@@ -75,14 +254,19 @@ struct EngineState {
   pub active:         Arc<Mutex<bool>>,
   /// Indicates that we want the engine to stop resolving positions
   pub stop_requested: Arc<Mutex<bool>>,
+  /// Incremented every time the engine becomes active, to tell searches apart
+  pub search_id:      Arc<Mutex<u64>>,
   /// Indicates when the engine was requested to start searching
   pub start_time:     Arc<Mutex<Instant>>,
+  /// Indicates that the engine is searching on the opponent's time
+  pub pondering:      Arc<Mutex<bool>>,
 }
 
 impl Analysis {
   /// Resets the analysis
   pub fn reset(&self) {
     self.result.lock().unwrap().clear();
+    self.set_selected_move(None);
     self.set_selective_depth(0);
     self.set_depth(0);
     self.set_nodes_visited(0);
@@ -99,6 +283,16 @@ impl Analysis {
     *pvs = result;
   }
 
+  /// Sets the move to play instead of the best move of the result
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:          Analysis struct reference
+  /// * `selected_move`: Move to play, None to play the best move
+  pub fn set_selected_move(&self, selected_move: Option<Move>) {
+    *self.selected_move.lock().unwrap() = selected_move;
+  }
+
   /// Sets the depth we have reached during the analysis
   ///
   /// ### Arguments
@@ -207,6 +401,21 @@ impl Analysis {
     let mut analysis_nodes_visited = self.nodes_visited.lock().unwrap();
     *analysis_nodes_visited = value;
   }
+
+  /// Computes the effective branching factor of the search, i.e.
+  /// nodes^(1/depth). The better the move ordering, the closer it gets to 2-3.
+  ///
+  /// ### Return value
+  ///
+  /// The effective branching factor, None if nothing was searched
+  pub fn get_branching_factor(&self) -> Option<f32> {
+    let depth = self.get_depth();
+    let nodes = self.get_nodes_visited();
+    if depth == 0 || nodes == 0 {
+      return None;
+    }
+    Some((nodes as f32).powf(1.0 / depth as f32))
+  }
 }
 
 impl Default for Analysis {
@@ -214,7 +423,9 @@ impl Default for Analysis {
     Analysis { result:          Arc::new(Mutex::new(SearchResult::new(1, Color::White))),
                depth:           Arc::new(Mutex::new(0)),
                selective_depth: Arc::new(Mutex::new(0)),
-               nodes_visited:   Arc::new(Mutex::new(0)), }
+               nodes_visited:   Arc::new(Mutex::new(0)),
+               debug_info:      Arc::new(Mutex::new(Vec::new())),
+               selected_move:   Arc::new(Mutex::new(None)), }
   }
 }
 
@@ -233,6 +444,18 @@ pub struct Engine {
   nnue:         Arc<Mutex<NNUE>>,
   /// Game History
   history:      GameHistory,
+  /// While pondering: position before the expected opponent move, and the
+  /// expected opponent move
+  ponder:       Option<(GameState, Move)>,
+  /// How often the opponent played the move we pondered on
+  ponder_stats: PonderStats,
+  /// Random number generator used for all the random decisions of the engine
+  rng:          Arc<Mutex<StdRng>>,
+}
+
+/// Path of the NNUE file loaded by new engines
+pub fn default_nnue_path() -> String {
+  format!("{}/{}", env!("CARGO_MANIFEST_DIR"), NNUE_FILE)
 }
 
 type AsyncResult = std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
@@ -244,24 +467,165 @@ impl Engine {
   /// Gets a new engine
   pub fn new(uci: bool) -> Self {
     initialize_chess_books();
-    let nnue_path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), NNUE_FILE);
+    let nnue_path = default_nnue_path();
 
     let mut engine =
-      Engine { position: GameState::default(),
-               analysis: Analysis::default(),
-               cache:    EngineCache::new(),
-               options:  EngineOptions::default(),
-               state:    EngineState { active:         Arc::new(Mutex::new(false)),
-                                       stop_requested: Arc::new(Mutex::new(false)),
-                                       start_time:     Arc::new(Mutex::new(Instant::now())), },
-               nnue:     Arc::new(Mutex::new(NNUE::load(nnue_path.as_str()).unwrap_or_default())),
-               history:  GameHistory::new(), };
+      Engine { position:     GameState::default(),
+               analysis:     Analysis::default(),
+               cache:        EngineCache::new(),
+               options:      EngineOptions::default(),
+               state:        EngineState { active:         Arc::new(Mutex::new(false)),
+                                           stop_requested: Arc::new(Mutex::new(false)),
+                                           search_id:      Arc::new(Mutex::new(0)),
+                                           start_time:     Arc::new(Mutex::new(Instant::now())),
+                                           pondering:      Arc::new(Mutex::new(false)), },
+               nnue:
+                 Arc::new(Mutex::new(NNUE::load(nnue_path.as_str()).unwrap_or_default())),
+               history:      GameHistory::new(),
+               ponder:       None,
+               ponder_stats: PonderStats::default(),
+               rng:          Arc::new(Mutex::new(StdRng::from_entropy())), };
 
     engine.options.uci = uci;
     engine.set_position(START_POSITION_FEN);
     engine
   }
 
+  /// Seeds the random number generator of the engine, so that its random
+  /// decisions (book moves, choice between equivalent lines) can be
+  /// reproduced.
+  ///
+  /// ### Arguments
+  ///
+  /// * `seed`: Seed to use for the random number generator
+  pub fn set_seed(&mut self, seed: u64) {
+    self.options.seed = Some(seed);
+    *self.rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+  }
+
+  /// Sets whether the engine continues searching after finding a winning
+  /// sequence.
+  ///
+  /// ### Arguments
+  ///
+  /// * `ponder`: Set to true to continue searching
+  pub fn set_ponder(&mut self, ponder: bool) {
+    self.options.ponder = ponder;
+  }
+
+  /// Sets the maximum depth of the search.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_depth`: Maximum depth of the search, 0 for no limit
+  pub fn set_maximum_depth(&mut self, max_depth: usize) {
+    self.options.max_depth = max_depth;
+  }
+
+  /// Sets the time limit of the search.
+  ///
+  /// ### Arguments
+  ///
+  /// * `time_ms`: Search time in milliseconds, 0 for no limit
+  pub fn set_search_time_limit(&mut self, time_ms: usize) {
+    self.options.max_search_time = time_ms;
+  }
+
+  /// Sets the number of threads used by the search.
+  /// More threads than logical CPUs are allowed, but will slow down each
+  /// thread.
+  ///
+  /// ### Arguments
+  ///
+  /// * `threads`: Number of threads, at least 1
+  pub fn set_maximum_threads(&mut self, threads: usize) {
+    let cpus = available_threads();
+    if threads > cpus {
+      warn!("Using {} search threads with only {} logical CPUs",
+            threads, cpus);
+    }
+    self.options.threads = max(threads, 1);
+  }
+
+  /// Sets whether the NNUE is used for evaluating positions.
+  ///
+  /// ### Arguments
+  ///
+  /// * `use_nnue`: Set to true to use the NNUE
+  pub fn set_use_nnue(&mut self, use_nnue: bool) {
+    self.options.use_nnue = use_nnue;
+  }
+
+  /// Sets the NNUE used for evaluating positions.
+  /// Engines sharing the same NNUE all pick up a net loaded into it, even
+  /// while searching.
+  ///
+  /// ### Arguments
+  ///
+  /// * `nnue`: Shared NNUE to use
+  pub fn set_nnue(&mut self, nnue: Arc<Mutex<NNUE>>) {
+    self.nnue = nnue;
+  }
+
+  /// Sets whether the engine plays book moves in known positions.
+  /// Without the book, opening positions are searched like any other.
+  ///
+  /// ### Arguments
+  ///
+  /// * `use_book`: Set to true to play book moves
+  pub fn set_use_book(&mut self, use_book: bool) {
+    self.options.use_book = use_book;
+  }
+
+  /// Sets whether the engine prints debug information.
+  ///
+  /// ### Arguments
+  ///
+  /// * `debug`: Set to true to print debug information
+  pub fn set_debug(&mut self, debug: bool) {
+    self.options.debug = debug;
+  }
+
+  /// Sets the play style of the engine.
+  ///
+  /// ### Arguments
+  ///
+  /// * `play_style`: Play style to use
+  pub fn set_play_style(&mut self, play_style: PlayStyle) {
+    self.options.play_style = play_style;
+  }
+
+  /// Sets the number of best lines returned by the engine.
+  ///
+  /// ### Arguments
+  ///
+  /// * `multi_pv`: Number of lines, at least 1
+  pub fn set_multipv(&mut self, multi_pv: usize) {
+    self.options.multi_pv = max(multi_pv, 1);
+  }
+
+  /// Limits the strength of the engine to an Elo: the search depth is capped
+  /// and the engine sometimes plays a move slightly worse than the best one.
+  /// The lower the Elo, the shallower the search and the more frequent and
+  /// bigger the inaccuracies.
+  ///
+  /// ### Arguments
+  ///
+  /// * `elo`: Elo to play at, clamped to [ELO_LIMIT_MIN, ELO_LIMIT_MAX]. None
+  ///   to play at full strength.
+  pub fn set_elo_limit(&mut self, elo: Option<u32>) {
+    self.options.elo_limit = elo.map(clamp_elo);
+  }
+
+  /// Sets how many moves are displayed for each line.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_pv_plies`: Number of moves, at least 1
+  pub fn set_max_pv_plies(&mut self, max_pv_plies: usize) {
+    self.options.max_pv_plies = max(max_pv_plies, 1);
+  }
+
   /// Checks if the engine is resolving a position
   ///
   /// ### Return value
@@ -271,16 +635,33 @@ impl Engine {
     return *self.state.active.lock().unwrap();
   }
 
-  /// Helper function that sets the "active" bool value in the engine
+  /// Helper function that sets the "active" bool value in the engine. A new
+  /// search ID is assigned when the engine becomes active.
   ///
   /// ### Arguments
   ///
   /// * `active`: The new value to apply to active
   fn set_engine_active(&self, active: bool) {
     let mut s = self.state.active.lock().unwrap();
+    if active && !*s {
+      *self.state.search_id.lock().unwrap() += 1;
+    }
     *s = active;
   }
 
+  /// Finds out which search the engine is running
+  ///
+  /// ### Return value
+  ///
+  /// ID of the ongoing search, None if the engine is not active
+  fn active_search_id(&self) -> Option<u64> {
+    let active = self.state.active.lock().unwrap();
+    if !*active {
+      return None;
+    }
+    Some(*self.state.search_id.lock().unwrap())
+  }
+
   /// Checks if the engine has been requested to stop evaluating
   ///
   /// ### Return value
@@ -300,6 +681,25 @@ impl Engine {
     *s = stop_requested;
   }
 
+  /// Checks if the engine is pondering, i.e. searching on the opponent's time
+  ///
+  /// ### Return value
+  ///
+  /// * True if the engine is pondering, False otherwise
+  pub fn is_pondering(&self) -> bool {
+    *self.state.pondering.lock().unwrap()
+  }
+
+  /// Helper function that sets the "pondering" bool value in the engine
+  ///
+  /// ### Arguments
+  ///
+  /// * `pondering`: The new value to apply to pondering
+  fn set_pondering(&self, pondering: bool) {
+    let mut s = self.state.pondering.lock().unwrap();
+    *s = pondering;
+  }
+
   /// Checks if the engine has been searching a position for too long
   /// It will compare the start time with the current time and the maximum time
   /// set in the engine options
@@ -310,13 +710,33 @@ impl Engine {
   /// and max_time is set to a non-zero value.
   fn has_been_searching_too_long(&self) -> bool {
     let max_time = self.options.max_search_time;
-    if max_time == 0 {
+    if max_time == 0 || self.is_pondering() {
       return false;
     }
     let start_time = self.get_start_time();
     (Instant::now() - start_time) > Duration::from_millis(max_time as u64)
   }
 
+  /// Checks if the search can stop before its time limit because the best move
+  /// did not change for enough depths. We never stop early when in check,
+  /// while pondering or without a time limit.
+  ///
+  /// ### Arguments
+  ///
+  /// * `stable_depths`: Number of consecutive completed depths for which the
+  ///   best move and its eval did not change
+  ///
+  /// ### Return value
+  ///
+  /// True if the search can stop
+  fn best_move_is_stable(&self, stable_depths: usize) -> bool {
+    self.options.stable_move_depths > 0
+    && stable_depths >= self.options.stable_move_depths
+    && self.options.max_search_time > 0
+    && !self.is_pondering()
+    && !self.position.board.is_check()
+  }
+
   /// Clears the cache of the engine.
   ///
   /// Note: You should not invoke this function when the engine is
@@ -343,6 +763,8 @@ impl Engine {
   /// Same as Engine::Default() or Engine::new(..)
   pub fn reset(&mut self) {
     self.stop();
+    self.ponder = None;
+    self.ponder_stats = PonderStats::default();
     self.position = GameState::from_fen(START_POSITION_FEN);
     self.analysis.reset();
     self.cache.clear();
@@ -363,16 +785,18 @@ impl Engine {
 
     let game_state = GameState::from_fen(fen);
     self.position = game_state.clone();
+    self.history.add(self.position.to_fen(), Move::null(), 0, Variation::new());
     let move_list = self.position.get_moves();
 
     // Compute move list if not known.
     if !self.cache.has_move_list(&game_state.board) {
       self.cache.set_move_list(&game_state.board, &move_list);
     }
+    self.log_board_masks();
   }
 
   /// Applies a move from the current position
-  /// Invalid moves will be ignored.
+  /// Invalid moves are logged and ignored, the side to play does not change.
   ///
   /// ### Arguments
   ///
@@ -383,15 +807,65 @@ impl Engine {
       self.stop();
     }
 
-    // FIXME: THis does not capture evals when opponent applies a move
-    // let eval = self.get_best_eval();
-    // let mv = self.position.last_moves.last().unwrap_or(&Move::null()).clone();
-    // self.history.add(self.position.to_fen(), mv, eval as isize);
-
-    self.position.apply_move_from_notation(chess_move);
+    // Evaluation of the position before the move, if we analyzed it
+    let eval = (self.get_eval().unwrap_or(0.0) * 100.0) as isize;
+    let mv = match self.position.apply_move_from_notation(chess_move) {
+      Ok(mv) => mv,
+      Err(error) => {
+        warn!("Ignoring move on position {}: {:?}",
+              self.position.to_fen(),
+              error);
+        return;
+      },
+    };
+    self.history.add(self.position.to_fen(), mv, eval, Variation::new());
     self.cache.clear_killer_moves();
     self.analysis.reset();
     self.analysis.decrement_depth();
+    self.log_board_masks();
+  }
+
+  /// Logs the checkers, pins and squares controlled by each side in the
+  /// current position, at debug level.
+  fn log_board_masks(&self) {
+    if !log_enabled!(Level::Debug) {
+      return;
+    }
+
+    let board = &self.position.board;
+    debug!("Checkers:\n{}", BoardMaskDisplay(board.checkers));
+    debug!("Pins:\n{}", BoardMaskDisplay(board.pins));
+    debug!("White control:\n{}",
+           BoardMaskDisplay(board.get_control_boardmask(Color::White)));
+    debug!("Black control:\n{}",
+           BoardMaskDisplay(board.get_control_boardmask(Color::Black)));
+  }
+
+  /// Takes back the last move applied on the current position.
+  /// The position is replayed from the start of the game history, so that
+  /// repetitions are still detected afterwards.
+  /// Nothing happens if no move was applied since the position was set.
+  pub fn undo_move(&mut self) {
+    if self.history.len() < 2 {
+      info!("No move to undo");
+      return;
+    }
+
+    if self.is_active() {
+      self.stop_blocking();
+    }
+
+    self.history.pop();
+    let mut entries = self.history.iter();
+    let mut game_state = GameState::from_fen(entries.next().unwrap().position.as_str());
+    for entry in entries {
+      game_state.apply_move(&entry.last_move);
+    }
+
+    self.ponder = None;
+    self.position = game_state;
+    self.cache.clear_killer_moves();
+    self.analysis.reset();
   }
 
   /// Starts analyzing the current position
@@ -405,35 +879,213 @@ impl Engine {
     }
 
     // Mark that we are now active and stop is not requested.
+    self.set_engine_active(true);
+    self.set_stop_requested(false);
+    self.search_position();
+  }
+
+  /// Starts analyzing the current position in a background thread
+  ///
+  /// Analysis will continue until stopped with the `stop()` method
+  ///
+  /// ### Return value
+  ///
+  /// Handle of the thread running the search, None if the engine is already
+  /// searching.
+  pub fn go_async(&self) -> Option<JoinHandle<()>> {
+    if self.is_active() {
+      debug!("We are already evaluating the position.");
+      return None;
+    }
+
+    // Mark the engine active before spawning, so that callers see it active
+    // as soon as we return.
+    self.set_engine_active(true);
     self.set_stop_requested(false);
+    let engine = self.clone();
+    Some(std::thread::spawn(move || engine.search_position()))
+  }
+
+  /// Starts analyzing the current position in a background thread, on the
+  /// opponent's time.
+  ///
+  /// The search time limit does not apply until `ponderhit()` is called, and
+  /// no best move is reported before `ponderhit()` or `stop()`.
+  ///
+  /// ### Return value
+  ///
+  /// Handle of the thread running the search, None if the engine is already
+  /// searching.
+  pub fn go_ponder(&self) -> Option<JoinHandle<()>> {
+    if self.is_active() {
+      debug!("We are already evaluating the position.");
+      return None;
+    }
+    self.set_pondering(true);
+    self.go_async()
+  }
+
+  /// Looks for a forced checkmate in the current position in a background
+  /// thread, like UCI `go mate N`. The best move is reported as for `go`: the
+  /// mating move, or the move of a regular search when there is no mate.
+  ///
+  /// The search can be interrupted with the `stop()` method.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_moves`: Maximum number of moves until the mate
+  ///
+  /// ### Return value
+  ///
+  /// Handle of the thread running the search, None if the engine is already
+  /// searching.
+  pub fn go_mate_async(&self, max_moves: usize) -> Option<JoinHandle<()>> {
+    if self.is_active() {
+      debug!("We are already evaluating the position.");
+      return None;
+    }
+
     self.set_engine_active(true);
+    self.set_stop_requested(false);
+    let engine = self.clone();
+    Some(std::thread::spawn(move || engine.search_mate_position(max_moves)))
+  }
+
+  /// Plays our move and starts pondering on the opponent reply predicted by
+  /// the last analysis.
+  ///
+  /// ### Arguments
+  ///
+  /// * `our_move`: Move that we played from the current position
+  ///
+  /// ### Return value
+  ///
+  /// The opponent move we are pondering on. None if the analysis has no
+  /// expected reply to our move, in which case we do not ponder.
+  pub fn ponder(&mut self, our_move: &Move) -> Option<Move> {
+    self.stop_blocking();
+    let expected_reply = self.get_analysis()
+                             .variations
+                             .iter()
+                             .find(|v| v.variation.get_first_move() == Some(*our_move))
+                             .and_then(|v| v.variation.get(1));
+
+    self.apply_move(our_move.to_string().as_str());
+    let expected_reply = expected_reply?;
+    if !self.position.get_moves().contains(&expected_reply) {
+      return None;
+    }
+
+    self.ponder = Some((self.position.clone(), expected_reply));
+    self.apply_move(expected_reply.to_string().as_str());
+    self.go_ponder();
+
+    Some(expected_reply)
+  }
+
+  /// Returns how often the opponent played the move we pondered on, since the
+  /// engine was created or reset
+  pub fn get_ponder_stats(&self) -> PonderStats {
+    self.ponder_stats
+  }
+
+  /// Indicates that the opponent played the move we were pondering on.
+  /// The ponder search continues as a regular search, with the time limit
+  /// counting from now.
+  pub fn ponderhit(&mut self) {
+    self.ponder = None;
+    self.set_start_time();
+    self.set_pondering(false);
+  }
+
+  /// Applies the move played by the opponent. If we were pondering on that
+  /// move, the ponder search is kept as the real search (ponderhit).
+  /// Otherwise the ponder search is discarded.
+  ///
+  /// ### Arguments
+  ///
+  /// * `chess_move`: Notation of the chess move played by the opponent
+  ///
+  /// ### Return value
+  ///
+  /// True on a ponderhit, the engine keeps searching. False otherwise, the
+  /// move is applied and a new search has to be started.
+  pub fn apply_opponent_move(&mut self, chess_move: &str) -> bool {
+    if let Some((position, expected_reply)) = self.ponder.take() {
+      let hit = expected_reply.to_string() == chess_move;
+      self.ponder_stats.record(hit);
+      if self.is_pondering() && hit {
+        self.ponderhit();
+        return true;
+      }
+
+      // Wrong guess, throw away the ponder search
+      self.stop_blocking();
+      self.history.pop();
+      self.position = position;
+      self.analysis.reset();
+    }
+
+    self.apply_move(chess_move);
+    false
+  }
+
+  /// Searches the current position, until the search is stopped, runs out of
+  /// time or reaches the maximum depth.
+  ///
+  /// The engine must be marked active before calling this function.
+  fn search_position(&self) {
     self.set_start_time(); // Capture that we started searching now.
     self.analysis.set_nodes_visited(0);
+    self.analysis.set_selected_move(None);
+    if self.options.use_nnue && !self.is_nnue_active() {
+      info!("Only {} ms to search, using the classical evaluation without NNUE",
+            self.options.max_search_time);
+    }
+    self.analysis.debug_info.lock().unwrap().clear();
+
+    // Make sure we know the move list. When in check, all moves are evasions
+    // and we order them specifically, unless a previous search already did.
+    if !self.cache.has_move_list(&self.position.board) && self.position.board.is_check() {
+      let mut moves = self.position.board.get_moves();
+      self.position.board.order_check_evasions(&mut moves);
+      self.cache.set_move_list(&self.position.board, &moves);
+    }
+    let root_moves = Engine::find_move_list(&self.cache, &self.position.board);
 
-    // Make sure we know the move list:
-    Engine::find_move_list(&self.cache, &self.position.board);
+    // Restrict the root moves if requested
+    let search_moves = self.get_search_moves(&self.position.board);
+    if !self.options.search_moves.is_empty() && search_moves.is_empty() {
+      warn!("None of the moves {} can be played, searching all moves",
+            Move::vec_to_string(&self.options.search_moves));
+    }
 
     // First check if we are in a known book position. If yes, just return the known
     // list
     let play_style = self.options.play_style;
-    let book_entry = get_book_moves(&self.position.board, play_style == PlayStyle::Provocative);
+    let book_entry = if self.options.use_book && search_moves.is_empty() {
+      get_book_moves(&self.position.board, play_style == PlayStyle::Provocative)
+    } else {
+      None
+    };
     if book_entry.is_some() {
       info!("Known position, returning book moves for {:?} play",
             play_style);
-      let mut move_list = book_entry.unwrap();
-      let mut rng = rand::thread_rng();
-      move_list.shuffle(&mut rng);
+      let move_list = self.sort_book_moves(book_entry.unwrap());
 
       let mut result: SearchResult =
         SearchResult::new(self.options.multi_pv, self.position.board.side_to_play);
 
-      for m in &move_list {
+      // Equal evaluations are inserted first, so insert in reverse order to keep
+      // the first book move on top.
+      for m in move_list.iter().rev() {
         result.update(VariationWithEval::new_from_move(0.0, *m));
       }
       self.analysis.update_result(result);
 
       // We are done
       self.print_uci_info();
+      self.wait_for_ponderhit();
       self.print_uci_best_move();
       self.set_stop_requested(false);
       self.set_engine_active(false);
@@ -442,15 +1094,17 @@ impl Engine {
 
     // If we have only one legal move, we should just give it a score and play it
     // instantaneously.
-    let moves = self.cache.get_move_list(&self.position.board).unwrap();
+    let mut moves = root_moves.to_vec();
+    if !search_moves.is_empty() {
+      moves = search_moves;
+    }
     if moves.len() == 1 {
       debug!("Single or no move available. Just evaluating quickly");
-      let mut game_state = self.position.clone();
-      game_state.apply_move(&moves[0]);
+      let game_state = self.position.with_move(&moves[0]);
 
       let mut evaluation_cache = self.cache.get_eval(&game_state.board).unwrap_or_default();
       if evaluation_cache.depth == 0 {
-        let game_status = is_game_over(&self.cache, &game_state.board);
+        let game_status = is_game_over(&self.cache, &game_state);
         let mut eval = get_eval_from_game_status(game_status);
         if eval.is_nan() {
           eval = evaluate_board(&game_state);
@@ -469,12 +1123,22 @@ impl Engine {
       self.analysis.set_selective_depth(evaluation_cache.depth);
 
       self.print_uci_info();
+      self.wait_for_ponderhit();
       self.print_uci_best_move();
       self.set_stop_requested(false);
       self.set_engine_active(false);
       return;
     }
 
+    // Lazy SMP: helper threads search the same position and share the
+    // transposition table and the move ordering, the main thread below is the
+    // only one reporting results.
+    let helpers = self.start_helper_threads();
+
+    // Number of consecutive depths for which the best move did not change
+    let mut stable_depths: usize = 0;
+    let mut previous_best: Option<(Move, f32)> = None;
+
     // Main search
     while !self.has_been_searching_too_long() && !self.stop_requested() {
       self.analysis.increment_depth();
@@ -482,6 +1146,7 @@ impl Engine {
 
       // Try to search for the current depth
       let result = self.search(&self.position.clone(),
+                               &EvalAccumulator::from_game_state(&self.position),
                                1,
                                self.analysis.get_depth(),
                                f32::MIN,
@@ -496,6 +1161,21 @@ impl Engine {
       // Depth completed - print UCI result if needed
       let result = result.unwrap(); // Safe due to is_none() above
       let best_eval = result.get_eval().unwrap();
+      let best_move = result.get_best_move();
+      stable_depths = match (previous_best, best_move) {
+        (Some((mv, eval)), Some(best))
+          if mv == best
+             && (eval - best_eval).abs() * 100.0 <= self.options.stable_move_margin_cp as f32 =>
+        {
+          stable_depths + 1
+        },
+        _ => 0,
+      };
+      previous_best = best_move.map(|mv| (mv, best_eval));
+      if self.options.debug {
+        self.print_debug(&self.get_root_evals_info(&result));
+      }
+      self.analysis.set_selected_move(self.apply_play_style(&result));
       self.analysis.update_result(result);
       self.print_uci_info();
 
@@ -507,31 +1187,589 @@ impl Engine {
         break;
       }
 
-      let max_depth = self.options.max_depth;
+      if self.best_move_is_stable(stable_depths) {
+        debug!("Best move stable for {} depths! Stopping search",
+               stable_depths);
+        break;
+      }
+
+      let max_depth = self.get_maximum_depth();
       if max_depth > 0 && self.analysis.get_depth() >= max_depth {
         break;
       }
     }
+    self.stop_helper_threads(helpers);
+    self.apply_elo_limit();
+    if let Some(branching_factor) = self.analysis.get_branching_factor() {
+      self.print_debug(&format!("branching factor {:.2}", branching_factor));
+    }
 
     // We are done
+    self.wait_for_ponderhit();
     self.print_uci_best_move();
     self.set_stop_requested(false);
     self.set_engine_active(false);
   }
 
-  /// Starts analyzing the current position
+  /// Spawns the helper threads of the search, if the engine is configured to
+  /// use more than 1 thread.
+  ///
+  /// ### Return value
+  ///
+  /// The helper engines, with the handle of the thread running their search
+  fn start_helper_threads(&self) -> Vec<(Engine, JoinHandle<()>)> {
+    let mut helpers = Vec::new();
+    for thread_index in 1..self.options.threads {
+      // Helpers share the whole cache and the start time, but have their own
+      // analysis and stop flag. Only exact scores are saved with a depth in
+      // the evaluation table, so the ones found by the helpers are as good as
+      // the ones of the main thread, whatever their alpha/beta windows.
+      let mut helper = self.clone();
+      helper.analysis = Analysis::default();
+      helper.state.stop_requested = Arc::new(Mutex::new(false));
+
+      let engine = helper.clone();
+      let handle = std::thread::spawn(move || engine.helper_search(thread_index));
+      helpers.push((helper, handle));
+    }
+    helpers
+  }
+
+  /// Stops the helper threads of the search and waits for them to finish.
+  /// The nodes they visited are added to the ones of the main thread.
+  ///
+  /// ### Arguments
+  ///
+  /// * `helpers`: Helper engines returned by `start_helper_threads`
+  fn stop_helper_threads(&self, helpers: Vec<(Engine, JoinHandle<()>)>) {
+    for (helper, handle) in helpers {
+      helper.set_stop_requested(true);
+      if handle.join().is_err() {
+        error!("Search helper thread panicked");
+      }
+      self.analysis
+          .set_nodes_visited(self.analysis.get_nodes_visited()
+                             + helper.analysis.get_nodes_visited());
+    }
+  }
+
+  /// Iterative deepening loop of a helper thread of the search. Helpers only
+  /// fill the shared evaluation table and reorder the shared move lists,
+  /// which the main thread picks up.
+  /// Every other helper starts one ply deeper, so that the threads do not all
+  /// search the same depth at the same time.
+  ///
+  /// ### Arguments
+  ///
+  /// * `thread_index`: Index of the helper thread, starting at 1
+  fn helper_search(&self, thread_index: usize) {
+    self.analysis.set_depth(thread_index % 2);
+    while !self.has_been_searching_too_long() && !self.stop_requested() {
+      self.analysis.increment_depth();
+      self.analysis.increment_selective_depth();
+      let result = self.search(&self.position,
+                               &EvalAccumulator::from_game_state(&self.position),
+                               1,
+                               self.analysis.get_depth(),
+                               f32::MIN,
+                               f32::MAX);
+      if result.is_none() {
+        break;
+      }
+
+      let max_depth = self.get_maximum_depth();
+      if max_depth > 0 && self.analysis.get_depth() >= max_depth {
+        break;
+      }
+    }
+  }
+
+  /// Sorts book moves randomly, the moves with a higher book weight having
+  /// more chances to end up first.
+  ///
+  /// ### Arguments
+  ///
+  /// * `move_list`: Book moves for the current position
+  ///
+  /// ### Return value
+  ///
+  /// The book moves, in the order in which they should be played
+  fn sort_book_moves(&self, move_list: Vec<Move>) -> Vec<Move> {
+    let mut rng = self.rng.lock().unwrap();
+    // Weighted random sampling: each move gets a key u^(1/weight), with u
+    // uniform in [0, 1), and moves are sorted by decreasing key.
+    let mut keyed_moves: Vec<(f64, Move)> =
+      move_list.into_iter()
+               .map(|m| {
+                 let weight =
+                   get_book_move_weight(&BOOK_WEIGHTS, &self.position.board, &m) as f64;
+                 (rng.gen::<f64>().powf(1.0 / weight), m)
+               })
+               .collect();
+    keyed_moves.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed_moves.into_iter().map(|(_, m)| m).collect()
+  }
+
+  /// Picks the move to play among the lines at the root of the search
+  /// according to the play style. Lines get a bonus or a penalty depending on
+  /// their first move, as long as they are not evaluated much worse than the
+  /// best line. The search result itself is left untouched.
+  ///
+  /// ### Arguments
+  ///
+  /// * `result`: Search result for the current position, sorted by evaluation
+  ///
+  /// ### Return value
+  ///
+  /// The move preferred by the play style, None to play the best move
+  fn apply_play_style(&self, result: &SearchResult) -> Option<Move> {
+    let play_style = self.options.play_style;
+    if play_style == PlayStyle::Normal || play_style == PlayStyle::Provocative {
+      return None;
+    }
+
+    // Do not mess with mating sequences
+    let best_eval = result.get_eval();
+    if best_eval.is_none() || best_eval.unwrap().abs() > 100.0 || best_eval.unwrap().is_nan() {
+      return None;
+    }
+
+    let board = &self.position.board;
+    let factor = Color::score_factor(board.side_to_play);
+    let best_score = best_eval.unwrap() * factor;
+    let mut best_line: Option<(f32, Move)> = None;
+    for line in &result.variations {
+      let Some(mv) = line.variation.get_first_move() else {
+        continue;
+      };
+      let mut score = line.eval * factor;
+      if best_score - score <= PLAY_STYLE_MAX_EVAL_LOSS {
+        score += play_style.get_move_bonus(board, &mv);
+      }
+      // Strict comparison, so that lines with the same score keep their order
+      if best_line.is_none_or(|(best, _)| score > best) {
+        best_line = Some((score, mv));
+      }
+    }
+    best_line.map(|(_, mv)| mv)
+  }
+
+  /// Maximum depth of the search, taking the Elo limit into account.
+  ///
+  /// ### Return value
+  ///
+  /// Maximum depth, 0 for no limit
+  fn get_maximum_depth(&self) -> usize {
+    let max_depth = self.options.max_depth;
+    match self.options.elo_limit {
+      None => max_depth,
+      Some(elo) if max_depth == 0 => elo_limit_max_depth(elo),
+      Some(elo) => min(max_depth, elo_limit_max_depth(elo)),
+    }
+  }
+
+  /// Weakens the choice of the move when the strength of the engine is
+  /// limited: from time to time, the move of a line evaluated a bit worse than
+  /// the best one is selected to be played. The analysis still reports the
+  /// best line. Lines losing to a forced mate or starting with a move losing
+  /// material are never picked.
+  fn apply_elo_limit(&self) {
+    let elo = match self.options.elo_limit {
+      Some(elo) => elo,
+      None => return,
+    };
+
+    let result = self.get_analysis();
+    let best_eval = match result.get_eval() {
+      Some(eval) if !eval.is_nan() => eval,
+      _ => return,
+    };
+
+    let mut rng = self.rng.lock().unwrap();
+    if rng.gen::<f32>() >= elo_limit_suboptimal_move_probability(elo) {
+      return;
+    }
+
+    let board = &self.position.board;
+    let factor = Color::score_factor(board.side_to_play);
+    let margin = elo_limit_eval_margin(elo);
+    let mut candidates: Vec<(usize, Move)> = Vec::new();
+    for (i, line) in result.variations.iter().enumerate().skip(1) {
+      let mv = match line.variation.get_first_move() {
+        Some(mv) => mv,
+        None => continue,
+      };
+      let losing_mate = mate_distance(line.eval).is_some_and(|d| d as f32 * factor < 0.0);
+      if !line.eval.is_nan()
+         && (best_eval - line.eval) * factor <= margin
+         && !losing_mate
+         && board.see_ge(&mv, 0)
+      {
+        candidates.push((i, mv));
+      }
+    }
+    if candidates.is_empty() {
+      return;
+    }
+
+    let (index, mv) = candidates[rng.gen_range(0..candidates.len())];
+    debug!("Elo limit {}: playing line {} instead of the best line",
+           elo, index);
+    self.analysis.set_selected_move(Some(mv));
+  }
+
+  /// Blocks while the engine is pondering, as the best move should not be
+  /// reported before the opponent has played, unless we are requested to stop.
+  fn wait_for_ponderhit(&self) {
+    while self.is_pondering() && !self.stop_requested() {
+      std::thread::sleep(Duration::from_millis(10));
+    }
+  }
+
+  /// Starts analyzing the current position
   ///
   /// Analysis will continue until stopped.
   pub fn stop(&self) {
     self.set_stop_requested(true);
+    self.set_pondering(false);
+  }
+
+  /// Requests the engine to stop searching, and spawns a watchdog that checks
+  /// that the search actually stopped. If the same search is still running
+  /// after the grace period, e.g. because the stop request was reset by the
+  /// search while it was starting, the stop is requested again. Searches
+  /// started after the stop request are left alone.
+  ///
+  /// ### Arguments
+  ///
+  /// * `grace_period`: Time given to the search to stop
+  ///
+  /// ### Return value
+  ///
+  /// Handle of the watchdog thread
+  pub fn stop_with_watchdog(&self, grace_period: Duration) -> JoinHandle<()> {
+    let search_id = self.active_search_id();
+    self.stop();
+    let engine = self.clone();
+    std::thread::spawn(move || {
+      std::thread::sleep(grace_period);
+      if search_id.is_some() && engine.active_search_id() == search_id {
+        error!("Search still running {:?} after being stopped, forcing it to stop. Position: {}",
+               grace_period,
+               engine.position.to_fen());
+        engine.stop();
+      }
+    })
   }
 
-  /// Returns the best move saved in the analysis
+  /// Requests the engine to stop searching and blocks until it has stopped.
+  fn stop_blocking(&self) {
+    while self.is_active() {
+      self.stop();
+      std::thread::sleep(Duration::from_millis(10));
+    }
+  }
+
+  /// Looks for a forced checkmate in the current position, like UCI
+  /// `go mate N`. Only forcing lines are searched, the analysis of the engine
+  /// is not updated.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_moves`: Maximum number of moves until the mate
+  ///
+  /// ### Return value
+  ///
+  /// The shortest forced mate, or `MateSearchResult::NoMate` if there is no
+  /// forced mate within `max_moves`
+  pub fn search_mate(&self, max_moves: usize) -> MateSearchResult {
+    search::mate::search_mate(&self.position.board, max_moves, &|| false)
+  }
+
+  /// Searches a forced checkmate in the current position and reports it.
+  /// Without mate, the regular search finds the best move instead. If the
+  /// search is stopped first, the moves are only evaluated statically, so
+  /// that we still report a legal move.
+  ///
+  /// The engine must be marked active before calling this function.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_moves`: Maximum number of moves until the mate
+  fn search_mate_position(&self, max_moves: usize) {
+    self.set_start_time();
+    self.analysis.reset();
+    let side_to_play = self.position.board.side_to_play;
+
+    let should_stop = || self.stop_requested() || self.has_been_searching_too_long();
+    match search::mate::search_mate(&self.position.board, max_moves, &should_stop) {
+      MateSearchResult::Mate(mv, moves) => {
+        let plies = 2 * moves - 1;
+        let eval = (MATE_EVAL + 1.0 - plies as f32) * Color::score_factor(side_to_play);
+        let mut result = SearchResult::new(self.options.multi_pv, side_to_play);
+        result.update(VariationWithEval::new_from_move(eval, mv));
+        self.analysis.update_result(result);
+        self.analysis.set_depth(plies);
+        self.analysis.set_selective_depth(plies);
+        self.print_uci_info();
+      },
+      MateSearchResult::NoMate if !self.stop_requested() => {
+        if self.options.uci {
+          println!("info string no mate in {} found", max_moves);
+        }
+        self.search_position();
+        return;
+      },
+      MateSearchResult::NoMate => {
+        self.analysis.update_result(self.evaluate_root_moves());
+      },
+    }
+
+    self.print_uci_best_move();
+    self.set_stop_requested(false);
+    self.set_engine_active(false);
+  }
+
+  /// Evaluates the moves of the current position statically, without
+  /// searching, e.g. to still have a move to play when a search is stopped
+  /// right away.
+  ///
+  /// ### Return value
+  ///
+  /// Search result with the static evaluation of each move
+  fn evaluate_root_moves(&self) -> SearchResult {
+    let mut result = SearchResult::new(self.options.multi_pv, self.position.board.side_to_play);
+    for m in self.position.get_moves() {
+      let game_state = self.position.with_move(&m);
+      let mut eval = get_eval_from_game_status(is_game_over(&self.cache, &game_state));
+      if eval.is_nan() {
+        eval = evaluate_board(&game_state);
+      }
+      result.update(VariationWithEval::new_from_move(eval, m));
+    }
+    result
+  }
+
+  /// Returns the move to play: the best move saved in the analysis, unless the
+  /// play style or the Elo limit selected the move of another line.
   pub fn get_best_move(&self) -> Option<Move> {
+    if let Some(selected_move) = *self.analysis.selected_move.lock().unwrap() {
+      return Some(selected_move);
+    }
     let analysis = self.analysis.result.lock().unwrap();
     analysis.get_best_move()
   }
 
+  /// Returns the principal variation saved in the analysis, i.e. the moves of
+  /// the best line, starting with the best move. Empty if there is no analysis.
+  pub fn get_pv(&self) -> Vec<Move> {
+    let analysis = self.analysis.result.lock().unwrap();
+    match analysis.variations.first() {
+      Some(line) => line.variation.to_vec(),
+      None => Vec::new(),
+    }
+  }
+
+  /// Checks if the current position is in the opening book of the play style
+  /// of the engine. The `use_book` option is not taken into account.
+  ///
+  /// ### Return value
+  ///
+  /// `BookStatus::InBook` with the number of book moves, or
+  /// `BookStatus::OutOfBook`
+  pub fn book_status(&self) -> BookStatus {
+    let provocative = self.options.play_style == PlayStyle::Provocative;
+    match get_book_moves(&self.position.board, provocative) {
+      Some(moves) if !moves.is_empty() => BookStatus::InBook(moves.len()),
+      _ => BookStatus::OutOfBook,
+    }
+  }
+
+  /// Evaluates a position without searching.
+  /// The NNUE evaluation is mixed in if the engine uses the NNUE.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_state`: Position to evaluate
+  ///
+  /// ### Return value
+  ///
+  /// Evaluation of the position, from White's point of view
+  pub fn static_eval(&self, game_state: &GameState) -> f32 {
+    let eval = evaluate_board(game_state);
+    if self.options.use_nnue {
+      self.blend_nnue_eval(game_state, eval)
+    } else {
+      eval
+    }
+  }
+
+  /// Runs a quick check of the engine components, to catch a broken move
+  /// generation, net or book before playing with them.
+  ///
+  /// ### Return value
+  ///
+  /// Ok if all checks passed, otherwise the description of each failed check
+  pub fn self_test(&self) -> Result<(), Vec<String>> {
+    let mut failures = Vec::new();
+    let start_position = GameState::from_fen(START_POSITION_FEN);
+
+    let nodes = start_position.board.perft(SELF_TEST_PERFT_DEPTH);
+    if nodes != SELF_TEST_PERFT_NODES {
+      failures.push(format!("Move generation: perft({}) of the start position is {} instead of {}",
+                            SELF_TEST_PERFT_DEPTH, nodes, SELF_TEST_PERFT_NODES));
+    }
+
+    if self.options.use_nnue {
+      let eval = self.nnue.lock().unwrap().eval(&start_position);
+      if !eval.is_finite() {
+        failures.push(format!("NNUE: evaluation of the start position is {}", eval));
+      }
+    }
+
+    match get_book_moves(&start_position.board, false) {
+      Some(moves) if !moves.is_empty() => {},
+      _ => failures.push(String::from("Book: no move for the start position")),
+    }
+
+    // Search with a separate engine, to leave our analysis and cache untouched
+    let mut engine = Engine::new(false);
+    engine.set_nnue(self.nnue.clone());
+    engine.set_use_nnue(self.options.use_nnue);
+    engine.set_use_book(false);
+    engine.set_maximum_depth(2);
+    engine.set_position(SELF_TEST_MATE_FEN);
+    engine.go();
+    match engine.get_best_move() {
+      Some(m) if m.to_string() == SELF_TEST_MATE_MOVE => {},
+      m => failures.push(format!("Search: found {:?} instead of mate in 1 with {} in {}",
+                                 m.map(|m| m.to_string()),
+                                 SELF_TEST_MATE_MOVE,
+                                 SELF_TEST_MATE_FEN)),
+    }
+
+    if failures.is_empty() {
+      Ok(())
+    } else {
+      Err(failures)
+    }
+  }
+
+  /// Checks the best move of the last search for a blunder beyond the search
+  /// horizon. The position after the move is searched shortly, and if the
+  /// opponent has a reply much better than what the search reported, the
+  /// position is searched again deeper. Only done if the `blunder_check`
+  /// option is set.
+  ///
+  /// ### Arguments
+  ///
+  /// * `time_ms`: Time we can spend on the check and on the new search. Too
+  ///   little time skips the check.
+  ///
+  /// ### Return value
+  ///
+  /// True if the move was a blunder and the position was searched again, the
+  /// analysis then contains the result of the new search.
+  pub fn check_for_blunder(&mut self, time_ms: usize) -> bool {
+    if !self.options.blunder_check || time_ms < BLUNDER_CHECK_MIN_TIME_MS {
+      return false;
+    }
+    let best_move = self.get_best_move();
+    let eval = self.get_eval();
+    let (Some(best_move), Some(eval)) = (best_move, eval) else {
+      return false;
+    };
+    let start_time = Instant::now();
+
+    // Search the position after our move with a separate analysis and cache
+    let mut verifier = self.clone();
+    verifier.cache = self.cache.with_separate_evals();
+    verifier.analysis = Analysis::default();
+    verifier.state = EngineState { active:         Arc::new(Mutex::new(false)),
+                                   stop_requested: Arc::new(Mutex::new(false)),
+                                   search_id:      Arc::new(Mutex::new(0)),
+                                   start_time:     Arc::new(Mutex::new(Instant::now())),
+                                   pondering:      Arc::new(Mutex::new(false)), };
+    verifier.ponder = None;
+    verifier.options = EngineOptions { uci: false,
+                                       use_book: false,
+                                       multi_pv: 1,
+                                       max_depth: BLUNDER_CHECK_DEPTH,
+                                       max_search_time: time_ms / 4,
+                                       threads: self.options.threads,
+                                       use_nnue: self.options.use_nnue,
+                                       ..Default::default() };
+    verifier.position = self.position.with_move(&best_move);
+    verifier.go();
+    let Some(verified_eval) = verifier.get_eval() else {
+      return false;
+    };
+
+    let eval_drop = match self.position.board.side_to_play {
+      Color::White => eval - verified_eval,
+      Color::Black => verified_eval - eval,
+    };
+    if eval_drop < BLUNDER_CHECK_MARGIN {
+      return false;
+    }
+
+    info!("{} looks like a blunder in {}: eval {} after the reply instead of {}, searching again",
+          best_move,
+          self.position.to_fen(),
+          format_eval(verified_eval),
+          format_eval(eval));
+    let (max_depth, max_search_time) = (self.options.max_depth, self.options.max_search_time);
+    if max_depth > 0 {
+      self.options.max_depth = self.analysis.get_depth() + BLUNDER_CHECK_EXTRA_DEPTH;
+    }
+    self.options.max_search_time =
+      time_ms.saturating_sub(start_time.elapsed().as_millis() as usize).max(1);
+    self.go();
+    self.options.max_depth = max_depth;
+    self.options.max_search_time = max_search_time;
+    true
+  }
+
+  /// Analyzes a list of positions one after the other, e.g. to answer the
+  /// requests of an analysis server in batch. The engine is reset before each
+  /// position, and each search is limited by the time given with the position.
+  /// The other options, e.g. the number of threads or lines, apply to all the
+  /// searches.
+  ///
+  /// The engine is left on the last valid position, and its search time limit
+  /// is restored.
+  ///
+  /// ### Arguments
+  ///
+  /// * `positions`: FEN of each position, with the time to spend on it in ms
+  ///
+  /// ### Return value
+  ///
+  /// The result of each search, in the order of the positions. Invalid FENs
+  /// are not searched and give the reason why they were rejected instead.
+  pub fn analyze_batch(&mut self,
+                       positions: &[(String, usize)])
+                       -> Vec<Result<SearchResult, FenError>> {
+    let max_search_time = self.options.max_search_time;
+    let mut results = Vec::with_capacity(positions.len());
+
+    for (fen, time_ms) in positions {
+      if let Err(error) = Board::try_from_fen(fen) {
+        warn!("Skipping the invalid FEN {} of the batch: {}", fen, error);
+        results.push(Err(error));
+        continue;
+      }
+
+      self.set_position(fen);
+      self.options.max_search_time = *time_ms;
+      self.go();
+      results.push(Ok(self.get_analysis()));
+    }
+
+    self.options.max_search_time = max_search_time;
+    results
+  }
+
   /// Returns the best eval saved in the analysis
   pub fn get_eval(&self) -> Option<f32> {
     let analysis = self.analysis.result.lock().unwrap();
@@ -546,6 +1784,18 @@ impl Engine {
       return;
     }
 
+    for line in self.get_uci_info() {
+      println!("{}", line);
+    }
+  }
+
+  /// Formats the UCI info lines of the current analysis, one per variation.
+  ///
+  /// ### Return value
+  ///
+  /// Lines such as `info score mate 2 depth 3 seldepth 5 nodes 1234 time 12 pv
+  /// c1b2 d4e3 b6d5`
+  pub fn get_uci_info(&self) -> Vec<String> {
     let result = self.analysis.result.lock().unwrap().clone();
     let depth = self.analysis.get_depth();
     let selective_depth = self.analysis.get_selective_depth();
@@ -553,27 +1803,25 @@ impl Engine {
     let start_time = self.get_start_time();
     let multi_pv_setting = self.options.multi_pv;
 
+    let mut lines = Vec::new();
     for i in 0..min(multi_pv_setting, result.variations.len()) {
-      let eval = result.variations[i].eval;
-      let score_string = if eval.abs() > 100.0 {
-        format!("score mate {}", ((eval.signum() * 200.0) - eval) as isize)
-      } else {
-        format!("score cp {}", (eval * 100.0) as isize)
-      };
       let multi_pv_string = if multi_pv_setting > 1 {
         String::from(format!(" multipv {} ", i + 1))
       } else {
-        String::new()
+        String::from(" ")
       };
-      println!("info {} depth {} seldepth {} nodes {} time {}{}pv {}",
-               score_string,
-               depth,
-               selective_depth,
-               nodes_visited,
-               (Instant::now() - start_time).as_millis(),
-               multi_pv_string,
-               result.variations[i].variation,);
+      let pv = result.variations[i].variation.to_string_with_max_plies(self.options.max_pv_plies);
+      lines.push(format!("info {} depth {} seldepth {} nodes {} time {}{}pv {}",
+                         format_uci_score(result.variations[i].eval),
+                         depth,
+                         selective_depth,
+                         nodes_visited,
+                         (Instant::now() - start_time).as_millis(),
+                         multi_pv_string,
+                         pv));
     }
+
+    lines
   }
 
   /// Prints the best move
@@ -584,12 +1832,52 @@ impl Engine {
     }
   }
 
+  /// Prints debug information for the GUI, if the debug option is set.
+  /// The lines printed during the last search are kept, see `get_debug_info`.
+  ///
+  /// ### Arguments
+  ///
+  /// * `debug_info`: Information to print after `info string`
   pub fn print_debug(&self, debug_info: &str) {
     if self.options.debug {
-      println!("info string {}", debug_info);
+      let line = format!("info string {}", debug_info);
+      println!("{}", line);
+      self.analysis.debug_info.lock().unwrap().push(line);
     }
   }
 
+  /// Returns the debug lines printed during the last search
+  pub fn get_debug_info(&self) -> Vec<String> {
+    self.analysis.debug_info.lock().unwrap().clone()
+  }
+
+  /// Describes what the engine thinks of the position after a completed
+  /// depth: static eval of the position and evaluations of the best root
+  /// moves.
+  ///
+  /// ### Arguments
+  ///
+  /// * `result`: Result of the search for the completed depth
+  ///
+  /// ### Return value
+  ///
+  /// Debug string, e.g.
+  /// `depth 3 static eval 0.12 root moves e2e4 0.35 d2d4 0.30 g1f3 0.21`
+  fn get_root_evals_info(&self, result: &SearchResult) -> String {
+    let root_moves: Vec<String> =
+      result.variations
+            .iter()
+            .take(DEBUG_ROOT_MOVES)
+            .filter_map(|line| {
+              line.variation.get_first_move().map(|mv| format!("{} {}", mv, format_eval(line.eval)))
+            })
+            .collect();
+    format!("depth {} static eval {} root moves {}",
+            self.analysis.get_depth(),
+            format_eval(evaluate_board(&self.position)),
+            root_moves.join(" "))
+  }
+
   /// Prints out the full game history.
   pub fn print_game_summary(&self) {
     println!("Game Summary:\n{}", self.history);
@@ -600,6 +1888,93 @@ impl Engine {
     self.analysis.result.lock().unwrap().clone()
   }
 
+  /// Returns the depth reached by the last search
+  pub fn get_depth(&self) -> usize {
+    self.analysis.get_depth()
+  }
+
+  /// Returns the number of nodes visited by the last search
+  pub fn get_nodes_visited(&self) -> usize {
+    self.analysis.get_nodes_visited()
+  }
+
+  /// Picks randomly one of the best lines of the analysis, among the lines
+  /// whose evaluation is within a margin of the best evaluation.
+  ///
+  /// ### Arguments
+  ///
+  /// * `eval_margin`: Maximum evaluation difference with the best line
+  ///
+  /// ### Return value
+  ///
+  /// Index of the line in the analysis and the line itself. None if the
+  /// analysis is empty.
+  pub fn select_line(&self, eval_margin: f32) -> Option<(usize, VariationWithEval)> {
+    let analysis = self.get_analysis();
+    if analysis.is_empty() {
+      return None;
+    }
+
+    let best_eval = analysis.get(0).eval;
+    let mut cutoff = 1;
+    while analysis.len() > cutoff {
+      let eval = analysis.get(cutoff).eval;
+      if eval.is_nan() || (best_eval - eval).abs() > eval_margin {
+        break;
+      }
+      cutoff += 1;
+    }
+
+    let index = self.rng.lock().unwrap().gen_range(0..cutoff);
+    Some((index, analysis.get(index)))
+  }
+
+  /// Selects a line after leaving the opening book, to diversify the games
+  /// of the engine: during the first `book_exit_randomness_plies` plies out of
+  /// book, a random line is picked among the lines evaluated within
+  /// `book_exit_margin_cp` of the best one. Moves losing more material than
+  /// the margin are never picked.
+  ///
+  /// ### Arguments
+  ///
+  /// * `plies_out_of_book`: Number of plies played since leaving the book
+  ///
+  /// ### Return value
+  ///
+  /// Index of the line in the analysis and the line itself. None if the
+  /// analysis is empty, or if we are too far from the book to randomize.
+  pub fn select_book_exit_line(&self,
+                               plies_out_of_book: usize)
+                               -> Option<(usize, VariationWithEval)> {
+    if plies_out_of_book >= self.options.book_exit_randomness_plies {
+      return None;
+    }
+    let analysis = self.get_analysis();
+    if analysis.is_empty() {
+      return None;
+    }
+
+    let board = &self.position.board;
+    let margin_cp = self.options.book_exit_margin_cp;
+    let best_eval = analysis.get(0).eval;
+    let mut candidates: Vec<usize> = vec![0];
+    for (i, line) in analysis.variations.iter().enumerate().skip(1) {
+      let mv = match line.variation.get_first_move() {
+        Some(mv) => mv,
+        None => continue,
+      };
+      if !line.eval.is_nan()
+         && (best_eval - line.eval).abs() * 100.0 <= margin_cp as f32
+         && board.see_ge(&mv, -(margin_cp as i32))
+      {
+        candidates.push(i);
+      }
+    }
+
+    let index = candidates[self.rng.lock().unwrap().gen_range(0..candidates.len())];
+    Some((index, analysis.get(index)))
+  }
+
   /// Returns a string of the best move continuation (e.g. d1c3 c2c8 f2g3)
   /// based on the board, using the engine cache.
   ///
@@ -626,15 +2001,13 @@ impl Engine {
       return line_string + " - no moves in cache...";
     }
 
-    Engine::find_move_list(&self.cache, &game_state.board);
-    let move_list = self.cache.get_move_list(&game_state.board).unwrap();
+    let move_list = Engine::find_move_list(&self.cache, &game_state.board);
     if move_list.is_empty() {
       return line_string
              + " - Empty move list ?? (check what happened it should not be GameStatus::OnGoing";
     }
     let best_move = move_list[0];
-    let mut best_new_state = game_state.clone();
-    best_new_state.apply_move(&best_move);
+    let best_new_state = game_state.with_move(&best_move);
     if evaluation_cache.eval.is_nan() {
       return best_move.to_string() + " - Not evaluated";
     }
@@ -651,8 +2024,27 @@ impl Engine {
 
     println!("Score for position {}: {}\n{}",
              self.position.to_fen(),
-             position_eval,
-             lines,);
+             format_eval(position_eval),
+             lines.display_lines(self.options.max_pv_plies));
+  }
+
+  /// Prints the best line stored in the cache from the current position, with
+  /// the depth and eval of each entry, followed by the cache table statistics.
+  pub fn print_transposition_table(&self) {
+    let line = self.cache.walk_best_line(&self.position, TRANSPOSITION_TABLE_LINE_MAX_LENGTH);
+    println!("Transposition table line for position {}:",
+             self.position.to_fen());
+    for (i, (mv, entry)) in line.iter().enumerate() {
+      println!("Move {:<2}: {} - Eval {:<7.2} @ depth {} - {:?}",
+               i, mv, entry.eval, entry.depth, entry.game_status);
+    }
+
+    let stats = self.cache.get_eval_table_stats();
+    println!("Entries: {}/{} ({:.2}% full) - Collisions: {}",
+             stats.entries,
+             stats.capacity,
+             stats.occupancy() * 100.0,
+             stats.collisions);
   }
 
   //----------------------------------------------------------------------------
@@ -683,11 +2075,74 @@ impl Engine {
   ///
   /// * cache:      EngineCache where the move list is stored at the end.
   /// * board:      Board configuration to determine a move list
-  fn find_move_list(cache: &EngineCache, board: &Board) {
-    // Check that we know the moves:
-    if !cache.has_move_list(board) {
-      cache.set_move_list(board, &board.get_moves());
+  ///
+  /// ### Return value
+  ///
+  /// The move list of the board. Use it rather than reading the cache again,
+  /// as other search threads may replace the entry in the meantime.
+  fn find_move_list(cache: &EngineCache, board: &Board) -> MoveList {
+    if let Some(move_list) = cache.get_move_list(board) {
+      return move_list;
     }
+    let moves = board.get_moves();
+    cache.set_move_list(board, &moves);
+    MoveList::new_from_slice(&moves)
+  }
+
+  /// Finds the root moves to which the search is restricted, see
+  /// `EngineOptions::search_moves`
+  ///
+  /// ### Arguments
+  ///
+  /// * `board`: Board configuration at the root of the search
+  ///
+  /// ### Return value
+  ///
+  /// Legal moves of the position that are part of the restriction. Empty if
+  /// there is no restriction or if none of its moves is legal.
+  fn get_search_moves(&self, board: &Board) -> Vec<Move> {
+    if self.options.search_moves.is_empty() {
+      return Vec::new();
+    }
+
+    // Moves of the restriction may come from a notation, without flags
+    let legal_moves = Engine::find_move_list(&self.cache, board);
+    legal_moves.into_iter()
+               .filter(|m| {
+                 self.options.search_moves.iter().any(|r| {
+                                                   r.src() == m.src()
+                                                   && r.dest() == m.dest()
+                                                   && r.promotion() == m.promotion()
+                                                 })
+               })
+               .collect()
+  }
+
+  /// Checks if the NNUE is used for the current search. It is not used when
+  /// the search time is below `nnue_min_search_time`, even if `use_nnue` is
+  /// set, as the classical evaluation is faster.
+  ///
+  /// ### Return value
+  ///
+  /// True if the search evaluations are mixed with the NNUE
+  pub fn is_nnue_active(&self) -> bool {
+    let max_time = self.options.max_search_time;
+    self.options.use_nnue && (max_time == 0 || max_time >= self.options.nnue_min_search_time)
+  }
+
+  /// Mixes a static evaluation with the NNUE evaluation of the position
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_state`: Position to evaluate
+  /// * `eval`:       Static evaluation of the position
+  ///
+  /// ### Return value
+  ///
+  /// Average of the static and NNUE evaluations
+  fn blend_nnue_eval(&self, game_state: &GameState, eval: f32) -> f32 {
+    let nnue_eval = self.nnue.lock().unwrap().eval(game_state);
+    eval * 0.5 + nnue_eval * 0.5
   }
 
   /// Updates the Alpha/Beta values based on the eval and side to play
@@ -723,6 +2178,7 @@ impl Engine {
   ///
   /// * `self`: Engine to use to store all the calculations
   /// * `game_state`:    Game state to start from in the evaluation tree
+  /// * `accumulator`: Material and piece-square table scores of `game_state`
   /// * `depth`:      Current depth at which we are in the search
   /// * `max_depth`:  Depth at which to stop
   /// * `alpha`:      Alpha value for the Alpha/Beta pruning
@@ -730,6 +2186,7 @@ impl Engine {
   /// * `start_time`: Time at which we started resolving the chess position
   fn search(&self,
             game_state: &GameState,
+            accumulator: &EvalAccumulator,
             depth: usize,
             max_depth: usize,
             mut alpha: f32,
@@ -744,27 +2201,85 @@ impl Engine {
       return None;
     }
 
+    // Scores are only saved in the transposition table when they are exact
+    let window = (alpha, beta);
+
+    // Futility pruning is never done at the root, where we want all the moves,
+    // nor in check or in endgames, where the static eval is misleading.
+    let can_prune = self.options.futility_pruning
+                    && depth > 1
+                    && game_state.board.checkers == 0
+                    && game_state.board.pieces.all().count_ones() > FUTILITY_MIN_PIECES;
+    let mut static_eval: Option<f32> = None;
+
+    // Reverse futility pruning: if the static eval already beats the score that
+    // the opponent can get elsewhere by a large margin, assume that this node
+    // will not be played.
+    let remaining_depth = max_depth - depth;
+    if can_prune && remaining_depth <= REVERSE_FUTILITY_MAX_DEPTH {
+      let eval = evaluate_board_with_accumulator(game_state, accumulator);
+      static_eval = Some(eval);
+      let margin = REVERSE_FUTILITY_MARGIN * (remaining_depth + 1) as f32;
+      let cutoff = match game_state.board.side_to_play {
+        Color::White => eval - margin >= beta,
+        Color::Black => eval + margin <= alpha,
+      };
+      if cutoff {
+        let mut result = SearchResult::new(1, game_state.board.side_to_play);
+        result.update(VariationWithEval { variation: Variation::new(),
+                                          eval });
+        return Some(result);
+      }
+    }
+
     // Check that we know the moves
-    Engine::find_move_list(&self.cache, &game_state.board);
-    let moves = self.cache.get_move_list(&game_state.board).unwrap();
+    let moves = Engine::find_move_list(&self.cache, &game_state.board);
     let mut result = SearchResult::new(NUMBER_OF_MOVES_IN_SEARCH_RESULTS,
                                        game_state.board.side_to_play);
+    let search_moves =
+      if depth == 1 { self.get_search_moves(&game_state.board) } else { Vec::new() };
 
     for m in moves {
+      // At the root, only search the moves we are restricted to
+      if !search_moves.is_empty() && !search_moves.contains(&m) {
+        continue;
+      }
+
+      // Futility pruning: at frontier nodes, quiet moves will not move the
+      // static eval by much. Skip them if they cannot improve our best score.
+      if can_prune
+         && depth == max_depth
+         && !result.is_empty()
+         && m.is_quiet()
+         && !game_state.board.gives_check(&m)
+      {
+        let eval = *static_eval.get_or_insert_with(|| {
+                                 evaluate_board_with_accumulator(game_state, accumulator)
+                               });
+        let futile = match game_state.board.side_to_play {
+          Color::White => eval + FUTILITY_MARGIN <= alpha,
+          Color::Black => eval - FUTILITY_MARGIN >= beta,
+        };
+        if futile {
+          continue;
+        }
+      }
+
       // println!("Move: {} - alpha-beta: {}/{}", m.to_string(), alpha, beta);
       // Here we have low trust in eval accuracy, so it has to be more than
       // good gap between alpha and beta before we prune.
-      if (alpha - 0.5) > beta {
+      if (alpha - ALPHA_BETA_PRUNING_MARGIN) > beta {
         // TODO: Test this a bit better, I think we are pruning stuff that should not
         // get pruned. println!("Skipping {} as it is pruned
         // {}/{}",game_state.to_fen(), alpha, beta);
         break;
       }
 
-      // If we are looking at a capture, make sure that we analyze possible
-      // recaptures by increasing temporarily the maximum depth
+      // If we are looking at a capture or a promotion, make sure that we analyze
+      // possible recaptures by increasing temporarily the maximum depth. Pawn
+      // captures are too common to be extended.
       let mut max_line_depth = max_depth;
-      if depth == max_depth && m.is_piece_capture() {
+      if depth == max_depth && m.is_tactical() && m.captured_piece() != Some(PieceType::Pawn) {
         if depth < self.analysis.get_depth() + 3 {
           max_line_depth = max_depth + 1;
           self.analysis.update_selective_depth(max_line_depth);
@@ -773,7 +2288,9 @@ impl Engine {
       }
 
       let mut new_game_state = game_state.clone();
-      new_game_state.apply_move(&m);
+      let delta = new_game_state.make_move(&m);
+      let mut new_accumulator = *accumulator;
+      new_accumulator.make_move(&delta, game_state.board.side_to_play);
 
       // Check if we just repeated the position too much or did not make progress.
       let draw = can_declare_draw(&new_game_state);
@@ -797,12 +2314,21 @@ impl Engine {
                                   eval_cache.eval,
                                   &mut alpha,
                                   &mut beta);
-        result.update(VariationWithEval::new_from_move(eval_cache.eval, m));
+        // Scores saved by a deeper search, e.g. from another search thread,
+        // come with a line: pick it up from the cache, so that the variation
+        // leads to the score.
+        let mut variation = VariationWithEval::new_from_move(eval_cache.eval, m);
+        if eval_cache.depth > 1 {
+          for (mv, _) in self.cache.walk_best_line(&new_game_state, VARIATION_LENGTH - 1) {
+            variation.variation.add(mv);
+          }
+        }
+        result.update(variation);
         continue;
       }
 
       if eval_cache.depth == 0 {
-        eval_cache.game_status = is_game_over(&self.cache, &new_game_state.board);
+        eval_cache.game_status = is_game_over(&self.cache, &new_game_state);
       };
 
       // No need to look at other moves in this variation if we found a checkmate for
@@ -811,18 +2337,19 @@ impl Engine {
       if eval_cache.game_status == GameStatus::WhiteWon
          || eval_cache.game_status == GameStatus::BlackWon
       {
-        if is_smothered_mate(&game_state.board, eval_cache.game_status) {
-          // Just assign a higher score to smothered mates
-          if eval_cache.game_status == GameStatus::WhiteWon {
-            eval = 220.0;
-          } else {
-            eval = -220.0;
-          }
+        if is_smothered_mate(&new_game_state.board, eval_cache.game_status) {
+          // Prefer smothered mates, with a bonus small enough to keep the mate
+          // distance decoded from the eval.
+          eval += SMOTHERED_MATE_BONUS * eval.signum();
         }
 
         // FIXME: We should make this a bit smarter, go one level up to save the good
         // move Also if there is an eval swing, not just checkmate.
-        self.cache.add_killer_move(&m);
+        // Captures and promotions are searched anyway, only quiet moves are
+        // worth remembering as killer moves.
+        if m.is_quiet() {
+          self.cache.add_killer_move(&m);
+        }
         Engine::update_alpha_beta(game_state.board.side_to_play, eval, &mut alpha, &mut beta);
         result.update(VariationWithEval::new_from_move(eval, m));
         eval_cache.eval = eval;
@@ -832,15 +2359,26 @@ impl Engine {
       }
 
       // Search more if the game is not over.
+      let mut move_eval: Option<f32> = None;
+      let mut exact_eval = true;
       if eval_cache.game_status == GameStatus::Ongoing {
         if depth < max_line_depth {
-          let sub_result = self.search(&new_game_state, depth + 1, max_line_depth, alpha, beta);
+          let move_window = (alpha, beta);
+          let sub_result = self.search_move(&new_game_state,
+                                            &new_accumulator,
+                                            depth + 1,
+                                            max_line_depth,
+                                            move_window,
+                                            !result.is_empty());
           if sub_result.is_none() {
             continue;
           }
           let mut sub_result = sub_result.unwrap();
           sub_result.push_move_to_variations(m);
           if !sub_result.is_empty() {
+            let sub_eval = sub_result.get(0).eval;
+            move_eval = Some(sub_eval);
+            exact_eval = Engine::is_exact_score(sub_eval, move_window);
             result.update(sub_result.get(0));
             Engine::update_alpha_beta(game_state.board.side_to_play,
                                       result.get_eval().expect("valid eval"),
@@ -849,31 +2387,37 @@ impl Engine {
           }
         } else if eval_cache.game_status == GameStatus::Ongoing && depth >= max_line_depth {
           // Evaluate our position
-          eval = evaluate_board(&new_game_state);
+          eval = evaluate_board_with_accumulator(&new_game_state, &new_accumulator);
           self.analysis.increment_nodes_visited();
 
           // FIXME:  NNUE eval is still too slow, we should implement incremental updates
-          if depth > 10 && self.options.use_nnue == true {
-            let nnue_eval = self.nnue.lock().unwrap().eval(&new_game_state);
-            // println!("board: {} - Eval: {} - NNUE Eval: {} - final eval
-            // {}",new_game_state.to_fen(), eval,nnue_eval,eval * 0.5 + nnue_eval * 0.5);
-            eval = eval * 0.5 + nnue_eval * 0.5;
+          if depth > 10 && self.is_nnue_active() {
+            eval = self.blend_nnue_eval(&new_game_state, eval);
           }
 
           result.update(VariationWithEval::new_from_move(eval, m));
           Engine::update_alpha_beta(game_state.board.side_to_play, eval, &mut alpha, &mut beta);
+          move_eval = Some(eval);
         }
       } else {
         // Here the game is no longer ongoing (draw, etc.)
         Engine::update_alpha_beta(game_state.board.side_to_play, eval, &mut alpha, &mut beta);
         result.update(VariationWithEval::new_from_move(eval, m));
+        move_eval = Some(eval);
       }
 
-      // Save the intermediate result in the transposition table
-      if !result.is_empty() {
-        eval_cache.eval = result.get_eval().expect("Result is not empty, eval should be valid");
-        eval_cache.depth = max_line_depth - depth + 1;
-        self.cache.set_eval(&new_game_state.board, eval_cache);
+      // Save the evaluation of the move in the transposition table. Scores
+      // that are only bounds keep a depth of 0, so that they are never used
+      // instead of searching the position.
+      if let Some(move_eval) = move_eval {
+        if exact_eval {
+          eval_cache.eval = move_eval;
+          eval_cache.depth = max_line_depth - depth + 1;
+          self.cache.set_eval(&new_game_state.board, eval_cache);
+        } else if eval_cache.depth == 0 {
+          eval_cache.eval = move_eval;
+          self.cache.set_eval(&new_game_state.board, eval_cache);
+        }
       }
     } // for m in &moves
 
@@ -882,19 +2426,21 @@ impl Engine {
 
       let best_move = &result.get_best_move().expect("Valid move in non-empty result");
       debug_assert!(!best_move.is_null(), "Best move is NULL.");
-      let mut pv = game_state.clone();
-      pv.apply_move(&best_move);
+      let pv = game_state.with_move(best_move);
       let mut best_move_eval = self.cache.get_eval(&pv.board).unwrap_or_default();
 
-      best_move_eval.depth += 1;
-      best_move_eval.eval = result.get_eval().expect("valid eval in non-empty result");
-      self.cache.set_eval(&game_state.board, best_move_eval);
+      // Outside of the search window, the score is only a bound
+      let eval = result.get_eval().expect("valid eval in non-empty result");
+      if Engine::is_exact_score(eval, window) {
+        best_move_eval.depth += 1;
+        best_move_eval.eval = eval;
+        self.cache.set_eval(&game_state.board, best_move_eval);
+      }
 
       // Influence next visit by promoting the multi_pv best moves to be first
       // in the move list
       let mut top_moves = result.get_top_moves();
-      Engine::find_move_list(&self.cache, &game_state.board);
-      let mut moves = self.cache.get_move_list(&game_state.board).unwrap().to_vec();
+      let mut moves = Engine::find_move_list(&self.cache, &game_state.board).to_vec();
       let initial_length = moves.len();
       moves.retain(|&m| !top_moves.contains(&m));
       top_moves.extend(moves);
@@ -911,6 +2457,79 @@ impl Engine {
     Some(result)
   }
 
+  /// Searches the position after a move. Below the root, moves played after
+  /// the best one found so far are first searched with a null window, which
+  /// only tells if they beat it. They are searched again with the full window
+  /// if they do. At the root, all moves get a full search, as we report the
+  /// evaluation of several lines.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_state`:  Game state after the move
+  /// * `accumulator`: Material and piece-square table scores of `game_state`
+  /// * `depth`:       Depth of `game_state` in the search
+  /// * `max_depth`:   Depth at which to stop
+  /// * `window`:      Alpha and beta values of the position before the move
+  /// * `scout`:       Set to true if a move was already searched in the
+  ///   position before the move
+  ///
+  /// ### Return value
+  ///
+  /// Search result of the position after the move, None if the search stopped
+  fn search_move(&self,
+                 game_state: &GameState,
+                 accumulator: &EvalAccumulator,
+                 depth: usize,
+                 max_depth: usize,
+                 window: (f32, f32),
+                 scout: bool)
+                 -> Option<SearchResult> {
+    // Positions at depth 2 are reached by the root moves. Inside a null window
+    // search, the window is already as narrow as it gets.
+    let (alpha, beta) = window;
+    if !self.options.principal_variation_search || !scout || depth <= 2 || beta - alpha <= PVS_WINDOW
+    {
+      return self.search(game_state, accumulator, depth, max_depth, alpha, beta);
+    }
+
+    // The side that played the move tries to beat its best score so far.
+    // Moves are only pruned once alpha exceeds beta by the pruning margin, so
+    // the bound is moved by the margin to stop as soon as the score is beaten.
+    let (scout_alpha, scout_beta) = match game_state.board.side_to_play {
+      Color::Black => (alpha, alpha - ALPHA_BETA_PRUNING_MARGIN + PVS_WINDOW),
+      Color::White => (beta + ALPHA_BETA_PRUNING_MARGIN - PVS_WINDOW, beta),
+    };
+    let result = self.search(game_state, accumulator, depth, max_depth, scout_alpha, scout_beta)?;
+    let improves = match (result.get_eval(), game_state.board.side_to_play) {
+      (None, _) => true,
+      (Some(eval), Color::Black) => eval > alpha,
+      (Some(eval), Color::White) => eval < beta,
+    };
+    if improves {
+      return self.search(game_state, accumulator, depth, max_depth, alpha, beta);
+    }
+    Some(result)
+  }
+
+  /// Checks if the score of a position is exact, i.e. if no move was pruned
+  /// because of it. Moves are pruned once alpha exceeds beta by the pruning
+  /// margin, so scores beyond the search window by more than the margin are
+  /// only bounds.
+  ///
+  /// ### Arguments
+  ///
+  /// * `eval`:   Score of the position
+  /// * `window`: Alpha and beta values with which the position was searched
+  ///
+  /// ### Return value
+  ///
+  /// True if the score can be saved in the transposition table
+  #[inline]
+  fn is_exact_score(eval: f32, window: (f32, f32)) -> bool {
+    let (alpha, beta) = window;
+    eval >= alpha - ALPHA_BETA_PRUNING_MARGIN && eval <= beta + ALPHA_BETA_PRUNING_MARGIN
+  }
+
   /// Checks the best move in the result and check if it is a winning sequence
   /// for the color indicated in argument
   #[inline]
@@ -918,9 +2537,10 @@ impl Engine {
     if eval.is_nan() {
       return false;
     }
+    let slowest_mate = Eval::Mate(MATING_SEQUENCE_MAX_PLIES);
     match color {
-      Color::White => eval > 150.0,
-      Color::Black => eval < -150.0,
+      Color::White => Eval::from_search_score(eval) > slowest_mate,
+      Color::Black => Eval::from_search_score(eval) < -slowest_mate,
     }
   }
 }
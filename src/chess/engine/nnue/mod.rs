@@ -610,41 +610,25 @@ impl NNUE {
     let mut a0: Array2<f32> = Array2::zeros((Self::LAYER_0_SIZE, input.len()));
 
     for m in 0..input.len() {
-      // ptp : piece to play (side to play) ; op: opposite pieces
-      let flip_board = input[m].board.side_to_play == Color::Black;
-      let (ptp, op) = match input[m].board.side_to_play {
-        Color::White => (input[m].board.pieces.white, input[m].board.pieces.black),
-        Color::Black => (input[m].board.pieces.black, input[m].board.pieces.white),
-      };
+      let side_to_play = input[m].board.side_to_play;
+      let flip_board = side_to_play == Color::Black;
 
       // Let's do: rook (offset = 0), queens (offset = 1 x 64), bishops (offset = 2 x
       // 64), knights (offset = 3 x 64), king (offset = 4 x 64), pawn (offset = 5 x
       // 64)
-      for (mut i, piece) in ptp {
-        if flip_board {
-          i = 63 - i;
-        }
-        match piece {
-          PieceType::King => a0[[i as usize + 4 * 64, m]] = 1.0,
-          PieceType::Queen => a0[[i as usize + 1 * 64, m]] = 1.0,
-          PieceType::Rook => a0[[i as usize, m]] = 1.0,
-          PieceType::Bishop => a0[[i as usize + 2 * 64, m]] = 1.0,
-          PieceType::Knight => a0[[i as usize + 3 * 64, m]] = 1.0,
-          PieceType::Pawn => a0[[i as usize + 5 * 64, m]] = 1.0,
-        }
-      }
-      // Same for opponent pieces, except that we have a 384 offset to everything
-      for (mut i, piece) in op {
+      // Opponent pieces come after, with a 384 offset to everything
+      for (mut i, color, piece) in input[m].board.pieces.iter() {
         if flip_board {
           i = 63 - i;
         }
+        let offset = if color == side_to_play { 0 } else { 384 };
         match piece {
-          PieceType::King => a0[[i as usize + 384 + 4 * 64, m]] = 1.0,
-          PieceType::Queen => a0[[i as usize + 384 + 1 * 64, m]] = 1.0,
-          PieceType::Rook => a0[[i as usize + 384, m]] = 1.0,
-          PieceType::Bishop => a0[[i as usize + 384 + 2 * 64, m]] = 1.0,
-          PieceType::Knight => a0[[i as usize + 384 + 3 * 64, m]] = 1.0,
-          PieceType::Pawn => a0[[i as usize + 384 + 5 * 64, m]] = 1.0,
+          PieceType::King => a0[[i as usize + offset + 4 * 64, m]] = 1.0,
+          PieceType::Queen => a0[[i as usize + offset + 1 * 64, m]] = 1.0,
+          PieceType::Rook => a0[[i as usize + offset, m]] = 1.0,
+          PieceType::Bishop => a0[[i as usize + offset + 2 * 64, m]] = 1.0,
+          PieceType::Knight => a0[[i as usize + offset + 3 * 64, m]] = 1.0,
+          PieceType::Pawn => a0[[i as usize + offset + 5 * 64, m]] = 1.0,
         }
       }
     }
@@ -729,6 +713,35 @@ impl NNUE {
 
     Ok(nnue)
   }
+
+  /// Loads a NNUE from a file and checks that it can evaluate positions,
+  /// i.e. that it has at least one layer after the input layer and a single
+  /// output node.
+  ///
+  /// ### Arguments
+  ///
+  /// * `input_file`: Path of the NNUE file to load
+  ///
+  /// ### Return value
+  ///
+  /// The NNUE, or an error if the file cannot be read or contains an
+  /// unusable net.
+  pub fn load_validated(input_file: &str) -> std::io::Result<Self> {
+    let nnue = Self::load(input_file)?;
+    if nnue.layers.len() < 2 || nnue.layers.last().unwrap().nodes != Self::LAYER_3_SIZE {
+      return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+                                     format!("Unusable NNUE architecture in {input_file}: {}",
+                                             nnue.architecture())));
+    }
+
+    Ok(nnue)
+  }
+
+  /// Describes the NNUE architecture with the number of nodes of each layer,
+  /// e.g. `768-64-8-1`
+  pub fn architecture(&self) -> String {
+    self.layers.iter().map(|l| l.nodes.to_string()).collect::<Vec<String>>().join("-")
+  }
 }
 
 //------------------------------------------------------------------------------
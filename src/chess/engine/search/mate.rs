@@ -0,0 +1,119 @@
+use crate::model::board::Board;
+use crate::model::moves::Move;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Outcome of a mate search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MateSearchResult {
+  /// The side to play forces a checkmate. Contains the first move of the
+  /// mating line and the number of moves, e.g. 2 for a mate in 2
+  Mate(Move, usize),
+  /// There is no forced checkmate within the number of moves searched
+  NoMate,
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Looks for a forced checkmate for the side to play, within a maximum number
+/// of moves (like UCI `go mate N`).
+///
+/// This is a dedicated search that only proves or disproves mates: it does
+/// not evaluate positions. Checking moves are tried first, and only checking
+/// moves are considered for the last move of the sequence. Shorter mates are
+/// searched first, so the mate found is the shortest one.
+///
+/// ### Arguments
+///
+/// * `board`:       Position to search
+/// * `max_moves`:   Maximum number of moves of the side to play until the mate
+/// * `should_stop`: Checked at each position, the search gives up when it
+///   returns true
+///
+/// ### Return value
+///
+/// The shortest forced mate, or `MateSearchResult::NoMate` if there is no
+/// forced mate within `max_moves` or if the search was stopped before finding
+/// one
+pub fn search_mate(board: &Board,
+                   max_moves: usize,
+                   should_stop: &dyn Fn() -> bool)
+                   -> MateSearchResult {
+  for moves in 1..=max_moves {
+    if let Some(mv) = find_mating_move(board, moves, should_stop) {
+      return MateSearchResult::Mate(mv, moves);
+    }
+    if should_stop() {
+      break;
+    }
+  }
+  MateSearchResult::NoMate
+}
+
+/// Finds a move forcing a checkmate in a number of moves, with the attacking
+/// side to play.
+///
+/// ### Arguments
+///
+/// * `board`:       Position, with the attacking side to play
+/// * `moves`:       Number of moves left to deliver the mate
+/// * `should_stop`: Returns true when the search has to give up
+///
+/// ### Return value
+///
+/// A move forcing the mate, None if the mate cannot be forced or the search
+/// was stopped
+fn find_mating_move(board: &Board, moves: usize, should_stop: &dyn Fn() -> bool) -> Option<Move> {
+  if should_stop() {
+    return None;
+  }
+
+  let mut candidates: Vec<(Move, bool)> =
+    board.get_moves().into_iter().map(|m| (m, board.gives_check(&m))).collect();
+  candidates.sort_by_key(|(_, check)| !check);
+
+  for (mv, check) in candidates {
+    // The last move has to deliver the mate
+    if moves == 1 && !check {
+      break;
+    }
+
+    let mut next_board = *board;
+    next_board.apply_move(&mv);
+    if is_mated_within(&next_board, moves - 1, should_stop) {
+      return Some(mv);
+    }
+  }
+  None
+}
+
+/// Checks if the defending side, to play, gets checkmated within a number of
+/// moves whatever it plays.
+///
+/// ### Arguments
+///
+/// * `board`:       Position, with the defending side to play
+/// * `moves`:       Number of moves left to the attacking side to deliver the
+///   mate
+/// * `should_stop`: Returns true when the search has to give up
+///
+/// ### Return value
+///
+/// True if all the replies lose to a forced mate
+fn is_mated_within(board: &Board, moves: usize, should_stop: &dyn Fn() -> bool) -> bool {
+  let replies = board.get_moves();
+  if replies.is_empty() {
+    return board.is_check();
+  }
+  if moves == 0 {
+    return false;
+  }
+
+  replies.iter().all(|reply| {
+                  let mut next_board = *board;
+                  next_board.apply_move(reply);
+                  find_mating_move(&next_board, moves, should_stop).is_some()
+                })
+}
@@ -1,11 +1,22 @@
 // Chess Engine
-use crate::engine::decrement_eval_if_mating_sequence;
+use crate::engine::{decrement_eval_if_mating_sequence, Eval};
 // Chess model
 use crate::model::moves::Move;
 use crate::model::piece::Color;
 use std::fmt::Display;
 
-const VARIATION_LENGTH: usize = 10;
+/// Maximum number of moves stored in a variation
+pub const VARIATION_LENGTH: usize = 10;
+/// Evaluations above this value (in absolute) are mating sequences
+pub const MATE_EVAL_THRESHOLD: f32 = 100.0;
+/// Evaluation of a checkmate, decremented by one for each ply before the mate
+pub const MATE_EVAL: f32 = 200.0;
+/// Evaluation of a clean extra pawn: its material value plus the positional
+/// bonuses it brings on average
+const CLEAN_PAWN_EVAL: f32 = 1.1;
+/// Factor converting evaluations to the centipawns reported to UCI GUIs, so
+/// that being up a clean pawn reports +100
+pub const CENTIPAWN_SCALE: f32 = 100.0 / CLEAN_PAWN_EVAL;
 
 #[derive(Debug, Clone)]
 pub struct Variation {
@@ -71,6 +82,33 @@ impl Variation {
     }
     Some(self.moves[0])
   }
+
+  pub fn get(&self, index: usize) -> Option<Move> {
+    if index >= self.length as usize {
+      return None;
+    }
+    Some(self.moves[index])
+  }
+
+  /// Copies the moves of the variation, in the order they are played
+  pub fn to_vec(&self) -> Vec<Move> {
+    self.moves[..self.length as usize].to_vec()
+  }
+
+  /// Formats the first moves of the variation, separated by spaces.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_plies`: Maximum number of moves to include
+  ///
+  /// ### Return value
+  ///
+  /// String with the moves of the variation, e.g. `e2e4 e7e5 g1f3`
+  pub fn to_string_with_max_plies(&self, max_plies: usize) -> String {
+    let plies = (self.length as usize).min(max_plies);
+    let moves: Vec<String> = self.moves[..plies].iter().map(|m| m.to_string()).collect();
+    moves.join(" ")
+  }
 }
 
 impl Display for Variation {
@@ -206,22 +244,83 @@ impl SearchResult {
     }
     self.sort = Color::opposite(self.sort);
   }
+
+  /// Formats the lines of the result for the console, one line per variation.
+  ///
+  /// ### Arguments
+  ///
+  /// * `max_pv_plies`: Maximum number of moves displayed per variation
+  ///
+  /// ### Return value
+  ///
+  /// Lines such as `Line 0 : Eval #2      @ depth 3 - c1b2 d4e3 b6d5`
+  pub fn display_lines(&self, max_pv_plies: usize) -> String {
+    let mut lines = String::from("Search Result:\n");
+    for (i, v) in self.variations.iter().enumerate() {
+      lines += format!("Line {:<2}: Eval {:<7} @ depth {} - {}\n",
+                       i,
+                       format_eval(v.eval),
+                       v.variation.len(),
+                       v.variation.to_string_with_max_plies(max_pv_plies)).as_str();
+    }
+
+    lines
+  }
 }
 
 impl Display for SearchResult {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    writeln!(f, "Search Result:")?;
-    let mut i = 0;
-    for v in &self.variations {
-      write!(f,
-             "Line {:<2}: Eval {:<7.2} @ depth {} - {}\n",
-             i,
-             v.eval,
-             v.variation.len(),
-             v.variation)?;
-      i += 1;
-    }
+    write!(f, "{}", self.display_lines(VARIATION_LENGTH))
+  }
+}
 
-    Ok(())
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Computes in how many moves a mating sequence ends.
+///
+/// ### Arguments
+///
+/// * `eval`: Evaluation of a line
+///
+/// ### Return value
+///
+/// Number of moves until checkmate, positive if White mates, negative if Black
+/// mates. None if the evaluation is not a mating sequence.
+pub fn mate_distance(eval: f32) -> Option<isize> {
+  if eval.is_nan() {
+    return None;
+  }
+
+  match Eval::from_search_score(eval) {
+    Eval::Mate(plies) => {
+      let plies = plies as isize;
+      Some(((plies.abs() - 1) / 2 + 1) * plies.signum())
+    },
+    Eval::Score(_) => None,
+  }
+}
+
+/// Formats an evaluation for the console, e.g. `0.35` or `#3` for a mate in 3
+///
+/// ### Arguments
+///
+/// * `eval`: Evaluation to format
+pub fn format_eval(eval: f32) -> String {
+  match mate_distance(eval) {
+    Some(moves) => format!("#{}", moves),
+    None => format!("{:.2}", eval),
+  }
+}
+
+/// Formats an evaluation for UCI info, e.g. `score cp 35` or `score mate 3`
+///
+/// ### Arguments
+///
+/// * `eval`: Evaluation to format
+pub fn format_uci_score(eval: f32) -> String {
+  match mate_distance(eval) {
+    Some(moves) => format!("score mate {}", moves),
+    None => format!("score cp {}", (eval * CENTIPAWN_SCALE).round() as isize),
   }
 }
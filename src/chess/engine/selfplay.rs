@@ -0,0 +1,585 @@
+use crate::engine::books::get_book_moves;
+use crate::engine::Engine;
+use crate::model::game_state::{render_line_san, GameState, GameStatus, START_POSITION_FEN};
+use crate::model::moves::Move;
+use crate::model::piece::Color;
+use log::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::fmt::Display;
+
+// -----------------------------------------------------------------------------
+// Constants
+
+/// Games longer than this number of half-moves are adjudicated as draws.
+/// The fifty-move rule should always end the games before that.
+pub const SELFPLAY_MAX_HALF_MOVES: usize = 1000;
+/// Number of half-moves played from the opening book to generate the
+/// openings of a match
+pub const MATCH_OPENING_HALF_MOVES: usize = 6;
+/// Z-score of the 95% confidence interval
+const CONFIDENCE_95_Z_SCORE: f64 = 1.96;
+
+// -----------------------------------------------------------------------------
+// Types
+
+/// Record of a game played between two engines
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+  /// Position from which the game started
+  pub start_fen:          String,
+  /// Moves played in the game
+  pub moves:              Vec<Move>,
+  /// Final status of the game
+  pub result:             GameStatus,
+  /// True if the first engine played with the white pieces
+  pub first_engine_white: bool,
+}
+
+impl GameRecord {
+  /// Returns the result of the game in PGN notation, e.g. "1-0"
+  pub fn result_string(&self) -> &'static str {
+    match self.result {
+      GameStatus::WhiteWon => "1-0",
+      GameStatus::BlackWon => "0-1",
+      GameStatus::Ongoing => "*",
+      GameStatus::Stalemate | GameStatus::ThreeFoldRepetition | GameStatus::Draw => "1/2-1/2",
+    }
+  }
+
+  /// Returns the score of the first engine: 1.0 for a win, 0.5 for a draw and
+  /// 0.0 for a loss.
+  pub fn first_engine_score(&self) -> f32 {
+    match (self.result, self.first_engine_white) {
+      (GameStatus::WhiteWon, true) | (GameStatus::BlackWon, false) => 1.0,
+      (GameStatus::WhiteWon, false) | (GameStatus::BlackWon, true) => 0.0,
+      _ => 0.5,
+    }
+  }
+
+  /// Exports the game in PGN format
+  pub fn to_pgn(&self) -> String {
+    let (white, black) = if self.first_engine_white {
+      ("Engine 1", "Engine 2")
+    } else {
+      ("Engine 2", "Engine 1")
+    };
+
+    let mut pgn = String::new();
+    pgn += "[Event \"Self-play\"]\n";
+    pgn += format!("[White \"{}\"]\n", white).as_str();
+    pgn += format!("[Black \"{}\"]\n", black).as_str();
+    pgn += format!("[Result \"{}\"]\n", self.result_string()).as_str();
+    if self.start_fen != START_POSITION_FEN {
+      pgn += "[SetUp \"1\"]\n";
+      pgn += format!("[FEN \"{}\"]\n", self.start_fen).as_str();
+    }
+    pgn.push('\n');
+
+    let game_state = GameState::from_fen(&self.start_fen);
+    let mut notations: Vec<String> = Vec::with_capacity(self.moves.len() + 1);
+    for (i, san) in render_line_san(&game_state, &self.moves).into_iter().enumerate() {
+      // Plies are even when White is to play
+      let ply = game_state.move_count as usize + i;
+      if ply.is_multiple_of(2) {
+        notations.push(format!("{}.", ply / 2 + 1));
+      } else if i == 0 {
+        notations.push(format!("{}...", ply / 2 + 1));
+      }
+      notations.push(san);
+    }
+    notations.push(self.result_string().to_string());
+
+    pgn += notations.join(" ").as_str();
+    pgn.push('\n');
+    pgn
+  }
+}
+
+/// Outcome of a Sequential Probability Ratio Test
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SprtResult {
+  /// H1 is accepted: engine A is stronger by at least the H1 Elo bound
+  Pass,
+  /// H0 is accepted: engine A is not stronger than the H0 Elo bound
+  Fail,
+  /// More games are needed to conclude
+  Continue,
+}
+
+/// Result of a match between 2 engines, from the point of view of engine A
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct MatchResult {
+  /// Games won by engine A
+  pub wins:   usize,
+  /// Games lost by engine A
+  pub losses: usize,
+  /// Drawn games
+  pub draws:  usize,
+}
+
+impl MatchResult {
+  /// Counts the wins, losses and draws of the first engine in a list of
+  /// games.
+  ///
+  /// ### Arguments
+  ///
+  /// * `records`: Games played between the 2 engines
+  pub fn from_records(records: &[GameRecord]) -> Self {
+    let mut result = MatchResult::default();
+    for record in records {
+      let score = record.first_engine_score();
+      if score == 1.0 {
+        result.wins += 1;
+      } else if score == 0.0 {
+        result.losses += 1;
+      } else {
+        result.draws += 1;
+      }
+    }
+    result
+  }
+
+  /// Returns the number of games played
+  pub fn games(&self) -> usize {
+    self.wins + self.losses + self.draws
+  }
+
+  /// Returns the average score of engine A, between 0.0 and 1.0
+  pub fn score(&self) -> f64 {
+    if self.games() == 0 {
+      return 0.5;
+    }
+    (self.wins as f64 + self.draws as f64 * 0.5) / self.games() as f64
+  }
+
+  /// Returns the variance of the score of a single game
+  fn score_variance(&self) -> f64 {
+    if self.games() == 0 {
+      return 0.0;
+    }
+    let score = self.score();
+    (self.wins as f64 * (1.0 - score).powi(2)
+     + self.losses as f64 * score.powi(2)
+     + self.draws as f64 * (0.5 - score).powi(2))
+    / self.games() as f64
+  }
+
+  /// Estimates the Elo difference between engine A and engine B.
+  /// Infinite if one engine won all the games.
+  pub fn elo_difference(&self) -> f64 {
+    score_to_elo(self.score())
+  }
+
+  /// Returns the error margin of the Elo difference, for a 95% confidence
+  /// interval.
+  pub fn elo_error_margin(&self) -> f64 {
+    if self.games() == 0 {
+      return f64::INFINITY;
+    }
+    let deviation = (self.score_variance() / self.games() as f64).sqrt();
+    let score = self.score();
+    let lower = score_to_elo(score - CONFIDENCE_95_Z_SCORE * deviation);
+    let upper = score_to_elo(score + CONFIDENCE_95_Z_SCORE * deviation);
+    if lower.is_infinite() || upper.is_infinite() {
+      return f64::INFINITY;
+    }
+    (upper - lower) / 2.0
+  }
+
+  /// Computes the log-likelihood ratio of H1 (Elo difference is `elo_1`)
+  /// against H0 (Elo difference is `elo_0`).
+  ///
+  /// ### Arguments
+  ///
+  /// * `elo_0`: Elo difference of the null hypothesis
+  /// * `elo_1`: Elo difference of the alternative hypothesis
+  pub fn log_likelihood_ratio(&self, elo_0: f64, elo_1: f64) -> f64 {
+    let variance = self.score_variance();
+    if variance == 0.0 {
+      return 0.0;
+    }
+    let score_0 = elo_to_score(elo_0);
+    let score_1 = elo_to_score(elo_1);
+    self.games() as f64 * (score_1 - score_0) * (2.0 * self.score() - score_0 - score_1)
+    / (2.0 * variance)
+  }
+
+  /// Runs a Sequential Probability Ratio Test on the match result.
+  ///
+  /// ### Arguments
+  ///
+  /// * `elo_0`: Elo difference of the null hypothesis, e.g. 0.0
+  /// * `elo_1`: Elo difference of the alternative hypothesis, e.g. 10.0
+  /// * `alpha`: Probability of accepting H1 while H0 is true, e.g. 0.05
+  /// * `beta`:  Probability of accepting H0 while H1 is true, e.g. 0.05
+  pub fn sprt(&self, elo_0: f64, elo_1: f64, alpha: f64, beta: f64) -> SprtResult {
+    let llr = self.log_likelihood_ratio(elo_0, elo_1);
+    if llr >= ((1.0 - beta) / alpha).ln() {
+      SprtResult::Pass
+    } else if llr <= (beta / (1.0 - alpha)).ln() {
+      SprtResult::Fail
+    } else {
+      SprtResult::Continue
+    }
+  }
+}
+
+impl Display for MatchResult {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f,
+           "Games: {} - W: {} L: {} D: {} - Score: {:.1}% - Elo: {:.1} +/- {:.1}",
+           self.games(),
+           self.wins,
+           self.losses,
+           self.draws,
+           self.score() * 100.0,
+           self.elo_difference(),
+           self.elo_error_margin())
+  }
+}
+
+// -----------------------------------------------------------------------------
+// Functions
+
+/// Plays games between two engines with default options, from the start
+/// position.
+///
+/// ### Arguments
+///
+/// * `games`:   Number of games to play
+/// * `time_ms`: Search time per move, in milliseconds
+///
+/// ### Return value
+///
+/// Records of all the games played
+pub fn selfplay(games: usize, time_ms: u64) -> Vec<GameRecord> {
+  let mut engine_1 = Engine::new(false);
+  let mut engine_2 = Engine::new(false);
+  selfplay_with_engines(&mut engine_1, &mut engine_2, games, time_ms, &[])
+}
+
+/// Plays games between two engines, which can be configured with different
+/// options. The engines alternate colors, and each opening is played once
+/// with each color.
+///
+/// ### Arguments
+///
+/// * `engine_1`: First engine, playing white in the first game
+/// * `engine_2`: Second engine
+/// * `games`:    Number of games to play
+/// * `time_ms`:  Search time per move, in milliseconds
+/// * `openings`: FENs of the start positions to use. Empty to start all the
+///   games from the start position.
+///
+/// ### Return value
+///
+/// Records of all the games played
+pub fn selfplay_with_engines(engine_1: &mut Engine,
+                             engine_2: &mut Engine,
+                             games: usize,
+                             time_ms: u64,
+                             openings: &[&str])
+                             -> Vec<GameRecord> {
+  let mut records = Vec::with_capacity(games);
+  for i in 0..games {
+    let start_fen = if openings.is_empty() {
+      START_POSITION_FEN
+    } else {
+      openings[(i / 2) % openings.len()]
+    };
+    let first_engine_white = i % 2 == 0;
+
+    let record = if first_engine_white {
+      play_game(engine_1, engine_2, start_fen, time_ms)
+    } else {
+      play_game(engine_2, engine_1, start_fen, time_ms)
+    };
+    let record = GameRecord { first_engine_white,
+                              ..record };
+
+    info!("Self-play game {}/{}: {} after {} half-moves",
+          i + 1,
+          games,
+          record.result_string(),
+          record.moves.len());
+    records.push(record);
+  }
+
+  records
+}
+
+/// Plays a single game between two engines.
+///
+/// ### Arguments
+///
+/// * `white`:     Engine playing with the white pieces
+/// * `black`:     Engine playing with the black pieces
+/// * `start_fen`: Position from which the game starts
+/// * `time_ms`:   Search time per move, in milliseconds
+///
+/// ### Return value
+///
+/// Record of the game, assuming the white engine is the first engine
+pub fn play_game(white: &mut Engine,
+                 black: &mut Engine,
+                 start_fen: &str,
+                 time_ms: u64)
+                 -> GameRecord {
+  let mut game_state = GameState::from_fen(start_fen);
+  let mut moves: Vec<Move> = Vec::new();
+
+  for engine in [&mut *white, &mut *black] {
+    engine.set_position(start_fen);
+    engine.set_search_time_limit(time_ms as usize);
+  }
+
+  let mut result = game_state.game_status();
+  while result == GameStatus::Ongoing {
+    if moves.len() >= SELFPLAY_MAX_HALF_MOVES {
+      warn!("Self-play game too long, adjudicating a draw. Position: {}",
+            game_state.to_fen());
+      result = GameStatus::Draw;
+      break;
+    }
+
+    let engine = match game_state.board.side_to_play {
+      Color::White => &mut *white,
+      Color::Black => &mut *black,
+    };
+    engine.go();
+    let mv = engine.get_best_move();
+    if mv.is_none() {
+      error!("Engine did not find a move in position {}",
+             game_state.to_fen());
+      break;
+    }
+    let mv = mv.unwrap();
+
+    game_state.apply_move(&mv);
+    white.apply_move(&mv.to_string());
+    black.apply_move(&mv.to_string());
+    moves.push(mv);
+
+    result = game_state.game_status();
+  }
+
+  GameRecord { start_fen: start_fen.to_string(),
+               moves,
+               result,
+               first_engine_white: true }
+}
+
+/// Converts an average score into an Elo difference
+///
+/// ### Arguments
+///
+/// * `score`: Average score, between 0.0 and 1.0
+fn score_to_elo(score: f64) -> f64 {
+  if score <= 0.0 {
+    return f64::NEG_INFINITY;
+  }
+  if score >= 1.0 {
+    return f64::INFINITY;
+  }
+  -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Converts an Elo difference into an expected average score
+///
+/// ### Arguments
+///
+/// * `elo`: Elo difference
+fn elo_to_score(elo: f64) -> f64 {
+  1.0 / (1.0 + 10.0_f64.powf(-elo / 400.0))
+}
+
+/// Generates opening positions by playing random moves from the opening book,
+/// starting from the start position. Use a seeded generator to get
+/// reproducible openings.
+///
+/// ### Arguments
+///
+/// * `rng`:        Random number generator used to pick the book moves
+/// * `count`:      Number of openings to generate
+/// * `half_moves`: Number of book moves to play in each opening. Openings stop
+///   earlier if we run out of book moves.
+///
+/// ### Return value
+///
+/// FENs of the openings
+pub fn generate_book_openings<R: Rng>(rng: &mut R, count: usize, half_moves: usize) -> Vec<String> {
+  let mut openings = Vec::with_capacity(count);
+  for _ in 0..count {
+    let mut game_state = GameState::from_fen(START_POSITION_FEN);
+    for _ in 0..half_moves {
+      let book_moves = get_book_moves(&game_state.board, false);
+      if book_moves.is_none() {
+        break;
+      }
+      match book_moves.unwrap().choose(rng) {
+        Some(mv) => game_state.apply_move(mv),
+        None => break,
+      }
+    }
+    openings.push(game_state.to_fen());
+  }
+  openings
+}
+
+/// Plays a match between two engines and reports the result from the point of
+/// view of engine A. Each opening is played twice, with colors swapped.
+///
+/// ### Arguments
+///
+/// * `engine_a`: Engine that we want to evaluate
+/// * `engine_b`: Reference engine
+/// * `games`:    Number of games to play
+/// * `openings`: FENs of the start positions. If empty, openings are generated
+///   from the opening book, using the random number generator of engine A.
+/// * `time_ms`:  Search time per move, in milliseconds
+///
+/// ### Return value
+///
+/// Wins, losses and draws of engine A
+pub fn run_match(engine_a: &mut Engine,
+                 engine_b: &mut Engine,
+                 games: usize,
+                 openings: &[&str],
+                 time_ms: u64)
+                 -> MatchResult {
+  let book_openings = if openings.is_empty() {
+    // Engine A seeds the openings, so that matches can be reproduced
+    let mut rng = engine_a.rng.lock().unwrap();
+    generate_book_openings(&mut *rng, games.div_ceil(2), MATCH_OPENING_HALF_MOVES)
+  } else {
+    Vec::new()
+  };
+  let book_openings: Vec<&str> = book_openings.iter().map(|fen| fen.as_str()).collect();
+  let openings = if openings.is_empty() { book_openings.as_slice() } else { openings };
+
+  let records = selfplay_with_engines(engine_a, engine_b, games, time_ms, openings);
+  let result = MatchResult::from_records(&records);
+  info!("Match result: {}", result);
+  result
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_selfplay_terminates_with_a_result() {
+    let records = selfplay(2, 10);
+    assert_eq!(2, records.len());
+    assert!(records[0].first_engine_white);
+    assert!(!records[1].first_engine_white);
+
+    for record in &records {
+      assert_ne!(GameStatus::Ongoing, record.result);
+      assert!(!record.moves.is_empty());
+
+      // All the moves are legal
+      let mut game_state = GameState::from_fen(&record.start_fen);
+      for mv in &record.moves {
+        assert!(game_state.get_moves().contains(mv));
+        game_state.apply_move(mv);
+      }
+
+      let pgn = record.to_pgn();
+      println!("{}", pgn);
+      assert!(pgn.contains(format!("[Result \"{}\"]", record.result_string()).as_str()));
+      assert!(pgn.trim_end().ends_with(record.result_string()));
+      assert!(pgn.contains("1. "));
+    }
+  }
+
+  #[test]
+  fn test_book_openings_are_reproducible() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    Engine::new(false); // Loads the books
+    let openings = generate_book_openings(&mut StdRng::seed_from_u64(1234), 8, 6);
+    assert_eq!(8, openings.len());
+    assert_ne!(START_POSITION_FEN, openings[0]);
+    assert_eq!(openings,
+               generate_book_openings(&mut StdRng::seed_from_u64(1234), 8, 6));
+  }
+
+  #[test]
+  fn test_game_record_to_pgn() {
+    let record = GameRecord { start_fen:          String::from("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"),
+                              moves:              Move::string_to_vec("e8d7 a1a7 d7c6"),
+                              result:             GameStatus::Ongoing,
+                              first_engine_white: false, };
+    let expected = "[Event \"Self-play\"]\n[White \"Engine 2\"]\n[Black \"Engine 1\"]\n[Result \"*\"]\n\
+                    [SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n\n1... Kd7 2. Ra7+ Kc6 *\n";
+    assert_eq!(expected, record.to_pgn());
+    assert_eq!(0.5, record.first_engine_score());
+  }
+
+  #[test]
+  fn test_match_result_statistics() {
+    let result = MatchResult { wins:   60,
+                               losses: 40,
+                               draws:  100, };
+    assert_eq!(200, result.games());
+    assert_eq!(0.55, result.score());
+    assert!((result.elo_difference() - 34.86).abs() < 0.01);
+    assert!(result.elo_error_margin() > 20.0 && result.elo_error_margin() < 40.0);
+    assert_eq!(SprtResult::Continue, result.sprt(0.0, 10.0, 0.05, 0.05));
+
+    let result = MatchResult { wins:   600,
+                               losses: 400,
+                               draws:  1000, };
+    assert_eq!(SprtResult::Pass, result.sprt(0.0, 10.0, 0.05, 0.05));
+    let result = MatchResult { wins:   400,
+                               losses: 600,
+                               draws:  1000, };
+    assert_eq!(SprtResult::Fail, result.sprt(0.0, 10.0, 0.05, 0.05));
+
+    let result = MatchResult { wins:   4,
+                               losses: 0,
+                               draws:  0, };
+    assert_eq!(f64::INFINITY, result.elo_difference());
+    assert_eq!(f64::INFINITY, result.elo_error_margin());
+    println!("{}", result);
+  }
+
+  #[test]
+  fn test_strong_engine_wins_the_match() {
+    // Limit the engines by depth rather than time, so that the result does not
+    // depend on the load of the machine running the test
+    let mut strong_engine = Engine::new(false);
+    strong_engine.set_maximum_depth(3);
+    let mut weak_engine = Engine::new(false);
+    weak_engine.set_maximum_depth(1);
+
+    let result = run_match(&mut strong_engine, &mut weak_engine, 4, &[], 0);
+    println!("{}", result);
+    assert_eq!(4, result.games());
+    assert!(result.elo_difference() > 100.0);
+  }
+
+  #[test]
+  fn test_elo_limited_engines_are_ordered() {
+    // The depth is capped by the Elo limit, seeds make the games reproducible
+    let mut strong_engine = Engine::new(false);
+    strong_engine.set_elo_limit(Some(2000));
+    strong_engine.set_seed(1);
+    let mut weak_engine = Engine::new(false);
+    weak_engine.set_elo_limit(Some(1200));
+    weak_engine.set_seed(2);
+    strong_engine.set_maximum_threads(1);
+    weak_engine.set_maximum_threads(1);
+
+    let result = run_match(&mut strong_engine, &mut weak_engine, 4, &[START_POSITION_FEN], 0);
+    println!("{}", result);
+    assert_eq!(4, result.games());
+    assert!(result.score() > 0.5);
+  }
+}
@@ -153,8 +153,8 @@ impl EndgameSquareTable {
     -5,   0,   0,   0,   0,   0,   0,  -5, // 4th row
     -5,   0,   0,   0,   0,   0,   0,  -5, // 5th row.
     -5,   0,   0,   0,   0,   0,   0,  -5, // 6th row
-     5,  10,  10,  10,  10,  10,  10,   5, // 7th row
-     0,   0,   0,   0,   0,   0,   0,   0, // 8th row 
+    -5,   0,   0,   0,   0,   0,   0,  -5, // 7th row
+     0,   0,   0,   5,   5,   0,   0,   0, // 8th row
   ];
 }
 
@@ -184,7 +184,18 @@ impl SquareTable {
     -20, -10, -10, -10, -10, -10, -10, -20, // 8th row
   ];
   
-  pub const KNIGHT: [isize; 64] = [
+  pub const WHITE_KNIGHT: [isize; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50, // 1st row
+    -40, -20,   0,   5,   5,   0, -20, -40, // 2nd row
+    -30,   5,  10,  15,  15,  10,   5, -30, // 3rd row
+    -30,   0,  15,  20,  20,  15,   0, -30, // 4th row
+    -30,   5,  15,  20,  20,  15,   5, -30, // 5th row
+    -30,   0,  10,  15,  15,  10,   0, -30, // 6th row
+    -40, -20,   0,   0,   0,   0, -20, -40, // 7th row
+    -50, -40, -30, -30, -30, -30, -40, -50, // 8th row
+  ];
+
+  pub const BLACK_KNIGHT: [isize; 64] = [
     -50, -40, -30, -30, -30, -30, -40, -50, // 1st row
     -40, -20,   0,   0,   0,   0, -20, -40, // 2nd row
     -30,   0,  10,  15,  15,  10,   0, -30, // 3rd row
@@ -1,5 +1,8 @@
 //------------------------------------------------------------------------------
 // Engine black-box Tests, checking sanity
+use crate::engine::config::options::available_threads;
+use crate::engine::search_result::{format_eval, format_uci_score, mate_distance,
+                                    VARIATION_LENGTH};
 use crate::engine::*;
 
 #[test]
@@ -560,8 +563,7 @@ fn test_sorting_moves_without_eval() {
   let game_state = GameState::from_fen(fen);
 
   let engine = Engine::new(false);
-  Engine::find_move_list(&engine.cache, &game_state.board);
-  let move_list = engine.cache.get_move_list(&game_state.board).unwrap();
+  let move_list = Engine::find_move_list(&engine.cache, &game_state.board);
   for m in move_list.get_moves() {
     println!("Move: {}", m.to_string());
   }
@@ -754,3 +756,924 @@ fn king_disappeared() {
   assert_ne!(best_move, Move::default());
   assert!(!analysis.is_empty());
 }
+
+#[test]
+fn test_ponderhit_keeps_the_ponder_search() {
+  let fen = "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5";
+  let mut engine = Engine::new(false);
+  engine.set_position(fen);
+  engine.options.max_depth = 4;
+  engine.go();
+  let our_move = engine.get_best_move().unwrap();
+
+  let expected_reply = engine.ponder(&our_move).expect("Analysis should have an expected reply");
+  assert!(engine.is_pondering());
+  assert!(engine.is_active());
+  std::thread::sleep(Duration::from_millis(200));
+
+  // Opponent plays the expected move, the ponder search carries on
+  let nodes_before_ponderhit = engine.analysis.get_nodes_visited();
+  assert!(engine.apply_opponent_move(expected_reply.to_string().as_str()));
+  assert!(!engine.is_pondering());
+  while engine.is_active() {
+    std::thread::sleep(Duration::from_millis(10));
+  }
+  let nodes_after_ponderhit = engine.analysis.get_nodes_visited() - nodes_before_ponderhit;
+  assert!(engine.get_best_move().is_some());
+
+  // Compare with a cold search of the same position
+  let mut cold_engine = Engine::new(false);
+  cold_engine.set_position(engine.position.to_fen().as_str());
+  cold_engine.options.max_depth = 4;
+  cold_engine.go();
+  let cold_nodes = cold_engine.analysis.get_nodes_visited();
+
+  println!("Nodes after ponderhit: {nodes_after_ponderhit} - Cold search nodes: {cold_nodes}");
+  assert!(nodes_after_ponderhit < cold_nodes);
+}
+
+#[test]
+fn test_ponder_without_expected_reply() {
+  let mut engine = Engine::new(false);
+  engine.set_position("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5");
+
+  // No analysis yet, so we cannot predict the reply.
+  assert!(engine.ponder(&Move::from_string("e8g8")).is_none());
+  assert!(!engine.is_pondering());
+  assert!(!engine.is_active());
+  assert_eq!("r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R w KQ - 1 6",
+             engine.position.to_fen());
+
+  // A wrong guess is discarded
+  engine.set_position("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5");
+  engine.options.max_depth = 3;
+  engine.go();
+  let our_move = engine.get_best_move().unwrap();
+  let mut expected_position = engine.position.clone();
+  expected_position.apply_move(&our_move);
+
+  let expected_reply = engine.ponder(&our_move).unwrap();
+  let other_reply = expected_position.get_moves()
+                                     .into_iter()
+                                     .find(|m| *m != expected_reply)
+                                     .unwrap();
+  assert!(!engine.apply_opponent_move(other_reply.to_string().as_str()));
+  expected_position.apply_move(&other_reply);
+  assert!(!engine.is_pondering());
+  assert!(!engine.is_active());
+  assert_eq!(expected_position.to_fen(), engine.position.to_fen());
+}
+
+#[test]
+fn test_ponderhit_rate() {
+  let fen = "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5";
+  let mut engine = Engine::new(false);
+  engine.set_position(fen);
+  engine.options.max_depth = 3;
+  assert_eq!(None, engine.get_ponder_stats().hit_rate());
+
+  // The opponent plays the expected reply once out of 4
+  for i in 0..4 {
+    engine.go();
+    let our_move = engine.get_best_move().unwrap();
+    let position = engine.position.with_move(&our_move);
+
+    let expected_reply = engine.ponder(&our_move).unwrap();
+    let reply = match i {
+      0 => expected_reply,
+      _ => position.get_moves().into_iter().find(|m| *m != expected_reply).unwrap(),
+    };
+    engine.apply_opponent_move(reply.to_string().as_str());
+    engine.stop();
+    while engine.is_active() {
+      std::thread::sleep(Duration::from_millis(10));
+    }
+  }
+
+  let stats = engine.get_ponder_stats();
+  assert_eq!(PonderStats { predictions: 4,
+                           hits:        1, },
+             stats);
+  assert_eq!(Some(0.25), stats.hit_rate());
+
+  // Moves played without pondering do not count
+  let our_move = engine.position.get_moves()[0].to_string();
+  engine.apply_move(our_move.as_str());
+  let reply = engine.position.get_moves()[0].to_string();
+  engine.apply_opponent_move(reply.as_str());
+  assert_eq!(stats, engine.get_ponder_stats());
+
+  engine.reset();
+  assert_eq!(None, engine.get_ponder_stats().hit_rate());
+}
+
+#[test]
+fn test_seeded_engines_make_the_same_choices() {
+  let mut engine_1 = Engine::new(false);
+  let mut engine_2 = Engine::new(false);
+  engine_1.set_seed(1234);
+  engine_2.set_seed(1234);
+
+  // Book moves are shuffled with the seeded generator
+  engine_1.go();
+  engine_2.go();
+  assert_eq!(engine_1.get_best_move(), engine_2.get_best_move());
+
+  // All book moves are evaluated equally, so the line selection is random too
+  let (index_1, line_1) = engine_1.select_line(0.015).unwrap();
+  let (index_2, line_2) = engine_2.select_line(0.015).unwrap();
+  assert_eq!(index_1, index_2);
+  assert_eq!(line_1.variation.get_first_move(), line_2.variation.get_first_move());
+
+  // Same sequence again after re-seeding
+  engine_1.set_seed(1234);
+  engine_1.go();
+  assert_eq!(engine_1.get_best_move(), engine_2.get_best_move());
+  assert_eq!(index_1, engine_1.select_line(0.015).unwrap().0);
+}
+
+#[test]
+fn test_aggressive_play_style_prefers_tactical_moves() {
+  let mut engine = Engine::new(false);
+  engine.set_position("4k3/8/8/8/8/8/5PPP/3Q2K1 w - - 0 1");
+  let moves = engine.position.board.get_moves();
+  let find_move = |notation: &str| *moves.iter().find(|m| m.to_string() == notation).unwrap();
+  let check = find_move("d1a4");
+  let quiet = find_move("h2h3");
+  let losing_check = find_move("d1d8");
+
+  let mut result = SearchResult::new(3, Color::White);
+  result.update(VariationWithEval::new_from_move(5.0, quiet));
+  result.update(VariationWithEval::new_from_move(4.9, check));
+  result.update(VariationWithEval::new_from_move(-4.0, losing_check));
+
+  // Normal play style plays the best move
+  assert_eq!(None, engine.apply_play_style(&result));
+
+  // Aggressive play style picks the check when it is roughly equal, but never
+  // plays a clearly losing move
+  engine.options.play_style = PlayStyle::Aggressive;
+  assert_eq!(Some(check), engine.apply_play_style(&result));
+
+  // The analysis still reports the best line
+  engine.analysis.update_result(result);
+  engine.analysis.set_selected_move(Some(check));
+  assert_eq!(Some(check), engine.get_best_move());
+  assert_eq!(Some(quiet), engine.get_analysis().get_best_move());
+  assert_eq!(vec![quiet], engine.get_pv());
+}
+
+#[test]
+fn test_elo_limit_keeps_the_best_line_in_the_analysis() {
+  let mut engine = Engine::new(false);
+  engine.set_position("4k3/8/8/8/8/8/5PPP/3Q2K1 w - - 0 1");
+  engine.set_elo_limit(Some(ELO_LIMIT_MIN));
+  let moves = engine.position.board.get_moves();
+  let find_move = |notation: &str| *moves.iter().find(|m| m.to_string() == notation).unwrap();
+  let best = find_move("h2h3");
+  let weaker = find_move("g2g3");
+
+  let mut result = SearchResult::new(2, Color::White);
+  result.update(VariationWithEval::new_from_move(5.0, best));
+  result.update(VariationWithEval::new_from_move(4.9, weaker));
+
+  // The weaker move gets picked from time to time, the analysis stays intact
+  let mut weaker_move_played = false;
+  for seed in 0..50 {
+    engine.set_seed(seed);
+    engine.analysis.update_result(result.clone());
+    engine.analysis.set_selected_move(None);
+    engine.apply_elo_limit();
+    weaker_move_played |= engine.get_best_move() == Some(weaker);
+    assert_eq!(Some(best), engine.get_analysis().get_best_move());
+    assert_eq!(Some(5.0), engine.get_analysis().get_eval());
+  }
+  assert!(weaker_move_played);
+}
+
+#[test]
+fn test_engine_option_setters() {
+  let mut engine = Engine::new(false);
+  engine.set_ponder(true);
+  engine.set_maximum_depth(7);
+  engine.set_search_time_limit(1234);
+  engine.set_maximum_threads(4);
+  engine.set_use_nnue(true);
+  engine.set_debug(true);
+  engine.set_play_style(PlayStyle::Aggressive);
+  engine.set_multipv(2);
+  assert!(engine.options.ponder);
+  assert_eq!(7, engine.options.max_depth);
+  assert_eq!(1234, engine.options.max_search_time);
+  assert_eq!(4, engine.options.threads);
+  assert!(engine.options.use_nnue);
+  assert!(engine.options.debug);
+  assert_eq!(PlayStyle::Aggressive, engine.options.play_style);
+  assert_eq!(2, engine.options.multi_pv);
+
+  // Invalid values are clamped
+  engine.set_maximum_threads(0);
+  assert_eq!(1, engine.options.threads);
+  engine.set_multipv(0);
+  assert_eq!(1, engine.options.multi_pv);
+
+  // The number of lines returned follows the multi_pv setting
+  let mut engine = Engine::new(false);
+  engine.set_multipv(3);
+  engine.go();
+  assert_eq!(3, engine.get_analysis().len());
+
+  engine.set_multipv(0);
+  engine.go();
+  assert_eq!(1, engine.get_analysis().len());
+}
+
+#[test]
+fn engine_without_book_searches_the_start_position() {
+  let mut engine = Engine::new(false);
+  engine.set_maximum_depth(3);
+  engine.go();
+  // Book move, nothing searched
+  assert_eq!(0, engine.analysis.get_nodes_visited());
+
+  engine.set_use_book(false);
+  engine.go();
+  assert!(engine.analysis.get_nodes_visited() > 0);
+  assert_eq!(3, engine.analysis.get_depth());
+  assert!(engine.get_best_move().is_some());
+}
+
+#[test]
+fn engine_undo_moves() {
+  let mut engine = Engine::new(false);
+  let mut fens = vec![engine.position.to_fen()];
+  for mv in ["e2e4", "e7e5", "g1f3"] {
+    engine.apply_move(mv);
+    fens.push(engine.position.to_fen());
+  }
+  assert_eq!("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+             fens[3]);
+
+  // Take back the moves one by one
+  for fen in fens.iter().rev().skip(1) {
+    engine.undo_move();
+    assert_eq!(*fen, engine.position.to_fen());
+  }
+  assert_eq!(START_POSITION_FEN, engine.position.to_fen());
+
+  // Nothing to undo at the root
+  engine.undo_move();
+  assert_eq!(START_POSITION_FEN, engine.position.to_fen());
+
+  // Invalid moves are not recorded
+  engine.apply_move("e2e5");
+  engine.undo_move();
+  assert_eq!(START_POSITION_FEN, engine.position.to_fen());
+}
+
+#[test]
+fn engine_futility_pruning() {
+  // Quiet middlegame position: pruning reduces the number of nodes we look at
+  let fen = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8";
+  let mut nodes = Vec::new();
+  for futility_pruning in [true, false] {
+    let mut engine = Engine::new(false);
+    engine.set_position(fen);
+    engine.options.max_depth = 4;
+    engine.options.futility_pruning = futility_pruning;
+    engine.go();
+    nodes.push(engine.analysis.get_nodes_visited());
+  }
+  println!("Nodes visited with/without futility pruning: {:?}", nodes);
+  assert!(nodes[0] < nodes[1]);
+
+  // Tactical positions: the best move is the same with and without pruning
+  let positions = [("1n4nr/5ppp/1N6/1P2p3/1P6/4kP2/1B1NP1PP/R3KB1R w KQ - 1 36", 2),
+                   ("8/8/2p1pkp1/p3p3/P1P1P1P1/6q1/7q/3K4 b - - 2 55", 2),
+                   ("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35", 3),
+                   ("r1bqk2r/ppppbp1p/2n5/3Bp1pQ/4P3/3P4/PPPN1PPP/R3K1NR b KQq - 0 7", 4)];
+  for (fen, depth) in positions {
+    let mut best_moves = Vec::new();
+    for futility_pruning in [true, false] {
+      let mut engine = Engine::new(false);
+      engine.set_position(fen);
+      engine.options.max_depth = depth;
+      engine.options.futility_pruning = futility_pruning;
+      engine.go();
+      best_moves.push(engine.get_best_move().unwrap());
+    }
+    assert_eq!(best_moves[0], best_moves[1], "Best move changed for {fen}");
+  }
+}
+
+#[test]
+fn engine_transposition_table_line_matches_best_line() {
+  // Forced checkmate in 2: c1b2 d4e3 b6d5
+  let mut engine = Engine::new(false);
+  engine.set_position("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35");
+  engine.options.max_depth = 3;
+  engine.go();
+  engine.print_transposition_table();
+
+  let best_line = engine.get_analysis().get(0);
+  let tt_line = engine.cache.walk_best_line(&engine.position, 30);
+  assert_eq!(best_line.variation.len(), tt_line.len());
+  for (i, (mv, _)) in tt_line.iter().enumerate() {
+    assert_eq!(best_line.variation.get(i), Some(*mv));
+  }
+  assert_eq!(best_line.eval, tt_line[0].1.eval);
+  assert_eq!(GameStatus::WhiteWon, tt_line.last().unwrap().1.game_status);
+
+  let stats = engine.cache.get_eval_table_stats();
+  assert!(stats.entries > 0);
+  assert!(stats.entries <= stats.capacity);
+  assert!(stats.occupancy() > 0.0);
+}
+
+#[test]
+fn engine_display_mate_distance_and_pv_length() {
+  // Forced checkmate in 2: c1b2 d4e3 b6d5
+  let mut engine = Engine::new(false);
+  engine.set_position("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35");
+  engine.options.max_depth = 3;
+  engine.set_multipv(1);
+  engine.go();
+
+  let analysis = engine.get_analysis();
+  let display = analysis.display_lines(VARIATION_LENGTH);
+  println!("{display}");
+  assert!(display.contains("Eval #2 "));
+  assert!(display.contains("c1b2 d4e3 b6d5"));
+  let uci_info = engine.get_uci_info();
+  println!("{:?}", uci_info);
+  assert_eq!(1, uci_info.len());
+  assert!(uci_info[0].starts_with("info score mate 2 depth"));
+  assert!(uci_info[0].ends_with(" pv c1b2 d4e3 b6d5"));
+
+  // Long lines are truncated
+  engine.set_max_pv_plies(2);
+  assert!(analysis.display_lines(2).contains("c1b2 d4e3\n"));
+  assert!(engine.get_uci_info()[0].ends_with(" pv c1b2 d4e3"));
+
+  // Black mating sequences and regular evals
+  assert_eq!("#-2", format_eval(-198.0));
+  assert_eq!("score mate -1", format_uci_score(-200.0));
+  assert_eq!("0.35", format_eval(0.35));
+  assert_eq!("score cp -109", format_uci_score(-1.2));
+}
+
+#[test]
+fn engine_display_smothered_mate_distance() {
+  // Smothered mate in 2: d5g8 e8g8 h6f7
+  let mut engine = Engine::new(false);
+  engine.set_position("4r2k/6pp/7N/3Q4/8/8/8/7K w - - 0 1");
+  engine.options.max_depth = 4;
+  engine.set_multipv(1);
+  engine.go();
+
+  let analysis = engine.get_analysis();
+  println!("{}", analysis.display_lines(VARIATION_LENGTH));
+  assert_eq!("d5g8", analysis.get_best_move().unwrap().to_string());
+  assert_eq!(Some(2), mate_distance(analysis.get_eval().unwrap()));
+  assert!(analysis.display_lines(VARIATION_LENGTH).contains("Eval #2 "));
+  assert!(engine.get_uci_info()[0].starts_with("info score mate 2 depth"));
+}
+
+#[test]
+fn eval_ordering_and_conversions() {
+  assert!(Eval::Mate(2) > Eval::Mate(3));
+  assert!(Eval::Mate(3) > Eval::Score(900));
+  assert!(Eval::Score(900) > Eval::DRAW);
+  assert!(Eval::DRAW > Eval::Mate(-3));
+  assert!(Eval::Mate(-3) > Eval::Mate(-2));
+  assert_eq!(Eval::MATE,
+             [Eval::Mate(5), Eval::MATE, Eval::Score(2000)].into_iter().max().unwrap());
+
+  // Negation gives the evaluation of the opponent
+  assert_eq!(Eval::Mate(-2), -Eval::Mate(2));
+  assert_eq!(Eval::Mate(4), -Eval::Mate(-4));
+  assert_eq!(Eval::Score(-35), -Eval::Score(35));
+  assert!(-Eval::Mate(2) < -Eval::Mate(3));
+
+  // Conversions with the search evaluations
+  assert_eq!(Eval::MATE, Eval::from_search_score(200.0));
+  assert_eq!(Eval::Mate(-3), Eval::from_search_score(-198.0));
+  assert_eq!(Eval::Score(35), Eval::from_search_score(0.35));
+  assert_eq!(Eval::DRAW, Eval::from_search_score(f32::NAN));
+  for eval in [200.0, -198.0, 150.0, 0.35, -1.2, 0.0] {
+    assert_eq!(eval, Eval::from_search_score(eval).to_search_score());
+  }
+}
+
+#[test]
+fn engine_varies_moves_after_leaving_the_book() {
+  // Quiet opening position, out of our book
+  let fen = "rnbqkbnr/1pppppp1/p6p/8/8/P6P/1PPPPPP1/RNBQKBNR w KQkq - 0 3";
+  let mut first_moves: Vec<String> = Vec::new();
+  for seed in 0..8 {
+    let mut engine = Engine::new(false);
+    engine.set_seed(seed);
+    engine.set_position(fen);
+    engine.options.max_depth = 3;
+    engine.options.book_exit_randomness_plies = 2;
+    engine.options.book_exit_margin_cp = 30;
+    engine.go();
+
+    // Too far from the book, the engine does not randomize anymore
+    assert!(engine.select_book_exit_line(2).is_none());
+
+    let (_, line) = engine.select_book_exit_line(0).unwrap();
+    let best_eval = engine.get_analysis().get(0).eval;
+    assert!((best_eval - line.eval).abs() <= 0.3);
+    let mv = line.variation.get_first_move().unwrap();
+    assert!(engine.position.board.see_ge(&mv, -30));
+    first_moves.push(mv.to_string());
+  }
+
+  println!("First moves out of book: {:?}", first_moves);
+  first_moves.sort();
+  first_moves.dedup();
+  assert!(first_moves.len() > 1);
+}
+
+#[test]
+fn engine_single_thread_search_is_unchanged() {
+  // With 1 thread, the search is sequential and gives exactly the same result
+  // as before the parallel search was introduced, every time.
+  let mut nodes = Vec::new();
+  for _ in 0..2 {
+    let mut engine = Engine::new(false);
+    engine.set_position("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4");
+    engine.set_maximum_threads(1);
+    engine.options.principal_variation_search = false;
+    engine.options.max_depth = 4;
+    engine.go();
+
+    let analysis = engine.get_analysis();
+    assert_eq!("d7d5 e4d5 f6d5 f3e5",
+               analysis.get(0).variation.to_string_with_max_plies(4));
+    assert!((analysis.get(0).eval - 0.4).abs() < 0.001);
+    nodes.push(engine.analysis.get_nodes_visited());
+  }
+  assert_eq!(nodes[0], nodes[1]);
+
+  // Searches use all the logical CPUs unless asked otherwise
+  assert_eq!(available_threads(), Engine::new(false).options.threads);
+}
+
+#[test]
+fn engine_multi_thread_search_finds_checkmate_in_two() {
+  // Forced checkmate in 2: c1b2 d4e3 b6d5
+  let mut engine = Engine::new(false);
+  engine.set_position("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35");
+  engine.set_maximum_threads(3);
+  engine.options.max_depth = 3;
+  engine.go();
+
+  assert_eq!("c1b2", engine.get_best_move().unwrap().to_string());
+  assert_eq!(198.0, engine.get_analysis().get(0).eval);
+  assert!(!engine.is_active());
+
+  // Scores found by the helpers come with their line
+  let pv: Vec<String> = engine.get_pv().iter().map(|m| m.to_string()).collect();
+  assert_eq!(vec!["c1b2", "d4e3", "b6d5"], pv);
+}
+
+#[test]
+fn engine_watchdog_stops_a_search_that_missed_the_stop() {
+  let mut engine = Engine::new(false);
+  engine.set_position("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4");
+  engine.set_maximum_depth(0);
+  engine.set_search_time_limit(0);
+
+  // The stop request comes while an infinite search is starting, right before
+  // it resets the request. Only the watchdog can stop the search.
+  let grace_period = Duration::from_millis(300);
+  let start = Instant::now();
+  engine.set_engine_active(true);
+  let watchdog = engine.stop_with_watchdog(grace_period);
+  engine.set_stop_requested(false);
+  let search_engine = engine.clone();
+  let search = std::thread::spawn(move || search_engine.search_position());
+  search.join().unwrap();
+  watchdog.join().unwrap();
+
+  assert!(start.elapsed() >= grace_period);
+  assert!(start.elapsed() < Duration::from_secs(5));
+  assert!(!engine.is_active());
+  assert!(engine.get_best_move().is_some());
+}
+
+#[test]
+fn engine_watchdog_does_not_stop_the_next_search() {
+  let mut engine = Engine::new(false);
+  engine.set_position("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4");
+  engine.set_maximum_depth(0);
+  engine.set_search_time_limit(0);
+
+  // The search stops as requested
+  let grace_period = Duration::from_millis(300);
+  let search = engine.go_async().unwrap();
+  let watchdog = engine.stop_with_watchdog(grace_period);
+  search.join().unwrap();
+  assert!(!engine.is_active());
+
+  // The opponent replies quickly and we search again within the grace period,
+  // for longer than the grace period
+  let search_time = Duration::from_millis(800);
+  engine.set_search_time_limit(search_time.as_millis() as usize);
+  let start = Instant::now();
+  let search = engine.go_async().unwrap();
+  watchdog.join().unwrap();
+  assert!(engine.is_active());
+  assert!(!engine.stop_requested());
+  search.join().unwrap();
+
+  assert!(start.elapsed() >= search_time);
+  assert!(engine.get_best_move().is_some());
+}
+
+#[test]
+fn engine_debug_prints_root_move_evals() {
+  let mut engine = Engine::new(true);
+  engine.set_position("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4");
+  engine.set_maximum_depth(2);
+  engine.go();
+  assert!(engine.get_debug_info().is_empty());
+
+  // One line per completed depth, then the branching factor
+  let mut engine = Engine::new(true);
+  engine.set_position("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4");
+  engine.set_maximum_depth(2);
+  engine.set_debug(true);
+  engine.go();
+  let debug_info = engine.get_debug_info();
+  assert_eq!(3, debug_info.len());
+  assert!(debug_info[2].starts_with("info string branching factor "));
+  let line = &debug_info[1];
+  assert!(line.starts_with("info string depth 2 static eval "));
+  let root_moves: Vec<&str> =
+    line.split(" root moves ").nth(1).unwrap().split_whitespace().collect();
+  assert_eq!(6, root_moves.len());
+  let best_move = engine.get_analysis().get(0).variation.get_first_move().unwrap();
+  assert_eq!(best_move.to_string(), root_moves[0]);
+  assert!(root_moves[1].parse::<f32>().is_ok());
+}
+
+#[test]
+fn engine_reports_the_branching_factor() {
+  let mut engine = Engine::new(false);
+  assert_eq!(None, engine.analysis.get_branching_factor());
+
+  engine.set_position(START_POSITION_FEN);
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_maximum_depth(4);
+  engine.set_debug(true);
+  engine.go();
+
+  let legal_moves = engine.position.get_moves().len() as f32;
+  let branching_factor = engine.analysis.get_branching_factor().unwrap();
+  println!("Branching factor: {}", branching_factor);
+  assert!(branching_factor > 1.0);
+  assert!(branching_factor < legal_moves);
+
+  let expected = format!("info string branching factor {:.2}", branching_factor);
+  assert_eq!(Some(&expected), engine.get_debug_info().last());
+}
+
+#[test]
+fn engine_finds_knight_underpromotion_fork() {
+  // c8=N+ forks the king and the queen, c8=Q only trades queens
+  let mut engine = Engine::new(false);
+  engine.set_position("8/q1P1k3/8/8/8/8/7P/7K w - - 0 1");
+  engine.options.max_depth = 4;
+  engine.go();
+
+  assert_eq!("c7c8N", engine.get_best_move().unwrap().to_string());
+}
+
+#[test]
+fn engine_finds_rook_underpromotion_avoiding_stalemate() {
+  // g8=Q is stalemate, g8=R wins. Any other move loses the pawn.
+  let mut engine = Engine::new(false);
+  engine.set_position("8/6P1/7k/8/6K1/8/8/8 w - - 0 1");
+  engine.options.max_depth = 4;
+  engine.go();
+
+  assert_eq!("g7g8R", engine.get_best_move().unwrap().to_string());
+}
+
+#[test]
+fn engine_self_test_reports_broken_components() {
+  let mut engine = Engine::new(false);
+  assert_eq!(Ok(()), engine.self_test());
+
+  // A net producing NaN evaluations is only a problem if we use it
+  let mut nnue = NNUE::default();
+  nnue.layers.last_mut().unwrap().state.b = f32::NAN;
+  engine.set_nnue(Arc::new(Mutex::new(nnue)));
+  assert_eq!(Ok(()), engine.self_test());
+
+  engine.set_use_nnue(true);
+  let failures = engine.self_test().unwrap_err();
+  assert!(failures.iter().any(|f| f.starts_with("NNUE")),
+          "{:?}",
+          failures);
+}
+
+#[test]
+fn uci_score_of_an_extra_pawn_is_about_100_cp() {
+  let engine = Engine::new(false);
+
+  // Start position where Black is missing a pawn
+  let game_state = GameState::from_fen("rnbqkbnr/1ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+  assert_eq!("score cp 100",
+             format_uci_score(engine.static_eval(&game_state)));
+
+  // Same position with and without a black pawn
+  let with_pawn =
+    GameState::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+  let without_pawn =
+    GameState::from_fen("r1bqkb1r/1ppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+  let eval = engine.static_eval(&without_pawn) - engine.static_eval(&with_pawn);
+  let score = format_uci_score(eval);
+  let centipawns: isize = score.trim_start_matches("score cp ").parse().unwrap();
+  assert!((90..=110).contains(&centipawns), "{}", score);
+}
+
+#[test]
+fn engine_principal_variation_search() {
+  // Same results as a full-window search, with fewer nodes
+  let positions = ["r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+                   "r1bqk2r/ppppbp1p/2n5/3Bp1pQ/4P3/3P4/PPPN1PPP/R3K1NR b KQq - 0 7",
+                   "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+                   "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                   "2r3k1/pp3ppp/4p3/3pP3/3P4/P4N2/1P3PPP/2R3K1 b - - 0 24",
+                   "8/8/2p1pkp1/p3p3/P1P1P1P1/6q1/7q/3K4 b - - 2 55"];
+  let mut nodes = [0, 0];
+  for fen in positions {
+    let mut results = Vec::new();
+    for (i, pvs) in [true, false].into_iter().enumerate() {
+      let mut engine = Engine::new(false);
+      engine.set_maximum_threads(1);
+      engine.set_position(fen);
+      engine.set_maximum_depth(4);
+      engine.options.principal_variation_search = pvs;
+      engine.go();
+      nodes[i] += engine.analysis.get_nodes_visited();
+      results.push((engine.get_best_move().unwrap(),
+                    Eval::from_search_score(engine.get_eval().unwrap())));
+    }
+    assert_eq!(results[0], results[1], "PVS changed the result for {fen}");
+  }
+  println!("Nodes visited with/without PVS: {:?}", nodes);
+  assert!(nodes[0] < nodes[1]);
+}
+
+#[test]
+fn engine_search_moves_restriction() {
+  // a1a8 is mate, but we only look at the moves we are restricted to
+  let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+  let search = |search_moves: &str| -> Move {
+    let mut engine = Engine::new(false);
+    engine.set_position(fen);
+    engine.options.use_book = false;
+    engine.set_maximum_depth(3);
+    engine.options.search_moves = Move::string_to_vec(search_moves);
+    engine.go();
+    engine.get_best_move().unwrap()
+  };
+
+  assert_eq!("g1f1", search("g1f1").to_string());
+  let best_move = search("g1h1 a1a2").to_string();
+  assert!(best_move == "g1h1" || best_move == "a1a2", "{}", best_move);
+
+  // Without a legal move in the restriction, all moves are searched
+  assert_eq!("a1a8", search("e2e4").to_string());
+  assert_eq!("a1a8", search("").to_string());
+}
+
+#[test]
+fn engine_stops_early_when_the_best_move_is_stable() {
+  // Nxd4 wins the queen, the best move does not change with the depth
+  let mut engine = Engine::new(false);
+  engine.set_position("rnb1kbnr/pppp1ppp/8/4p3/3qP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3");
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_maximum_depth(0);
+  engine.set_search_time_limit(3000);
+  engine.options.stable_move_depths = 2;
+  let start = Instant::now();
+  engine.go();
+  assert_eq!("f3d4", engine.get_best_move().unwrap().to_string());
+  assert!(start.elapsed() < Duration::from_millis(1500));
+  assert!(engine.analysis.get_depth() >= 3);
+
+  // In check, we use all the time we have
+  let mut engine = Engine::new(false);
+  engine.set_position("rnb1kbnr/pppp1ppp/8/4p3/4P2q/5P2/PPPP2PP/RNBQKBNR w KQkq - 1 3");
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_maximum_depth(0);
+  engine.set_search_time_limit(1000);
+  engine.options.stable_move_depths = 2;
+  let start = Instant::now();
+  engine.go();
+  assert!(start.elapsed() >= Duration::from_millis(1000));
+}
+
+#[test]
+fn engine_skips_the_nnue_in_time_pressure() {
+  let mut engine = Engine::new(false);
+  engine.set_position("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5");
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_use_nnue(true);
+  engine.options.nnue_min_search_time = 100;
+
+  // Very little time: classical evaluation only
+  engine.set_search_time_limit(50);
+  assert!(!engine.is_nnue_active());
+  engine.go();
+  assert!(engine.get_best_move().is_some());
+
+  // Plenty of time, or no limit at all: the NNUE is used
+  engine.set_search_time_limit(2000);
+  assert!(engine.is_nnue_active());
+  engine.set_search_time_limit(0);
+  assert!(engine.is_nnue_active());
+
+  // Never used if disabled, and always used without a threshold
+  engine.set_use_nnue(false);
+  assert!(!engine.is_nnue_active());
+  engine.set_use_nnue(true);
+  engine.options.nnue_min_search_time = 0;
+  engine.set_search_time_limit(50);
+  assert!(engine.is_nnue_active());
+}
+
+#[test]
+fn engine_checks_its_move_for_blunders() {
+  // At depth 1, exd5 leaves the queen hanging to Bxe5
+  let fen = "r1bqk2r/pp3ppp/n1pbpn2/3pQ3/B3P3/5N2/PPPP1PPP/RNB1K2R w KQkq - 6 7";
+  let mut engine = Engine::new(false);
+  engine.set_position(fen);
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_maximum_depth(1);
+  engine.go();
+  assert_eq!("e4d5", engine.get_best_move().unwrap().to_string());
+
+  // Nothing happens without the option or without enough time
+  assert!(!engine.check_for_blunder(5000));
+  engine.options.blunder_check = true;
+  assert!(!engine.check_for_blunder(100));
+  assert_eq!("e4d5", engine.get_best_move().unwrap().to_string());
+
+  // The check finds the drop and searches again deeper
+  assert!(engine.check_for_blunder(5000));
+  assert_ne!("e4d5", engine.get_best_move().unwrap().to_string());
+  assert!(engine.analysis.get_depth() > 1);
+  assert_eq!(1, engine.options.max_depth);
+
+  // A sound move is left alone
+  let mut engine = Engine::new(false);
+  engine.set_position("rnb1kbnr/pppp1ppp/8/4p3/3qP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3");
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_maximum_depth(3);
+  engine.options.blunder_check = true;
+  engine.go();
+  assert_eq!("f3d4", engine.get_best_move().unwrap().to_string());
+  assert!(!engine.check_for_blunder(5000));
+  assert_eq!("f3d4", engine.get_best_move().unwrap().to_string());
+}
+
+#[test]
+fn engine_analyzes_positions_in_batch() {
+  // White is a queen up, Black is a rook up, an invalid FEN and a mate in 1
+  let positions = vec![(String::from("4k3/pppp4/8/8/8/8/PPPP4/3QK3 w - - 0 1"), 300),
+                       (String::from("r3k3/pppp4/8/8/8/8/PPPP4/4K3 b - - 0 1"), 300),
+                       (String::from("8/8/8/8 w - - 0 1"), 300),
+                       (String::from("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), 300),];
+
+  let mut engine = Engine::new(false);
+  engine.set_use_book(false);
+  engine.set_maximum_threads(1);
+  engine.set_search_time_limit(5000);
+  let results = engine.analyze_batch(&positions);
+  assert_eq!(positions.len(), results.len());
+  assert_eq!(5000, engine.options.max_search_time);
+
+  // The invalid FEN is reported without stopping the batch
+  assert_eq!(Some(&FenError::InvalidRankCount(4)),
+             results[2].as_ref().err());
+
+  // The other positions are searched, the side with more material is better
+  for (i, expected_sign) in [(0, 1.0), (1, -1.0), (3, 1.0)] {
+    let analysis = results[i].as_ref().unwrap();
+    let game_state = GameState::from_fen(&positions[i].0);
+    let best_move = analysis.get_best_move().unwrap();
+    assert!(game_state.get_moves().contains(&best_move),
+            "{} is not legal in {}",
+            best_move,
+            positions[i].0);
+    assert_eq!(expected_sign, analysis.get_eval().unwrap().signum());
+  }
+  assert_eq!("a1a8", results[3].as_ref().unwrap().get_best_move().unwrap().to_string());
+}
+
+#[test]
+fn engine_get_pv() {
+  // Nothing searched yet
+  let mut engine = Engine::new(false);
+  engine.set_position("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35");
+  assert!(engine.get_pv().is_empty());
+
+  // Mate in 2: c1b2 d4e3 b6d5
+  engine.options.max_search_time = 5000;
+  engine.options.max_depth = 3;
+  engine.go();
+  let pv: Vec<String> = engine.get_pv().iter().map(|m| m.to_string()).collect();
+  assert_eq!(vec!["c1b2", "d4e3", "b6d5"], pv);
+  assert_eq!(engine.get_best_move(), engine.get_pv().first().copied());
+}
+
+#[test]
+fn engine_book_status() {
+  use crate::engine::books::get_book_moves;
+
+  // Ruy Lopez, in our book
+  let fen = "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3";
+  let mut engine = Engine::new(false);
+  engine.set_position(fen);
+  let book_moves = get_book_moves(&engine.position.board, false).unwrap();
+  assert!(!book_moves.is_empty());
+  assert_eq!(BookStatus::InBook(book_moves.len()), engine.book_status());
+
+  // Middlegame, out of book
+  engine.set_position("2r3k1/pp3ppp/4p3/3pP3/3P4/P4N2/1P3PPP/2R3K1 b - - 0 24");
+  assert_eq!(BookStatus::OutOfBook, engine.book_status());
+}
+
+#[test]
+fn engine_search_mate() {
+  use crate::engine::search::mate::MateSearchResult;
+
+  // Mate in 2: c1b2 d4e3 b6d5
+  let mut engine = Engine::new(false);
+  engine.set_position("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35");
+  match engine.search_mate(5) {
+    MateSearchResult::Mate(mv, moves) => assert_eq!(("c1b2", 2), (mv.to_string().as_str(), moves)),
+    MateSearchResult::NoMate => panic!("Mate in 2 not found"),
+  }
+  assert_eq!(MateSearchResult::NoMate, engine.search_mate(1));
+
+  // Mate in 3 for Black, starting with a queen check
+  engine.set_position("2r3k1/p4p2/3Rp2p/1p2P1pK/8/1P4P1/P3Q2P/1q6 b - - 0 1");
+  match engine.search_mate(5) {
+    MateSearchResult::Mate(mv, moves) => assert_eq!(("b1g6", 3), (mv.to_string().as_str(), moves)),
+    MateSearchResult::NoMate => panic!("Mate in 3 not found"),
+  }
+  assert_eq!(MateSearchResult::NoMate, engine.search_mate(2));
+
+  // Mate in 1
+  engine.set_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+  match engine.search_mate(3) {
+    MateSearchResult::Mate(mv, moves) => assert_eq!(("a1a8", 1), (mv.to_string().as_str(), moves)),
+    MateSearchResult::NoMate => panic!("Mate in 1 not found"),
+  }
+
+  // No mate in the start position
+  engine.set_position(START_POSITION_FEN);
+  assert_eq!(MateSearchResult::NoMate, engine.search_mate(2));
+}
+
+#[test]
+fn engine_go_mate_reports_a_best_move() {
+  // Mate in 2: c1b2 d4e3 b6d5
+  let mut engine = Engine::new(false);
+  engine.set_position("1n4nr/5ppp/1N6/1P2p3/1P1k4/5P2/1p1NP1PP/R1B1KB1R w KQ - 0 35");
+  engine.go_mate_async(5).unwrap().join().unwrap();
+  assert!(!engine.is_active());
+  assert_eq!("c1b2", engine.get_best_move().unwrap().to_string());
+  assert_eq!(Some(2), mate_distance(engine.get_analysis().get_eval().unwrap()));
+
+  // No mate: the regular search finds the move to play
+  engine.set_position("2r3k1/pp3ppp/4p3/3pP3/3P4/P4N2/1P3PPP/2R3K1 b - - 0 24");
+  engine.options.max_depth = 3;
+  engine.go_mate_async(2).unwrap().join().unwrap();
+  assert!(!engine.is_active());
+  assert!(engine.position.get_moves().contains(&engine.get_best_move().unwrap()));
+  assert_eq!(3, engine.get_depth());
+
+  // Stopped during the mate search: we still report a legal move
+  engine.set_position("r1bq1rk1/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R1BQ1RK1 w - - 0 8");
+  let handle = engine.go_mate_async(10).unwrap();
+  engine.stop();
+  handle.join().unwrap();
+  assert!(!engine.is_active());
+  assert!(engine.position.get_moves().contains(&engine.get_best_move().unwrap()));
+}
+
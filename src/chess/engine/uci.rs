@@ -1,7 +1,11 @@
 // Imports / dependencies
+use chess::engine::config::options::available_threads;
 use chess::engine::config::play_style::*;
+use chess::engine::config::strength::{ELO_LIMIT_MAX, ELO_LIMIT_MIN};
+use chess::engine::search_result::VARIATION_LENGTH;
 use chess::engine::*;
 use chess::model::game_state::START_POSITION_FEN;
+use chess::model::moves::{is_move_notation, Move};
 use regex::Regex;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -14,6 +18,10 @@ const POSITION_CMD_MOVE_REGEX: &str =
   r#"^position\s*[\s0-9a-zA-Z\/-]*\smoves\s(?P<moves>[\s0-9a-zA-Z\/-]*)"#;
 const SET_OPTION_NAME_VALUE_REGEX: &str =
   r#"^setoption\s+name\s+(?P<name>.+)\s+value\s+(?P<value>.+)"#;
+/// Default value of the UCI_Elo option
+const UCI_ELO_DEFAULT: u32 = 1500;
+/// Maximum value of the Threads option
+const UCI_THREADS_MAX: usize = 256;
 
 /// This module prodives a UCI interface to the engine
 ///
@@ -33,8 +41,14 @@ DESCRIPTION
       Decides if the engine should use the NNUE. The NNUE is currently very slow
       and not incredible at prediction positions.
 
+    setoption name OwnBook type check default true
+      Decides if the engine plays moves from its opening book in known
+      positions. Set to false to search every position.
+
     setoption name ponder value <bool>
-      Decides if we should ponder. Same as running \"go ponder\" if set to true
+      Decides if we should keep searching after finding a winning sequence.
+      Use \"go ponder\" to search on the opponent's time, followed by
+      \"ponderhit\" if the opponent played the expected move or \"stop\".
 
     setoption name play_style type combo default Normal var Conservative var Normal var Aggressive var Provocative
       Decides how the engine should play. Normal is the default.
@@ -42,8 +56,31 @@ DESCRIPTION
       Use Aggressive to play aggressively.
       Use Provocative to play weaker opponents.
   
-    setoption name multi_pv type spin default 3 min 0 max 5
+    setoption name multi_pv type spin default 3 min 1 max 5
       Sets how many lines the engine will print in the info during the search.
+
+    setoption name max_pv_plies type spin default 10 min 1 max 10
+      Sets how many moves the engine will print for each line.
+
+    setoption name Threads type spin default <logical CPUs> min 1 max 256
+      Number of threads used by the search. Defaults to the number of logical
+      CPUs. 1 searches sequentially, which gives reproducible results.
+
+    setoption name UCI_LimitStrength type check default false
+      Limits the strength of the engine to the Elo set with UCI_Elo.
+
+    setoption name UCI_Elo type spin default 1500 min 800 max 2800
+      Elo at which the engine plays when UCI_LimitStrength is set.
+
+    setoption name seed value <u64>
+      Seeds the random decisions of the engine (book moves, choice between
+      equivalent lines), so that games can be reproduced.
+
+  debug commands:
+
+    tt
+      Prints the best line stored in the transposition table after a search,
+      with the depth and eval of each entry, and the table usage statistics.
 ";
 
 // Main function
@@ -60,6 +97,10 @@ fn main() -> ExitCode {
   let position_moves_re = Regex::new(POSITION_CMD_MOVE_REGEX).unwrap();
   let options_re = Regex::new(SET_OPTION_NAME_VALUE_REGEX).unwrap();
 
+  // Strength limit, only applied when UCI_LimitStrength is set
+  let mut limit_strength = false;
+  let mut uci_elo = UCI_ELO_DEFAULT;
+
   // Parse each line until we are EOF:
   let mut read_bytes = 1;
   let mut line = String::new();
@@ -81,9 +122,18 @@ fn main() -> ExitCode {
         println!("id author Nicolas W");
         println!("");
         println!("option name use_nnue type check default false");
+        println!("option name OwnBook type check default true");
         println!("option name ponder type check default false");
         println!("option name play_style type combo default Normal var Conservative var Normal var Aggressive var Provocative");
-        println!("option name multi_pv type spin default 3 min 0 max 5");
+        println!("option name multi_pv type spin default 3 min 1 max 5");
+        println!("option name max_pv_plies type spin default 10 min 1 max 10");
+        println!("option name Threads type spin default {} min 1 max {}",
+                 std::cmp::min(available_threads(), UCI_THREADS_MAX),
+                 UCI_THREADS_MAX);
+        println!("option name UCI_LimitStrength type check default false");
+        println!("option name UCI_Elo type spin default {} min {} max {}",
+                 UCI_ELO_DEFAULT, ELO_LIMIT_MIN, ELO_LIMIT_MAX);
+        println!("option name seed type string default <empty>");
         println!("uciok");
       },
       "isready" => {
@@ -92,9 +142,9 @@ fn main() -> ExitCode {
 
       "debug" => {
         if line.contains("on") {
-          engine.options.debug = true;
+          engine.set_debug(true);
         } else {
-          engine.options.debug = false;
+          engine.set_debug(false);
         }
       },
 
@@ -116,20 +166,45 @@ fn main() -> ExitCode {
         match name {
           "use_nnue" => {
             let value = value.parse::<bool>().unwrap_or(false);
-            engine.options.use_nnue = value;
+            engine.set_use_nnue(value);
+          },
+          "OwnBook" => {
+            let value = value.parse::<bool>().unwrap_or(true);
+            engine.set_use_book(value);
           },
           "ponder" => {
             let value = value.parse::<bool>().unwrap_or(false);
-            engine.options.ponder = value;
+            engine.set_ponder(value);
           },
           "play_style" => {
             let value = value.parse::<PlayStyle>().unwrap_or_default();
-            engine.options.play_style = value;
+            engine.set_play_style(value);
           },
           "multi_pv" => {
             let mut value = value.parse::<usize>().unwrap_or(3);
             value = std::cmp::min(value, 5);
-            engine.options.multi_pv = value;
+            engine.set_multipv(value);
+          },
+          "max_pv_plies" => {
+            let value = value.parse::<usize>().unwrap_or(VARIATION_LENGTH);
+            engine.set_max_pv_plies(value);
+          },
+          "Threads" => {
+            let value = value.parse::<usize>().unwrap_or(1);
+            engine.set_maximum_threads(std::cmp::min(value, UCI_THREADS_MAX));
+          },
+          "UCI_LimitStrength" => {
+            limit_strength = value.parse::<bool>().unwrap_or(false);
+            engine.set_elo_limit(limit_strength.then_some(uci_elo));
+          },
+          "UCI_Elo" => {
+            uci_elo = value.parse::<u32>().unwrap_or(UCI_ELO_DEFAULT);
+            engine.set_elo_limit(limit_strength.then_some(uci_elo));
+          },
+          "seed" => {
+            if let Ok(value) = value.parse::<u64>() {
+              engine.set_seed(value);
+            }
           },
           _ => {},
         }
@@ -188,23 +263,39 @@ fn main() -> ExitCode {
       },
 
       "go" => {
+        // Mate search, falling back to a regular search without mate
+        if let Some(max_moves) = parse_mate_moves(&line) {
+          let _ = engine.go_mate_async(max_moves);
+          continue;
+        }
         // Check some of the options passed:
         if line.contains("infinite") {
-          engine.options.max_depth = 0;
+          engine.set_maximum_depth(0);
         }
+        engine.options.search_moves = parse_search_moves(&line);
+        // Get started searching:
         if line.contains("ponder") {
-          engine.options.ponder = true;
+          let _ = engine.go_ponder();
+        } else {
+          let _ = engine.go_async();
         }
-        // Get started searching:
-        let engine_clone = engine.clone();
-        let _ = std::thread::spawn(move || engine_clone.go());
         // TODO: Find out why the cache is empty when we stop here.
       },
+      "ponderhit" => {
+        engine.ponderhit();
+      },
       "stop" => {
         stop_engine_blocking(&engine);
         engine.print_evaluations();
       },
 
+      "tt" => {
+        if engine.is_active() {
+          continue;
+        }
+        engine.print_transposition_table();
+      },
+
       // TODO: Use a debug option instead
       "show_state" => {
         println!("Position: {}", engine.position.to_fen());
@@ -240,3 +331,38 @@ pub fn stop_engine_blocking(engine: &Engine) {
     std::thread::sleep(std::time::Duration::from_millis(10));
   }
 }
+
+/// Reads the maximum number of moves of a mate search, e.g. `go mate 3`
+///
+/// ### Arguments
+///
+/// * `line`: go command
+///
+/// ### Return value
+///
+/// Number of moves after mate, None if the command is not a mate search
+fn parse_mate_moves(line: &str) -> Option<usize> {
+  line.split_whitespace()
+      .skip_while(|word| *word != "mate")
+      .nth(1)
+      .and_then(|moves| moves.parse::<usize>().ok())
+}
+
+/// Reads the root moves to which a go command restricts the search, e.g.
+/// `go searchmoves e2e4 d2d4 wtime 1000`
+///
+/// ### Arguments
+///
+/// * `line`: go command
+///
+/// ### Return value
+///
+/// Moves listed after searchmoves, empty if there are none
+fn parse_search_moves(line: &str) -> Vec<Move> {
+  line.split_whitespace()
+      .skip_while(|word| *word != "searchmoves")
+      .skip(1)
+      .take_while(|word| is_move_notation(word))
+      .map(Move::from_string)
+      .collect()
+}
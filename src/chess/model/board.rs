@@ -1,5 +1,6 @@
 use crate::model::board_geometry::diagonals::DIAGONALS;
 use crate::model::board_geometry::lines::LINES;
+use crate::model::board_geometry::passed_pawns_areas::*;
 use crate::model::board_geometry::rays::RAYS;
 use crate::model::board_geometry::*;
 use crate::model::board_mask::*;
@@ -25,6 +26,48 @@ pub const INVALID_SQUARE: u8 = 255;
 /// Default start position FEN
 const START_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+/// Color-specific squares and pieces used when generating moves
+struct MoveGenerationTable {
+  /// Kingside castling move
+  kingside_castle:              Move,
+  /// Queenside castling move
+  queenside_castle:             Move,
+  /// Squares that must be empty to castle kingside
+  kingside_free_squares:        BoardMask,
+  /// Squares that must be empty to castle queenside
+  queenside_free_squares:       BoardMask,
+  /// Squares that cannot be attacked by the opponent to castle kingside
+  kingside_unattacked_squares:  BoardMask,
+  /// Squares that cannot be attacked by the opponent to castle queenside
+  queenside_unattacked_squares: BoardMask,
+  /// Pieces a pawn can promote to, queen first
+  promotions:                   [Promotion; 4],
+}
+
+const WHITE_MOVE_GENERATION_TABLE: MoveGenerationTable =
+  MoveGenerationTable { kingside_castle:              castle_mv!(4, 6),
+                        queenside_castle:             castle_mv!(4, 2),
+                        kingside_free_squares:        FREE_SQUARE_MASK_WHITE_KINGSIDE,
+                        queenside_free_squares:       FREE_SQUARE_MASK_WHITE_QUEENSIDE,
+                        kingside_unattacked_squares:  UNATTACKED_SQUARE_MASK_WHITE_KINGSIDE,
+                        queenside_unattacked_squares: UNATTACKED_SQUARE_MASK_WHITE_QUEENSIDE,
+                        promotions:                   [Promotion::WhiteQueen,
+                                                       Promotion::WhiteRook,
+                                                       Promotion::WhiteKnight,
+                                                       Promotion::WhiteBishop], };
+
+const BLACK_MOVE_GENERATION_TABLE: MoveGenerationTable =
+  MoveGenerationTable { kingside_castle:              castle_mv!(60, 62),
+                        queenside_castle:             castle_mv!(60, 58),
+                        kingside_free_squares:        FREE_SQUARE_MASK_BLACK_KINGSIDE,
+                        queenside_free_squares:       FREE_SQUARE_MASK_BLACK_QUEENSIDE,
+                        kingside_unattacked_squares:  UNATTACKED_SQUARE_MASK_BLACK_KINGSIDE,
+                        queenside_unattacked_squares: UNATTACKED_SQUARE_MASK_BLACK_QUEENSIDE,
+                        promotions:                   [Promotion::BlackQueen,
+                                                       Promotion::BlackRook,
+                                                       Promotion::BlackKnight,
+                                                       Promotion::BlackBishop], };
+
 // -----------------------------------------------------------------------------
 //  Macros
 
@@ -55,6 +98,27 @@ pub struct Board {
   pub hash:              BoardHash,
 }
 
+/// Reasons for which a FEN string can be rejected
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FenError {
+  /// The FEN does not have 6 space-separated fields
+  InvalidFieldCount(usize),
+  /// The piece placement does not have 8 ranks
+  InvalidRankCount(usize),
+  /// A rank (1 to 8) of the piece placement does not describe 8 squares
+  InvalidRankLength(u8),
+  /// Unknown character in the piece placement
+  InvalidPieceChar(char),
+  /// A side does not have exactly one king
+  InvalidKingCount(Color),
+  /// The side to move is not "w" or "b"
+  InvalidSideToMove(String),
+  /// The castling rights are not "-" or a combination of "KQkq"
+  InvalidCastlingRights(String),
+  /// The en-passant square is not "-" or a square on the 3rd or 6th rank
+  InvalidEnPassantSquare(String),
+}
+
 // -----------------------------------------------------------------------------
 // Implementations
 
@@ -74,9 +138,22 @@ impl Board {
   ///
   /// **NOTE**: This may be an illegal configuration.
   pub fn new_random() -> Self {
+    Board::new_random_with_rng(&mut rand::thread_rng())
+  }
+
+  /// Initialize a board with a random arrangement of pieces, using the
+  /// provided random number generator. Use a seeded generator to get
+  /// reproducible boards.
+  ///
+  /// **NOTE**: This may be an illegal configuration.
+  ///
+  /// ### Arguments
+  ///
+  /// * `rng`: Random number generator used to place the pieces
+  pub fn new_random_with_rng<R: Rng>(rng: &mut R) -> Self {
     let mut board = Board::new();
 
-    let color_rand = rand::random::<bool>();
+    let color_rand = rng.gen::<bool>();
     board.side_to_play = match color_rand {
       true => Color::White,
       false => Color::Black,
@@ -84,12 +161,10 @@ impl Board {
 
     board.castling_rights = CastlingRights::none();
 
-    let mut rng = rand::thread_rng();
-
     // Let's try to place pieces:
     // White King
     let square = rng.gen_range(0..64);
-    set_square_in_mask!(square, board.pieces.white.king);
+    board.pieces.add(WHITE_KING, square);
 
     // Place the black king, try until they do not touch:
     loop {
@@ -98,9 +173,9 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.black.king);
+      board.pieces.add(BLACK_KING, square);
       if (board.get_attackers(board.get_king(Color::White), Color::Black)) != 0 {
-        board.pieces.black.king = 0;
+        board.pieces.remove(square);
         continue;
       } else {
         break;
@@ -114,13 +189,13 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.white.pawn);
+      board.pieces.add(WHITE_PAWN, square);
       board.update_checkers();
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.white.pawn);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -130,20 +205,25 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.black.pawn);
+      board.pieces.add(BLACK_PAWN, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.black.pawn);
+        board.pieces.remove(square);
         continue;
       }
     }
 
     // Clean up pawns on the 1st and 8th ranks before adding pieces that could
     // deliver checks
-    board.pieces.white.pawn &= !(BOARD_UP_EDGE | BOARD_DOWN_EDGE);
-    board.pieces.black.pawn &= !(BOARD_UP_EDGE | BOARD_DOWN_EDGE);
+    for square in 0..64_u8 {
+      let edge = square_in_mask!(square, BOARD_UP_EDGE | BOARD_DOWN_EDGE);
+      let piece = board.pieces.get(square);
+      if edge && (piece == WHITE_PAWN || piece == BLACK_PAWN) {
+        board.pieces.remove(square);
+      }
+    }
 
     // Try to add knights:
     for _ in 0..2 {
@@ -152,12 +232,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.white.knight);
+      board.pieces.add(WHITE_KNIGHT, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.white.knight);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -167,12 +247,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.black.knight);
+      board.pieces.add(BLACK_KNIGHT, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.black.knight);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -183,12 +263,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.white.bishop);
+      board.pieces.add(WHITE_BISHOP, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.white.bishop);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -198,12 +278,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.black.bishop);
+      board.pieces.add(BLACK_BISHOP, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.black.bishop);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -214,12 +294,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.white.rook);
+      board.pieces.add(WHITE_ROOK, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.white.rook);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -229,12 +309,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.black.rook);
+      board.pieces.add(BLACK_ROOK, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.black.rook);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -245,12 +325,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.white.queen);
+      board.pieces.add(WHITE_QUEEN, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.white.queen);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -260,12 +340,12 @@ impl Board {
         continue;
       }
 
-      set_square_in_mask!(square, board.pieces.black.queen);
+      board.pieces.add(BLACK_QUEEN, square);
       if board.get_attackers(board.get_king(Color::White), Color::Black)
          | board.get_attackers(board.get_king(Color::Black), Color::White)
          != 0
       {
-        unset_square_in_mask!(square, board.pieces.black.queen);
+        board.pieces.remove(square);
         continue;
       }
     }
@@ -285,10 +365,8 @@ impl Board {
     self.hash = 0;
 
     // Add the hash from the pieces
-    for i in 0..64_u8 {
-      if self.pieces.get(i) != NO_PIECE {
-        self.update_hash_piece(i);
-      }
+    for (square, _, _) in self.pieces.iter() {
+      self.hash ^= ZOBRIST_TABLE[(self.pieces.get(square) - 1) as usize][square as usize];
     }
 
     // Add the hash from the side to play
@@ -305,6 +383,18 @@ impl Board {
     }
   }
 
+  /// Computes the board hash from scratch, without modifying the board. Used
+  /// to verify that the hash updated incrementally is correct.
+  ///
+  /// ### Return value
+  ///
+  /// Hash value for the board
+  fn get_recomputed_hash(&self) -> BoardHash {
+    let mut board = *self;
+    board.compute_hash();
+    board.hash
+  }
+
   // Adds/Removes a piece in the board hash value.
   fn update_hash_piece(&mut self, i: u8) {
     self.hash ^= ZOBRIST_TABLE[(self.pieces.get(i) - 1) as usize][i as usize];
@@ -478,6 +568,264 @@ impl Board {
     attacked_squares
   }
 
+  /// Counts the number of pieces attacking a square.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  /// * `target_square` -  Square for which we want to know the attackers
+  /// * `color` -          Color of the attacking pieces
+  ///
+  /// ### Return value
+  ///
+  /// Number of pieces of the color attacking the square
+  #[inline]
+  pub fn attackers_count(&self, target_square: u8, color: Color) -> u32 {
+    self.get_attackers(target_square, color).count_ones()
+  }
+
+  /// Finds the attacker of a square with the lowest material value.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  /// * `target_square` -  Square for which we want to know the attackers
+  /// * `color` -          Color of the attacking pieces
+  ///
+  /// ### Return value
+  ///
+  /// Square and type of the least valuable attacker, None if the square is not
+  /// attacked.
+  #[inline]
+  pub fn least_valuable_attacker(&self,
+                                 target_square: u8,
+                                 color: Color)
+                                 -> Option<(u8, PieceType)> {
+    self.least_valuable_attacker_with_removed(target_square, color, 0)
+  }
+
+  /// Finds the attacker of a square with the lowest material value, ignoring
+  /// pieces that have already been removed from the board, e.g. during a
+  /// sequence of captures. Sliders behind removed pieces are attacking the
+  /// square (x-rays).
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  /// * `target_square` -  Square for which we want to know the attackers
+  /// * `color` -          Color of the attacking pieces
+  /// * `removed` -        Pieces to consider as removed from the board
+  ///
+  /// ### Return value
+  ///
+  /// Square and type of the least valuable attacker, None if the square is not
+  /// attacked.
+  pub fn least_valuable_attacker_with_removed(&self,
+                                              target_square: u8,
+                                              color: Color,
+                                              removed: BoardMask)
+                                              -> Option<(u8, PieceType)> {
+    debug_assert!(target_square < 64,
+                  "least_valuable_attacker for square {} - board: {}",
+                  target_square,
+                  self.to_fen());
+
+    let square = target_square as usize;
+    let occupancy = self.pieces.all() & !removed;
+    let (attacking_pieces, pawn_control) = match color {
+      Color::White => (&self.pieces.white, BLACK_PAWN_CONTROL[square]),
+      Color::Black => (&self.pieces.black, WHITE_PAWN_CONTROL[square]),
+    };
+    let rook_rays = get_rook_moves(0, occupancy, square);
+    let bishop_rays = get_bishop_moves(0, occupancy, square);
+
+    // Candidates, sorted by increasing material value
+    let candidates = [(pawn_control & attacking_pieces.pawn, PieceType::Pawn),
+                      (KNIGHT_MOVES[square] & attacking_pieces.knight, PieceType::Knight),
+                      (bishop_rays & attacking_pieces.bishop, PieceType::Bishop),
+                      (rook_rays & attacking_pieces.rook, PieceType::Rook),
+                      ((rook_rays | bishop_rays) & attacking_pieces.queen, PieceType::Queen),
+                      (KING_MOVES[square] & attacking_pieces.king, PieceType::King)];
+
+    for (attackers, piece_type) in candidates {
+      let attackers = attackers & !removed;
+      if attackers != 0 {
+        return Some((attackers.trailing_zeros() as u8, piece_type));
+      }
+    }
+
+    None
+  }
+
+  /// Material value of a piece used in static exchange evaluations, in
+  /// centipawns.
+  ///
+  /// ### Arguments
+  ///
+  /// * `piece_type` - Type of the piece
+  #[inline]
+  fn see_value(piece_type: PieceType) -> i32 {
+    (Piece::material_value_from_type(piece_type) * 100.0) as i32
+  }
+
+  /// Computes the material won by the move itself and the value of the piece
+  /// that stands on the destination square after the move, for static
+  /// exchange evaluations.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` - A Board object representing a position, side to play, etc.
+  /// * `mv` -   Move to evaluate
+  ///
+  /// ### Return value
+  ///
+  /// Tuple with the material captured (including promotions), the value of
+  /// the moved piece and the squares vacated by the move.
+  fn see_initial_exchange(&self, mv: &Move) -> (i32, i32, BoardMask) {
+    let mut removed: BoardMask = 1 << mv.src();
+    let mut captured_value = match self.pieces.get(mv.u8_dest()) {
+      NO_PIECE => 0,
+      piece => Board::see_value(PieceType::from_u8(piece)),
+    };
+    if mv.is_en_passant() {
+      captured_value = Board::see_value(PieceType::Pawn);
+      // The captured pawn is next to the source square, on the same rank
+      removed |= 1 << ((mv.src() & !7) | (mv.dest() & 7));
+    }
+
+    let mut piece_value = Board::see_value(PieceType::from_u8(self.pieces.get(mv.u8_src())));
+    if mv.promotion() != Promotion::NoPromotion {
+      let promoted_value = Board::see_value(PieceType::from_u8(mv.promotion().to_piece_const()));
+      captured_value += promoted_value - piece_value;
+      piece_value = promoted_value;
+    }
+
+    (captured_value, piece_value, removed)
+  }
+
+  /// Static exchange evaluation: computes the material balance of the
+  /// sequence of captures on the destination square of a move, each side
+  /// capturing with its least valuable piece and being able to stop
+  /// capturing when it is not favorable anymore. Pins are not considered.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` - A Board object representing a position, side to play, etc.
+  /// * `mv` -   Move to evaluate, typically a capture
+  ///
+  /// ### Return value
+  ///
+  /// Material won by the side playing the move, in centipawns. Negative if
+  /// the move loses material.
+  pub fn see(&self, mv: &Move) -> i32 {
+    let target = mv.u8_dest();
+    let (captured_value, mut piece_value, mut removed) = self.see_initial_exchange(mv);
+    let mut color = match Piece::color(self.pieces.get(mv.u8_src())) {
+      Some(color) => Color::opposite(color),
+      None => return 0,
+    };
+
+    // gains[i] is the material balance for the side making the i-th capture
+    let mut gains = [0; 32];
+    gains[0] = captured_value;
+    let mut depth = 0;
+    while depth < gains.len() - 1 {
+      let attacker = self.least_valuable_attacker_with_removed(target, color, removed);
+      if attacker.is_none() {
+        break;
+      }
+      let (square, piece_type) = attacker.unwrap();
+      removed |= 1 << square;
+
+      // The king cannot capture on a square that is still defended
+      if piece_type == PieceType::King
+         && self.least_valuable_attacker_with_removed(target, Color::opposite(color), removed)
+                .is_some()
+      {
+        break;
+      }
+
+      depth += 1;
+      gains[depth] = piece_value - gains[depth - 1];
+      piece_value = Board::see_value(piece_type);
+      color = Color::opposite(color);
+    }
+
+    // Each side can decide to stop capturing
+    while depth > 0 {
+      gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+      depth -= 1;
+    }
+    gains[0]
+  }
+
+  /// Checks if the static exchange evaluation of a move reaches a threshold.
+  /// This is faster than computing the full static exchange evaluation, as
+  /// it stops as soon as the threshold is proven reachable or unreachable.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -      A Board object representing a position, side to play,
+  ///   etc.
+  /// * `mv` -        Move to evaluate, typically a capture
+  /// * `threshold` - Material balance to reach, in centipawns
+  ///
+  /// ### Return value
+  ///
+  /// True if `see(mv) >= threshold`
+  pub fn see_ge(&self, mv: &Move, threshold: i32) -> bool {
+    let target = mv.u8_dest();
+    let (captured_value, piece_value, mut removed) = self.see_initial_exchange(mv);
+    let mut color = match Piece::color(self.pieces.get(mv.u8_src())) {
+      Some(color) => color,
+      None => return threshold <= 0,
+    };
+
+    // Even if the piece is captured for free, we are above the threshold
+    let mut swap = captured_value - threshold;
+    if swap < 0 {
+      return false;
+    }
+    // Even if the opponent recaptures, we are above the threshold
+    swap = piece_value - swap;
+    if swap <= 0 {
+      return true;
+    }
+
+    // result is true if the side that played the move reaches the threshold
+    // when the exchange stops here
+    let mut result = true;
+    loop {
+      color = Color::opposite(color);
+      let attacker = self.least_valuable_attacker_with_removed(target, color, removed);
+      if attacker.is_none() {
+        break;
+      }
+      let (square, piece_type) = attacker.unwrap();
+      removed |= 1 << square;
+      result = !result;
+
+      if piece_type == PieceType::King {
+        // The king can only capture if the square is not defended anymore
+        let defended = self.least_valuable_attacker_with_removed(target,
+                                                                 Color::opposite(color),
+                                                                 removed)
+                           .is_some();
+        return if defended { !result } else { result };
+      }
+
+      swap = Board::see_value(piece_type) - swap;
+      if swap < result as i32 {
+        break;
+      }
+    }
+
+    result
+  }
+
   /// Returns the number of checks on the board.
   ///
   /// ### Arguments
@@ -493,6 +841,124 @@ impl Board {
     self.checkers.count_few_ones()
   }
 
+  /// Checks if the king of the side to play is in check.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  ///
+  /// ### Return value
+  ///
+  /// True if the side to play is in check, false otherwise
+  #[inline]
+  pub fn is_check(&self) -> bool {
+    self.checkers != 0
+  }
+
+  /// Checks if the side to play is checkmated.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  ///
+  /// ### Return value
+  ///
+  /// True if the side to play is in check and has no legal move
+  pub fn is_checkmate(&self) -> bool {
+    self.is_check() && !self.has_legal_moves()
+  }
+
+  /// Checks if the side to play is stalemated.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  ///
+  /// ### Return value
+  ///
+  /// True if the side to play is not in check and has no legal move
+  pub fn is_stalemate(&self) -> bool {
+    !self.is_check() && !self.has_legal_moves()
+  }
+
+  /// Checks if the side to play has at least one legal move. This is faster
+  /// than generating all the moves, as we stop at the first piece that can
+  /// move.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -           A Board object representing a position, side to play,
+  ///   etc.
+  ///
+  /// ### Return value
+  ///
+  /// True if there is at least one legal move in the position
+  pub fn has_legal_moves(&self) -> bool {
+    let color = self.side_to_play;
+    let (king, pawns, opponent_pawns) = match color {
+      Color::White => (self.pieces.white.king, self.pieces.white.pawn, self.pieces.black.pawn),
+      Color::Black => (self.pieces.black.king, self.pieces.black.pawn, self.pieces.white.pawn),
+    };
+    let ssp = self.get_color_mask(color);
+    let op = self.get_color_mask(Color::opposite(color));
+    let king_position = self.get_king(color) as usize;
+
+    // The king is the most likely to be able to move when in check, we look at
+    // it first. Castling does not need to be considered: if castling is
+    // possible, the king can also move one square.
+    let (king_destinations, _) = self.get_piece_destinations(king_position, op, ssp);
+    if king_destinations != 0 {
+      return true;
+    }
+
+    let checking_ray: BoardMask = match self.checkers.count_few_ones() {
+      0 => u64::MAX,
+      1 => unsafe {
+        RAYS.get_unchecked(king_position).get_unchecked(self.checkers.trailing_zeros() as usize)
+        | self.checkers
+      },
+      _ => return false,
+    };
+
+    let mut pieces = ssp & !king;
+    while pieces != 0 {
+      let source_square = pieces.trailing_zeros() as u8;
+      let (mut destinations, _) = self.get_piece_destinations(source_square as usize, op, ssp);
+
+      // Same restrictions as in the move generation
+      if square_in_mask!(source_square, self.pins) {
+        if LINES[king_position][source_square as usize] & self.pins != 0 {
+          destinations &= self.pins & ROOK_SPAN[source_square as usize] & ROOK_SPAN[king_position];
+        } else if DIAGONALS[king_position][source_square as usize] & self.pins != 0 {
+          destinations &=
+            self.pins & BISHOP_SPAN[source_square as usize] & BISHOP_SPAN[king_position];
+        }
+      }
+
+      if square_in_mask!(source_square, pawns)
+         && self.en_passant_square != INVALID_SQUARE
+         && self.checkers.count_few_ones() == 1
+         && (self.checkers & opponent_pawns) != 0
+      {
+        destinations &= checking_ray | (1 << self.en_passant_square);
+      } else {
+        destinations &= checking_ray;
+      }
+
+      if destinations != 0 {
+        return true;
+      }
+
+      // Remove the last bit set to 1:
+      pieces &= pieces - 1;
+    }
+
+    false
+  }
+
   /// Computes the boardmask of the possible destinations for a piece on a
   /// square.
   ///
@@ -598,6 +1064,35 @@ impl Board {
     }
   }
 
+  /// Computes the pseudo-attacks of the piece on a square: the squares it
+  /// attacks, ignoring pins and the side to play.
+  /// Squares occupied by pieces of the same color are included, so that the
+  /// mask can be used to count defenders. Unlike `get_piece_control_mask`,
+  /// sliding pieces stop on the enemy king.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -   A Board object representing a position, side to play, etc.
+  /// * `square` - Square of the piece for which we want the attacks
+  ///
+  /// ### Return value
+  ///
+  /// BoardMask value indicating all the squares attacked by the piece, 0 if
+  /// the square is empty
+  pub fn attacks_from(&self, square: u8) -> BoardMask {
+    let occupancy = self.pieces.all();
+    match self.pieces.get(square) {
+      WHITE_KING | BLACK_KING => KING_MOVES[square as usize],
+      WHITE_QUEEN | BLACK_QUEEN => get_queen_moves(0, occupancy, square as usize),
+      WHITE_ROOK | BLACK_ROOK => get_rook_moves(0, occupancy, square as usize),
+      WHITE_BISHOP | BLACK_BISHOP => get_bishop_moves(0, occupancy, square as usize),
+      WHITE_KNIGHT | BLACK_KNIGHT => KNIGHT_MOVES[square as usize],
+      WHITE_PAWN => get_white_pawn_captures(square as usize),
+      BLACK_PAWN => get_black_pawn_captures(square as usize),
+      _ => 0,
+    }
+  }
+
   /// Converts Rank / File into a board index
   ///
   /// Returns an index in the range 0..63. Asserts in debug mode if
@@ -636,6 +1131,22 @@ impl Board {
     self.pieces.get(Board::fr_to_index(file, rank))
   }
 
+  /// Returns the color and type of the piece on a square.
+  /// Prefer `pieces.get` in performance critical code, it avoids the
+  /// conversion from the u8 piece value.
+  ///
+  /// ### Arguments
+  ///
+  /// * `square`: Board square index, [0..=63]
+  ///
+  /// ### Return value
+  ///
+  /// Color and type of the piece, None if the square is empty
+  pub fn piece_on(&self, square: u8) -> Option<(Color, PieceType)> {
+    let piece = self.pieces.get(square);
+    Piece::color(piece).map(|color| (color, PieceType::from_u8(piece)))
+  }
+
   // ---------------------------------------------------------------------------
   // Move related functions
 
@@ -664,160 +1175,144 @@ impl Board {
   /// Vector of Moves
   #[inline]
   pub fn get_white_moves(&self) -> Vec<Move> {
-    let mut all_moves = Vec::with_capacity(MAXIMUM_LEGAL_MOVES);
-    // This is used to down-prioritize some moves
-    let mut other_moves = Vec::with_capacity(MAXIMUM_LEGAL_MOVES);
+    self.get_moves_for(Color::White)
+  }
 
-    let mut ssp = self.get_color_mask(Color::White);
-    let op = self.get_color_mask(Color::Black);
+  // Get all the possible moves for black in a position
+  #[inline]
+  pub fn get_black_moves(&self) -> Vec<Move> {
+    self.get_moves_for(Color::Black)
+  }
 
-    // Try castling first. This will have an influence on the engine if
-    // interesting moves are placed first.
-    if self.castling_rights.K()
-       && self.checks() == 0
-       && (self.pieces.all() & FREE_SQUARE_MASK_WHITE_KINGSIDE) == 0
-       && self.get_attacked_squares(UNATTACKED_SQUARE_MASK_WHITE_KINGSIDE, Color::Black) == 0
-    {
-      other_moves.push(castle_mv!(4, 6));
-    }
-    if self.castling_rights.Q()
-       && self.checks() == 0
-       && (self.pieces.all() & FREE_SQUARE_MASK_WHITE_QUEENSIDE) == 0
-       && self.get_attacked_squares(UNATTACKED_SQUARE_MASK_WHITE_QUEENSIDE, Color::Black) == 0
-    {
-      other_moves.push(castle_mv!(4, 2));
+  /// Counts the leaf nodes of the move tree, to validate the move generation
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:  Board to start from
+  /// * `depth`: Number of half-moves to play
+  ///
+  /// ### Return value
+  ///
+  /// Number of positions reachable after exactly `depth` half-moves
+  pub fn perft(&self, depth: usize) -> usize {
+    if depth == 0 {
+      return 1;
     }
 
-    let mut checking_ray: BoardMask = u64::MAX;
-    let king_position = self.get_king(Color::White) as usize;
-
-    match self.checkers.count_few_ones() {
-      0 => {},
-      1 => {
-        checking_ray = unsafe {
-          RAYS.get_unchecked(king_position).get_unchecked(self.checkers.trailing_zeros() as usize)
-          | self.checkers
-        }
-      },
-      _ => ssp = self.pieces.white.king,
+    let moves = self.get_moves();
+    if depth == 1 {
+      return moves.len();
     }
 
-    // Only generate moves if we have a piece on the square
-    while ssp != 0 {
-      let source_square = ssp.trailing_zeros() as u8;
-      let (mut destinations, promotion) =
-        self.get_piece_destinations(source_square as usize,
-                                    op,
-                                    self.get_color_mask(Color::White));
-
-      // Restrict destinations not to move out of pins.
-      // if there is a check, you can only move into checking rays with other pieces
-      // than the king.
-      if square_in_mask!(source_square, self.pins) {
-        if LINES[king_position][source_square as usize] & self.pins != 0 {
-          destinations &= self.pins & ROOK_SPAN[source_square as usize] & ROOK_SPAN[king_position];
-        } else if DIAGONALS[king_position][source_square as usize] & self.pins != 0 {
-          destinations &=
-            self.pins & BISHOP_SPAN[source_square as usize] & BISHOP_SPAN[king_position];
-        }
-      }
-
-      // If a pawn double jump delivers check, we should be able to en-passant it,
-      // it removes the checking piece even though outside of the checking ray
-      // If a pawn double jumps but no pawn is delivering check, it's a discovered
-      // check.
-      if square_in_mask!(source_square, self.pieces.white.pawn)
-         && self.en_passant_square != INVALID_SQUARE
-         && self.checkers.count_few_ones() == 1
-         && (self.checkers & self.pieces.black.pawn) != 0
-      {
-        destinations &= checking_ray | (1 << self.en_passant_square);
-      } else if source_square != king_position as u8 {
-        destinations &= checking_ray;
-      }
-
-      while destinations != 0 {
-        let destination_square = destinations.trailing_zeros() as u8;
-
-        // Determine if this is a capture or en-passant
-        let capture = PieceType::from_u8(self.pieces.get(destination_square as u8));
-        let en_passant = square_in_mask!(source_square, self.pieces.white.pawn)
-                         && destination_square == self.en_passant_square;
-
-        if en_passant {
-          all_moves.push(en_passant_mv!(source_square, destination_square));
-        } else if promotion {
-          all_moves.push(mv!(source_square,
-                             destination_square,
-                             Promotion::WhiteQueen,
-                             capture));
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::WhiteRook,
-                               capture));
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::WhiteKnight,
-                               capture));
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::WhiteBishop,
-                               capture));
-        } else if capture != PieceType::King {
-          // King means no capture.. not pretty I know.
-          all_moves.push(mv!(source_square,
-                             destination_square,
-                             Promotion::NoPromotion,
-                             capture));
-        } else {
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::NoPromotion,
-                               capture));
-        }
+    let mut nodes = 0;
+    for m in &moves {
+      let mut board = *self;
+      board.apply_move(m);
+      nodes += board.perft(depth - 1);
+    }
+    nodes
+  }
 
-        // Remove the last bit set to 1:
-        destinations &= destinations - 1;
-      }
+  /// Computes the material count used to determine the game phase, i.e. the
+  /// weighted sum of the queens, rooks and minor pieces of both sides.
+  ///
+  /// ### Return value
+  ///
+  /// Game phase value, 62 in the starting position
+  pub fn game_phase_value(&self) -> u32 {
+    self.pieces.queens().count_few_ones() * QUEEN_PHASE_VALUE
+    + self.pieces.rooks().count_few_ones() * ROOK_PHASE_VALUE
+    + self.pieces.minors().count_few_ones() * MINOR_PHASE_VALUE
+  }
 
-      // Remove the last bit set to 1:
-      ssp &= ssp - 1;
+  /// Orders the moves of a position where the side to play is in check:
+  /// captures of the checking piece first, with the least valuable piece
+  /// first, then blocks along the checking ray, then king moves. Moves of the
+  /// same kind keep their relative order.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:  Board on which the moves are played
+  /// * `moves`: Legal moves of the position, re-ordered in place
+  pub fn order_check_evasions(&self, moves: &mut [Move]) {
+    if !self.is_check() {
+      return;
     }
 
-    all_moves.append(&mut other_moves);
-    all_moves
+    let king_position = self.get_king(self.side_to_play) as usize;
+    let checking_ray = match self.checks() {
+      1 => RAYS[king_position][self.checkers.trailing_zeros() as usize],
+      _ => 0,
+    };
+
+    // Kind of evasion first, then value of the piece for captures
+    let evasion_rank = |m: &Move| {
+      let piece_value = (Piece::material_value_from_u8(self.pieces.get(m.u8_src())) * 100.0) as u32;
+      if square_in_mask!(m.u8_dest(), self.checkers) || m.is_en_passant() {
+        (0, piece_value)
+      } else if m.u8_src() as usize != king_position && square_in_mask!(m.u8_dest(), checking_ray) {
+        (1, 0)
+      } else {
+        (2, 0)
+      }
+    };
+    moves.sort_by_key(evasion_rank);
   }
 
-  // Get all the possible moves for black in a position
-  #[inline]
-  pub fn get_black_moves(&self) -> Vec<Move> {
+  /// Get all the possible moves for a color in a position
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:  Board reference to calculate legal moves from
+  /// * `color`: Side for which we generate the moves. Checks and pins are the
+  ///   ones of the side to play.
+  ///
+  /// ### Return value
+  ///
+  /// Vector of Moves, with the most interesting moves placed first
+  pub fn get_moves_for(&self, color: Color) -> Vec<Move> {
     let mut all_moves = Vec::with_capacity(MAXIMUM_LEGAL_MOVES);
     // This is used to down-prioritize some moves
     let mut other_moves = Vec::with_capacity(MAXIMUM_LEGAL_MOVES);
 
-    let mut ssp = self.get_color_mask(Color::Black);
-    let op = self.get_color_mask(Color::White);
+    let table = match color {
+      Color::White => &WHITE_MOVE_GENERATION_TABLE,
+      Color::Black => &BLACK_MOVE_GENERATION_TABLE,
+    };
+    let (our_pieces, their_pieces) = match color {
+      Color::White => (&self.pieces.white, &self.pieces.black),
+      Color::Black => (&self.pieces.black, &self.pieces.white),
+    };
+    let (kingside_castle, queenside_castle) = match color {
+      Color::White => (self.castling_rights.K(), self.castling_rights.Q()),
+      Color::Black => (self.castling_rights.k(), self.castling_rights.q()),
+    };
+
+    let opponent = Color::opposite(color);
+    let mut ssp = self.get_color_mask(color);
+    let op = self.get_color_mask(opponent);
 
-    // Now check castling.
-    if self.castling_rights.k()
+    // Try castling first. This will have an influence on the engine if
+    // interesting moves are placed first.
+    if kingside_castle
        && self.checks() == 0
-       && (self.pieces.all() & FREE_SQUARE_MASK_BLACK_KINGSIDE) == 0
-       && self.get_attacked_squares(UNATTACKED_SQUARE_MASK_BLACK_KINGSIDE, Color::White) == 0
+       && (self.pieces.all() & table.kingside_free_squares) == 0
+       && self.get_attacked_squares(table.kingside_unattacked_squares, opponent) == 0
     {
-      other_moves.push(castle_mv!(60, 62));
+      other_moves.push(table.kingside_castle);
     }
-    if self.castling_rights.q()
+    if queenside_castle
        && self.checks() == 0
-       && (self.pieces.all() & FREE_SQUARE_MASK_BLACK_QUEENSIDE) == 0
-       && self.get_attacked_squares(UNATTACKED_SQUARE_MASK_BLACK_QUEENSIDE, Color::White) == 0
+       && (self.pieces.all() & table.queenside_free_squares) == 0
+       && self.get_attacked_squares(table.queenside_unattacked_squares, opponent) == 0
     {
-      other_moves.push(castle_mv!(60, 58));
+      other_moves.push(table.queenside_castle);
     }
 
     let mut checking_ray: BoardMask = u64::MAX;
-    let king_position = self.get_king(Color::Black) as usize;
+    let king_position = self.get_king(color) as usize;
 
-    match self.checkers.count_ones() {
+    match self.checkers.count_few_ones() {
       0 => {},
       1 => {
         checking_ray = unsafe {
@@ -825,16 +1320,14 @@ impl Board {
           | self.checkers
         }
       },
-      _ => ssp = self.pieces.black.king,
+      _ => ssp = our_pieces.king,
     }
 
     // Only generate moves if we have a piece on the square
     while ssp != 0 {
       let source_square = ssp.trailing_zeros() as u8;
       let (mut destinations, promotion) =
-        self.get_piece_destinations(source_square as usize,
-                                    op,
-                                    self.get_color_mask(Color::Black));
+        self.get_piece_destinations(source_square as usize, op, self.get_color_mask(color));
 
       // Restrict destinations not to move out of pins.
       // if there is a check, you can only move into checking rays with other pieces
@@ -852,10 +1345,10 @@ impl Board {
       // it removes the checking piece even though outside of the checking ray
       // If a pawn double jumps but no pawn is delivering check, it's a discovered
       // check.
-      if square_in_mask!(source_square, self.pieces.black.pawn)
+      if square_in_mask!(source_square, our_pieces.pawn)
          && self.en_passant_square != INVALID_SQUARE
          && self.checkers.count_few_ones() == 1
-         && (self.checkers & self.pieces.white.pawn) != 0
+         && (self.checkers & their_pieces.pawn) != 0
       {
         destinations &= checking_ray | (1 << self.en_passant_square);
       } else if source_square != king_position as u8 {
@@ -867,28 +1360,20 @@ impl Board {
 
         // Determine if this is a capture or en-passant
         let capture = PieceType::from_u8(self.pieces.get(destination_square));
-        let en_passant = square_in_mask!(source_square, self.pieces.black.pawn)
+        let en_passant = square_in_mask!(source_square, our_pieces.pawn)
                          && destination_square == self.en_passant_square;
 
         if en_passant {
           all_moves.push(en_passant_mv!(source_square, destination_square));
         } else if promotion {
+          // Queen promotions first, under-promotions are down-prioritized
           all_moves.push(mv!(source_square,
                              destination_square,
-                             Promotion::BlackQueen,
+                             table.promotions[0],
                              capture));
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::BlackRook,
-                               capture));
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::BlackKnight,
-                               capture));
-          other_moves.push(mv!(source_square,
-                               destination_square,
-                               Promotion::BlackBishop,
-                               capture));
+          for under_promotion in &table.promotions[1..] {
+            other_moves.push(mv!(source_square, destination_square, *under_promotion, capture));
+          }
         } else if capture != PieceType::King {
           // King means no capture.. not pretty I know.
           all_moves.push(mv!(source_square,
@@ -910,6 +1395,10 @@ impl Board {
       ssp &= ssp - 1;
     }
 
+    debug_assert!(all_moves.len() + other_moves.len() <= MAXIMUM_LEGAL_MOVES,
+                  "Generated {} moves, more than the maximum of {}",
+                  all_moves.len() + other_moves.len(),
+                  MAXIMUM_LEGAL_MOVES);
     all_moves.append(&mut other_moves);
     all_moves
   }
@@ -1059,6 +1548,12 @@ impl Board {
     self.update_hash_side_to_play();
     self.update_checkers();
     self.update_pins();
+
+    debug_assert_eq!(self.hash,
+                     self.get_recomputed_hash(),
+                     "Incremental hash mismatch after {} on board {}",
+                     chess_move,
+                     self.to_fen());
   }
 
   /// Flips the board, i.e. changes the side to play
@@ -1071,6 +1566,37 @@ impl Board {
     self.side_to_play = Color::opposite(self.side_to_play);
   }
 
+  /// Mirrors the board vertically and swaps the colors of the pieces, giving
+  /// the same position from the point of view of the other side.
+  /// Castling rights, en-passant square and side to play are mirrored too.
+  ///
+  /// ### Return value
+  ///
+  /// Color-reflected board
+  pub fn mirror(&self) -> Board {
+    let mut board = Board::new();
+    for (square, color, p_type) in self.pieces.iter() {
+      let mirrored_piece = Piece { p_type,
+                                   color: Color::opposite(color) };
+      board.pieces.add(mirrored_piece.as_u8(), square ^ 56);
+    }
+
+    board.side_to_play = Color::opposite(self.side_to_play);
+    board.castling_rights = CastlingRights::none();
+    board.castling_rights.set_K(self.castling_rights.k());
+    board.castling_rights.set_Q(self.castling_rights.q());
+    board.castling_rights.set_k(self.castling_rights.K());
+    board.castling_rights.set_q(self.castling_rights.Q());
+    if self.en_passant_square != INVALID_SQUARE {
+      board.en_passant_square = self.en_passant_square ^ 56;
+    }
+
+    board.compute_hash();
+    board.update_checkers();
+    board.update_pins();
+    board
+  }
+
   /// Takes a move notation from a PGN, tries to find the corresponding move
   /// and apply it on our board.
   ///
@@ -1168,59 +1694,58 @@ impl Board {
         }
       }
     } else {
-      // Here we are in the case of Source piece, destination square:
-      // Note that chars is inverted: Nbd7 -> 7,d,b,N
-      let chars: Vec<char> = notation.chars().rev().collect();
-      let mut dest = String::new();
-      dest.push(chars[1]);
-      dest.push(chars[0]);
-      let destination_square = string_to_square(dest.as_str());
-
-      // Put a limitation on the source square (with a BoardMask) if the initial piece
-      // and/or file/rank is indicated
-      let mut source_mask = u64::MAX;
-
-      if !notation.starts_with('K')
-         && !notation.starts_with('Q')
-         && !notation.starts_with('R')
-         && !notation.starts_with('B')
-         && !notation.starts_with('N')
-      {
-        source_mask &= self.pieces.pawns();
+      // Here we are in the case of:
+      // [piece] [source file] [source rank] destination, e.g. Nbd7, R1a3, Qh4e1
+      let chars: Vec<char> = notation.chars().collect();
+      if chars.len() < 2 || chars.len() > 5 {
+        println!("Could not parse move notation: {} for board {}",
+                 move_notation,
+                 self.to_fen());
+        return Err(());
+      }
+
+      let destination_square =
+        string_to_square(chars[chars.len() - 2..].iter().collect::<String>().as_str());
+
+      // Put a limitation on the source square (with a BoardMask) based on the
+      // piece and the file/rank that are indicated
+      let mut disambiguation = &chars[..chars.len() - 2];
+      let (mut source_mask, piece_letter) = match disambiguation.first() {
+        Some('K') => (self.pieces.white.king | self.pieces.black.king, true),
+        Some('Q') => (self.pieces.queens(), true),
+        Some('R') => (self.pieces.rooks(), true),
+        Some('B') => (self.pieces.bishops(), true),
+        Some('N') => (self.pieces.knights(), true),
+        _ => (self.pieces.pawns(), false),
+      };
+      if piece_letter {
+        disambiguation = &disambiguation[1..];
       }
 
-      for i in 0..(notation.chars().count() - 2) {
-        source_mask &= match notation.chars().nth(i) {
-          Some('a') => FILES[0],
-          Some('b') => FILES[1],
-          Some('c') => FILES[2],
-          Some('d') => FILES[3],
-          Some('e') => FILES[4],
-          Some('f') => FILES[5],
-          Some('g') => FILES[6],
-          Some('h') => FILES[7],
-          Some('1') => RANKS[0],
-          Some('2') => RANKS[1],
-          Some('3') => RANKS[2],
-          Some('4') => RANKS[3],
-          Some('5') => RANKS[4],
-          Some('6') => RANKS[5],
-          Some('7') => RANKS[6],
-          Some('8') => RANKS[7],
-          Some('K') => self.pieces.white.king | self.pieces.black.king,
-          Some('Q') => self.pieces.queens(),
-          Some('R') => self.pieces.rooks(),
-          Some('B') => self.pieces.bishops(),
-          Some('N') => self.pieces.knights(),
+      // At most a source file followed by a source rank.
+      if disambiguation.len() > 2 {
+        println!("Could not identify source file/rank move: {} for board {}",
+                 move_notation,
+                 self.to_fen());
+        return Err(());
+      }
+      let mut expect_file = true;
+      for c in disambiguation {
+        source_mask &= match c {
+          'a'..='h' if expect_file => FILES[(*c as u8 - b'a') as usize],
+          '1'..='8' => RANKS[(*c as u8 - b'1') as usize],
           _ => {
             println!("Could not identify source file/rank move: {} for board {}",
                      move_notation,
                      self.to_fen());
             return Err(());
           },
-        }
+        };
+        expect_file = false;
       }
 
+      // The notation must designate a single legal move
+      let mut matching_moves = 0;
       for m in candidate_moves {
         if m.dest() == destination_square as move_t
            && m.is_capture() == capture
@@ -1228,9 +1753,15 @@ impl Board {
            && square_in_mask!(m.src(), source_mask)
         {
           mv = m;
-          break;
+          matching_moves += 1;
         }
       }
+      if matching_moves > 1 {
+        println!("Ambiguous move notation: {} for board {}",
+                 move_notation,
+                 self.to_fen());
+        return Err(());
+      }
     }
 
     // Did we find the move?
@@ -1250,6 +1781,196 @@ impl Board {
     Ok(mv)
   }
 
+  /// Converts a move to its Standard Algebraic Notation (SAN), e.g. `Nbd7`,
+  /// `exd5`, `O-O` or `e8=Q+`
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:       Board on which the move is about to be played
+  /// * `chess_move`: Legal move in the board position
+  ///
+  /// ### Return Value
+  ///
+  /// String with the SAN for the move, including the check (+) or
+  /// checkmate (#) suffix.
+  pub fn move_to_san(&self, chess_move: &Move) -> String {
+    self.move_to_san_with_legal_moves(chess_move, &self.get_moves())
+  }
+
+  /// Same as `move_to_san`, using an already computed list of legal moves to
+  /// disambiguate the move. Use this to convert several moves of the same
+  /// position without generating the move list each time.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:        Board on which the move is about to be played
+  /// * `chess_move`:  Legal move in the board position
+  /// * `legal_moves`: All the legal moves in the board position
+  ///
+  /// ### Return Value
+  ///
+  /// String with the SAN for the move, including the check (+) or
+  /// checkmate (#) suffix.
+  pub fn move_to_san_with_legal_moves(&self, chess_move: &Move, legal_moves: &[Move]) -> String {
+    let mut san = String::new();
+    let source = chess_move.u8_src();
+    let destination = chess_move.u8_dest();
+    let piece = self.piece_on(source);
+    let piece_type = piece.map(|(_, piece_type)| piece_type).unwrap_or_default();
+
+    if chess_move.is_castle() {
+      if destination % 8 == 6 {
+        san.push_str("O-O");
+      } else {
+        san.push_str("O-O-O");
+      }
+    } else {
+      if piece_type == PieceType::Pawn {
+        if chess_move.is_capture() {
+          san.push((b'a' + source % 8) as char);
+        }
+      } else {
+        san.push(Piece { p_type: piece_type,
+                         color:  Color::White, }.as_char());
+
+        // Check if another piece of the same type can go to the same square
+        let ambiguous_sources: Vec<u8> = legal_moves.iter()
+                                                    .filter(|m| {
+                                                      m.dest() == chess_move.dest()
+                                                      && m.src() != chess_move.src()
+                                                      && self.piece_on(m.u8_src()) == piece
+                                                    })
+                                                    .map(|m| m.u8_src())
+                                                    .collect();
+
+        if !ambiguous_sources.is_empty() {
+          let same_file = ambiguous_sources.iter().any(|s| s % 8 == source % 8);
+          let same_rank = ambiguous_sources.iter().any(|s| s / 8 == source / 8);
+          if !same_file {
+            san.push((b'a' + source % 8) as char);
+          } else if !same_rank {
+            san.push((b'1' + source / 8) as char);
+          } else {
+            san.push_str(square_to_string(source).as_str());
+          }
+        }
+      }
+
+      if chess_move.is_capture() {
+        san.push('x');
+      }
+      san.push_str(square_to_string(destination).as_str());
+
+      if let Some(promotion) = chess_move.promotion().to_char() {
+        san.push('=');
+        san.push(promotion.to_ascii_uppercase());
+      }
+    }
+
+    // Check or checkmate suffix
+    let mut board = *self;
+    board.apply_move(chess_move);
+    if board.is_check() {
+      if board.is_checkmate() {
+        san.push('#');
+      } else {
+        san.push('+');
+      }
+    }
+
+    san
+  }
+
+  /// Determines if a move gives check to the opponent king, without applying
+  /// the move on the board.
+  ///
+  /// Direct checks from the moved (or promoted) piece and discovered checks
+  /// from sliding pieces are detected. En-passant captures and castling are
+  /// also handled.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self` -       A Board object representing a position, side to play,
+  ///   etc.
+  /// * `chess_move` - A legal move to play on the board
+  ///
+  /// ### Return value
+  ///
+  /// True if the opponent king is in check after the move, false otherwise
+  pub fn gives_check(&self, chess_move: &Move) -> bool {
+    let source = chess_move.u8_src();
+    let destination = chess_move.u8_dest();
+    let (our_pieces, king_square) = match self.side_to_play {
+      Color::White => (self.pieces.white, self.get_king(Color::Black) as usize),
+      Color::Black => (self.pieces.black, self.get_king(Color::White) as usize),
+    };
+
+    // Piece standing on the destination square after the move
+    let piece_type = if chess_move.promotion() != Promotion::NoPromotion {
+      PieceType::from_u8(chess_move.promotion().to_piece_const())
+    } else {
+      PieceType::from_u8(self.pieces.get(source))
+    };
+
+    // Our pieces after the move, the moved piece is removed from its source
+    let mut pawns = our_pieces.pawn;
+    let mut knights = our_pieces.knight;
+    let mut diagonal_sliders = our_pieces.bishop | our_pieces.queen;
+    let mut line_sliders = our_pieces.majors();
+    unset_square_in_mask!(source, pawns);
+    unset_square_in_mask!(source, knights);
+    unset_square_in_mask!(source, diagonal_sliders);
+    unset_square_in_mask!(source, line_sliders);
+    match piece_type {
+      PieceType::Pawn => set_square_in_mask!(destination, pawns),
+      PieceType::Knight => set_square_in_mask!(destination, knights),
+      PieceType::Bishop => set_square_in_mask!(destination, diagonal_sliders),
+      PieceType::Rook => set_square_in_mask!(destination, line_sliders),
+      PieceType::Queen => {
+        set_square_in_mask!(destination, diagonal_sliders);
+        set_square_in_mask!(destination, line_sliders);
+      },
+      PieceType::King => {},
+    }
+
+    // Occupancy after the move
+    let mut occupancy = self.pieces.all();
+    unset_square_in_mask!(source, occupancy);
+    set_square_in_mask!(destination, occupancy);
+
+    if chess_move.is_en_passant() {
+      // The captured pawn also vacates its square
+      let captured_square = match self.side_to_play {
+        Color::White => destination - 8,
+        Color::Black => destination + 8,
+      };
+      unset_square_in_mask!(captured_square, occupancy);
+    } else if chess_move.is_castle() {
+      // The rook moves as well
+      let (rook_source, rook_destination) = match destination {
+        6 => (7, 5),
+        2 => (0, 3),
+        62 => (63, 61),
+        _ => (56, 59),
+      };
+      unset_square_in_mask!(rook_source, occupancy);
+      set_square_in_mask!(rook_destination, occupancy);
+      unset_square_in_mask!(rook_source, line_sliders);
+      set_square_in_mask!(rook_destination, line_sliders);
+    }
+
+    // note: Here it is inverted on purpose, like in get_attackers
+    let pawn_control = match self.side_to_play {
+      Color::White => BLACK_PAWN_CONTROL[king_square],
+      Color::Black => WHITE_PAWN_CONTROL[king_square],
+    };
+
+    (pawn_control & pawns) != 0
+    || (KNIGHT_MOVES[king_square] & knights) != 0
+    || (get_rook_moves(0, occupancy, king_square) & line_sliders) != 0
+    || (get_bishop_moves(0, occupancy, king_square) & diagonal_sliders) != 0
+  }
+
   /// Makes sure that the number of checks on the board is correct.
   ///
   /// ### Arguments
@@ -1398,6 +2119,177 @@ impl Board {
     }
   }
 
+  /// Counts the number of pieces of a given type and color on the board
+  ///
+  /// ### Arguments
+  ///
+  /// * `color` - Color of the pieces to count
+  /// * `piece` - Type of the pieces to count
+  ///
+  /// ### Return value
+  ///
+  /// Number of pieces matching the color and type
+  #[inline]
+  pub fn piece_count(&self, color: Color, piece: PieceType) -> u32 {
+    let masks = match color {
+      Color::White => &self.pieces.white,
+      Color::Black => &self.pieces.black,
+    };
+    match piece {
+      PieceType::King => masks.king.count_few_ones(),
+      PieceType::Queen => masks.queen.count_few_ones(),
+      PieceType::Rook => masks.rook.count_few_ones(),
+      PieceType::Bishop => masks.bishop.count_few_ones(),
+      PieceType::Knight => masks.knight.count_few_ones(),
+      PieceType::Pawn => masks.pawn.count_ones(),
+    }
+  }
+
+  /// Checks if a pawn is passed, i.e. no enemy pawn stands in front of it on
+  /// its file or the adjacent files
+  ///
+  /// ### Arguments
+  ///
+  /// * `square` - Square of the pawn
+  /// * `color` -  Color of the pawn
+  ///
+  /// ### Return value
+  ///
+  /// True if the pawn is passed, false if it is not or if there is no pawn of
+  /// that color on the square
+  #[inline]
+  pub fn is_passed_pawn(&self, square: u8, color: Color) -> bool {
+    let (pawns, enemy_pawns, passed_pawn_area) = match color {
+      Color::White => (self.pieces.white.pawn, self.pieces.black.pawn, &WHITE_PASSED_PAWN_AREA),
+      Color::Black => (self.pieces.black.pawn, self.pieces.white.pawn, &BLACK_PASSED_PAWN_AREA),
+    };
+    square_in_mask!(square, pawns) && passed_pawn_area[square as usize] & enemy_pawns == 0
+  }
+
+  /// Checks if a pawn is isolated, i.e. there is no friendly pawn on the
+  /// adjacent files
+  ///
+  /// ### Arguments
+  ///
+  /// * `square` - Square of the pawn
+  /// * `color` -  Color of the pawn
+  ///
+  /// ### Return value
+  ///
+  /// True if the pawn is isolated, false if it is not or if there is no pawn
+  /// of that color on the square
+  #[inline]
+  pub fn is_isolated_pawn(&self, square: u8, color: Color) -> bool {
+    let pawns = match color {
+      Color::White => self.pieces.white.pawn,
+      Color::Black => self.pieces.black.pawn,
+    };
+    let file = (square % 8) as usize;
+    let mut adjacent_files: BoardMask = 0;
+    if file > 0 {
+      adjacent_files |= FILES[file - 1];
+    }
+    if file < 7 {
+      adjacent_files |= FILES[file + 1];
+    }
+    square_in_mask!(square, pawns) && adjacent_files & pawns == 0
+  }
+
+  /// Computes the zone around a king: the king square, the squares adjacent
+  /// to it and one extra rank in front of it.
+  ///
+  /// ### Arguments
+  ///
+  /// * `color` - Color of the king
+  ///
+  /// ### Return value
+  ///
+  /// BoardMask of the king zone, empty if there is no king of that color
+  pub fn king_zone(&self, color: Color) -> BoardMask {
+    let king_square = self.get_king(color) as usize;
+    if king_square >= 64 {
+      return 0;
+    }
+    let zone = KING_MOVES[king_square] | (1 << king_square);
+    match color {
+      Color::White => zone | (zone << 8),
+      Color::Black => zone | (zone >> 8),
+    }
+  }
+
+  /// Counts the pieces of a color attacking a zone and sums their attack
+  /// weights (knights and bishops 2, rooks 3, queens 5, pawns 1). A piece
+  /// attacking several squares of the zone is counted once.
+  ///
+  /// ### Arguments
+  ///
+  /// * `zone` -  Squares to check, usually the `king_zone` of a king
+  /// * `color` - Color of the attacking pieces
+  ///
+  /// ### Return value
+  ///
+  /// Tuple with the number of attackers and their total attack weight
+  pub fn attackers_on_zone(&self, zone: BoardMask, color: Color) -> (u8, u32) {
+    let mut attackers: BoardMask = 0;
+    let mut surface = zone;
+    while surface != 0 {
+      let square = surface.trailing_zeros() as u8;
+      attackers |= self.get_attackers(square, color);
+      surface &= surface - 1;
+    }
+
+    let count = attackers.count_ones() as u8;
+    let mut weight: u32 = 0;
+    while attackers != 0 {
+      let square = attackers.trailing_zeros() as u8;
+      weight += match self.piece_on(square) {
+        Some((_, PieceType::Queen)) => 5,
+        Some((_, PieceType::Rook)) => 3,
+        Some((_, PieceType::Bishop)) | Some((_, PieceType::Knight)) => 2,
+        Some((_, PieceType::Pawn)) => 1,
+        _ => 0,
+      };
+      attackers &= attackers - 1;
+    }
+    (count, weight)
+  }
+
+  /// Counts the enemy pieces attacking the zone around the king of a color.
+  ///
+  /// ### Arguments
+  ///
+  /// * `color` - Color of the king under attack
+  ///
+  /// ### Return value
+  ///
+  /// Tuple with the number of attackers and their total attack weight, see
+  /// `attackers_on_zone`
+  pub fn count_attackers_near_king(&self, color: Color) -> (u8, u32) {
+    self.attackers_on_zone(self.king_zone(color), Color::opposite(color))
+  }
+
+  /// Computes the material balance on the board, kings excluded.
+  ///
+  /// ### Return value
+  ///
+  /// Material balance in centipawns, positive if White is ahead, negative if
+  /// Black is ahead.
+  pub fn material_balance(&self) -> i32 {
+    let mut balance: i32 = 0;
+    for piece in [PieceType::Queen,
+                  PieceType::Rook,
+                  PieceType::Bishop,
+                  PieceType::Knight,
+                  PieceType::Pawn]
+    {
+      let value = (Piece::material_value_from_type(piece) * 100.0).round() as i32;
+      balance += (self.piece_count(Color::White, piece) as i32
+                  - self.piece_count(Color::Black, piece) as i32)
+                 * value;
+    }
+    balance
+  }
+
   /// Converts first substring of a FEN (with the pieces) to a board
   ///
   /// ### Arguments:
@@ -1406,13 +2298,77 @@ impl Board {
   ///
   /// ### Return Value
   ///
-  /// Board object matching the FEN
+  /// Board object matching the FEN. If the FEN is invalid, an error is logged
+  /// and the board is filled with whatever could be parsed.
   pub fn from_fen(fen: &str) -> Self {
+    match Board::try_from_fen(fen) {
+      Ok(board) => board,
+      Err(e) => {
+        error!("Invalid FEN {}: {}", fen, e);
+        Board::parse_fen(fen)
+      },
+    }
+  }
+
+  /// Converts a FEN to a board, checking that the FEN is well-formed.
+  ///
+  /// ### Arguments:
+  ///
+  /// * `fen` : fen to use to create a board object
+  ///
+  /// ### Return Value
+  ///
+  /// Board object matching the FEN, or the reason why the FEN is invalid.
+  pub fn try_from_fen(fen: &str) -> Result<Self, FenError> {
+    let fen_parts: Vec<&str> = fen.split(' ').collect();
+    if fen_parts.len() != 6 {
+      return Err(FenError::InvalidFieldCount(fen_parts.len()));
+    }
+
+    PieceSet::try_from_fen(fen_parts[0])?;
+    if fen_parts[0].matches('K').count() != 1 {
+      return Err(FenError::InvalidKingCount(Color::White));
+    }
+    if fen_parts[0].matches('k').count() != 1 {
+      return Err(FenError::InvalidKingCount(Color::Black));
+    }
+
+    if !matches!(fen_parts[1], "w" | "b") {
+      return Err(FenError::InvalidSideToMove(fen_parts[1].to_string()));
+    }
+
+    let castling = fen_parts[2];
+    if castling != "-"
+       && (castling.is_empty()
+           || castling.chars().any(|c| !"KQkq".contains(c))
+           || castling.chars().any(|c| castling.matches(c).count() > 1))
+    {
+      return Err(FenError::InvalidCastlingRights(castling.to_string()));
+    }
+
+    let en_passant = fen_parts[3];
+    if en_passant != "-"
+       && (en_passant.len() != 2
+           || string_to_square(en_passant) == INVALID_SQUARE
+           || !(en_passant.ends_with('3') || en_passant.ends_with('6')))
+    {
+      return Err(FenError::InvalidEnPassantSquare(en_passant.to_string()));
+    }
+
+    Ok(Board::parse_fen(fen))
+  }
+
+  /// Converts a FEN to a board without checking it. Unexpected characters are
+  /// ignored.
+  ///
+  /// ### Arguments:
+  ///
+  /// * `fen` : fen to use to create a board object
+  fn parse_fen(fen: &str) -> Self {
     let mut board = Board::new();
 
     let fen_parts: Vec<&str> = fen.split(' ').collect();
     if fen_parts.len() < 6 {
-      error!("FEN string too small to generate a board");
       return board;
     }
 
@@ -1449,36 +2405,12 @@ impl Board {
   ///
   /// String containing the FEN description of the board.
   pub fn to_fen(&self) -> String {
-    let mut fen = String::new();
-    let mut empty_squares = 0;
-    for rank in (1..=8).rev() {
-      for file in 1..=8 {
-        match self.get_piece(file, rank) {
-          NO_PIECE => empty_squares += 1,
-          WHITE_KING..=BLACK_PAWN => {
-            if empty_squares > 0 {
-              fen.push(char::from_digit(empty_squares, 10).unwrap());
-              empty_squares = 0;
-            }
-            fen.push(Piece::u8_to_char(self.get_piece(file, rank)).unwrap());
-          },
-          _ => {},
-        }
-      }
-      if empty_squares > 0 {
-        fen.push(char::from_digit(empty_squares, 10).unwrap());
-        empty_squares = 0;
-      }
-      if rank != 1 {
-        fen.push('/');
-      }
-    }
-
-    fen
+    self.pieces.to_fen_placement()
   }
 
   /// Determines if a position is a game over due to insufficient material or
-  /// not
+  /// not: lone kings, a single minor piece, or bishops all on the same square
+  /// color.
   ///
   /// ### Arguments
   ///
@@ -1500,16 +2432,14 @@ impl Board {
       return false;
     }
 
-    if (self.pieces.white.bishop
-        | self.pieces.black.bishop
-        | self.pieces.white.knight
-        | self.pieces.black.knight)
-                                   .count_ones()
-       > 1
-    {
-      return false;
+    let knights = self.pieces.white.knight | self.pieces.black.knight;
+    let bishops = self.pieces.white.bishop | self.pieces.black.bishop;
+    if (knights | bishops).count_ones() <= 1 {
+      return true;
     }
-    true
+
+    // Any number of bishops, all on the same square color, cannot mate
+    knights == 0 && ((bishops & LIGHT_SQUARES) == 0 || (bishops & DARK_SQUARES) == 0)
   }
 }
 
@@ -1530,6 +2460,25 @@ impl std::fmt::Display for Board {
   }
 }
 
+impl std::fmt::Display for FenError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      FenError::InvalidFieldCount(count) => write!(f, "expected 6 fields, found {}", count),
+      FenError::InvalidRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+      FenError::InvalidRankLength(rank) => write!(f, "rank {} does not have 8 squares", rank),
+      FenError::InvalidPieceChar(c) => write!(f, "invalid piece character '{}'", c),
+      FenError::InvalidKingCount(color) => write!(f, "{} does not have exactly one king", color),
+      FenError::InvalidSideToMove(side) => write!(f, "invalid side to move '{}'", side),
+      FenError::InvalidCastlingRights(castling) => {
+        write!(f, "invalid castling rights '{}'", castling)
+      },
+      FenError::InvalidEnPassantSquare(square) => {
+        write!(f, "invalid en-passant square '{}'", square)
+      },
+    }
+  }
+}
+
 // -----------------------------------------------------------------------------
 // Default implementations for our board
 
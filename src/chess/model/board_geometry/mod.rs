@@ -6,6 +6,7 @@ pub mod rays;
 
 use crate::model::board::*;
 use crate::model::board_mask::BoardMask;
+use rays::RAYS;
 use std::cmp::max;
 
 // -----------------------------------------------------------------------------
@@ -34,6 +35,10 @@ pub static RANKS: [u64; 8] = [0x00000000000000FF,
                               0x0000FF0000000000,
                               0x00FF000000000000,
                               0xFF00000000000000];
+/// Diagonal going from a1 to h8
+pub static A1_H8_DIAGONAL: BoardMask = 0x8040201008040201;
+/// Anti-diagonal going from h1 to a8
+pub static H1_A8_ANTI_DIAGONAL: BoardMask = 0x0102040810204080;
 /// Files boardmasks
 pub static FILES: [u64; 8] = [0x0101010101010101,
                               0x0202020202020202,
@@ -64,6 +69,109 @@ pub fn get_king_distance(king_position: u8, destination: u8) -> u8 {
   max(sf.abs_diff(df), sr.abs_diff(dr))
 }
 
+/// Finds the squares strictly between two squares on the same line or
+/// diagonal.
+///
+/// # Arguments
+///
+/// * `a` - Square index of one end of the segment
+/// * `b` - Square index of the other end of the segment
+///
+/// # Return value
+///
+/// Boardmask of the squares between a and b, both excluded. Empty if the
+/// squares are not on the same line or diagonal.
+pub fn squares_between(a: u8, b: u8) -> BoardMask {
+  RAYS[a as usize][b as usize] & !(1 << b)
+}
+
+/// Checks if three squares are on the same line or diagonal, in any order.
+///
+/// # Arguments
+///
+/// * `a` - Square index
+/// * `b` - Square index
+/// * `c` - Square index
+///
+/// # Return value
+///
+/// True if the squares are aligned. Two identical squares are always aligned
+/// with a third one.
+pub fn aligned(a: u8, b: u8, c: u8) -> bool {
+  if a == b || b == c || a == c {
+    return true;
+  }
+
+  // One of the squares has to be between the two others
+  RAYS[a as usize][c as usize] & (1 << b) != 0
+  || RAYS[b as usize][a as usize] & (1 << c) != 0
+  || RAYS[c as usize][b as usize] & (1 << a) != 0
+}
+
+/// Finds the file of a square
+///
+/// # Arguments
+///
+/// * `square` - Square index
+///
+/// # Return value
+///
+/// Boardmask of the whole file, including the square
+pub fn file_of(square: u8) -> BoardMask {
+  FILES[(square % 8) as usize]
+}
+
+/// Finds the rank of a square
+///
+/// # Arguments
+///
+/// * `square` - Square index
+///
+/// # Return value
+///
+/// Boardmask of the whole rank, including the square
+pub fn rank_of(square: u8) -> BoardMask {
+  RANKS[(square / 8) as usize]
+}
+
+/// Finds the diagonal of a square, going in the a1-h8 direction
+///
+/// # Arguments
+///
+/// * `square` - Square index
+///
+/// # Return value
+///
+/// Boardmask of the whole diagonal, including the square
+pub fn diagonal_of(square: u8) -> BoardMask {
+  // Moving the a1-h8 diagonal up or down by the difference between rank and file
+  let offset = (square / 8) as i32 - (square % 8) as i32;
+  if offset >= 0 {
+    A1_H8_DIAGONAL << (8 * offset)
+  } else {
+    A1_H8_DIAGONAL >> (-8 * offset)
+  }
+}
+
+/// Finds the anti-diagonal of a square, going in the h1-a8 direction
+///
+/// # Arguments
+///
+/// * `square` - Square index
+///
+/// # Return value
+///
+/// Boardmask of the whole anti-diagonal, including the square
+pub fn anti_diagonal_of(square: u8) -> BoardMask {
+  // Moving the h1-a8 anti-diagonal up or down, squares on it have rank + file = 7
+  let offset = (square / 8) as i32 + (square % 8) as i32 - 7;
+  if offset >= 0 {
+    H1_A8_ANTI_DIAGONAL << (8 * offset)
+  } else {
+    H1_A8_ANTI_DIAGONAL >> (-8 * offset)
+  }
+}
+
 // -----------------------------------------------------------------------------
 //  Tests
 
@@ -105,6 +213,129 @@ mod tests {
     assert_eq!(get_king_distance(destination, start), 0);
   }
 
+  #[test]
+  fn test_lines_of_a_square() {
+    use crate::model::moves::string_to_square;
+
+    let e4 = string_to_square("e4");
+    assert_eq!(FILES[4], file_of(e4));
+    assert_eq!(RANKS[3], rank_of(e4));
+    // b1-h7 diagonal and h1-a8 anti-diagonal
+    assert_eq!(0x0080402010080402, diagonal_of(e4));
+    assert_eq!(0x0102040810204080, anti_diagonal_of(e4));
+
+    // Corners only see themselves on one of their diagonals
+    assert_eq!(1, anti_diagonal_of(string_to_square("a1")));
+    assert_eq!(A1_H8_DIAGONAL, diagonal_of(string_to_square("h8")));
+    assert_eq!(1 << 63, anti_diagonal_of(string_to_square("h8")));
+
+    for square in 0..64 {
+      for line in [file_of(square), rank_of(square)] {
+        assert_eq!(8, line.count_ones());
+        assert!(square_in_mask!(square, line));
+      }
+      for line in [diagonal_of(square), anti_diagonal_of(square)] {
+        assert!(square_in_mask!(square, line));
+      }
+      assert_eq!(1 << square, diagonal_of(square) & anti_diagonal_of(square));
+    }
+  }
+
+  /// Walks from a square in a direction until the edge of the board or an
+  /// occupied square, which is included
+  fn slide(square: u8, occupancy: BoardMask, directions: &[(i8, i8)]) -> BoardMask {
+    let mut destinations: BoardMask = 0;
+    for (file_step, rank_step) in directions {
+      let mut file = (square % 8) as i8 + file_step;
+      let mut rank = (square / 8) as i8 + rank_step;
+      while (0..8).contains(&file) && (0..8).contains(&rank) {
+        let destination = (rank * 8 + file) as u8;
+        set_square_in_mask!(destination, destinations);
+        if square_in_mask!(destination, occupancy) {
+          break;
+        }
+        file += file_step;
+        rank += rank_step;
+      }
+    }
+    destinations
+  }
+
+  #[test]
+  fn test_slider_tables_are_consistent() {
+    use crate::model::piece_moves::{get_bishop_moves, get_rook_moves};
+    use crate::model::tables::bishop_destinations::BISHOP_SPAN;
+    use crate::model::tables::rook_destinations::ROOK_SPAN;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+    const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, -1), (1, -1), (-1, 1)];
+
+    for square in 0..64 {
+      // On an empty board, sliders see their whole lines except their own square
+      let rook_lines = (file_of(square) | rank_of(square)) & !(1 << square);
+      let bishop_lines = (diagonal_of(square) | anti_diagonal_of(square)) & !(1 << square);
+      assert_eq!(rook_lines, ROOK_SPAN[square as usize]);
+      assert_eq!(rook_lines, get_rook_moves(0, 0, square as usize));
+      assert_eq!(bishop_lines, BISHOP_SPAN[square as usize]);
+      assert_eq!(bishop_lines, get_bishop_moves(0, 0, square as usize));
+    }
+
+    // With pieces in the way, the magic lookups match a square by square walk
+    let mut rng = StdRng::seed_from_u64(1132);
+    for _ in 0..200 {
+      let occupancy: BoardMask = rng.gen::<u64>() & rng.gen::<u64>();
+      for square in 0..64 {
+        let occupancy = occupancy & !(1 << square);
+        assert_eq!(slide(square, occupancy, &ROOK_DIRECTIONS),
+                   get_rook_moves(0, occupancy, square as usize));
+        assert_eq!(slide(square, occupancy, &BISHOP_DIRECTIONS),
+                   get_bishop_moves(0, occupancy, square as usize));
+      }
+    }
+  }
+
+  #[test]
+  fn test_squares_between_and_aligned() {
+    let a1 = Board::fr_to_index(1, 1);
+    let a4 = Board::fr_to_index(1, 4);
+    let a8 = Board::fr_to_index(1, 8);
+    let d4 = Board::fr_to_index(4, 4);
+    let h8 = Board::fr_to_index(8, 8);
+    let c2 = Board::fr_to_index(3, 2);
+    let e1 = Board::fr_to_index(5, 1);
+    let h1 = Board::fr_to_index(8, 1);
+
+    // Orthogonal: a2, a3
+    assert_eq!(0x0000_0000_0001_0100, squares_between(a1, a4));
+    assert_eq!(0x0000_0000_0001_0100, squares_between(a4, a1));
+    // f1, g1
+    assert_eq!(0x0000_0000_0000_0060, squares_between(e1, h1));
+    // Diagonal: b2, c3
+    assert_eq!(0x0000_0000_0004_0200, squares_between(a1, d4));
+    // e5, f6, g7
+    assert_eq!(0x0040_2010_0000_0000, squares_between(d4, h8));
+    // Adjacent, identical and non-aligned squares
+    assert_eq!(0, squares_between(a1, Board::fr_to_index(2, 2)));
+    assert_eq!(0, squares_between(a1, a1));
+    assert_eq!(0, squares_between(a1, c2));
+    assert_eq!(0, squares_between(c2, h8));
+
+    // Orthogonal and diagonal triples, in any order
+    assert!(aligned(a1, a4, a8));
+    assert!(aligned(a8, a1, a4));
+    assert!(aligned(a4, a8, a1));
+    assert!(aligned(a1, d4, h8));
+    assert!(aligned(h8, a1, d4));
+    assert!(aligned(a1, e1, h1));
+    // Not aligned
+    assert!(!aligned(a1, c2, h8));
+    assert!(!aligned(a1, a4, d4));
+    assert!(!aligned(e1, d4, a8));
+    assert!(!aligned(a4, d4, h8));
+  }
+
   #[test]
   fn generate_lines_between_squares() {
     use crate::model::board_mask::BoardMask;
@@ -10,6 +10,18 @@ use super::board::Board;
 /// - etc...
 pub type BoardMask = u64;
 
+/// Wrapper displaying a board mask as a grid, e.g. in logs:
+/// `debug!("Pins:\n{}", BoardMaskDisplay(board.pins));`
+pub struct BoardMaskDisplay(pub BoardMask);
+
+// -----------------------------------------------------------------------------
+//  Constants
+
+/// Mask of the light squares (b1, d1, ..., a2, c2, ...)
+pub const LIGHT_SQUARES: BoardMask = 0x55AA_55AA_55AA_55AA;
+/// Mask of the dark squares (a1, c1, ..., b2, d2, ...)
+pub const DARK_SQUARES: BoardMask = !LIGHT_SQUARES;
+
 pub trait CountFewOnes {
   /// Counts the number of binary ones in the representation when there are few ones.
   ///
@@ -95,37 +107,44 @@ pub use set_square_in_mask;
 pub use square_in_mask;
 pub use unset_square_in_mask;
 
+// -----------------------------------------------------------------------------
+//  Display implementations
+
+impl std::fmt::Display for BoardMaskDisplay {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(board_mask_to_string(self.0).as_str())
+  }
+}
+
 // -----------------------------------------------------------------------------
 //  Functions
 
 /// Helper macro printing a board mask
 ///
-/// Will print 1 when the BoardMask is set, 0 when it's not set.
+/// Will print X when the BoardMask is set, . when it's not set.
 ///
 /// ### Arguments
 ///
 /// * `mask` board mask to print
 ///
 pub fn print_board_mask(mask: BoardMask) {
-  let mut representation = String::from("\n");
-  for rank in (1..=8).rev() {
-    for file in 1..=8 {
-      if (mask >> Board::fr_to_index(file, rank) & 1) == 1 {
-        representation.push('1');
-      } else {
-        representation.push('0');
-      }
-      representation.push(' ');
-    }
-    representation.push('\n');
-  }
-
-  println!("{representation}");
+  println!("\n{}", board_mask_to_string(mask));
 }
 
-/// Helper function returning a string from a board mask
-///
-/// Will print x when the BoardMask is set, . when it's not set.
+/// Helper function returning a string from a board mask, with the ranks and
+/// files labels, e.g. for the 4 center squares:
+///
+/// ```text
+/// 8 . . . . . . . .
+/// 7 . . . . . . . .
+/// 6 . . . . . . . .
+/// 5 . . . X X . . .
+/// 4 . . . X X . . .
+/// 3 . . . . . . . .
+/// 2 . . . . . . . .
+/// 1 . . . . . . . .
+///   a b c d e f g h
+/// ```
 ///
 /// ### Arguments
 ///
@@ -134,17 +153,50 @@ pub fn print_board_mask(mask: BoardMask) {
 pub fn board_mask_to_string(mask: BoardMask) -> String {
   let mut string = String::new();
   for rank in (1..=8).rev() {
+    string.push(char::from_digit(rank as u32, 10).unwrap());
     for file in 1..=8 {
       let square_index = Board::fr_to_index(file, rank);
+      string.push(' ');
       if ((mask >> square_index) & 1) == 1 {
-        string.push('x');
+        string.push('X');
       } else {
         string.push('.');
       }
-      string.push(' ');
     }
     string.push('\n');
   }
+  string.push_str("  a b c d e f g h\n");
   string
 }
 
+// -----------------------------------------------------------------------------
+//  Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_board_mask_to_string() {
+    // d4, e4, d5, e5
+    let center: BoardMask = 0x0000_0018_1800_0000;
+    let expected = "8 . . . . . . . .
+7 . . . . . . . .
+6 . . . . . . . .
+5 . . . X X . . .
+4 . . . X X . . .
+3 . . . . . . . .
+2 . . . . . . . .
+1 . . . . . . . .
+  a b c d e f g h
+";
+    assert_eq!(expected, board_mask_to_string(center));
+    assert_eq!(expected, BoardMaskDisplay(center).to_string());
+
+    // a1 and h8
+    let corners: BoardMask = 0x8000_0000_0000_0001;
+    let string = board_mask_to_string(corners);
+    assert!(string.starts_with("8 . . . . . . . X\n"));
+    assert!(string.contains("1 X . . . . . . .\n"));
+  }
+}
@@ -29,6 +29,30 @@ pub enum GameStatus {
   Draw,
 }
 
+/// Reasons for which a move notation cannot be applied on a position
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MoveError {
+  /// The notation is not a move, e.g. "e2e9" or "hello"
+  InvalidNotation(String),
+  /// The notation is a move, but it is not legal in the position
+  IllegalMove(String),
+}
+
+/// Changes made on the board by a move, so that evaluations can be updated
+/// with a delta instead of being recomputed from scratch.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MoveDelta {
+  /// Type of the piece that moved, with its source and destination squares
+  pub moved: (PieceType, u8, u8),
+  /// Type and square of the captured piece. The square differs from the
+  /// destination of the move for en-passant captures.
+  pub captured: Option<(PieceType, u8)>,
+  /// Type of the piece that a pawn promoted to
+  pub promoted: Option<PieceType>,
+  /// Source and destination squares of the rook when castling
+  pub castling_rook: Option<(u8, u8)>,
+}
+
 /// Captures all the data required in a Chess Game
 /// to identify Stalemates, repetitions, etc.
 ///
@@ -155,12 +179,125 @@ impl GameState {
     // self.last_positions.iter().fold(0,|count, x| if *x == self.board.hash { count + 1 } else { count },)
   }
 
+  /// Number of half-moves played since the last capture or pawn move, i.e.
+  /// the halfmove clock used for the fifty-move rule.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`: GameState reference
+  ///
+  /// ### Return value
+  ///
+  /// Number of reversible half-moves played in a row
+  ///
+  pub fn halfmove_clock(&self) -> u8 {
+    self.ply
+  }
+
+  /// Number of half-moves that can still be played before the game is drawn by
+  /// the fifty-move rule, if no capture or pawn move happens.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`: GameState reference
+  ///
+  /// ### Return value
+  ///
+  /// Half-moves left before the fifty-move rule applies, 0 if it already does
+  ///
+  pub fn moves_until_fifty_move_draw(&self) -> u8 {
+    100_u8.saturating_sub(self.ply)
+  }
+
+  /// Checks if the current position has occurred for the third time, i.e. if
+  /// a draw by threefold repetition can be claimed.
+  ///
+  /// The position history is reset on irreversible moves (captures and pawn
+  /// moves), so only positions that can actually recur are compared.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`: GameState reference
+  ///
+  /// ### Return value
+  ///
+  /// True if the current position is on the board for the third time
+  ///
+  pub fn is_draw_by_repetition(&self) -> bool {
+    self.get_board_repetitions() >= 2
+  }
+
+  /// Determines if the game is over, and how.
+  ///
+  /// Checks in order: checkmate, stalemate, insufficient material, fifty-move
+  /// rule and threefold repetition.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`: GameState reference
+  ///
+  /// ### Return value
+  ///
+  /// Status of the game, `GameStatus::Ongoing` if none of the above applies
+  ///
+  pub fn game_status(&self) -> GameStatus {
+    self.game_status_with_legal_moves(!self.get_moves().is_empty())
+  }
+
+  /// Same as `game_status`, for callers that already computed the legal
+  /// moves of the position.
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`:            GameState reference
+  /// * `has_legal_moves`: Whether the side to play has any legal move
+  ///
+  /// ### Return value
+  ///
+  /// Status of the game, `GameStatus::Ongoing` if it is not over
+  ///
+  pub fn game_status_with_legal_moves(&self, has_legal_moves: bool) -> GameStatus {
+    if !has_legal_moves {
+      return match (self.board.checkers.count_few_ones(), self.board.side_to_play) {
+        (0, _) => GameStatus::Stalemate,
+        (_, Color::White) => GameStatus::BlackWon,
+        (_, Color::Black) => GameStatus::WhiteWon,
+      };
+    }
+
+    if self.board.is_game_over_by_insufficient_material() || self.ply >= 100 {
+      return GameStatus::Draw;
+    }
+
+    if self.is_draw_by_repetition() {
+      return GameStatus::ThreeFoldRepetition;
+    }
+
+    GameStatus::Ongoing
+  }
+
   /// Get all the possible moves in a position, for the side to play.
   #[inline]
   pub fn get_moves(&self) -> Vec<Move> {
     self.board.get_moves()
   }
 
+  /// Get all the possible moves in a position, for the side to play, in
+  /// Standard Algebraic Notation (SAN), e.g. `Nbd7`, `exd5`, `O-O`
+  ///
+  /// ### Arguments
+  ///
+  /// * `self`: GameState reference
+  ///
+  /// ### Return value
+  ///
+  /// List of SAN strings, in the same order as `get_moves`
+  ///
+  pub fn legal_moves_san(&self) -> Vec<String> {
+    let moves = self.get_moves();
+    moves.iter().map(|m| self.board.move_to_san_with_legal_moves(m, &moves)).collect()
+  }
+
   pub fn get_king_square(&self) -> u8 {
     match self.board.side_to_play {
       Color::White => self.board.get_white_king_square(),
@@ -197,15 +334,32 @@ impl GameState {
     Move::null()
   }
 
-  /// Same as `apply_move`, except that it takes a move notation
+  /// Same as `apply_move`, except that it takes a move notation.
+  /// The position is left untouched if the move cannot be applied.
   ///
   /// ### Arguments
   ///
-  /// * `chess_move`: Reference to a move.
+  /// * `move_notation`: Move notation to apply, e.g. "e2e4" or "e7e8q"
   ///
-  pub fn apply_move_from_notation(&mut self, move_notation: &str) {
-    let m = self.get_move_from_notation(move_notation.trim());
-    self.apply_move(&m);
+  /// ### Return value
+  ///
+  /// The move applied, or the reason why it could not be applied
+  ///
+  pub fn apply_move_from_notation(&mut self, move_notation: &str) -> Result<Move, MoveError> {
+    let move_notation = move_notation.trim();
+    if !is_move_notation(move_notation) {
+      return Err(MoveError::InvalidNotation(move_notation.to_string()));
+    }
+
+    // Promotions may come in lower case for White, e.g. e7e8q
+    let candidates = self.board.get_moves();
+    match candidates.iter().find(|m| m.to_string().eq_ignore_ascii_case(move_notation)) {
+      Some(m) => {
+        self.apply_move(m);
+        Ok(*m)
+      },
+      None => Err(MoveError::IllegalMove(move_notation.to_string())),
+    }
   }
 
   /// Applies a move for the game.
@@ -253,6 +407,79 @@ impl GameState {
     self.board.apply_move(chess_move);
   }
 
+  /// Computes the game state after a move, leaving this one untouched.
+  ///
+  /// The whole state is copied before applying the move. Prefer this over
+  /// `clone()` followed by `apply_move()`, so that the copy can be optimized
+  /// in one place.
+  ///
+  /// ### Arguments
+  ///
+  /// * `chess_move`: Reference to a move.
+  ///
+  /// ### Return value
+  ///
+  /// New game state, with the move applied
+  ///
+  #[must_use]
+  pub fn with_move(&self, chess_move: &Move) -> GameState {
+    let mut game_state = self.clone();
+    game_state.apply_move(chess_move);
+    game_state
+  }
+
+  /// Applies a move for the game and reports what changed on the board.
+  ///
+  /// ### Arguments
+  ///
+  /// * `chess_move`: Reference to a move.
+  ///
+  /// ### Return value
+  ///
+  /// Pieces moved, captured or promoted by the move
+  ///
+  pub fn make_move(&mut self, chess_move: &Move) -> MoveDelta {
+    let src = chess_move.u8_src();
+    let dest = chess_move.u8_dest();
+    let moved_piece = PieceType::from_u8(self.board.pieces.get(src));
+
+    let destination_piece = self.board.pieces.get(dest);
+    let captured = if destination_piece != NO_PIECE {
+      Some((PieceType::from_u8(destination_piece), dest))
+    } else if moved_piece == PieceType::Pawn
+              && dest == self.board.en_passant_square
+              && src % 8 != dest % 8
+    {
+      // The captured pawn is next to the source square, on the file of the
+      // destination
+      Some((PieceType::Pawn, src - src % 8 + dest % 8))
+    } else {
+      None
+    };
+
+    let promoted = match chess_move.promotion() {
+      Promotion::NoPromotion => None,
+      promotion => Some(PieceType::from_u8(promotion.to_piece_const())),
+    };
+
+    let castling_rook = if moved_piece == PieceType::King && src.abs_diff(dest) == 2 {
+      if dest > src {
+        Some((src + 3, src + 1))
+      } else {
+        Some((src - 4, src - 1))
+      }
+    } else {
+      None
+    };
+
+    self.apply_move(chess_move);
+
+    MoveDelta { moved: (moved_piece, src, dest),
+                captured,
+                promoted,
+                castling_rook }
+  }
+
   /// Applies all moves from a vector of moves
   ///
   /// ### Arguments
@@ -272,14 +499,32 @@ impl GameState {
   /// * `move_list`: String with move notations, e.g. "e2e4 e7e5"
   ///
   pub fn apply_move_list(&mut self, move_list: &str) -> () {
-    if move_list.is_empty() {
-      return;
+    if let Err(index) = self.apply_uci_moves(move_list) {
+      warn!("Stopped applying the move list at move {} on {}",
+            index,
+            self.to_fen());
     }
+  }
 
-    let moves: Vec<&str> = move_list.split(' ').collect();
-    for chess_move in moves {
-      self.apply_move_from_notation(chess_move);
+  /// Applies a list of UCI moves, such as the `moves` string that Lichess
+  /// sends with the whole game. Moves are applied one after the other, the
+  /// moves before a bad one stay applied.
+  ///
+  /// ### Arguments
+  ///
+  /// * `moves`: Space separated UCI moves, e.g. "e2e4 e7e5 g1f3"
+  ///
+  /// ### Return value
+  ///
+  /// Err with the index of the first move that could not be applied
+  ///
+  pub fn apply_uci_moves(&mut self, moves: &str) -> Result<(), usize> {
+    for (index, chess_move) in moves.split_whitespace().enumerate() {
+      if self.apply_move_from_notation(chess_move).is_err() {
+        return Err(index);
+      }
     }
+    Ok(())
   }
 
   /// Attempts to apply a move on the board based on its PGN notation
@@ -304,6 +549,39 @@ impl GameState {
   }
 }
 
+/// Converts a line of moves to Standard Algebraic Notation (SAN). Each move
+/// is converted on the position reached after the previous moves, so checks
+/// and mates are marked with `+` and `#`.
+///
+/// ### Arguments
+///
+/// * `start`: Position from which the line starts
+/// * `moves`: Moves of the line
+///
+/// ### Return value
+///
+/// SAN of each move of the line, e.g. `["Qxc6+", "bxc6", "Nxc6#"]`. If a move
+/// is illegal, the line stops with a marker such as `(e2e5 illegal)`.
+pub fn render_line_san(start: &GameState, moves: &[Move]) -> Vec<String> {
+  let mut board = start.board;
+  let mut sans: Vec<String> = Vec::with_capacity(moves.len());
+  for mv in moves {
+    let legal_moves = board.get_moves();
+    let legal_move = legal_moves.iter().find(|m| m.to_string() == mv.to_string());
+    match legal_move {
+      Some(legal_move) => {
+        sans.push(board.move_to_san_with_legal_moves(legal_move, &legal_moves));
+        board.apply_move(legal_move);
+      },
+      None => {
+        sans.push(format!("({} illegal)", mv));
+        break;
+      },
+    }
+  }
+  sans
+}
+
 // -----------------------------------------------------------------------------
 // Display/Default implementations for our game state
 impl std::fmt::Debug for GameState {
@@ -22,12 +22,12 @@ pub const DESTINATION_SHIFT: move_t = 6;
 
 /// Bit shift to apply to verify if the move is a capture
 pub const CAPTURE_SHIFT: move_t = 16;
-/// Bit shift to apply to check which piece has been captured
+/// Bitmask used to parse the captured piece, see `Move::captured_piece`
 pub const CAPTURE_MASK: move_t = 0b111;
 /// Bit shift to apply to verify if the move delivers check
 pub const CHECK_SHIFT: move_t = 19;
 /// Mask to apply to the number of checks
-const CHECK_MASK: move_t = 0b11;
+pub const CHECK_MASK: move_t = 0b11;
 
 /// Bit shift to apply to verify if the move is marked as a castling move
 pub const CASTLE_SHIFT: move_t = 21;
@@ -236,7 +236,8 @@ impl Shl<move_t> for Promotion {
 ///
 /// ### Fields
 ///
-/// * `data`: Contains the source, destination and promotion
+/// * `data`: Contains the source, destination, promotion, captured piece,
+///   number of checks and the castle/en-passant flags, packed in 23 bits
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub struct Move {
   /// Move data, representing source -> destination and an optional promotion.
@@ -249,6 +250,13 @@ pub struct Move {
   /// is_castle mask      : 0b 0000 0000 0010 0000 0000 0000 0000 0000
   /// en_passant mask     : 0b 0000 0000 0100 0000 0000 0000 0000 0000
   ///
+  /// The promotion bits hold a `Promotion` value, the capture bits hold the
+  /// `PieceType` of the captured piece, with 0 (king) meaning no capture.
+  /// Bits above the en-passant flag are always 0.
+  ///
+  /// The null move has all bits set to 0, i.e. a1 to a1. Since the source
+  /// and destination of an actual move differ, it cannot be mistaken for one.
+  ///
   /// Note that capture/gives_check depends on the board configuration and
   /// does not need to be exact in all use-cases.
   ///
@@ -288,7 +296,8 @@ impl Move {
     unsafe { std::mem::transmute(((self.data >> PROMOTION_SHIFT) & PROMOTION_MASK) as u8) }
   }
 
-  /// Returns true if the move has been marked to be a capture.
+  /// Returns true if the move has been marked to be a capture, including
+  /// en-passant captures.
   /// This depends on the board, and moves generated e.g. from a notation
   /// may not have accurate information here.
   #[inline]
@@ -296,9 +305,16 @@ impl Move {
     (self.data >> CAPTURE_SHIFT) & CAPTURE_MASK != 0
   }
 
-  /// Returns the piece that was captured by the move
+  /// Returns the type of the piece captured by the move, None if the move is
+  /// not a capture.
+  ///
+  /// En-passant captures return `Some(PieceType::Pawn)`, but the captured pawn
+  /// is not on the destination square of the move. Use `is_en_passant` to
+  /// tell them apart.
+  /// This depends on the board, and moves generated e.g. from a notation
+  /// may not have accurate information here.
   #[inline]
-  pub fn get_captured_piece(&self) -> Option<PieceType> {
+  pub fn captured_piece(&self) -> Option<PieceType> {
     match (self.data >> CAPTURE_SHIFT) & CAPTURE_MASK {
       1 => Some(PieceType::Queen),
       2 => Some(PieceType::Rook),
@@ -309,7 +325,8 @@ impl Move {
     }
   }
 
-  /// Returns the piece that was captured by the move
+  /// Returns true if the move captures a piece other than a pawn, i.e. a
+  /// queen, a rook, a bishop or a knight.
   #[inline]
   pub fn is_piece_capture(&self) -> bool {
     matches!((self.data >> CAPTURE_SHIFT) & CAPTURE_MASK, 1..5)
@@ -339,6 +356,24 @@ impl Move {
     (self.data >> EN_PASSANT_SHIFT) & 1 != 0
   }
 
+  /// Returns whether the move is tactical, i.e. it captures a piece (including
+  /// en-passant) or promotes a pawn. Castling moves are not tactical.
+  /// This depends on the board, and moves generated e.g. from a notation
+  /// may not have accurate information here.
+  #[inline]
+  pub fn is_tactical(&self) -> bool {
+    self.is_capture() || self.promotion() != Promotion::NoPromotion
+  }
+
+  /// Returns whether the move is quiet, i.e. it neither captures nor promotes.
+  /// Castling moves are quiet.
+  /// This depends on the board, and moves generated e.g. from a notation
+  /// may not have accurate information here.
+  #[inline]
+  pub fn is_quiet(&self) -> bool {
+    !self.is_tactical()
+  }
+
   /// Null / illegal move
   pub const fn null() -> Self {
     Move { data: 0 }
@@ -425,6 +460,23 @@ pub fn square_to_string(square: u8) -> String {
   string
 }
 
+/// Checks if a string is a move in long algebraic notation, e.g. "e2e4" or
+/// "e7e8q" (promotions can be upper case). It does not check if the move is
+/// legal.
+///
+/// ### Arguments
+///
+/// * `move_notation`: String to check
+pub fn is_move_notation(move_notation: &str) -> bool {
+  if !move_notation.is_ascii() || (move_notation.len() != 4 && move_notation.len() != 5) {
+    return false;
+  }
+
+  string_to_square(&move_notation[0..2]) != INVALID_SQUARE
+  && string_to_square(&move_notation[2..4]) != INVALID_SQUARE
+  && (move_notation.len() == 4 || "qrbnQRBN".contains(&move_notation[4..5]))
+}
+
 /// Converts the square algebraic notation to a number from 0 to 63.
 pub fn string_to_square(string: &str) -> u8 {
   let mut square_value: u8 = 0;
@@ -38,6 +38,11 @@ pub const BISHOP_VALUE: f32 = 3.05;
 pub const KNIGHT_VALUE: f32 = 3.0;
 pub const PAWN_VALUE: f32 = 1.0;
 
+// Piece weights used to determine the game phase. Pawns and kings do not count.
+pub const QUEEN_PHASE_VALUE: u32 = 9;
+pub const ROOK_PHASE_VALUE: u32 = 5;
+pub const MINOR_PHASE_VALUE: u32 = 3;
+
 // -----------------------------------------------------------------------------
 //  Strucs/Enums
 
@@ -181,6 +186,25 @@ impl Piece {
     }
   }
 
+  /// Indicates how much a piece weighs when determining the game phase.
+  ///
+  /// ### Arguments
+  ///
+  /// * `piece_type`: Type of the piece
+  ///
+  /// ### Return value
+  ///
+  /// Weight of the piece in `Board::game_phase_value`
+  ///
+  pub fn phase_value_from_type(piece_type: PieceType) -> u32 {
+    match piece_type {
+      PieceType::Queen => QUEEN_PHASE_VALUE,
+      PieceType::Rook => ROOK_PHASE_VALUE,
+      PieceType::Bishop | PieceType::Knight => MINOR_PHASE_VALUE,
+      PieceType::King | PieceType::Pawn => 0,
+    }
+  }
+
   /// Converts a piece into a u8, that can be used to represents on an array
   /// of squares for the chess board.
   ///
@@ -1,4 +1,5 @@
 // From our libraries
+use crate::model::board::FenError;
 use crate::model::board_mask::*;
 use crate::model::piece::*;
 use log::*;
@@ -310,91 +311,126 @@ impl PieceSet {
   }
 
   /// Converts the first part of the FEN string into a Piece Set.
+  /// A malformed piece placement is reported, and its invalid parts are
+  /// skipped: unknown characters, squares beyond the h-file and ranks beyond
+  /// the 8th. Use `try_from_fen` to reject it instead.
+  ///
+  /// ### Arguments
+  ///
+  /// * `fen`: FEN string, or only its piece placement field
   ///
   /// ### Return value
   ///
   /// PieceSet containing the pieces indicated in the FEN string
   pub fn from_fen(fen: &str) -> Self {
+    let (piece_set, error) = PieceSet::parse_fen_placement(fen);
+    if let Some(e) = error {
+      error!("Invalid piece placement in FEN {}: {}", fen, e);
+    }
+    piece_set
+  }
+
+  /// Converts the first part of the FEN string into a Piece Set, checking
+  /// that it describes 8 ranks of 8 squares with known piece letters.
+  ///
+  /// ### Arguments
+  ///
+  /// * `fen`: FEN string, or only its piece placement field
+  ///
+  /// ### Return value
+  ///
+  /// PieceSet containing the pieces indicated in the FEN string, or the reason
+  /// why the piece placement is invalid
+  pub fn try_from_fen(fen: &str) -> Result<Self, FenError> {
+    match PieceSet::parse_fen_placement(fen) {
+      (piece_set, None) => Ok(piece_set),
+      (_, Some(e)) => Err(e),
+    }
+  }
+
+  /// Parses the first part of the FEN string, skipping its invalid parts.
+  ///
+  /// ### Arguments
+  ///
+  /// * `fen`: FEN string, or only its piece placement field
+  ///
+  /// ### Return value
+  ///
+  /// PieceSet containing the valid pieces indicated in the FEN string, and the
+  /// first problem found in the piece placement, if any
+  fn parse_fen_placement(fen: &str) -> (Self, Option<FenError>) {
+    let placement = fen.split(' ').next().unwrap_or_default();
+    let ranks: Vec<&str> = placement.split('/').collect();
+    let mut error = None;
+    if ranks.len() != 8 {
+      error = Some(FenError::InvalidRankCount(ranks.len()));
+    }
+
     let mut piece_set = PieceSet::new();
+    // The placement starts with the 8th rank
+    for (rank_string, rank) in ranks.iter().zip((0..8).rev()) {
+      let mut file: u32 = 0;
+      for c in rank_string.chars() {
+        let piece = match c {
+          '1'..='8' => {
+            file += c as u32 - '0' as u32;
+            continue;
+          },
+          '.' => Err(c),
+          _ => Piece::char_to_u8(c),
+        };
+        match piece {
+          Ok(piece) => {
+            if file < 8 {
+              piece_set.add(piece, rank * 8 + file as u8);
+            }
+            file += 1;
+          },
+          Err(c) => {
+            error = error.or(Some(FenError::InvalidPieceChar(c)));
+          },
+        }
+      }
+      if file != 8 {
+        error = error.or(Some(FenError::InvalidRankLength(rank + 1)));
+      }
+    }
+
+    (piece_set, error)
+  }
 
-    let fen_parts: Vec<&str> = fen.split(' ').collect();
-    if fen_parts.is_empty() {
-      error!("FEN string too small to generate a piece set. Returning empty set.");
-      return piece_set;
+  /// Converts the Piece Set to the first part of a FEN string, the inverse of
+  /// `from_fen`.
+  ///
+  /// ### Return value
+  ///
+  /// Piece placement field of the FEN, e.g.
+  /// `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR`
+  pub fn to_fen_placement(&self) -> String {
+    // Pieces come by increasing square, i.e. rank by rank and file by file
+    let mut ranks: [String; 8] = Default::default();
+    let mut next_file: [u8; 8] = [0; 8];
+    for (square, color, p_type) in self.iter() {
+      let (rank, file) = ((square / 8) as usize, square % 8);
+      if file > next_file[rank] {
+        ranks[rank].push(char::from_digit((file - next_file[rank]) as u32, 10).unwrap());
+      }
+      ranks[rank].push(Piece { p_type, color }.as_char());
+      next_file[rank] = file + 1;
     }
 
-    let mut rank = 7;
-    let mut file = 0;
-    for c in fen_parts[0].chars() {
-      match c {
-        'K' => {
-          piece_set.add(WHITE_KING, rank * 8 + file);
-          file += 1;
-        },
-        'Q' => {
-          piece_set.add(WHITE_QUEEN, rank * 8 + file);
-          file += 1;
-        },
-        'R' => {
-          piece_set.add(WHITE_ROOK, rank * 8 + file);
-          file += 1;
-        },
-        'B' => {
-          piece_set.add(WHITE_BISHOP, rank * 8 + file);
-          file += 1;
-        },
-        'N' => {
-          piece_set.add(WHITE_KNIGHT, rank * 8 + file);
-          file += 1;
-        },
-        'P' => {
-          piece_set.add(WHITE_PAWN, rank * 8 + file);
-          file += 1;
-        },
-        'k' => {
-          piece_set.add(BLACK_KING, rank * 8 + file);
-          file += 1;
-        },
-        'q' => {
-          piece_set.add(BLACK_QUEEN, rank * 8 + file);
-          file += 1;
-        },
-        'r' => {
-          piece_set.add(BLACK_ROOK, rank * 8 + file);
-          file += 1;
-        },
-        'b' => {
-          piece_set.add(BLACK_BISHOP, rank * 8 + file);
-          file += 1;
-        },
-        'n' => {
-          piece_set.add(BLACK_KNIGHT, rank * 8 + file);
-          file += 1;
-        },
-        'p' => {
-          piece_set.add(BLACK_PAWN, rank * 8 + file);
-          file += 1;
-        },
-        '1' => file += 1,
-        '2' => file += 2,
-        '3' => file += 3,
-        '4' => file += 4,
-        '5' => file += 5,
-        '6' => file += 6,
-        '7' => file += 7,
-        '/' => {
-          rank -= 1;
-          file = 0
-        },
-        ' ' => {
-          // If we find a space, abort, as we are getting somewhere else in the FEN
-          break;
-        },
-        _ => {},
+    let mut fen = String::new();
+    for rank in (0..8).rev() {
+      fen.push_str(&ranks[rank]);
+      if next_file[rank] < 8 {
+        fen.push(char::from_digit((8 - next_file[rank]) as u32, 10).unwrap());
+      }
+      if rank != 0 {
+        fen.push('/');
       }
     }
 
-    piece_set
+    fen
   }
 
   /// Returns the piece on a square.
@@ -443,6 +479,17 @@ impl PieceSet {
     self.white.all() | self.black.all()
   }
 
+  /// Iterates over all the pieces on the board, by increasing square index.
+  ///
+  /// ### Return value
+  ///
+  /// Iterator over the `(square, color, piece type)` of each piece on the board
+  #[inline]
+  pub fn iter(&self) -> impl Iterator<Item = (u8, Color, PieceType)> + '_ {
+    PieceSetIterator { squares: &self.squares,
+                       mask:    self.all(), }
+  }
+
   /// Returns a boardmask of all queen pieces.
   ///
   /// ### Return value
@@ -589,6 +636,33 @@ impl Default for PieceSet {
 
 // -----------------------------------------------------------------------------
 //  Iterator implementation
+
+/// Iterator over the pieces of a PieceSet, see `PieceSet::iter()`
+struct PieceSetIterator<'a> {
+  /// Square -> piece table of the piece set
+  squares: &'a [u8; 64],
+  /// Occupied squares that have not been visited yet
+  mask:    BoardMask,
+}
+
+impl Iterator for PieceSetIterator<'_> {
+  type Item = (u8, Color, PieceType);
+
+  // Returns the next piece, going through the occupied squares
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.mask == 0 {
+      return None;
+    }
+
+    let square = self.mask.trailing_zeros() as u8;
+    self.mask &= self.mask - 1;
+
+    let piece = self.squares[square as usize];
+    let color = if piece >= BLACK_KING { Color::Black } else { Color::White };
+    Some((square, color, PieceType::from_u8(piece)))
+  }
+}
+
 impl Iterator for PieceMasks {
   // We can refer to this type using Self::Item
   type Item = (u8, PieceType);
@@ -3,6 +3,7 @@ use crate::model::board_mask::*;
 use crate::model::castling_rights::*;
 use crate::model::moves::*;
 use crate::model::piece::*;
+use crate::model::piece_moves::MAXIMUM_LEGAL_MOVES;
 use crate::model::piece_set::*;
 
 #[test]
@@ -289,6 +290,168 @@ fn test_game_over_insufficient_material() {
   let fen = "8/4nk2/8/8/8/2KP4/8/8 w - - 0 1";
   let board = Board::from_fen(fen);
   assert_eq!(false, board.is_game_over_by_insufficient_material());
+
+  // Bishops on the same square color cannot mate
+  let fen = "8/5k2/4b3/8/8/2KB4/8/8 w - - 0 1";
+  let board = Board::from_fen(fen);
+  assert!(board.is_game_over_by_insufficient_material());
+
+  let fen = "8/5k2/8/8/8/2KBB3/8/8 w - - 0 1";
+  let board = Board::from_fen(fen);
+  assert!(!board.is_game_over_by_insufficient_material());
+
+  let fen = "8/5k2/3b4/8/8/2KB4/8/8 w - - 0 1";
+  let board = Board::from_fen(fen);
+  assert!(!board.is_game_over_by_insufficient_material());
+}
+
+#[test]
+fn test_maximum_legal_moves() {
+  // Known position with the highest number of legal moves
+  let fen = "R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1";
+  let board = Board::from_fen(fen);
+  let moves = board.get_moves();
+  assert_eq!(MAXIMUM_LEGAL_MOVES, moves.len());
+  // The move list should not have been re-allocated
+  assert_eq!(MAXIMUM_LEGAL_MOVES, moves.capacity());
+}
+
+#[test]
+fn test_piece_count_and_material_balance() {
+  let board = Board::default();
+  assert_eq!(0, board.material_balance());
+  assert_eq!(8, board.piece_count(Color::White, PieceType::Pawn));
+  assert_eq!(2, board.piece_count(Color::Black, PieceType::Knight));
+  assert_eq!(1, board.piece_count(Color::White, PieceType::Queen));
+  assert_eq!(1, board.piece_count(Color::Black, PieceType::King));
+
+  // White is up a rook
+  let fen = "rnbqkbn1/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQq - 0 1";
+  let board = Board::from_fen(fen);
+  assert_eq!(1, board.piece_count(Color::Black, PieceType::Rook));
+  assert_eq!(500, board.material_balance());
+
+  // Black has a queen against white's bishop and 2 pawns
+  let fen = "4k3/3q4/8/8/8/8/PP6/2B1K3 w - - 0 1";
+  let board = Board::from_fen(fen);
+  assert_eq!(2, board.piece_count(Color::White, PieceType::Pawn));
+  assert_eq!(0, board.piece_count(Color::Black, PieceType::Pawn));
+  assert_eq!(-445, board.material_balance());
+}
+
+#[test]
+fn test_passed_and_isolated_pawns() {
+  let board = Board::from_fen("4k3/1p4p1/8/3P4/8/8/P4PP1/4K3 w - - 0 1");
+
+  // Clearly passed, and isolated too
+  assert!(board.is_passed_pawn(string_to_square("d5"), Color::White));
+  assert!(board.is_isolated_pawn(string_to_square("d5"), Color::White));
+
+  // Isolated, but blocked by the pawn on the adjacent file
+  assert!(!board.is_passed_pawn(string_to_square("a2"), Color::White));
+  assert!(board.is_isolated_pawn(string_to_square("a2"), Color::White));
+  assert!(!board.is_passed_pawn(string_to_square("b7"), Color::Black));
+  assert!(board.is_isolated_pawn(string_to_square("b7"), Color::Black));
+
+  // Normal pawns
+  for s in ["f2", "g2"] {
+    assert!(!board.is_passed_pawn(string_to_square(s), Color::White));
+    assert!(!board.is_isolated_pawn(string_to_square(s), Color::White));
+  }
+  assert!(!board.is_passed_pawn(string_to_square("g7"), Color::Black));
+
+  // No pawn of that color on the square
+  assert!(!board.is_passed_pawn(string_to_square("e4"), Color::White));
+  assert!(!board.is_isolated_pawn(string_to_square("e4"), Color::White));
+  assert!(!board.is_passed_pawn(string_to_square("d5"), Color::Black));
+  assert!(!board.is_isolated_pawn(string_to_square("d5"), Color::Black));
+}
+
+#[test]
+fn test_king_zone_and_attackers() {
+  // Black queen, rook, bishop and knight all aiming at the white castled king
+  let board = Board::from_fen("6k1/5ppp/3b1r2/8/6nq/8/5PPP/6K1 w - - 0 1");
+
+  // f1-h1, f2-h2 and f3-h3 for White, f8-h8, f7-h7 and f6-h6 for Black
+  assert_eq!(0x0000000000E0E0E0, board.king_zone(Color::White));
+  assert_eq!(0xE0E0E00000000000, board.king_zone(Color::Black));
+
+  let (white_count, white_weight) = board.count_attackers_near_king(Color::White);
+  assert_eq!(4, white_count);
+  assert_eq!(5 + 3 + 2 + 2, white_weight);
+
+  // The black king is safely tucked behind its pawns
+  let (black_count, black_weight) = board.count_attackers_near_king(Color::Black);
+  assert_eq!(0, black_count);
+  assert_eq!(0, black_weight);
+  assert!(white_weight >= black_weight + 10);
+
+  // Same result when asking directly for the zone
+  assert_eq!((white_count, white_weight),
+             board.attackers_on_zone(board.king_zone(Color::White), Color::Black));
+}
+
+#[test]
+fn test_piece_on() {
+  let board = Board::from_fen("r1bqk2r/pp1nbppp/2p1pn2/3p2B1/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQkq - 0 7");
+  let expected = "r.bqk..r\
+                  pp.nbppp\
+                  ..p.pn..\
+                  ...p..B.\
+                  ..PP....\
+                  ..N.PN..\
+                  PP...PPP\
+                  R..QKB.R";
+  let expected: Vec<char> = expected.chars().filter(|c| !c.is_whitespace()).collect();
+  assert_eq!(64, expected.len());
+
+  for (i, c) in expected.iter().enumerate() {
+    // The expected squares are listed from a8 to h1
+    let square = ((7 - i / 8) * 8 + i % 8) as u8;
+    let piece = match c {
+      '.' => None,
+      _ => {
+        let piece = Piece::from_char(*c).unwrap();
+        Some((piece.color, piece.p_type))
+      },
+    };
+    assert_eq!(piece, board.piece_on(square), "square {}", square);
+  }
+
+  assert_eq!(Some((Color::White, PieceType::King)), board.piece_on(4));
+  assert_eq!(Some((Color::Black, PieceType::Knight)), board.piece_on(51));
+  assert_eq!(None, board.piece_on(36));
+}
+
+#[test]
+fn test_move_to_san() {
+  let find_move = |board: &Board, notation: &str| {
+    board.get_moves().into_iter().find(|m| m.to_string() == notation).expect(notation)
+  };
+
+  let board = Board::default();
+  assert_eq!("e4", board.move_to_san(&find_move(&board, "e2e4")));
+  assert_eq!("Nf3", board.move_to_san(&find_move(&board, "g1f3")));
+
+  // Knights on c3 and g3 can both go to e4, rooks on a1 and a5 to a3
+  let board = Board::from_fen("r3k2r/8/8/R2p4/8/2N3N1/8/R3K2R w KQkq - 0 1");
+  assert_eq!("Nce4", board.move_to_san(&find_move(&board, "c3e4")));
+  assert_eq!("Nge4", board.move_to_san(&find_move(&board, "g3e4")));
+  assert_eq!("R1a3", board.move_to_san(&find_move(&board, "a1a3")));
+  assert_eq!("R5a3", board.move_to_san(&find_move(&board, "a5a3")));
+  assert_eq!("Rxd5", board.move_to_san(&find_move(&board, "a5d5")));
+  assert_eq!("O-O", board.move_to_san(&find_move(&board, "e1g1")));
+  assert_eq!("O-O-O", board.move_to_san(&find_move(&board, "e1c1")));
+
+  // Pawn captures, promotions, checks and checkmates
+  let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+  assert_eq!("exd5", board.move_to_san(&find_move(&board, "e4d5")));
+  assert_eq!("Bb5+", board.move_to_san(&find_move(&board, "f1b5")));
+  let board = Board::from_fen("8/P7/8/8/8/8/k7/4K3 w - - 0 1");
+  assert_eq!("a8=Q+", board.move_to_san(&find_move(&board, "a7a8Q")));
+  assert_eq!("a8=N", board.move_to_san(&find_move(&board, "a7a8N")));
+  let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+  assert_eq!("Ra8#", board.move_to_san(&find_move(&board, "a1a8")));
 }
 
 #[ignore]
@@ -306,6 +469,199 @@ fn generate_ranks_files() {
   println!("pub const FILES:[u64; 8] = {:#018X?};", files);
 }
 
+#[test]
+fn test_find_move_from_pgn_notation_disambiguation() {
+  let find_move = |board: &mut Board, notation: &str| {
+    board.find_move_from_pgn_notation(notation).unwrap().to_string()
+  };
+
+  // Knights on b8 and f6 can both go to d7
+  let fen = "rnbqkb1r/ppp1pppp/5n2/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 1 3";
+  let mut board = Board::from_fen(fen);
+  assert_eq!("b8d7", find_move(&mut board, "Nbd7"));
+  assert_eq!("f6d7", find_move(&mut board, "Nfd7"));
+  assert!(board.find_move_from_pgn_notation("Nd7").is_err());
+
+  // Knights on g5 and g1 can both go to f3
+  let mut board = Board::from_fen("4k3/8/8/6N1/8/8/8/4K1N1 w - - 0 1");
+  assert_eq!("g5f3", find_move(&mut board, "N5f3"));
+  assert_eq!("g1f3", find_move(&mut board, "N1f3"));
+  assert!(board.find_move_from_pgn_notation("Nf3").is_err());
+
+  // Rooks on d8 and h8 can both go to f8
+  let mut board = Board::from_fen("3r3r/8/8/8/8/8/8/k5K1 b - - 0 1");
+  assert_eq!("d8f8", find_move(&mut board, "Rdf8"));
+  assert_eq!("h8f8", find_move(&mut board, "Rhf8"));
+
+  // Rooks on a1 and a5 can both go to a3
+  let mut board = Board::from_fen("r3k2r/8/8/R2p4/8/2N3N1/8/R3K2R w KQkq - 0 1");
+  assert_eq!("a1a3", find_move(&mut board, "R1a3"));
+  assert_eq!("a5a3", find_move(&mut board, "R5a3"));
+  assert_eq!("a5d5", find_move(&mut board, "Rxd5"));
+
+  // Queens on h4, h1 and e4 can all go to e1
+  let mut board = Board::from_fen("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1");
+  assert_eq!("h4e1", find_move(&mut board, "Qh4e1"));
+  assert_eq!("h1e1", find_move(&mut board, "Qh1e1+"));
+  assert_eq!("e4e1", find_move(&mut board, "Qee1"));
+  assert!(board.find_move_from_pgn_notation("Qhe1").is_err());
+  assert!(board.find_move_from_pgn_notation("Q4e1").is_err());
+
+  // Pawn captures
+  let mut board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/2P1P3/8/PP1P1PPP/RNBQKBNR w KQkq - 0 3");
+  assert_eq!("e4d5", find_move(&mut board, "exd5"));
+  assert_eq!("c4d5", find_move(&mut board, "cxd5"));
+  assert_eq!("e4e5", find_move(&mut board, "e5"));
+
+  // Invalid notations
+  assert!(board.find_move_from_pgn_notation("").is_err());
+  assert!(board.find_move_from_pgn_notation("N").is_err());
+  assert!(board.find_move_from_pgn_notation("Nb1b1c3").is_err());
+  assert!(board.find_move_from_pgn_notation("N1bc3").is_err());
+}
+
+#[test]
+fn test_gives_check() {
+  let find_move = |board: &Board, notation: &str| {
+    board.get_moves().into_iter().find(|m| m.to_string() == notation).expect(notation)
+  };
+
+  // Direct knight check
+  let board = Board::from_fen("4k3/8/8/8/6N1/8/8/4K3 w - - 0 1");
+  assert!(board.gives_check(&find_move(&board, "g4f6")));
+  assert!(!board.gives_check(&find_move(&board, "g4e3")));
+
+  // Discovered check by the rook on e1
+  let board = Board::from_fen("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1");
+  assert!(board.gives_check(&find_move(&board, "e2c3")));
+  assert!(board.gives_check(&find_move(&board, "e2g3")));
+  assert!(!board.gives_check(&find_move(&board, "e1d1")));
+
+  // Promotions check depending on the promoted piece
+  let board = Board::from_fen("3k4/1P6/8/8/8/8/8/4K3 w - - 0 1");
+  assert!(board.gives_check(&find_move(&board, "b7b8Q")));
+  assert!(board.gives_check(&find_move(&board, "b7b8R")));
+  assert!(!board.gives_check(&find_move(&board, "b7b8N")));
+  assert!(!board.gives_check(&find_move(&board, "b7b8B")));
+
+  // Castling, the rook gives check on the f-file
+  let board = Board::from_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1");
+  assert!(board.gives_check(&find_move(&board, "e1g1")));
+  assert!(!board.gives_check(&find_move(&board, "e1f2")));
+
+  // En-passant opens the 5th rank for the rook
+  let board = Board::from_fen("8/8/8/k2pP2R/8/8/8/4K3 w - d6 0 1");
+  assert!(board.gives_check(&find_move(&board, "e5d6")));
+  assert!(!board.gives_check(&find_move(&board, "e5e6")));
+
+  // Compare with applying the moves on a few positions, for both sides
+  let fens = ["r3k2r/8/8/R2p4/8/2N3N1/8/R3K2R w KQkq - 0 1",
+              "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 2 3",
+              "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+              "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"];
+  for fen in fens {
+    let board = Board::from_fen(fen);
+    for m in board.get_moves() {
+      let mut new_board = board;
+      new_board.apply_move(&m);
+      assert_eq!(new_board.checkers != 0,
+                 board.gives_check(&m),
+                 "Move {} on position {}",
+                 m,
+                 fen);
+    }
+  }
+}
+
+#[test]
+fn test_least_valuable_attacker() {
+  // e5 is attacked by the d4 pawn, the c4 knight and the e1 rook
+  let board = Board::from_fen("4k3/8/8/4p3/2NP4/8/8/4R1K1 w - - 0 1");
+  let e5 = string_to_square("e5");
+  assert_eq!(3, board.attackers_count(e5, Color::White));
+  assert_eq!(Some((string_to_square("d4"), PieceType::Pawn)),
+             board.least_valuable_attacker(e5, Color::White));
+  assert_eq!(None, board.least_valuable_attacker(e5, Color::Black));
+
+  // Remove the pawn and the knight, the rook is left
+  let pawn = 1 << string_to_square("d4");
+  let knight = 1 << string_to_square("c4");
+  let rook = 1 << string_to_square("e1");
+  assert_eq!(Some((string_to_square("c4"), PieceType::Knight)),
+             board.least_valuable_attacker_with_removed(e5, Color::White, pawn));
+  assert_eq!(Some((string_to_square("e1"), PieceType::Rook)),
+             board.least_valuable_attacker_with_removed(e5, Color::White, pawn | knight));
+  assert_eq!(None,
+             board.least_valuable_attacker_with_removed(e5, Color::White, pawn | knight | rook));
+
+  // X-ray: the queen in front is attacking, the rook behind appears once the
+  // queen is removed
+  let board = Board::from_fen("4k3/8/8/4p3/8/4Q3/8/4R1K1 w - - 0 1");
+  let queen = 1 << string_to_square("e3");
+  assert_eq!(1, board.attackers_count(e5, Color::White));
+  assert_eq!(Some((string_to_square("e3"), PieceType::Queen)),
+             board.least_valuable_attacker(e5, Color::White));
+  assert_eq!(Some((string_to_square("e1"), PieceType::Rook)),
+             board.least_valuable_attacker_with_removed(e5, Color::White, queen));
+}
+
+#[test]
+fn test_static_exchange_evaluation() {
+  let see = |fen: &str, mv: &str| -> i32 {
+    let board = Board::from_fen(fen);
+    let mv = board.get_moves().into_iter().find(|m| m.to_string() == mv).unwrap();
+    board.see(&mv)
+  };
+
+  // Free pawn
+  assert_eq!(100, see("4k3/8/8/4p3/3P4/8/8/4K3 w - - 0 1", "d4e5"));
+  // Queen takes a defended pawn
+  assert_eq!(-850, see("4k3/8/3p4/4p3/8/8/8/4Q1K1 w - - 0 1", "e1e5"));
+  // Rook takes a pawn defended by a rook that will not recapture
+  assert_eq!(100,
+             see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"));
+  // Knight takes a pawn defended by a knight
+  assert_eq!(-200,
+             see("1k1r4/1ppn3p/p7/4p3/8/P2N2P1/1PP4P/2K5 w - - 0 1", "d3e5"));
+  // En-passant
+  assert_eq!(100, see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"));
+  // Promotion with capture
+  assert_eq!(1350, see("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8Q"));
+  // The king cannot recapture on a defended square
+  assert_eq!(100, see("8/8/8/8/8/3k4/4p3/4RK2 w - - 0 1", "e1e2"));
+  // Quiet move to an attacked square
+  assert_eq!(-300, see("4k3/8/8/8/4p3/8/8/2N1K3 w - - 0 1", "c1d3"));
+}
+
+#[test]
+fn test_see_ge_agrees_with_see() {
+  let fens = ["1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+              "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+              "4k3/8/3p4/4p3/8/8/8/4Q1K1 w - - 0 1",
+              "3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1",
+              "8/8/8/8/8/3k4/4p3/4RK2 w - - 0 1",
+              "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4"];
+  let mut boards: Vec<Board> = fens.iter().map(|fen| Board::from_fen(fen)).collect();
+  for _ in 0..200 {
+    boards.push(Board::new_random());
+  }
+
+  for board in boards {
+    for mv in board.get_moves() {
+      let see = board.see(&mv);
+      for threshold in (-1200..=1200).step_by(50).chain([see - 1, see, see + 1]) {
+        assert_eq!(see >= threshold,
+                   board.see_ge(&mv, threshold),
+                   "see_ge({}, {}) disagrees with see = {} on {}",
+                   mv,
+                   threshold,
+                   see,
+                   board.to_fen());
+      }
+    }
+  }
+}
+
 #[test]
 fn test_pins_mask_calculations() {
   // Here we have a queen pinning a pawn
@@ -409,6 +765,56 @@ fn test_get_attackers() {
   assert_eq!(e, a);
 }
 
+#[test]
+fn test_attacks_from() {
+  // Legal destinations of the piece on a square
+  let destinations = |board: &Board, square: &str| -> BoardMask {
+    let square = string_to_square(square);
+    board.get_moves()
+         .iter()
+         .filter(|m| m.src() as u8 == square)
+         .fold(0, |mask, m| mask | (1 << m.dest()))
+  };
+
+  // The bishop on e2 is pinned: no legal move, but it still attacks d3 and f3
+  let board = Board::from_fen("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1");
+  assert_eq!(0, destinations(&board, "e2"));
+  let attacks = board.attacks_from(string_to_square("e2"));
+  assert_eq!(0, attacks & (1 << string_to_square("e8")));
+  assert!(square_in_mask!(string_to_square("d3"), attacks));
+  assert!(square_in_mask!(string_to_square("a6"), attacks));
+  assert!(square_in_mask!(string_to_square("h5"), attacks));
+  assert!(square_in_mask!(string_to_square("d1"), attacks));
+  assert!(square_in_mask!(string_to_square("f1"), attacks));
+
+  // The rook on e8 attacks the pinned bishop but not the king behind it, and
+  // defends its own king
+  let attacks = board.attacks_from(string_to_square("e8"));
+  assert!(square_in_mask!(string_to_square("e2"), attacks));
+  assert!(!square_in_mask!(string_to_square("e1"), attacks));
+  assert!(square_in_mask!(string_to_square("g8"), attacks));
+  assert!(!square_in_mask!(string_to_square("h8"), attacks));
+
+  // The knight on f3 defends the pawn on e5: it attacks e5 without being able
+  // to move there
+  let board = Board::from_fen("4k3/8/8/4P3/8/5N2/8/4K3 w - - 0 1");
+  let attacks = board.attacks_from(string_to_square("f3"));
+  let moves = destinations(&board, "f3");
+  assert!(square_in_mask!(string_to_square("e5"), attacks));
+  assert!(!square_in_mask!(string_to_square("e5"), moves));
+  assert!(square_in_mask!(string_to_square("e1"), attacks));
+  assert!(!square_in_mask!(string_to_square("e1"), moves));
+  assert_eq!(attacks & !board.pieces.white.all(), moves);
+
+  // Pawns attack diagonally, not in front of them
+  let attacks = board.attacks_from(string_to_square("e5"));
+  assert_eq!((1 << string_to_square("d6")) | (1 << string_to_square("f6")),
+             attacks);
+
+  // Empty squares attack nothing
+  assert_eq!(0, board.attacks_from(string_to_square("a1")));
+}
+
 #[test]
 fn apply_under_promotion() {
   let fen = "8/8/6k1/8/8/4K3/5pq1/8 b - - 3 72";
@@ -498,3 +904,349 @@ fn check_with_discovery_no_en_passant() {
   }
   //assert_eq!(13, moves.len());
 }
+
+#[test]
+fn test_try_from_fen() {
+  let valid_fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                    "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w Kq e6 0 2",
+                    "4k3/8/8/8/8/8/8/4K3 b - - 12 60"];
+  for fen in valid_fens {
+    let board = Board::try_from_fen(fen);
+    assert!(board.is_ok(), "{} should be valid", fen);
+    assert_eq!(Board::from_fen(fen), board.unwrap());
+  }
+
+  let invalid_fens =
+    [("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::InvalidFieldCount(4)),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", FenError::InvalidRankCount(7)),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidRankLength(2)),
+     ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPieceChar('9')),
+     ("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPieceChar('x')),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKKNR w KQkq - 0 1",
+      FenError::InvalidKingCount(Color::White)),
+     ("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+      FenError::InvalidKingCount(Color::Black)),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+      FenError::InvalidSideToMove(String::from("x"))),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+      FenError::InvalidCastlingRights(String::from("KQkx"))),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKkq - 0 1",
+      FenError::InvalidCastlingRights(String::from("KKkq"))),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+      FenError::InvalidEnPassantSquare(String::from("e4"))),
+     ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z6 0 1",
+      FenError::InvalidEnPassantSquare(String::from("z6")))];
+  for (fen, error) in invalid_fens {
+    assert_eq!(Err(error), Board::try_from_fen(fen), "FEN: {}", fen);
+  }
+
+  // The lenient version still returns a board
+  let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1");
+  assert_eq!(Color::White, board.side_to_play);
+}
+
+#[test]
+fn test_piece_set_fen_placement() {
+  // Round trip over the positions of a few games
+  let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+              "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R b KQkq - 0 4",
+              "8/5pk1/5p1p/2R5/5K2/1r4P1/7P/8 b - - 8 43",
+              "8/q1P1k3/8/8/8/8/7P/7K w - - 0 1"];
+  for fen in fens {
+    let mut board = Board::from_fen(fen);
+    for ply in 0..60 {
+      let placement = board.pieces.to_fen_placement();
+      assert_eq!(Ok(board.pieces),
+                 PieceSet::try_from_fen(&placement),
+                 "{}",
+                 placement);
+      assert_eq!(board.pieces, PieceSet::from_fen(&placement));
+      assert_eq!(placement, board.to_fen());
+
+      let moves = board.get_moves();
+      if moves.is_empty() {
+        break;
+      }
+      board.apply_move(&moves[(ply * 7) % moves.len()]);
+    }
+  }
+
+  // Edge cases: empty ranks, full ranks and the full FEN
+  let placement = "8/8/8/8/8/8/8/8";
+  assert_eq!(Ok(PieceSet::new()), PieceSet::try_from_fen(placement));
+  assert_eq!(placement, PieceSet::new().to_fen_placement());
+  let piece_set = PieceSet::try_from_fen("rnbqkbnr/8/8/8/8/8/8/RNBQKBNR w KQkq - 0 1").unwrap();
+  assert_eq!(BLACK_ROOK, piece_set.get(string_to_square("a8")));
+  assert_eq!(WHITE_KING, piece_set.get(string_to_square("e1")));
+  assert_eq!(16, piece_set.all().count_ones());
+  assert_eq!("rnbqkbnr/8/8/8/8/8/8/RNBQKBNR", piece_set.to_fen_placement());
+  assert_eq!("8/8/8/3k4/8/8/3K4/8",
+             PieceSet::from_fen("8/8/8/3k4/8/8/3K4/8").to_fen_placement());
+
+  // Malformed placements are rejected
+  let invalid_placements = [("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+                             FenError::InvalidRankLength(7)),
+                            ("rnbqkbnr/pppppppp/8/8/8/44p/PPPPPPPP/RNBQKBNR",
+                             FenError::InvalidRankLength(3)),
+                            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN",
+                             FenError::InvalidRankLength(1)),
+                            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR",
+                             FenError::InvalidRankLength(1)),
+                            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8",
+                             FenError::InvalidRankCount(9)),
+                            ("rnbqkbnr/pppppppp/8/8/3X4/8/PPPPPPPP/RNBQKBNR",
+                             FenError::InvalidPieceChar('X')),
+                            ("rnbqkbnr/pppppppp/8/8/3.4/8/PPPPPPPP/RNBQKBNR",
+                             FenError::InvalidPieceChar('.')),
+                            ("rnbqkbnr/pppppppp/8/8/0/8/PPPPPPPP/RNBQKBNR",
+                             FenError::InvalidPieceChar('0'))];
+  for (placement, error) in invalid_placements {
+    assert_eq!(Err(error), PieceSet::try_from_fen(placement), "{}", placement);
+  }
+
+  // The lenient version skips the invalid squares
+  let start_position = PieceSet::default();
+  assert_eq!(start_position,
+             PieceSet::from_fen("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"));
+  assert_eq!(start_position,
+             PieceSet::from_fen("rnbqkbnr/pppppppp/8/8/3X4/8/PPPPPPPP/RNBQKBNR"));
+  assert_eq!(start_position,
+             PieceSet::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8"));
+}
+
+#[test]
+fn test_check_checkmate_stalemate() {
+  // Back-rank mate
+  let board = Board::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+  assert!(board.is_check());
+  assert!(board.is_checkmate());
+  assert!(!board.is_stalemate());
+
+  // Stalemate
+  let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+  assert!(!board.is_check());
+  assert!(!board.is_checkmate());
+  assert!(board.is_stalemate());
+
+  // Check, but the king can escape
+  let board = Board::from_fen("4k3/8/8/8/8/8/4R3/4K3 b - - 0 1");
+  assert!(board.is_check());
+  assert!(!board.is_checkmate());
+  assert!(!board.is_stalemate());
+
+  // Check that can only be blocked
+  let board = Board::from_fen("3R2k1/5ppp/8/8/8/8/4r3/6K1 b - - 1 1");
+  assert!(board.is_check());
+  assert!(!board.is_checkmate());
+
+  // Double check, the king cannot move
+  let board = Board::from_fen("3qkb2/3p1p2/3N4/8/8/8/8/4R1K1 b - - 0 1");
+  assert!(board.is_check());
+  assert_eq!(2, board.checks());
+  assert!(board.is_checkmate());
+
+  // Only a pinned piece left, and the king is blocked
+  let board = Board::from_fen("k7/1r1N4/8/1N1Q4/8/8/8/6K1 b - - 0 1");
+  assert!(board.is_stalemate());
+
+  // Start position
+  let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+  assert!(!board.is_check());
+  assert!(!board.is_checkmate());
+  assert!(!board.is_stalemate());
+}
+
+#[test]
+fn test_has_legal_moves_matches_move_generation() {
+  let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+              "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 2 3",
+              "8/8/6k1/8/8/4K3/5pq1/8 b - - 3 72",
+              "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"];
+  for fen in fens {
+    let mut board = Board::from_fen(fen);
+    for i in 0..200 {
+      let moves = board.get_moves();
+      assert_eq!(!moves.is_empty(),
+                 board.has_legal_moves(),
+                 "FEN: {}",
+                 board.to_fen());
+      if moves.is_empty() {
+        break;
+      }
+      board.apply_move(&moves[(i * 7 + 3) % moves.len()]);
+    }
+  }
+}
+
+#[test]
+fn test_incremental_hash_matches_recomputed_hash() {
+  use crate::model::game_state::GameState;
+  use rand::seq::SliceRandom;
+
+  let mut rng = rand::thread_rng();
+  let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+              "r3k2r/pppq1ppp/2n2n2/2bpp1B1/2B1P1b1/2NP1N2/PPPQ1PPP/R3K2R w KQkq - 4 8",
+              "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+              "8/P1k5/8/8/8/8/1K4p1/8 w - - 0 1"];
+  for fen in fens {
+    for _ in 0..20 {
+      let mut board = Board::from_fen(fen);
+      for _ in 0..100 {
+        let moves = board.get_moves();
+        let mv = moves.choose(&mut rng);
+        if mv.is_none() {
+          break;
+        }
+        board.apply_move(mv.unwrap());
+
+        let recomputed = Board::from_fen(GameState::from_board(&board).to_fen().as_str());
+        assert_eq!(recomputed.hash,
+                   board.hash,
+                   "Hash mismatch after {} on {}",
+                   mv.unwrap(),
+                   board.to_fen());
+      }
+    }
+  }
+}
+
+#[test]
+fn test_mirror() {
+  use crate::model::game_state::GameState;
+
+  let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+  let mirrored = board.mirror();
+  assert_eq!(board.pieces, mirrored.pieces);
+  assert_eq!(board.castling_rights, mirrored.castling_rights);
+  assert_eq!(Color::Black, mirrored.side_to_play);
+
+  let board = Board::from_fen("r3k2r/pp3ppp/8/3pP3/8/8/PPP2PPP/R3K1R1 w Qkq d6 0 12");
+  let mirrored = board.mirror();
+  assert_eq!("r3k1r1/ppp2ppp/8/8/3Pp3/8/PP3PPP/R3K2R b KQq d3 0 1",
+             GameState::from_board(&mirrored).to_fen());
+  assert_eq!(board, mirrored.mirror());
+  assert_eq!(Board::from_fen(&GameState::from_board(&mirrored).to_fen()).hash,
+             mirrored.hash);
+
+  // Checks are mirrored too
+  let board = Board::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+  assert!(board.is_check());
+  assert!(board.mirror().is_check());
+}
+
+#[test]
+fn test_piece_set_iterator() {
+  let piece_set: PieceSet = PieceSet::default();
+  let pieces: Vec<(u8, Color, PieceType)> = piece_set.iter().collect();
+  assert_eq!(32, pieces.len());
+
+  // Pieces come by increasing square index
+  assert_eq!((0, Color::White, PieceType::Rook), pieces[0]);
+  assert_eq!((3, Color::White, PieceType::Queen), pieces[3]);
+  assert_eq!((4, Color::White, PieceType::King), pieces[4]);
+  assert_eq!((59, Color::Black, PieceType::Queen), pieces[27]);
+  assert_eq!((60, Color::Black, PieceType::King), pieces[28]);
+  assert_eq!((63, Color::Black, PieceType::Rook), pieces[31]);
+
+  // Everything matches the square table
+  for (square, color, piece_type) in &pieces {
+    let piece = Piece { p_type: *piece_type,
+                        color:  *color, };
+    assert_eq!(piece.as_u8(), piece_set.get(*square));
+  }
+  let pawns = pieces.iter().filter(|(_, _, t)| *t == PieceType::Pawn).count();
+  let white = pieces.iter().filter(|(_, c, _)| *c == Color::White).count();
+  assert_eq!(16, pawns);
+  assert_eq!(16, white);
+
+  assert_eq!(0, PieceSet::new().iter().count());
+}
+
+#[test]
+fn test_move_generation_is_color_symmetric() {
+  // Sorted moves of a board, with squares optionally flipped vertically
+  fn move_set(board: &Board, flip: bool) -> Vec<(u8, u8, PieceType, bool)> {
+    let flip = if flip { 56 } else { 0 };
+    let mut moves = Vec::new();
+    for m in board.get_moves() {
+      let promotion = PieceType::from_u8(m.promotion().to_piece_const());
+      moves.push((m.u8_src() ^ flip, m.u8_dest() ^ flip, promotion, m.is_capture()));
+    }
+    moves.sort_by_key(|(src, dest, promotion, _)| (*src, *dest, *promotion as u8));
+    moves
+  }
+
+  let fens = ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+              "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+              "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+              "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+              "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+              "4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1"];
+  let mut boards: Vec<Board> = fens.iter().map(|fen| Board::from_fen(fen)).collect();
+  for _ in 0..500 {
+    boards.push(Board::new_random());
+  }
+
+  // White moves on a board are the black moves on the mirrored board and
+  // vice versa
+  for board in boards {
+    assert_eq!(move_set(&board, false),
+               move_set(&board.mirror(), true),
+               "Different moves for the mirrored board of {}",
+               board.to_fen());
+  }
+}
+
+#[test]
+fn test_perft() {
+  let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+  assert_eq!(1, board.perft(0));
+  assert_eq!(20, board.perft(1));
+  assert_eq!(400, board.perft(2));
+  assert_eq!(8902, board.perft(3));
+
+  // Castling, en-passant and promotions
+  let board =
+    Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+  assert_eq!(48, board.perft(1));
+  assert_eq!(2039, board.perft(2));
+
+  let board = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+  assert_eq!(2812, board.perft(3));
+}
+
+#[test]
+fn test_game_phase_value() {
+  let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+  assert_eq!(62, board.game_phase_value());
+
+  // Pawns and kings do not count
+  let board = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+  assert_eq!(10, board.game_phase_value());
+
+  // Promoted pieces count like the others
+  let board = Board::from_fen("1Q2k3/8/8/8/8/8/8/3nK2q w - - 0 1");
+  assert_eq!(21, board.game_phase_value());
+}
+
+#[test]
+fn test_order_check_evasions() {
+  // The bishop checks and is protected by the queen: taking it with the knight
+  // is the only good move
+  let board = Board::from_fen("6k1/5ppp/5q2/8/8/8/2R2b1P/3N2K1 w - - 0 1");
+  let mut moves = board.get_moves();
+  board.order_check_evasions(&mut moves);
+  assert_eq!("d1f2 c2f2 g1f1 g1h1 g1g2",
+             Move::vec_to_string(&moves),
+             "Unexpected order: {}",
+             Move::vec_to_string(&moves));
+
+  // Blocks come before the king moves
+  let board = Board::from_fen("4r1k1/5ppp/8/8/8/8/3N1PPP/4K3 w - - 0 1");
+  let mut moves = board.get_moves();
+  board.order_check_evasions(&mut moves);
+  assert_eq!("d2e4",
+             moves[0].to_string(),
+             "{}",
+             Move::vec_to_string(&moves));
+}
@@ -1,6 +1,7 @@
 use crate::model::board::*;
 use crate::model::game_state::*;
 use crate::model::moves::*;
+use crate::model::piece::{Color, PieceType, NO_PIECE};
 
 #[test]
 fn game_state_display_test() {
@@ -54,7 +55,7 @@ fn test_get_list_of_moves() {
   }
 
   // Apply the en-passant move, check that the destination capture pawn is gone.
-  game_state.apply_move_from_notation("d5c6");
+  game_state.apply_move_from_notation("d5c6").unwrap();
   let expected_fen = "r2q1rk1/p2b1ppp/2Pbpn2/8/2B5/2N2Q2/PP3PPP/R1B2RK1 b - - 0 14";
   assert_eq!(expected_fen, game_state.to_fen());
 }
@@ -63,7 +64,7 @@ fn test_get_list_of_moves() {
 fn test_apply_some_moves() {
   let fen = "r2qk2r/p1pb1ppp/3bpn2/8/2BP4/2N2Q2/PP3PPP/R1B2RK1 b kq - 2 12";
   let mut game_state = GameState::from_fen(fen);
-  game_state.apply_move_from_notation("a7a5");
+  game_state.apply_move_from_notation("a7a5").unwrap();
 
   let expected_fen = "r2qk2r/2pb1ppp/3bpn2/p7/2BP4/2N2Q2/PP3PPP/R1B2RK1 w kq - 0 13";
   assert_eq!(expected_fen, game_state.to_fen().as_str());
@@ -79,6 +80,26 @@ fn test_apply_some_moves() {
   */
 }
 
+#[test]
+fn test_with_move() {
+  let fen = "r2qk2r/p1pb1ppp/3bpn2/8/2BP4/2N2Q2/PP3PPP/R1B2RK1 b kq - 2 12";
+  let game_state = GameState::from_fen(fen);
+  let mv = Move::from_string("d8e7");
+
+  let new_state = game_state.with_move(&mv);
+  assert_eq!(fen, game_state.to_fen());
+  assert_eq!(0, game_state.last_positions.count(new_state.board.hash));
+
+  let mut expected_state = game_state.clone();
+  expected_state.apply_move(&mv);
+  assert_eq!(expected_state.to_fen(), new_state.to_fen());
+  assert_eq!(expected_state.board.hash, new_state.board.hash);
+  assert_eq!(expected_state.ply, new_state.ply);
+  assert_eq!(expected_state.last_positions.len(),
+             new_state.last_positions.len());
+  assert_eq!(1, new_state.last_positions.count(game_state.board.hash));
+}
+
 #[test]
 fn test_check_legal_moves() {
   let fen = "4B3/p5k1/1pp4p/8/8/P6P/5PP1/2R3K1 b - - 0 37";
@@ -254,3 +275,262 @@ fn test_get_moves_while_in_check() {
 
   assert_eq!(5, computed_moves.len());
 }
+
+#[test]
+fn test_draw_by_repetition() {
+  let mut game_state = GameState::default();
+  assert!(!game_state.is_draw_by_repetition());
+
+  // Shuffle the knights back and forth, start position occurs a 2nd time
+  game_state.apply_move_list("g1f3 g8f6 f3g1 f6g8");
+  assert!(!game_state.is_draw_by_repetition());
+
+  // ... and a 3rd time
+  game_state.apply_move_list("g1f3 g8f6 f3g1 f6g8");
+  assert!(game_state.is_draw_by_repetition());
+
+  // A pawn move resets the history
+  game_state.apply_move_list("e2e4");
+  assert!(!game_state.is_draw_by_repetition());
+  game_state.apply_move_list("g8f6 g1f3 f6g8 f3g1 g8f6 g1f3 f6g8 f3g1");
+  assert!(game_state.is_draw_by_repetition());
+}
+
+#[test]
+fn test_halfmove_clock() {
+  let mut game_state = GameState::default();
+  assert_eq!(0, game_state.halfmove_clock());
+  assert_eq!(100, game_state.moves_until_fifty_move_draw());
+
+  // Knight moves are reversible
+  game_state.apply_move_list("g1f3 g8f6 b1c3");
+  assert_eq!(3, game_state.halfmove_clock());
+  assert_eq!(97, game_state.moves_until_fifty_move_draw());
+
+  // A pawn move resets the count
+  game_state.apply_move_list("d7d5");
+  assert_eq!(0, game_state.halfmove_clock());
+  assert_eq!(100, game_state.moves_until_fifty_move_draw());
+
+  // ... and so does a capture
+  game_state.apply_move_list("c3b5 b8c6");
+  assert_eq!(98, game_state.moves_until_fifty_move_draw());
+  game_state.apply_move_list("b5c7");
+  assert_eq!(100, game_state.moves_until_fifty_move_draw());
+
+  // The count is read from the FEN, and never goes below 0
+  let game_state = GameState::from_fen("8/8/4k3/8/8/3K4/8/7R w - - 94 120");
+  assert_eq!(94, game_state.halfmove_clock());
+  assert_eq!(6, game_state.moves_until_fifty_move_draw());
+  let game_state = GameState::from_fen("8/8/4k3/8/8/3K4/8/7R w - - 102 120");
+  assert_eq!(0, game_state.moves_until_fifty_move_draw());
+}
+
+#[test]
+fn test_game_status() {
+  // FEN, moves played from the FEN and expected status
+  let fool_mate = "f2f3 e7e5 g2g4 d8h4";
+  let knight_shuffle = "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1";
+  let back_rank_mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+  let positions =
+    [(START_POSITION_FEN, "", GameStatus::Ongoing),
+     (START_POSITION_FEN, "e2e4 e7e5", GameStatus::Ongoing),
+     (START_POSITION_FEN, fool_mate, GameStatus::BlackWon),
+     (back_rank_mate, "a1a8", GameStatus::WhiteWon),
+     ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", "", GameStatus::Stalemate),
+     ("7k/8/5QK1/8/8/8/8/8 w - - 0 1", "f6f7", GameStatus::Stalemate),
+     ("8/8/4k3/8/8/3NK3/8/8 w - - 0 1", "", GameStatus::Draw),
+     ("8/8/4k3/8/3p4/4K3/8/8 w - - 0 1", "e3d4", GameStatus::Draw),
+     ("8/8/4k3/8/8/3RK3/8/8 w - - 98 80", "d3d1", GameStatus::Ongoing),
+     ("8/8/4k3/8/8/3RK3/8/8 w - - 99 80", "d3d1", GameStatus::Draw),
+     // A checkmate on the 100th ply wins the game
+     ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80", "a1a8", GameStatus::WhiteWon),
+     (START_POSITION_FEN, knight_shuffle, GameStatus::Ongoing),
+     (START_POSITION_FEN, &format!("{knight_shuffle} f6g8"), GameStatus::ThreeFoldRepetition)];
+
+  for (fen, moves, status) in positions {
+    let mut game_state = GameState::from_fen(fen);
+    game_state.apply_move_list(moves);
+    assert_eq!(status,
+               game_state.game_status(),
+               "{} after '{}'",
+               fen,
+               moves);
+  }
+}
+
+#[test]
+fn test_legal_moves_san() {
+  let game_state = GameState::default();
+  let moves = game_state.legal_moves_san();
+  assert_eq!(20, moves.len());
+  assert!(moves.contains(&String::from("e4")));
+  assert!(moves.contains(&String::from("Nf3")));
+
+  // Knights on c3 and g3 go to e4, rooks on a1 and a5 go to a3, queens on h4,
+  // h1 and e4 go to e1
+  let fens = ["r3k2r/8/8/R2p4/8/2N3N1/8/R3K2R w KQkq - 0 1",
+              "1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1"];
+  let expected_moves = ["Nce4", "Nge4", "R1a3", "R5a3", "Rxd5", "O-O", "O-O-O", "Qh4e1", "Q1e1",
+                        "Qee1", "Q4h2+", "Q1h2+"];
+  let mut found_moves = 0;
+
+  for fen in fens {
+    let game_state = GameState::from_fen(fen);
+    let san_moves = game_state.legal_moves_san();
+    assert_eq!(game_state.get_moves().len(), san_moves.len());
+    found_moves += expected_moves.iter().filter(|m| san_moves.contains(&m.to_string())).count();
+
+    // Each SAN string goes back to the same move
+    for (mv, san) in game_state.get_moves().iter().zip(san_moves.iter()) {
+      let mut board = game_state.board;
+      assert_eq!(Ok(*mv),
+                 board.find_move_from_pgn_notation(san),
+                 "SAN: {} - FEN: {}",
+                 san,
+                 fen);
+    }
+  }
+
+  assert_eq!(expected_moves.len(), found_moves);
+}
+
+#[test]
+fn test_render_line_san() {
+  let game_state = GameState::default();
+
+  // Scholar's mate, and a bishop sacrifice on f7 with check
+  let moves = Move::string_to_vec("e2e4 e7e5 f1c4 b8c6 d1h5 g8f6 h5f7");
+  assert_eq!(vec!["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"],
+             render_line_san(&game_state, &moves));
+  let moves = Move::string_to_vec("e2e4 e7e5 f1c4 b8c6 c4f7 e8f7");
+  assert_eq!(vec!["e4", "e5", "Bc4", "Nc6", "Bxf7+", "Kxf7"],
+             render_line_san(&game_state, &moves));
+
+  // Illegal move in the middle of the line
+  let moves = Move::string_to_vec("e2e4 e7e5 e1e3 e8e7");
+  assert_eq!(vec!["e4", "e5", "(e1e3 illegal)"],
+             render_line_san(&game_state, &moves));
+
+  // Empty line, and a line from another position
+  assert!(render_line_san(&game_state, &[]).is_empty());
+  let game_state = GameState::from_fen("4k3/8/8/8/8/8/4r3/R3K3 w Q - 0 1");
+  let moves = Move::string_to_vec("e1e2 e8d7 a1a7");
+  assert_eq!(vec!["Kxe2", "Kd7", "Ra7+"],
+             render_line_san(&game_state, &moves));
+}
+
+#[test]
+fn test_apply_move_from_notation_errors() {
+  let mut game_state = GameState::default();
+
+  // Legal move
+  assert_eq!(Ok(Move::from_string("e2e4")),
+             game_state.apply_move_from_notation("e2e4"));
+  assert_eq!(Color::Black, game_state.board.side_to_play);
+
+  // Well-formed, but white cannot play again
+  let fen = game_state.to_fen();
+  assert_eq!(Err(MoveError::IllegalMove(String::from("d2d4"))),
+             game_state.apply_move_from_notation("d2d4"));
+  assert_eq!(Err(MoveError::IllegalMove(String::from("e7e8q"))),
+             game_state.apply_move_from_notation("e7e8q"));
+
+  // Not a move at all
+  for garbage in ["", "e2", "e2e9", "i2i4", "e7e8k", "hello", "e2e4e5", "é2e4"] {
+    assert_eq!(Err(MoveError::InvalidNotation(garbage.to_string())),
+               game_state.apply_move_from_notation(garbage));
+  }
+
+  // The position did not change
+  assert_eq!(fen, game_state.to_fen());
+  assert_eq!(Color::Black, game_state.board.side_to_play);
+
+  // Promotions are accepted in lower or upper case
+  let mut game_state = GameState::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+  assert_eq!(Ok(Move::from_string("e7e8Q")),
+             game_state.apply_move_from_notation("e7e8q"));
+}
+
+#[test]
+fn test_make_move_reports_the_captured_piece() {
+  let find_move = |game_state: &GameState, notation: &str| {
+    game_state.get_moves().into_iter().find(|m| m.to_string() == notation).unwrap()
+  };
+
+  // Normal capture: the knight takes the pawn on e5
+  let mut game_state =
+    GameState::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 2");
+  let mv = find_move(&game_state, "f3e5");
+  let delta = game_state.make_move(&mv);
+  assert_eq!((PieceType::Knight, string_to_square("f3"), string_to_square("e5")),
+             delta.moved);
+  assert_eq!(Some((PieceType::Pawn, string_to_square("e5"))),
+             delta.captured);
+  assert_eq!(None, delta.promoted);
+  assert_eq!(Color::Black, game_state.board.side_to_play);
+
+  // En-passant: the captured pawn is not on the destination square
+  let mut game_state =
+    GameState::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3");
+  let mv = find_move(&game_state, "e5f6");
+  let delta = game_state.make_move(&mv);
+  assert_eq!(Some((PieceType::Pawn, string_to_square("f5"))),
+             delta.captured);
+  assert_eq!(NO_PIECE,
+             game_state.board.pieces.get(string_to_square("f5")));
+
+  // Non-capture
+  let mut game_state = GameState::default();
+  let mv = find_move(&game_state, "g1f3");
+  let delta = game_state.make_move(&mv);
+  assert_eq!(None, delta.captured);
+  assert_eq!(None, delta.castling_rook);
+
+  // Capture with promotion, and castling
+  let mut game_state = GameState::from_fen("1r2k3/P7/8/8/8/8/8/4K2R w K - 0 1");
+  let mv = find_move(&game_state, "a7b8Q");
+  let delta = game_state.make_move(&mv);
+  assert_eq!(Some((PieceType::Rook, string_to_square("b8"))),
+             delta.captured);
+  assert_eq!(Some(PieceType::Queen), delta.promoted);
+  let mut game_state = GameState::from_fen("1r2k3/P7/8/8/8/8/8/4K2R w K - 0 1");
+  let mv = find_move(&game_state, "e1g1");
+  let delta = game_state.make_move(&mv);
+  assert_eq!(Some((string_to_square("h1"), string_to_square("f1"))),
+             delta.castling_rook);
+}
+
+#[test]
+fn test_apply_uci_moves() {
+  // Morphy vs Duke of Brunswick and Count Isouard, Paris 1858
+  let moves = "e2e4 e7e5 g1f3 d7d6 d2d4 c8g4 d4e5 g4f3 d1f3 d6e5 f1c4 g8f6 f3b3 d8e7 b1c3 c7c6 \
+               c1g5 b7b5 c3b5 c6b5 c4b5 b8d7 e1c1 a8d8 d1d7 d8d7 h1d1 e7e6 b5d7 f6d7 b3b8 d7b8 \
+               d1d8";
+  let mut game_state = GameState::from_fen(START_POSITION_FEN);
+  assert_eq!(Ok(()), game_state.apply_uci_moves(moves));
+  assert_eq!("1n1Rkb1r/p4ppp/4q3/4p1B1/4P3/8/PPP2PPP/2K5 b k - 1 17",
+             game_state.to_fen());
+  // Checkmate
+  assert!(game_state.get_moves().is_empty());
+  assert!(game_state.board.checkers != 0);
+
+  // Nothing to apply
+  let mut game_state = GameState::from_fen(START_POSITION_FEN);
+  assert_eq!(Ok(()), game_state.apply_uci_moves(""));
+  assert_eq!(START_POSITION_FEN, game_state.to_fen());
+}
+
+#[test]
+fn test_apply_uci_moves_stops_at_the_first_bad_move() {
+  // Illegal move at index 2: the first 2 moves stay applied
+  let mut game_state = GameState::from_fen(START_POSITION_FEN);
+  assert_eq!(Err(2), game_state.apply_uci_moves("e2e4 e7e5 e1e3 g1f3"));
+  assert_eq!("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+             game_state.to_fen());
+
+  // Invalid notation
+  let mut game_state = GameState::from_fen(START_POSITION_FEN);
+  assert_eq!(Err(1), game_state.apply_uci_moves("e2e4 hello e7e5"));
+  assert_eq!(Err(0), game_state.apply_uci_moves("e2e4"));
+}
@@ -1,4 +1,6 @@
+use crate::model::board::Board;
 use crate::model::moves::*;
+use crate::model::piece::PieceType;
 
 #[test]
 fn square_to_string_test() {
@@ -87,3 +89,160 @@ fn string_to_vec() {
   assert_eq!(vec[0], m0);
   assert_eq!(vec[1], m1);
 }
+
+#[test]
+fn quiet_and_tactical_moves() {
+  let find_move = |fen: &str, notation: &str| -> Move {
+    let board = Board::from_fen(fen);
+    board.get_moves().into_iter().find(|m| m.to_string() == notation).unwrap()
+  };
+
+  // Normal move
+  let m = find_move("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                    "e2e4");
+  assert!(m.is_quiet());
+  assert!(!m.is_tactical());
+
+  // Capture
+  let m = find_move("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+                    "e4d5");
+  assert!(!m.is_quiet());
+  assert!(m.is_tactical());
+
+  // Promotion
+  let m = find_move("8/4P3/8/8/8/8/k7/7K w - - 0 1", "e7e8Q");
+  assert!(!m.is_quiet());
+  assert!(m.is_tactical());
+
+  // En-passant
+  let m = find_move("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                    "e5f6");
+  assert!(m.is_en_passant());
+  assert!(!m.is_quiet());
+  assert!(m.is_tactical());
+
+  // Castling
+  let m = find_move("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1");
+  assert!(m.is_castle());
+  assert!(m.is_quiet());
+  assert!(!m.is_tactical());
+}
+
+#[test]
+fn capture_queries() {
+  let find_move = |board: &Board, notation: &str| -> Move {
+    board.get_moves().into_iter().find(|m| m.to_string() == notation).unwrap()
+  };
+
+  // Quiet move
+  let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+  let m = find_move(&board, "g1f3");
+  assert!(!m.is_capture());
+  assert!(!m.is_piece_capture());
+  assert_eq!(None, m.captured_piece());
+  assert_eq!("Nf3", board.move_to_san(&m));
+
+  // Normal captures
+  let board = Board::from_fen("rnb1kbnr/ppp1pppp/8/3q4/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 0 3");
+  let m = find_move(&board, "c3d5");
+  assert!(m.is_capture());
+  assert!(m.is_piece_capture());
+  assert!(!m.is_en_passant());
+  assert_eq!(Some(PieceType::Queen), m.captured_piece());
+  assert_eq!("Nxd5", board.move_to_san(&m));
+
+  let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+  let m = find_move(&board, "e4d5");
+  assert!(m.is_capture());
+  assert!(!m.is_piece_capture());
+  assert_eq!(Some(PieceType::Pawn), m.captured_piece());
+  assert_eq!("exd5", board.move_to_san(&m));
+
+  // En-passant: the captured pawn is not on the destination square
+  let mut board = Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3");
+  let m = find_move(&board, "e5f6");
+  assert!(m.is_capture());
+  assert!(m.is_en_passant());
+  assert!(!m.is_piece_capture());
+  assert_eq!(Some(PieceType::Pawn), m.captured_piece());
+  assert_eq!("exf6", board.move_to_san(&m));
+  assert_eq!(Ok(m), board.find_move_from_pgn_notation("exf6"));
+
+  // Pushing the pawn instead is not a capture
+  let m = find_move(&board, "e5e6");
+  assert!(!m.is_capture());
+  assert_eq!(None, m.captured_piece());
+  assert_eq!(Ok(m), board.find_move_from_pgn_notation("e6"));
+}
+
+#[test]
+fn move_packing_round_trip() {
+  let promotions = [Promotion::NoPromotion,
+                    Promotion::WhiteQueen,
+                    Promotion::WhiteRook,
+                    Promotion::WhiteBishop,
+                    Promotion::WhiteKnight,
+                    Promotion::BlackQueen,
+                    Promotion::BlackRook,
+                    Promotion::BlackBishop,
+                    Promotion::BlackKnight];
+
+  // Captured pieces, by value of the capture bits
+  let captures = [None,
+                  Some(PieceType::Queen),
+                  Some(PieceType::Rook),
+                  Some(PieceType::Bishop),
+                  Some(PieceType::Knight),
+                  Some(PieceType::Pawn)];
+
+  for src in 0..64 as move_t {
+    for dest in 0..64 as move_t {
+      for promotion in promotions {
+        for (capture, captured) in captures.iter().enumerate() {
+          for checks in 0..4 {
+            let m = mv!(src, dest, promotion as u8, capture, checks);
+            assert_eq!(src, m.src());
+            assert_eq!(src as u8, m.u8_src());
+            assert_eq!(dest, m.dest());
+            assert_eq!(dest as u8, m.u8_dest());
+            assert_eq!(promotion, m.promotion());
+            assert_eq!(capture != 0, m.is_capture());
+            assert_eq!(*captured, m.captured_piece());
+            assert_eq!(checks as move_t, m.gives_check());
+            assert!(!m.is_castle());
+            assert!(!m.is_en_passant());
+            assert_eq!(src == dest && m.data == 0, m.is_null());
+          }
+        }
+      }
+
+      // Flags set by the dedicated macros
+      let m = castle_mv!(src, dest);
+      assert_eq!((src, dest), (m.src(), m.dest()));
+      assert!(m.is_castle());
+      assert!(!m.is_en_passant());
+      assert!(!m.is_capture());
+      assert_eq!(Promotion::NoPromotion, m.promotion());
+      assert_eq!(0, m.gives_check());
+
+      let m = en_passant_mv!(src, dest);
+      assert_eq!((src, dest), (m.src(), m.dest()));
+      assert!(m.is_en_passant());
+      assert!(!m.is_castle());
+      assert_eq!(Some(PieceType::Pawn), m.captured_piece());
+      assert_eq!(Promotion::NoPromotion, m.promotion());
+      assert_eq!(0, m.gives_check());
+
+      // Nothing is packed above the en-passant flag
+      assert_eq!(0, (m.data | castle_mv!(src, dest).data) >> (EN_PASSANT_SHIFT + 1));
+
+      // Only a1a1 without any flag is the null move
+      assert_eq!(src == dest && src == 0, mv!(src, dest).is_null());
+      assert!(!m.is_null());
+    }
+  }
+
+  assert!(Move::null().is_null());
+  assert_eq!(Move::null(), Move::default());
+  assert_eq!(0, Move::null().data);
+}
@@ -1,7 +1,9 @@
 // Internal crates
-use crate::api::LichessApi;
+use crate::api::{ApiError, LichessApi};
+use crate::types::BotAccountStatus;
 
 // External crates
+use log::*;
 use serde_json::Value as JsonValue;
 
 impl LichessApi {
@@ -10,10 +12,11 @@ impl LichessApi {
   ///
   /// ### Returns
   ///
-  /// Result with JSON value (containing account information) in case of success.
+  /// Result with JSON value (containing account information) in case of
+  /// success. `ApiError::Unauthorized` if Lichess refuses our API token.
   ///
-  pub async fn get_profile(&self) -> Result<JsonValue, ()> {
-    self.lichess_get("account").await
+  pub async fn get_profile(&self) -> Result<JsonValue, ApiError> {
+    self.try_lichess_get("account").await
   }
 
   /// Fetches our username from Lichess
@@ -43,4 +46,81 @@ impl LichessApi {
   pub async fn get_ongoing_games(&self) -> Result<JsonValue, ()> {
     self.lichess_get("account/playing").await
   }
+
+  /// Upgrades our account to a bot account, so that it can use the bot API
+  /// endpoints.
+  /// Queries using the `bot/account/upgrade` API endpoint.
+  ///
+  /// The upgrade is irreversible and only works on accounts that have not
+  /// played any game, so we check our profile before requesting it.
+  ///
+  /// ### Returns
+  ///
+  /// Result indicating if the account is now a bot account
+  ///
+  pub async fn upgrade_to_bot_account(&self) -> Result<(), ()> {
+    let profile = self.get_profile().await.map_err(|_| ())?;
+    match bot_account_status(&profile) {
+      BotAccountStatus::Bot => {
+        info!("Our account is already a bot account");
+        return Ok(());
+      },
+      BotAccountStatus::NotUpgradable => {
+        warn!("Our account already played games, it cannot be upgraded to a bot account");
+        return Err(());
+      },
+      BotAccountStatus::Upgradable => {},
+    }
+
+    self.lichess_post("bot/account/upgrade", "").await?;
+    info!("Our account was upgraded to a bot account");
+    Ok(())
+  }
+}
+
+/// Checks from our profile whether our account can use the bot API endpoints
+///
+/// ### Arguments
+///
+/// * `profile` JSON profile received from the `account` API endpoint
+///
+/// ### Returns
+///
+/// Bot status of the account
+pub fn bot_account_status(profile: &JsonValue) -> BotAccountStatus {
+  if profile["title"].as_str() == Some("BOT") {
+    BotAccountStatus::Bot
+  } else if profile["count"]["all"].as_u64() == Some(0) {
+    BotAccountStatus::Upgradable
+  } else {
+    BotAccountStatus::NotUpgradable
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bot_account_status_from_profile() {
+    let profile: JsonValue =
+      serde_json::from_str(r#"{"id":"schnecken_bot","title":"BOT","count":{"all":2500}}"#).unwrap();
+    assert_eq!(BotAccountStatus::Bot, bot_account_status(&profile));
+
+    // Brand-new account, without any game
+    let profile: JsonValue =
+      serde_json::from_str(r#"{"id":"new_account","count":{"all":0,"rated":0}}"#).unwrap();
+    assert_eq!(BotAccountStatus::Upgradable, bot_account_status(&profile));
+
+    // Human account that already played, the upgrade would fail
+    let profile: JsonValue =
+      serde_json::from_str(r#"{"id":"human","title":"GM","count":{"all":12}}"#).unwrap();
+    assert_eq!(BotAccountStatus::NotUpgradable,
+               bot_account_status(&profile));
+
+    // We do not know how many games were played, do not risk it
+    let profile: JsonValue = serde_json::from_str(r#"{"id":"unknown"}"#).unwrap();
+    assert_eq!(BotAccountStatus::NotUpgradable,
+               bot_account_status(&profile));
+  }
 }
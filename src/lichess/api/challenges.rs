@@ -1,5 +1,6 @@
 // Internal crates
 use crate::api::LichessApi;
+use crate::traits::GameSeeker;
 use crate::types::Clock;
 
 // External crates
@@ -58,16 +59,17 @@ impl LichessApi {
   ///
   /// * `player`: Username of the player to challenge
   /// * `clock`:  Clock settings (set clock.initial for time, and clock.increment for the increment)
+  /// * `rated`:  Set to true to send a rated challenge
   ///
   /// ### Returns
   ///
   /// Result
   ///
-  pub async fn send_challenge(&self, player: &str, clock: &Clock) -> Result<(), ()> {
+  pub async fn send_challenge(&self, player: &str, clock: &Clock, rated: bool) -> Result<(), ()> {
     let api_endpoint: String = format!("challenge/{}", player);
     let body_parameters = format!(
-      "rated=true&clock.limit={}&clock.increment={}&color=random&variant=standard",
-      clock.initial, clock.increment
+      "rated={}&clock.limit={}&clock.increment={}&color=random&variant=standard",
+      rated, clock.initial, clock.increment
     );
     if self.lichess_post(&api_endpoint, body_parameters.as_str()).await.is_ok() {
       Ok(())
@@ -75,4 +77,53 @@ impl LichessApi {
       Err(())
     }
   }
+
+  /// Creates a public seek, so that any player can join the game.
+  /// Lichess keeps the request open until the seek is accepted or expires.
+  /// BOT accounts are not allowed to seek games, Lichess refuses the seek.
+  ///
+  /// ### Parameters
+  ///
+  /// * `clock`: Clock settings (set clock.initial for time, and clock.increment
+  ///   for the increment)
+  /// * `rated`: Set to true to seek a rated game
+  ///
+  /// ### Returns
+  ///
+  /// Result, Err if the request failed or Lichess refused the seek
+  ///
+  pub async fn create_seek(&self, clock: &Clock, rated: bool) -> Result<(), ()> {
+    let api_endpoint: String = String::from("board/seek");
+    let body_parameters = format!("rated={}&time={}&increment={}&color=random&variant=standard",
+                                  rated,
+                                  clock.initial as f32 / 60.0,
+                                  clock.increment);
+    match self.lichess_post(&api_endpoint, body_parameters.as_str()).await {
+      Ok(json_value) if json_value["error"].is_null() => Ok(()),
+      Ok(json_value) => {
+        info!("Lichess refused our seek for {}+{}: {}",
+              clock.initial, clock.increment, json_value["error"]);
+        Err(())
+      },
+      Err(()) => {
+        warn!("Error creating a seek for {}+{}",
+              clock.initial, clock.increment);
+        Err(())
+      },
+    }
+  }
+}
+
+impl GameSeeker for LichessApi {
+  async fn create_seek(&self, clock: &Clock, rated: bool) -> Result<(), ()> {
+    LichessApi::create_seek(self, clock, rated).await
+  }
+
+  async fn get_online_bots(&self, count: usize) -> Result<Vec<String>, ()> {
+    LichessApi::get_online_bots(self, count).await
+  }
+
+  async fn send_challenge(&self, player: &str, clock: &Clock, rated: bool) -> Result<(), ()> {
+    LichessApi::send_challenge(self, player, clock, rated).await
+  }
 }
@@ -0,0 +1,123 @@
+// Internal crates
+use crate::api::{ApiError, LichessApi};
+use crate::types::*;
+// External crates
+use log::*;
+use urlencoding::encode;
+
+// Constants
+static EXPLORER_BASE_URL: &str = "https://explorer.lichess.ovh/";
+
+impl LichessApi {
+  /// Queries the opening explorer for the moves played in a position.
+  /// Refer to https://lichess.org/api#tag/Opening-Explorer
+  ///
+  /// ### Arguments
+  ///
+  /// * `fen` FEN of the position to look up
+  /// * `db`  Database to query, masters or Lichess games
+  ///
+  /// ### Returns
+  ///
+  /// Result with the moves played in the position and their statistics.
+  /// `ApiError::Request` if the request failed, e.g. network issue or rate
+  /// limit, `ApiError::InvalidJson` if the reply could not be parsed.
+  /// The list of moves is empty if the position is not in the database.
+  pub async fn opening_explorer(&self,
+                                fen: &str,
+                                db: ExplorerDb)
+                                -> Result<ExplorerResult, ApiError> {
+    let url = format!("{}{}?fen={}", EXPLORER_BASE_URL, db.endpoint(), encode(fen));
+    debug!("Lichess opening explorer GET request at {}", url);
+
+    let response_result = self.client
+                              .get(url)
+                              .header("Authorization", format!("Bearer {}", self.token))
+                              .send()
+                              .await;
+    if let Err(error) = response_result {
+      warn!("Error issuing a request to the opening explorer {}", error);
+      return Err(ApiError::Request);
+    }
+
+    let response = response_result.unwrap();
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+      warn!("Rate limited by the opening explorer");
+      return Err(ApiError::Request);
+    }
+    if !response.status().is_success() {
+      warn!("Opening explorer replied with status {}", response.status());
+      return Err(ApiError::Request);
+    }
+
+    let response_text_result = response.text().await;
+    if let Err(error) = response_text_result {
+      warn!("Error reading the payload from the opening explorer {}",
+            error);
+      return Err(ApiError::Request);
+    }
+
+    parse_explorer_result(&response_text_result.unwrap())
+  }
+}
+
+/// Parses the reply of the opening explorer
+///
+/// ### Arguments
+///
+/// * `payload` JSON payload received from the opening explorer
+///
+/// ### Returns
+///
+/// Result with the moves and statistics of the position,
+/// `ApiError::InvalidJson` if the payload is not a valid explorer reply
+pub fn parse_explorer_result(payload: &str) -> Result<ExplorerResult, ApiError> {
+  match serde_json::from_str::<ExplorerResult>(payload) {
+    Ok(result) => Ok(result),
+    Err(error) => {
+      warn!("Error parsing the opening explorer reply: {}", error);
+      Err(ApiError::InvalidJson)
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_explorer_moves_and_stats() {
+    let payload = r#"{"white":1212,"draws":160,"black":1406,
+      "moves":[
+        {"uci":"e7e5","san":"e5","averageRating":2412,"white":603,"draws":80,"black":683,"game":null},
+        {"uci":"c7c5","san":"c5","averageRating":2398,"white":400,"draws":100,"black":500,"game":null},
+        {"uci":"e7e6","san":"e6","white":209,"draws":30,"black":123,"game":null}
+      ],
+      "topGames":[],
+      "opening":{"eco":"B00","name":"King's Pawn Game"}}"#;
+
+    let result = parse_explorer_result(payload).unwrap();
+    assert_eq!(1212, result.white);
+    assert_eq!(160, result.draws);
+    assert_eq!(1406, result.black);
+    assert_eq!(3, result.moves.len());
+    assert_eq!("e7e5", result.moves[0].uci);
+    assert_eq!("c5", result.moves[1].san);
+    assert_eq!(Some(2412), result.moves[0].average_rating);
+    assert_eq!(None, result.moves[2].average_rating);
+    assert_eq!(1000, result.moves[1].games());
+    assert_eq!(0.55, result.moves[1].score(Color::Black));
+    assert_eq!("B00", result.opening.unwrap().eco);
+  }
+
+  #[test]
+  fn parse_explorer_position_out_of_database() {
+    let payload = r#"{"white":0,"draws":0,"black":0,"moves":[],"topGames":[],"opening":null}"#;
+    let result = parse_explorer_result(payload).unwrap();
+    assert!(result.moves.is_empty());
+    assert!(result.opening.is_none());
+
+    assert_eq!(Some(ApiError::InvalidJson),
+               parse_explorer_result("Too many requests").err());
+  }
+}
@@ -1,5 +1,6 @@
 // Internal crates
 use crate::api::LichessApi;
+use crate::traits::{GamePlayer, GameTerminator};
 use crate::types::*;
 // External crates
 use log::*;
@@ -52,6 +53,48 @@ impl LichessApi {
     Ok(())
   }
 
+  /// Offers or accepts a draw. Used to claim the draw in dead drawn positions
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID on which we claim the draw
+  ///
+  /// ### Returns
+  ///
+  /// Result indicating if we had error claiming the draw
+  pub async fn claim_draw(&self, game_id: &str) -> Result<(), ()> {
+    let api_endpoint: String = format!("bot/game/{game_id}/draw/yes");
+    let _json_response: JsonValue;
+    if let Ok(json) = self.lichess_post(&api_endpoint, "").await {
+      _json_response = json;
+    } else {
+      return Err(());
+    }
+
+    Ok(())
+  }
+
+  /// Declines the draw offer of our opponent, or withdraws our own draw offer
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID on which we decline the draw
+  ///
+  /// ### Returns
+  ///
+  /// Result indicating if we had error declining the draw
+  pub async fn decline_draw(&self, game_id: &str) -> Result<(), ()> {
+    let api_endpoint: String = format!("bot/game/{game_id}/draw/no");
+    let _json_response: JsonValue;
+    if let Ok(json) = self.lichess_post(&api_endpoint, "").await {
+      _json_response = json;
+    } else {
+      return Err(());
+    }
+
+    Ok(())
+  }
+
   /// Writes in the game chat, using spectator room
   ///
   /// ### Arguments
@@ -200,3 +243,31 @@ impl LichessApi {
     let _ = self.claim_victory(game_id).await;
   }
 }
+
+impl GameTerminator for LichessApi {
+  async fn abort_game(&self, game_id: &str) -> Result<(), ()> {
+    LichessApi::abort_game(self, game_id).await
+  }
+
+  async fn resign_game(&self, game_id: &str) -> Result<(), ()> {
+    LichessApi::resign_game(self, game_id).await
+  }
+
+  async fn claim_draw(&self, game_id: &str) -> Result<(), ()> {
+    LichessApi::claim_draw(self, game_id).await
+  }
+
+  async fn decline_draw(&self, game_id: &str) -> Result<(), ()> {
+    LichessApi::decline_draw(self, game_id).await
+  }
+}
+
+impl GamePlayer for LichessApi {
+  async fn get_ongoing_games(&self) -> Result<JsonValue, ()> {
+    LichessApi::get_ongoing_games(self).await
+  }
+
+  async fn make_move(&self, game_id: &str, chess_move: &str, offer_draw: bool) -> bool {
+    LichessApi::make_move(self, game_id, chess_move, offer_draw).await
+  }
+}
@@ -1,10 +1,16 @@
 use crate::api::LichessApi;
 use crate::helpers;
-use crate::traits::GameStreamHandler;
+use crate::traits::{GameExporter, GameStreamHandler};
 use futures_util::StreamExt;
 use log::*;
 use serde_json::Value as JsonValue;
 
+// Constants
+/// Game export endpoint, it is not located under the API base URL. We only
+/// ask for the moves, without tags, clocks or evaluations.
+static GAME_EXPORT_URL: &str = "https://lichess.org/game/export/";
+static GAME_EXPORT_OPTIONS: &str = "?tags=false&clocks=false&evals=false&opening=false";
+
 impl LichessApi {
   /// Unique ID used by the application to stream games
   const GAMES_STREAM_ID: &str = "schnecken_bot";
@@ -107,4 +113,52 @@ impl LichessApi {
     info!("Finished to stream game events for game id {game_id}");
     Ok(())
   }
+
+  /// Exports a game in PGN format, with only the moves and the result.
+  /// Refer to https://lichess.org/api#tag/Games/operation/gamePgn
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID to export
+  ///
+  /// ### Returns
+  ///
+  /// Result with the PGN text of the game. Err if the request failed, e.g.
+  /// network issue or the export not being available yet.
+  pub async fn get_game_pgn(&self, game_id: &str) -> Result<String, ()> {
+    let url = format!("{}{}{}", GAME_EXPORT_URL, game_id, GAME_EXPORT_OPTIONS);
+    debug!("Lichess game export GET request at {}", url);
+
+    let response_result = self.client
+                              .get(url)
+                              .header("Authorization", format!("Bearer {}", self.token))
+                              .header("Accept", "application/x-chess-pgn")
+                              .send()
+                              .await;
+    if let Err(error) = response_result {
+      warn!("Error issuing a request to export game {}: {}",
+            game_id, error);
+      return Err(());
+    }
+
+    let response = response_result.unwrap();
+    if !response.status().is_success() {
+      debug!("Game export for {} replied with status {}", game_id, response.status());
+      return Err(());
+    }
+
+    match response.text().await {
+      Ok(pgn) => Ok(pgn),
+      Err(error) => {
+        warn!("Error reading the PGN of game {}: {}", game_id, error);
+        Err(())
+      },
+    }
+  }
+}
+
+impl GameExporter for LichessApi {
+  async fn get_game_pgn(&self, game_id: &str) -> Result<String, ()> {
+    LichessApi::get_game_pgn(self, game_id).await
+  }
 }
@@ -1,6 +1,7 @@
 // Submodules
 pub mod account;
 pub mod challenges;
+pub mod explorer;
 pub mod game;
 pub mod games;
 pub mod users;
@@ -13,6 +14,7 @@ use futures_util::StreamExt;
 use log::*;
 use reqwest;
 use serde_json::Value as JsonValue;
+use std::fmt;
 
 // Constants
 static API_BASE_URL: &str = "https://lichess.org/api/";
@@ -24,51 +26,113 @@ unsafe impl Sync for LichessApi {}
 #[derive(Debug, Clone)]
 pub struct LichessApi {
   /// Reqwest client use to send HTTP/HTTPS requests
-  client: reqwest::Client,
+  client:   reqwest::Client,
   /// Lichess API token, giving us access to an account and some permissions
-  token: String,
+  token:    String,
+  /// URL prepended to the API endpoints
+  base_url: String,
+}
+
+/// Reasons for which a request to the Lichess API failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiError {
+  /// The request could not be sent, or its answer could not be read
+  Request,
+  /// Lichess refused our API token (HTTP 401): it is invalid, revoked or lacks
+  /// the permissions for the endpoint
+  Unauthorized,
+  /// The answer of Lichess is not valid JSON
+  InvalidJson,
+}
+
+impl fmt::Display for ApiError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ApiError::Request => write!(f, "the request could not be completed"),
+      ApiError::Unauthorized => {
+        write!(f, "your Lichess token is invalid or lacks bot:play scope")
+      },
+      ApiError::InvalidJson => write!(f, "the answer is not valid JSON"),
+    }
+  }
 }
 
 impl LichessApi {
   /// Creates a new instance of a LichessApi, using the API token
   pub fn new(token: &str) -> Self {
-    LichessApi {
-      client: reqwest::Client::new(),
-      token: String::from(token),
-    }
+    LichessApi::with_base_url(token, API_BASE_URL)
+  }
+
+  /// Creates a new instance of a LichessApi sending its requests to another
+  /// server than Lichess, e.g. a local test server.
+  ///
+  /// ### Arguments
+  ///
+  /// * `token` API token sent with the requests
+  /// * `base_url` URL prepended to the endpoints, e.g. `"http://localhost:8080/api/"`
+  pub fn with_base_url(token: &str, base_url: &str) -> Self {
+    LichessApi { client:   reqwest::Client::new(),
+                 token:    String::from(token),
+                 base_url: String::from(base_url), }
   }
 
   //----------------------------------------------------------------------------
   // Private functions
 
   /// Sends a GET request to a given Endpoint
-  async fn get(&self, api_endpoint: &str) -> Result<reqwest::Response, reqwest::Error> {
-    debug!("Lichess GET request at {}{}", API_BASE_URL, api_endpoint);
-    self
-      .client
-      .get(format!("{}{}", API_BASE_URL, api_endpoint))
-      .header("Authorization", format!("Bearer {}", self.token))
-      .header("Accept", "application/x-ndjson")
-      .send()
-      .await
+  async fn get(&self, api_endpoint: &str) -> Result<reqwest::Response, ApiError> {
+    debug!("Lichess GET request at {}{}", self.base_url, api_endpoint);
+    let response_result = self.client
+                              .get(format!("{}{}", self.base_url, api_endpoint))
+                              .header("Authorization", format!("Bearer {}", self.token))
+                              .header("Accept", "application/x-ndjson")
+                              .send()
+                              .await;
+    Self::check_response(response_result, api_endpoint)
   }
 
   /// Sends a POST request to a given Endpoint
-  async fn post(
-    &self,
-    api_endpoint: &str,
-    body: &str,
-  ) -> Result<reqwest::Response, reqwest::Error> {
-    debug!("Lichess POST request at {}{}", API_BASE_URL, api_endpoint);
-    self
-      .client
-      .post(format!("{}{}", API_BASE_URL, api_endpoint))
-      .header("Authorization", format!("Bearer {}", self.token))
-      .header("Accept", "application/x-ndjson")
-      .header("Content-Type", "application/x-www-form-urlencoded")
-      .body(body.to_string())
-      .send()
-      .await
+  async fn post(&self, api_endpoint: &str, body: &str) -> Result<reqwest::Response, ApiError> {
+    debug!("Lichess POST request at {}{}", self.base_url, api_endpoint);
+    let response_result = self.client
+                              .post(format!("{}{}", self.base_url, api_endpoint))
+                              .header("Authorization", format!("Bearer {}", self.token))
+                              .header("Accept", "application/x-ndjson")
+                              .header("Content-Type", "application/x-www-form-urlencoded")
+                              .body(body.to_string())
+                              .send()
+                              .await;
+    Self::check_response(response_result, api_endpoint)
+  }
+
+  /// Checks the outcome of a request, singling out the requests that Lichess
+  /// refused because of our API token.
+  ///
+  /// ### Arguments
+  ///
+  /// * `response_result` Outcome of the request
+  /// * `api_endpoint` Endpoint of the request, for the logs
+  ///
+  /// ### Returns
+  ///
+  /// The response, or the reason why the request failed.
+  fn check_response(response_result: Result<reqwest::Response, reqwest::Error>,
+                    api_endpoint: &str)
+                    -> Result<reqwest::Response, ApiError> {
+    let response = match response_result {
+      Ok(response) => response,
+      Err(error) => {
+        warn!("Error issuing a request to Lichess at {api_endpoint}: {error}");
+        return Err(ApiError::Request);
+      },
+    };
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+      error!("Lichess refused our request at {api_endpoint}: {}",
+             ApiError::Unauthorized);
+      return Err(ApiError::Unauthorized);
+    }
+    Ok(response)
   }
 
   //----------------------------------------------------------------------------
@@ -85,21 +149,30 @@ impl LichessApi {
   ///
   /// Result with a JSON value received in the API response.
   pub async fn lichess_get(&self, api_endpoint: &str) -> Result<JsonValue, ()> {
-    let response_result = self.get(api_endpoint).await;
-
-    if let Err(error) = response_result {
-      warn!("Error issuing a Get request to Lichess {}", error);
-      return Err(());
-    }
+    self.try_lichess_get(api_endpoint).await.map_err(|_| ())
+  }
 
-    let response_text_result = response_result.unwrap().text().await;
+  /// Generic HTTPS Get request to Lichess, telling why the request failed.
+  ///
+  /// ### Arguments
+  ///
+  /// * `api_endpoint` Endpoint for the API, e.g. `"account/playing"` to do a
+  ///   Get to `https://lichess.org/api/account/playing`
+  ///
+  /// ### Returns
+  ///
+  /// Result with a JSON value received in the API response, or the reason why
+  /// the request failed, e.g. `ApiError::Unauthorized` if our token is refused.
+  pub async fn try_lichess_get(&self, api_endpoint: &str) -> Result<JsonValue, ApiError> {
+    let response = self.get(api_endpoint).await?;
+    let response_text_result = response.text().await;
 
     if let Err(error) = response_text_result {
       warn!(
         "Error reading the payload from Get request to Lichess {}",
         error
       );
-      return Err(());
+      return Err(ApiError::Request);
     }
 
     let json_value_result = serde_json::from_str(&response_text_result.unwrap());
@@ -109,7 +182,7 @@ impl LichessApi {
         warn!(
           "Error parsing JSON from the Lichess Response for API call {api_endpoint}. Error:{error}"
         );
-        return Err(());
+        return Err(ApiError::InvalidJson);
       },
     };
 
@@ -130,8 +203,7 @@ impl LichessApi {
   /// Result with a JSON value received in the API response.
   pub async fn lichess_post(&self, api_endpoint: &str, body: &str) -> Result<JsonValue, ()> {
     let response_result = self.post(api_endpoint, body).await;
-    if let Err(e) = response_result {
-      warn!("Error issuing a Get request to Lichess {e}");
+    if response_result.is_err() {
       return Err(());
     }
 
@@ -364,3 +436,72 @@ impl LichessApi {
     Ok(())
   }
 } // impl LichessApi
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::{Read, Write};
+  use std::net::TcpListener;
+
+  /// Starts a local server answering `count` requests with HTTP 401, like
+  /// Lichess does for an invalid token.
+  ///
+  /// ### Returns
+  ///
+  /// The base URL of the server
+  fn start_unauthorized_server(count: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+      for stream in listener.incoming().take(count) {
+        let mut stream = stream.unwrap();
+        // Read the whole request before answering
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+          let length = stream.read(&mut buffer).unwrap();
+          request.extend_from_slice(&buffer[..length]);
+          let text = String::from_utf8_lossy(&request).to_string();
+          if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+            let content_length = headers.lines()
+                                        .find_map(|l| {
+                                          l.to_lowercase()
+                                           .strip_prefix("content-length:")
+                                           .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                                        })
+                                        .unwrap_or(0);
+            if body.len() >= content_length {
+              break;
+            }
+          }
+          if length == 0 {
+            break;
+          }
+        }
+        let body = r#"{"error":"No such token"}"#;
+        let response = format!("HTTP/1.1 401 Unauthorized\r\nContent-Type: application/json\r\n\
+                                Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                               body.len(),
+                               body);
+        stream.write_all(response.as_bytes()).unwrap();
+      }
+    });
+    format!("http://{}/api/", address)
+  }
+
+  #[test]
+  fn invalid_token_is_reported_as_unauthorized() {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let api = LichessApi::with_base_url("invalid_token", &start_unauthorized_server(4));
+
+    // The JSON error payload is not mistaken for an answer
+    assert_eq!(Err(ApiError::Unauthorized),
+               rt.block_on(api.try_lichess_get("account")));
+    assert_eq!(Err(()), rt.block_on(api.lichess_get("account/playing")));
+    assert_eq!(Err(()),
+               rt.block_on(api.lichess_post("bot/game/abcd1234/move/e2e4", "")));
+
+    // ... and it is propagated to the callers looking up our account
+    assert_eq!(Err(ApiError::Unauthorized), rt.block_on(api.get_profile()));
+  }
+}
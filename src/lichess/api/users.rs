@@ -73,4 +73,63 @@ impl LichessApi {
 
     Some((user_1_score, user_2_score))
   }
+
+  /// Lists bots that are currently online
+  /// Refer to https://lichess.org/api#tag/Bot/operation/apiBotOnline
+  ///
+  /// ### Arguments
+  ///
+  /// * `count` Maximum number of bots to list
+  ///
+  /// ### Returns
+  ///
+  /// Result with the user IDs of the online bots
+  ///
+  pub async fn get_online_bots(&self, count: usize) -> Result<Vec<String>, ()> {
+    let endpoint: String = format!("bot/online?nb={}", count);
+    let response = self.get(&endpoint).await.map_err(|_| ())?;
+
+    match response.text().await {
+      Ok(ndjson) => Ok(parse_user_ids(&ndjson)),
+      Err(error) => {
+        warn!("Error reading the list of online bots: {}", error);
+        Err(())
+      },
+    }
+  }
+}
+
+/// Reads the user IDs in a list of users received as ndjson, one user per
+/// line. Lines that cannot be parsed are skipped.
+///
+/// ### Arguments
+///
+/// * `ndjson` List of users, one JSON object per line
+///
+/// ### Returns
+///
+/// The user IDs of the users
+///
+fn parse_user_ids(ndjson: &str) -> Vec<String> {
+  ndjson.lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter_map(|user| user["id"].as_str().map(String::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn user_ids_from_ndjson() {
+    let ndjson = r#"{"id":"schnecken_bot","username":"schnecken_bot","title":"BOT"}
+{"id":"maia1","username":"maia1","title":"BOT"}
+
+{"username":"no_id"}
+"#;
+    assert_eq!(vec![String::from("schnecken_bot"), String::from("maia1")],
+               parse_user_ids(ndjson));
+    assert!(parse_user_ids("").is_empty());
+  }
 }
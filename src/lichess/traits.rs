@@ -1,5 +1,7 @@
+use crate::types::Clock;
 // Other crates
 use serde_json::Value as JsonValue;
+use std::future::Future;
 
 /// Trait for an object that can be invoked using the Event Streams
 pub trait EventStreamHandler {
@@ -29,3 +31,96 @@ pub trait GameStreamHandler {
   /// * `game_id`    Game ID
   fn game_stream_handler(&self, json_value: JsonValue, game_id: String);
 }
+
+/// Trait for an object that can end ongoing games, typically the Lichess API
+pub trait GameTerminator {
+  /// Aborts a game. This is only possible before both players have moved.
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID to abort
+  fn abort_game(&self, game_id: &str) -> impl Future<Output = Result<(), ()>> + Send;
+
+  /// Resigns a game
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID to resign
+  fn resign_game(&self, game_id: &str) -> impl Future<Output = Result<(), ()>> + Send;
+
+  /// Offers or accepts a draw
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID on which we claim the draw
+  fn claim_draw(&self, game_id: &str) -> impl Future<Output = Result<(), ()>> + Send;
+
+  /// Declines a draw offer, or withdraws our own
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID on which we decline the draw
+  fn decline_draw(&self, game_id: &str) -> impl Future<Output = Result<(), ()>> + Send;
+}
+
+/// Trait for an object that can look up our ongoing games and play moves in
+/// them, typically the Lichess API
+pub trait GamePlayer {
+  /// Lists the games we are currently playing
+  fn get_ongoing_games(&self) -> impl Future<Output = Result<JsonValue, ()>> + Send;
+
+  /// Plays a move in a game
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id`    Game ID on which we play
+  /// * `chess_move` Notation of the move to make
+  /// * `offer_draw` Set this to true to make a draw offer.
+  fn make_move(&self,
+               game_id: &str,
+               chess_move: &str,
+               offer_draw: bool)
+               -> impl Future<Output = bool> + Send;
+}
+
+/// Trait for an object that can look for opponents, typically the Lichess API
+pub trait GameSeeker {
+  /// Creates a public seek for a game
+  ///
+  /// ### Arguments
+  ///
+  /// * `clock` Clock settings of the game
+  /// * `rated` Set this to true to seek a rated game
+  fn create_seek(&self, clock: &Clock, rated: bool) -> impl Future<Output = Result<(), ()>> + Send;
+
+  /// Lists the user IDs of bots that are currently online
+  ///
+  /// ### Arguments
+  ///
+  /// * `count` Maximum number of bots to list
+  fn get_online_bots(&self, count: usize) -> impl Future<Output = Result<Vec<String>, ()>> + Send;
+
+  /// Challenges a player to a game
+  ///
+  /// ### Arguments
+  ///
+  /// * `player` Username of the player to challenge
+  /// * `clock`  Clock settings of the game
+  /// * `rated`  Set this to true to send a rated challenge
+  fn send_challenge(&self,
+                    player: &str,
+                    clock: &Clock,
+                    rated: bool)
+                    -> impl Future<Output = Result<(), ()>> + Send;
+}
+
+/// Trait for an object that can export finished games, typically the Lichess
+/// API
+pub trait GameExporter {
+  /// Exports a game in PGN format
+  ///
+  /// ### Arguments
+  ///
+  /// * `game_id` Game ID to export
+  fn get_game_pgn(&self, game_id: &str) -> impl Future<Output = Result<String, ()>> + Send;
+}
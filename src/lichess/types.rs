@@ -38,6 +38,11 @@ impl GameStart {
   pub fn opponent_is_bot(&self) -> bool {
     self.opponent.username.contains("BOT ")
   }
+
+  /// Checks if the game is played with days per move instead of a clock
+  pub fn is_correspondence(&self) -> bool {
+    self.speed == "correspondence"
+  }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -62,23 +67,108 @@ pub struct GameFull {
   #[serde(rename = "initialFen")]
   pub initial_fen: String,
   pub state:       GameState,
+  #[serde(default)]
+  pub white:       GameFullPlayer,
+  #[serde(default)]
+  pub black:       GameFullPlayer,
+}
+
+impl GameFull {
+  /// Finds the colors played by a user in the game. Both colors are returned
+  /// if the user plays against themselves.
+  ///
+  /// ### Arguments
+  ///
+  /// * `user_id`: Lichess ID of the user, the username is also accepted
+  pub fn colors_of(&self, user_id: &str) -> Vec<Color> {
+    let mut colors = Vec::new();
+    if self.white.is(user_id) {
+      colors.push(Color::White);
+    }
+    if self.black.is(user_id) {
+      colors.push(Color::Black);
+    }
+    colors
+  }
+}
+
+/// Player of a game, as described in the gameFull events. AI opponents only
+/// have an AI level.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct GameFullPlayer {
+  pub id:       Option<String>,
+  pub name:     Option<String>,
+  pub rating:   Option<usize>,
+  #[serde(rename = "aiLevel")]
+  pub ai_level: Option<u8>,
+}
+
+impl GameFullPlayer {
+  /// Checks if the player is a given user. Lichess IDs are lowercase
+  /// usernames, so the comparison ignores the case.
+  ///
+  /// ### Arguments
+  ///
+  /// * `user_id`: Lichess ID of the user, the username is also accepted
+  pub fn is(&self, user_id: &str) -> bool {
+    self.id.as_ref().is_some_and(|id| id.eq_ignore_ascii_case(user_id))
+  }
 }
 
 /// Game state object received during the games
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GameState {
-  pub moves:  String,
-  pub wtime:  usize,
-  pub btime:  usize,
-  pub winc:   usize,
-  pub binc:   usize,
-  pub status: GameStatus,
-  pub winner: Option<Color>,
+  pub moves:    String,
+  pub wtime:    usize,
+  pub btime:    usize,
+  pub winc:     usize,
+  pub binc:     usize,
+  pub status:   GameStatus,
+  pub winner:   Option<Color>,
+  /// White halved their clock in an arena game. Only sent when set.
+  #[serde(default)]
+  pub wberserk: bool,
+  /// Black halved their clock in an arena game. Only sent when set.
+  #[serde(default)]
+  pub bberserk: bool,
+  /// White offers a draw. Only sent when set.
+  #[serde(default)]
+  pub wdraw:    bool,
+  /// Black offers a draw. Only sent when set.
+  #[serde(default)]
+  pub bdraw:    bool,
 }
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+impl GameState {
+  /// Checks if a player went berserk in an arena game.
+  ///
+  /// ### Arguments
+  ///
+  /// * `color`: Side of the player
+  pub fn has_berserked(&self, color: Color) -> bool {
+    match color {
+      Color::White => self.wberserk,
+      Color::Black => self.bberserk,
+    }
+  }
+
+  /// Checks if a player currently offers a draw.
+  ///
+  /// ### Arguments
+  ///
+  /// * `color`: Side of the player
+  pub fn offers_draw(&self, color: Color) -> bool {
+    match color {
+      Color::White => self.wdraw,
+      Color::Black => self.bdraw,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum GameStatus {
+  Created,
   Started,
   Aborted,
   Mate,
@@ -88,7 +178,21 @@ pub enum GameStatus {
   Draw,
   Outoftime,
   Cheat,
+  /// Aborted by Lichess because a player did not make their first move in time
+  #[serde(rename = "noStart")]
   NoStart,
+  #[serde(rename = "unknownFinish")]
+  UnknownFinish,
+  #[serde(rename = "variantEnd")]
+  VariantEnd,
+}
+
+impl GameStatus {
+  /// Checks if the game is over with this status. Nothing happens in the game
+  /// anymore once it is over.
+  pub fn is_terminal(&self) -> bool {
+    !matches!(self, GameStatus::Created | GameStatus::Started)
+  }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -143,7 +247,7 @@ pub enum Title {
 }
 
 /// Clock used for the game.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Clock {
   pub initial:   i32,
   pub increment: i32,
@@ -189,6 +293,9 @@ pub struct Variant {
 #[serde(rename_all = "snake_case")]
 pub enum VariantKey {
   Standard,
+  /// Standard rules, starting from an arbitrary position
+  #[serde(rename = "fromPosition")]
+  FromPosition,
   Chess960,
   KingOfTheHill,
   ThreeCheck,
@@ -213,3 +320,87 @@ pub enum ChatRoom {
   Spectator,
   Player,
 }
+
+/// Whether a Lichess account can use the bot API endpoints
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BotAccountStatus {
+  /// The account has the BOT title
+  Bot,
+  /// The account has not played any game yet and can be upgraded
+  Upgradable,
+  /// The account already played games and cannot be upgraded anymore
+  NotUpgradable,
+}
+
+/// Databases available in the opening explorer
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExplorerDb {
+  /// Over-the-board games of masters (2200+ FIDE)
+  Masters,
+  /// Games played on Lichess
+  Lichess,
+}
+
+impl ExplorerDb {
+  /// Returns the endpoint of the database on the opening explorer
+  pub fn endpoint(&self) -> &str {
+    match self {
+      ExplorerDb::Masters => "masters",
+      ExplorerDb::Lichess => "lichess",
+    }
+  }
+}
+
+/// Move found in the opening explorer, with the results of the games where it
+/// was played
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ExplorerMove {
+  pub uci:            String,
+  pub san:            String,
+  pub white:          u64,
+  pub draws:          u64,
+  pub black:          u64,
+  #[serde(rename = "averageRating")]
+  pub average_rating: Option<u32>,
+}
+
+impl ExplorerMove {
+  /// Returns the number of games where the move was played
+  pub fn games(&self) -> u64 {
+    self.white + self.draws + self.black
+  }
+
+  /// Returns the average score obtained with this move, between 0.0 and 1.0,
+  /// from the point of view of a color
+  ///
+  /// ### Arguments
+  ///
+  /// * `color`: Side for which we compute the score
+  pub fn score(&self, color: Color) -> f64 {
+    if self.games() == 0 {
+      return 0.5;
+    }
+    let wins = match color {
+      Color::White => self.white,
+      Color::Black => self.black,
+    };
+    (wins as f64 + self.draws as f64 * 0.5) / self.games() as f64
+  }
+}
+
+/// Opening name and ECO code given by the opening explorer
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ExplorerOpening {
+  pub eco:  String,
+  pub name: String,
+}
+
+/// Statistics of a position in the opening explorer
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ExplorerResult {
+  pub white:   u64,
+  pub draws:   u64,
+  pub black:   u64,
+  pub moves:   Vec<ExplorerMove>,
+  pub opening: Option<ExplorerOpening>,
+}
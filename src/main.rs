@@ -1,8 +1,6 @@
 // External crates
 use anyhow::{anyhow, Result};
 use log::*;
-use std::io;
-use tokio::time::{sleep, Duration};
 
 // Local modules
 mod bot;
@@ -30,23 +28,37 @@ async fn main_loop() -> Result<()> {
   info!("Lichess API token loaded successfully");
 
   // Starts the bot, it will stream incoming events
-  let schnecken_bot = bot::state::BotState::new(API_TOKEN).await;
+  let schnecken_bot = match bot::state::BotState::new(API_TOKEN).await {
+    Ok(bot_state) => bot_state,
+    Err(error) => {
+      error!("Cannot start the bot: {}", error);
+      return Err(anyhow!("Invalid Lichess API token"));
+    },
+  };
+
+  // Make sure that the engine works before playing games with it
+  let mut engine = chess::engine::Engine::new(false);
+  schnecken_bot.net.attach(&mut engine);
+  if let Err(failures) = engine.self_test() {
+    for failure in failures {
+      error!("Engine self-test failed - {}", failure);
+    }
+    return Err(anyhow!("Engine self-test failed"));
+  }
+  info!("Engine self-test passed");
+
   schnecken_bot.start();
 
-  loop {
+  // Execute command line inputs until we have to exit
+  let mut commands = bot::console::read_console_commands();
+  bot::console::run_command_loop(&mut commands, schnecken_bot.exit_requested(), |command| {
     use bot::commands::BotCommands;
-    // Read command line inputs for ever, until we have to exit
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-
-    schnecken_bot.execute_command(input.trim());
+    schnecken_bot.execute_command(command);
+  }).await;
+  info!("Exiting the Lichess bot... ");
 
-    sleep(Duration::from_millis(100)).await;
-    if schnecken_bot.should_exit() {
-      info!("Exiting the Lichess bot... ");
-      break;
-    }
-  }
+  // Leave the ongoing games if requested
+  schnecken_bot.shutdown().await;
 
   // End the main loop.
   Ok(())